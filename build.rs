@@ -0,0 +1,13 @@
+//The gRPC service is generated from proto/langwitch.proto, but only when the grpc feature is on.
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    #[cfg(feature = "grpc")]
+    {
+        let protoc = protoc_bin_vendored::protoc_bin_path().expect("there's no vendored protoc for this platform");
+        std::env::set_var("PROTOC", protoc);
+        tonic_prost_build::configure()
+            .build_client(false)
+            .compile_protos(&["proto/langwitch.proto"], &["proto"])
+            .expect("couldn't compile proto/langwitch.proto");
+    }
+}
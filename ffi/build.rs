@@ -0,0 +1,16 @@
+//Generates the header from the extern "C" functions into OUT_DIR on every build, so a change that can't be turned into C fails the build. The copy Swift and Kotlin bridge to, include/langwitch.h, is committed and only rewritten when LANGWITCH_WRITE_HEADER is set, so building never touches the source tree:
+//    LANGWITCH_WRITE_HEADER=1 cargo build -p langwitch-ffi
+
+fn main() {
+    println!("cargo:rerun-if-changed=src/lib.rs");
+    println!("cargo:rerun-if-changed=cbindgen.toml");
+    println!("cargo:rerun-if-env-changed=LANGWITCH_WRITE_HEADER");
+    let crate_dir = std::env::var("CARGO_MANIFEST_DIR").expect("cargo sets CARGO_MANIFEST_DIR");
+    let out_dir = std::env::var("OUT_DIR").expect("cargo sets OUT_DIR");
+    let config = cbindgen::Config::from_file(format!("{}/cbindgen.toml", crate_dir)).expect("cbindgen.toml is readable");
+    let header = cbindgen::generate_with_config(&crate_dir, config).expect("the extern \"C\" functions can be turned into a header");
+    header.write_to_file(format!("{}/langwitch.h", out_dir));
+    if std::env::var_os("LANGWITCH_WRITE_HEADER").is_some() {
+        header.write_to_file(format!("{}/include/langwitch.h", crate_dir));
+    }
+}
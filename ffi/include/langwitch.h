@@ -0,0 +1,70 @@
+/* Generated from ffi/src/lib.rs by cbindgen when the crate is built. Don't edit it by hand. */
+
+#ifndef LANGWITCH_H
+#define LANGWITCH_H
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// A deck being studied.
+typedef struct LangwitchDeck LangwitchDeck;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Loads a deck from the contents of a gems.json, with the contents of a progress.json and a config.json, either of which may be null. Returns null if any of them won't parse.
+//
+// # Safety
+//
+// Every non-null argument must be a nul-terminated string that stays valid for the call.
+struct LangwitchDeck *langwitch_deck_create(const char *gems_json,
+                                            const char *progress_json,
+                                            const char *config_json);
+
+// Frees a deck. Null is ignored.
+//
+// # Safety
+//
+// `deck` must have come from langwitch_deck_create and not have been destroyed already.
+void langwitch_deck_destroy(struct LangwitchDeck *deck);
+
+// The card to study next as JSON, or the string "null" once nothing is left. The same card comes back until it's graded.
+//
+// # Safety
+//
+// `deck` must be a live deck from langwitch_deck_create.
+char *langwitch_next_gem_json(struct LangwitchDeck *deck);
+
+// Grades the card being studied from JSON like {"gem": 3, "grades": {"cat": 1.0, "sat": 0.0}}, with "grade" for every facet it doesn't list and "hints" for how many hints each facet needed. Returns what the grading did, as JSON.
+//
+// # Safety
+//
+// `deck` must be a live deck from langwitch_deck_create, and `request_json` a nul-terminated string.
+char *langwitch_grade_json(struct LangwitchDeck *deck,
+                           const char *request_json);
+
+// The progress so far as the contents of a progress.json, for the app to save and pass to langwitch_deck_create next time.
+//
+// # Safety
+//
+// `deck` must be a live deck from langwitch_deck_create.
+char *langwitch_progress_json(struct LangwitchDeck *deck);
+
+// Frees a string the library returned. Null is ignored.
+//
+// # Safety
+//
+// `string` must have come from this library and not have been freed already.
+void langwitch_string_free(char *string);
+
+// Why the last call on this thread returned null, or null if nothing has failed yet. The string belongs to the library and stays valid until the next failure on this thread.
+const char *langwitch_last_error(void);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* LANGWITCH_H */
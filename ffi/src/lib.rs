@@ -0,0 +1,143 @@
+//The review engine as a C library, for Swift and Kotlin apps that embed it instead of talking to `langwitch serve`. include/langwitch.h is generated from this file (see build.rs).
+//A deck is an opaque handle from langwitch_deck_create, freed with langwitch_deck_destroy. Everything else goes in and out as UTF-8 JSON strings, the same JSON `langwitch serve` uses (see langwitch::embed). The app keeps progress_json wherever it likes and hands it back to langwitch_deck_create next time.
+//Strings the library returns belong to the caller, who frees them with langwitch_string_free. A call that fails returns null and leaves a message for langwitch_last_error on the same thread. Panics are caught at the boundary and reported the same way, since unwinding into C is undefined.
+
+use std::{
+    cell::RefCell,
+    ffi::{c_char, CStr, CString},
+    panic::{self, AssertUnwindSafe},
+    ptr,
+    time::SystemTime,
+};
+
+use langwitch::{embed::EmbeddedDeck, LangwitchError, Result};
+
+/// A deck being studied.
+pub struct LangwitchDeck {
+    deck: EmbeddedDeck,
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_last_error(message: String) {
+    //An interior nul can't go into a C string, so it's dropped from the message.
+    let message = CString::new(message.replace('\0', "")).unwrap_or_default();
+    LAST_ERROR.with(|last_error| *last_error.borrow_mut() = Some(message));
+}
+
+//Runs `call`, turning an error or a panic into a null return and a message for langwitch_last_error.
+fn guarded<T>(call: impl FnOnce() -> Result<*mut T>) -> *mut T {
+    match panic::catch_unwind(AssertUnwindSafe(call)) {
+        Ok(Ok(value)) => value,
+        Ok(Err(e)) => {
+            set_last_error(e.to_string());
+            ptr::null_mut()
+        }
+        Err(_) => {
+            set_last_error("langwitch panicked".to_string());
+            ptr::null_mut()
+        }
+    }
+}
+
+//The bytes of a C string, or None for a null pointer.
+unsafe fn bytes<'a>(string: *const c_char) -> Option<&'a [u8]> {
+    if string.is_null() {
+        None
+    } else {
+        Some(CStr::from_ptr(string).to_bytes())
+    }
+}
+
+unsafe fn text<'a>(string: *const c_char) -> Result<&'a str> {
+    let bytes = bytes(string).ok_or_else(|| LangwitchError::InvalidArgument("expected a string, got null".to_string()))?;
+    std::str::from_utf8(bytes).map_err(|e| LangwitchError::InvalidArgument(format!("not UTF-8: {}", e)))
+}
+
+fn owned_string(string: String) -> Result<*mut c_char> {
+    let string = CString::new(string).map_err(|e| LangwitchError::InvalidArgument(e.to_string()))?;
+    Ok(string.into_raw())
+}
+
+unsafe fn deck<'a>(deck: *mut LangwitchDeck) -> Result<&'a mut LangwitchDeck> {
+    deck.as_mut().ok_or_else(|| LangwitchError::InvalidArgument("expected a deck, got null".to_string()))
+}
+
+/// Loads a deck from the contents of a gems.json, with the contents of a progress.json and a config.json, either of which may be null. Returns null if any of them won't parse.
+///
+/// # Safety
+///
+/// Every non-null argument must be a nul-terminated string that stays valid for the call.
+#[no_mangle]
+pub unsafe extern "C" fn langwitch_deck_create(gems_json: *const c_char, progress_json: *const c_char, config_json: *const c_char) -> *mut LangwitchDeck {
+    guarded(|| {
+        let gems = bytes(gems_json).ok_or_else(|| LangwitchError::InvalidArgument("expected the deck's gems, got null".to_string()))?;
+        let deck = EmbeddedDeck::from_json(gems, bytes(progress_json), bytes(config_json))?;
+        Ok(Box::into_raw(Box::new(LangwitchDeck { deck })))
+    })
+}
+
+/// Frees a deck. Null is ignored.
+///
+/// # Safety
+///
+/// `deck` must have come from langwitch_deck_create and not have been destroyed already.
+#[no_mangle]
+pub unsafe extern "C" fn langwitch_deck_destroy(deck: *mut LangwitchDeck) {
+    if !deck.is_null() {
+        drop(Box::from_raw(deck));
+    }
+}
+
+/// The card to study next as JSON, or the string "null" once nothing is left. The same card comes back until it's graded.
+///
+/// # Safety
+///
+/// `deck` must be a live deck from langwitch_deck_create.
+#[no_mangle]
+pub unsafe extern "C" fn langwitch_next_gem_json(deck: *mut LangwitchDeck) -> *mut c_char {
+    guarded(|| owned_string(self::deck(deck)?.deck.next_gem_json(SystemTime::now())?))
+}
+
+/// Grades the card being studied from JSON like {"gem": 3, "grades": {"cat": 1.0, "sat": 0.0}}, with "grade" for every facet it doesn't list and "hints" for how many hints each facet needed. Returns what the grading did, as JSON.
+///
+/// # Safety
+///
+/// `deck` must be a live deck from langwitch_deck_create, and `request_json` a nul-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn langwitch_grade_json(deck: *mut LangwitchDeck, request_json: *const c_char) -> *mut c_char {
+    guarded(|| owned_string(self::deck(deck)?.deck.grade_json(text(request_json)?, SystemTime::now())?))
+}
+
+/// The progress so far as the contents of a progress.json, for the app to save and pass to langwitch_deck_create next time.
+///
+/// # Safety
+///
+/// `deck` must be a live deck from langwitch_deck_create.
+#[no_mangle]
+pub unsafe extern "C" fn langwitch_progress_json(deck: *mut LangwitchDeck) -> *mut c_char {
+    guarded(|| {
+        let progress = self::deck(deck)?.deck.progress_json()?;
+        owned_string(String::from_utf8(progress).map_err(|e| LangwitchError::InvalidArgument(e.to_string()))?)
+    })
+}
+
+/// Frees a string the library returned. Null is ignored.
+///
+/// # Safety
+///
+/// `string` must have come from this library and not have been freed already.
+#[no_mangle]
+pub unsafe extern "C" fn langwitch_string_free(string: *mut c_char) {
+    if !string.is_null() {
+        drop(CString::from_raw(string));
+    }
+}
+
+/// Why the last call on this thread returned null, or null if nothing has failed yet. The string belongs to the library and stays valid until the next failure on this thread.
+#[no_mangle]
+pub extern "C" fn langwitch_last_error() -> *const c_char {
+    LAST_ERROR.with(|last_error| last_error.borrow().as_ref().map_or(ptr::null(), |message| message.as_ptr()))
+}
@@ -0,0 +1,66 @@
+//The review engine as a native Node module, so an Electron or Node front end can call it in-process instead of spawning `langwitch` for every card. Build it with `cargo build -p langwitch-node --release` and load the library as a .node file (napi-rs's CLI does the renaming and packaging).
+//Cards and grades are plain JavaScript objects shaped like the JSON `langwitch serve` uses (see langwitch::embed).
+
+use std::{fs, time::SystemTime};
+
+use napi::{Error, Result};
+use napi_derive::napi;
+use serde_json::Value;
+
+use langwitch::{embed::EmbeddedDeck, LangwitchError};
+
+fn js_error(e: impl std::fmt::Display) -> Error {
+    Error::from_reason(e.to_string())
+}
+
+fn read(path: &str) -> Result<Vec<u8>> {
+    fs::read(path).map_err(|e| js_error(LangwitchError::Io(e)))
+}
+
+fn to_value(json: String) -> Result<Value> {
+    serde_json::from_str(&json).map_err(js_error)
+}
+
+/// A deck being studied.
+#[napi]
+pub struct Collection {
+    deck: EmbeddedDeck,
+}
+
+#[napi]
+impl Collection {
+    /// Loads a deck from the contents of a gems.json, with the contents of a progress.json and a config.json if there are any.
+    #[napi(factory)]
+    pub fn from_json(gems: String, progress: Option<String>, config: Option<String>) -> Result<Collection> {
+        let deck = EmbeddedDeck::from_json(gems.as_bytes(), progress.as_deref().map(str::as_bytes), config.as_deref().map(str::as_bytes)).map_err(js_error)?;
+        Ok(Collection { deck })
+    }
+
+    /// Loads a deck from a gems.json on disk, with a progress.json and a config.json if they're given.
+    #[napi(factory)]
+    pub fn open(gems_path: String, progress_path: Option<String>, config_path: Option<String>) -> Result<Collection> {
+        let gems = read(&gems_path)?;
+        let progress = progress_path.map(|path| read(&path)).transpose()?;
+        let config = config_path.map(|path| read(&path)).transpose()?;
+        let deck = EmbeddedDeck::from_json(&gems, progress.as_deref(), config.as_deref()).map_err(js_error)?;
+        Ok(Collection { deck })
+    }
+
+    /// The card to study next, or null once nothing is left. The same card comes back until it's graded.
+    #[napi]
+    pub fn next_gem(&mut self) -> Result<Value> {
+        to_value(self.deck.next_gem_json(SystemTime::now()).map_err(js_error)?)
+    }
+
+    /// Grades the card being studied, e.g. {gem: 3, grades: {cat: 1.0, sat: 0.0}}, with `grade` for every facet it doesn't list and `hints` for how many hints each facet needed. Returns what the grading did.
+    #[napi]
+    pub fn grade(&mut self, request: Value) -> Result<Value> {
+        to_value(self.deck.grade_json(&request.to_string(), SystemTime::now()).map_err(js_error)?)
+    }
+
+    /// The progress so far, as the contents of a progress.json to save and hand back to fromJson or open next time.
+    #[napi]
+    pub fn progress_json(&self) -> Result<String> {
+        String::from_utf8(self.deck.progress_json().map_err(js_error)?).map_err(js_error)
+    }
+}
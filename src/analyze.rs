@@ -0,0 +1,235 @@
+//How readable is a piece of text for this learner right now? Every word is run through the same normalization as the collection's facets and checked against known_facets, which gives the share of running words that are known (the usual comprehensibility measure, where ~98% is comfortable reading), the unknown words worth learning first, and how many sentences are already readable or i+1 (exactly one unknown word).
+
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde::{Serialize, Deserialize};
+
+use crate::{
+    collection::GemCollection,
+    error::{LangwitchError, Result},
+    mine::split_sentences,
+    tokenize::tokenize_words,
+};
+#[cfg(feature = "native")]
+use crate::import::epub::read_chapters;
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Default)]
+pub struct CoverageReport {
+    /// Running words in the text, counting repeats.
+    pub tokens: usize,
+    pub known_tokens: usize,
+    /// `known_tokens / tokens`, from 0.0 to 1.0. A text with no words counts as fully covered.
+    pub coverage: f64,
+    /// Every unknown word and how many times it appears, most frequent first.
+    pub unknown_words: Vec<(String, usize)>,
+    pub sentences: usize,
+    /// Sentences with no unknown words at all.
+    pub known_sentences: usize,
+    /// Sentences with exactly one unknown word, the ones that teach best.
+    pub i_plus_one_sentences: usize,
+}
+
+/// How comprehensible a deck's gems are: how many of them have at least each share of their facets known.
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Default)]
+pub struct DeckCoverage {
+    pub gems: usize,
+    pub known_facets: usize,
+    pub unknown_facets: usize,
+    /// The average share of a gem's facets that are known.
+    pub mean_coverage: f64,
+    /// Cumulative, from the top: `{"min_coverage": 0.9, "gems": 120}` means 120 gems have at least 90% of their facets known.
+    pub buckets: Vec<CoverageBucket>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct CoverageBucket {
+    pub min_coverage: f64,
+    pub gems: usize,
+}
+
+/// Where one word of a frequency list stands against the deck.
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum ListEntryStatus {
+    /// Already known, so nothing needs teaching.
+    Known,
+    /// The ordering introduces it on its own: some gem has it as the only unknown facet by the time it comes up.
+    IPlusOne,
+    /// The deck has it, but it only ever turns up next to other unknown facets, so it gets taught in a clump.
+    WithOthers,
+    /// No gem in the deck has it, so it would never be taught.
+    NotInDeck,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct ListEntry {
+    /// The word as it appears in the deck, after normalization.
+    pub word: String,
+    pub status: ListEntryStatus,
+    /// Which ordering step (from 1) introduces it, for the words the deck teaches.
+    pub step: Option<usize>,
+}
+
+/// How much of a frequency list the deck covers, entry by entry in the list's own order. See [`GemCollection::frequency_list_coverage`].
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Default)]
+pub struct FrequencyListReport {
+    pub entries: Vec<ListEntry>,
+    pub known: usize,
+    pub i_plus_one: usize,
+    pub with_others: usize,
+    pub not_in_deck: usize,
+}
+
+/// The thresholds [`GemCollection::deck_coverage`] reports on.
+pub const COVERAGE_THRESHOLDS: [f64; 6] = [1.0, 0.95, 0.9, 0.8, 0.5, 0.0];
+
+impl GemCollection {
+    /// Measures how comprehensible every gem is with what's known. Known facets are only stripped from gems when the collection is indexed, and this needs to see them, so call it before indexing.
+    pub fn deck_coverage(&self) -> DeckCoverage {
+        let mut counts = [0; COVERAGE_THRESHOLDS.len()];
+        let mut total_coverage = 0.0;
+        for gem in self.gems.iter() {
+            let known = gem.unknown_facets.iter().filter(|facet| self.known_facets.contains(facet)).count();
+            let coverage = if gem.unknown_facets.is_empty() { 1.0 } else { known as f64 / gem.unknown_facets.len() as f64 };
+            total_coverage += coverage;
+            for (count, threshold) in counts.iter_mut().zip(COVERAGE_THRESHOLDS.iter()) {
+                if coverage >= *threshold {
+                    *count += 1;
+                }
+            }
+        }
+        let facets_in_gems: HashSet<_> = self.gems.iter().flat_map(|gem| gem.unknown_facets.iter()).collect();
+        let known_facets = facets_in_gems.iter().filter(|facet| self.known_facets.contains(facet)).count();
+        DeckCoverage {
+            gems: self.gems.len(),
+            known_facets,
+            unknown_facets: facets_in_gems.len() - known_facets,
+            mean_coverage: if self.gems.is_empty() { 1.0 } else { total_coverage / self.gems.len() as f64 },
+            buckets: COVERAGE_THRESHOLDS
+                .iter()
+                .zip(counts.iter())
+                .map(|(min_coverage, gems)| CoverageBucket { min_coverage: *min_coverage, gems: *gems })
+                .collect(),
+        }
+    }
+
+    /// Measures how much of `text` the learner can already read, given `known_facets`.
+    pub fn analyze_text(&self, text: &str) -> Result<CoverageReport> {
+        let mut report = CoverageReport::default();
+        let mut unknown_counts: HashMap<String, usize> = HashMap::new();
+        for sentence in split_sentences(text) {
+            let words = self.normalize_facet_names(&tokenize_words(&sentence))?;
+            if words.is_empty() {
+                continue;
+            }
+            report.sentences += 1;
+            let mut unknown_in_sentence: Vec<&String> = Vec::new();
+            for word in words.iter() {
+                report.tokens += 1;
+                let known = self.interner.get(word).is_some_and(|facet| self.known_facets.contains(&facet));
+                if known {
+                    report.known_tokens += 1;
+                } else {
+                    *unknown_counts.entry(word.clone()).or_insert(0) += 1;
+                    if !unknown_in_sentence.contains(&word) {
+                        unknown_in_sentence.push(word);
+                    }
+                }
+            }
+            match unknown_in_sentence.len() {
+                0 => report.known_sentences += 1,
+                1 => report.i_plus_one_sentences += 1,
+                _ => {}
+            }
+        }
+        report.coverage = if report.tokens == 0 {
+            1.0
+        } else {
+            report.known_tokens as f64 / report.tokens as f64
+        };
+        let mut unknown_words: Vec<(String, usize)> = unknown_counts.into_iter().collect();
+        unknown_words.sort_by(|(word_a, count_a), (word_b, count_b)| count_b.cmp(count_a).then(word_a.cmp(word_b)));
+        report.unknown_words = unknown_words;
+        Ok(report)
+    }
+}
+
+impl GemCollection {
+    /// Checks every word of a frequency list against the deck: whether it's known already, taught i+1, only taught alongside other new facets, or missing altogether, which says whether the deck needs more material before starting.
+    /// The ordering runs to the end on a copy of the collection, so this collection is left as it was. Repeated words (after normalization) are only reported once, at their first position.
+    pub fn frequency_list_coverage(&self, words: &[String]) -> Result<FrequencyListReport> {
+        let words = self.normalize_facet_names(words)?;
+        let mut ordering = self.clone();
+        ordering.index_all_gems_by_number();
+        //The step each facet first comes up at, and whether it came up alone.
+        let mut introduced: HashMap<String, (usize, bool)> = HashMap::new();
+        let mut step = 0;
+        loop {
+            let facets = match ordering.step() {
+                Ok(facets) => facets,
+                Err(LangwitchError::EmptyCollection) => break,
+                Err(e) => return Err(e),
+            };
+            step += 1;
+            let alone = facets.len() == 1;
+            for facet in facets {
+                introduced.entry(facet).or_insert((step, alone));
+            }
+        }
+        let mut report = FrequencyListReport::default();
+        let mut seen = HashSet::new();
+        for word in words {
+            if !seen.insert(word.clone()) {
+                continue;
+            }
+            let known = self.interner.get(&word).is_some_and(|facet| self.known_facets.contains(&facet));
+            let (status, step) = match introduced.get(&word) {
+                _ if known => (ListEntryStatus::Known, None),
+                Some((step, true)) => (ListEntryStatus::IPlusOne, Some(*step)),
+                Some((step, false)) => (ListEntryStatus::WithOthers, Some(*step)),
+                None => (ListEntryStatus::NotInDeck, None),
+            };
+            match status {
+                ListEntryStatus::Known => report.known += 1,
+                ListEntryStatus::IPlusOne => report.i_plus_one += 1,
+                ListEntryStatus::WithOthers => report.with_others += 1,
+                ListEntryStatus::NotInDeck => report.not_in_deck += 1,
+            }
+            report.entries.push(ListEntry { word, status, step });
+        }
+        Ok(report)
+    }
+}
+
+//The plain text of a .txt or .epub file, or None for anything else.
+fn readable_file_text(path: &Path) -> Result<Option<String>> {
+    let extension = path.extension().map(|extension| extension.to_string_lossy().to_lowercase());
+    match extension.as_deref() {
+        Some("txt") => Ok(Some(fs::read_to_string(path)?)),
+        #[cfg(feature = "native")]
+        Some("epub") => {
+            let chapters = read_chapters(path)?;
+            Ok(Some(chapters.into_iter().map(|chapter| chapter.text).collect::<Vec<String>>().join("\n\n")))
+        }
+        _ => Ok(None),
+    }
+}
+
+impl GemCollection {
+    /// Scores every .txt and .epub file directly inside `dir` with [`GemCollection::analyze_text`] and returns them most comprehensible first, which is a good guess at what to read next.
+    pub fn rank_texts<P: AsRef<Path>>(&self, dir: P) -> Result<Vec<(PathBuf, CoverageReport)>> {
+        let mut ranked = Vec::new();
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if let Some(text) = readable_file_text(&path)? {
+                ranked.push((path, self.analyze_text(&text)?));
+            }
+        }
+        ranked.sort_by(|(path_a, report_a), (path_b, report_b)| report_b.coverage.total_cmp(&report_a.coverage).then(path_a.cmp(path_b)));
+        Ok(ranked)
+    }
+}
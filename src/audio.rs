@@ -0,0 +1,114 @@
+//Audio on cards. A side with the audio role names a file (either plainly or as Anki's `[sound:file.mp3]`, which is how imported decks have it), looked up in the deck's media directory. Playback goes through an external player, so no audio stack has to be linked in and any format the player knows works.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::{Child, Command, Stdio},
+};
+
+use serde::{Serialize, Deserialize};
+
+use crate::{
+    collection::GemCollection,
+    error::{LangwitchError, Result},
+    gem::GemId,
+    normalize::ExternalCommand,
+    side::SideRole,
+    tts::TtsHook,
+};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct AudioOptions {
+    /// Where the deck's audio files are kept. Relative names on audio sides are looked up here; None looks them up in the working directory.
+    pub media_dir: Option<PathBuf>,
+    /// The player to run. The file's path goes after `args`.
+    pub player: ExternalCommand,
+    /// Plays a card's audio as soon as it's shown.
+    pub autoplay: bool,
+    /// Synthesizes speech for gems without a recording. See [`crate::tts`].
+    pub tts: Option<TtsHook>,
+    /// What kind of file the speech hook writes, as an extension.
+    pub tts_extension: String,
+}
+
+impl Default for AudioOptions {
+    fn default() -> Self {
+        AudioOptions {
+            media_dir: None,
+            player: ExternalCommand {
+                program: "ffplay".to_string(),
+                args: ["-nodisp", "-autoexit", "-loglevel", "quiet"].into_iter().map(String::from).collect(),
+            },
+            autoplay: true,
+            tts: None,
+            tts_extension: "wav".to_string(),
+        }
+    }
+}
+
+/// The file an audio side refers to, or None if the side is empty.
+pub fn audio_reference(side: &str) -> Option<&str> {
+    let side = side.trim();
+    let reference = match side.find("[sound:") {
+        Some(start) => {
+            let name = &side[start + "[sound:".len()..];
+            name.split(']').next().unwrap_or(name)
+        }
+        None => side,
+    };
+    Some(reference.trim()).filter(|reference| !reference.is_empty())
+}
+
+impl AudioOptions {
+    /// Where `reference` (a name from an audio side) is on disk.
+    pub fn resolve(&self, reference: &str) -> PathBuf {
+        match &self.media_dir {
+            Some(media_dir) => media_dir.join(reference),
+            None => PathBuf::from(reference),
+        }
+    }
+
+    /// Starts playing `path` and returns straight away. The player's process is handed back so it can be stopped when the card changes.
+    pub fn play(&self, path: &Path) -> Result<Child> {
+        Ok(Command::new(&self.player.program)
+            .args(&self.player.args)
+            .arg(path)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()?)
+    }
+}
+
+impl GemCollection {
+    /// The audio file for gem `gem_id`, if it has an audio side naming a file that exists.
+    pub fn audio_path(&self, gem_id: GemId, options: &AudioOptions) -> Option<PathBuf> {
+        let path = options.resolve(audio_reference(self.side(gem_id, SideRole::Audio)?)?);
+        path.is_file().then_some(path)
+    }
+
+    /// Copies `file` into the media directory and points gem `gem_id`'s audio side at the copy, returning where it went. The copy is named after the gem's key, so attaching audio again replaces it.
+    /// A gem without an id is given its key as one first, so changing its sides doesn't change what journals and progress know it by.
+    pub fn attach_audio(&mut self, gem_id: GemId, file: &Path, options: &AudioOptions) -> Result<PathBuf> {
+        let side_number = self.side_roles
+            .side(SideRole::Audio)
+            .ok_or_else(|| LangwitchError::Audio("no side of this deck has the audio role".to_string()))?;
+        let gem = self.gems.get_mut(gem_id.0).ok_or(LangwitchError::MissingGem(gem_id.0))?;
+        let mut name = gem.key.0.clone();
+        if let Some(extension) = file.extension() {
+            name.push('.');
+            name.push_str(&extension.to_string_lossy());
+        }
+        let destination = options.resolve(&name);
+        if let Some(parent) = destination.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::copy(file, &destination)?;
+        if gem.id.is_none() {
+            gem.id = Some(gem.key.0.clone());
+        }
+        gem.sides.insert(side_number, name);
+        Ok(destination)
+    }
+}
@@ -0,0 +1,124 @@
+//Autosaving an interactive review session. Every grade still goes into the journal straight away, which is what makes it safe; progress and a checkpoint of the session (which gems are queued up to be shown next, which were just shown) are only written every so many reviews or seconds. A checkpoint left behind at startup means the last session never finished: the journaled grades newer than anything in the saved progress are applied again, so none are lost, and the session can pick up where it was.
+
+use std::{
+    collections::HashSet,
+    fs,
+    io::ErrorKind,
+    path::Path,
+    time::{Duration, Instant, SystemTime},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    collection::GemCollection,
+    error::Result,
+    gem::GemKey,
+    journal::{sort_chronologically, ReviewEvent},
+    storage::atomic::write_atomically,
+    timestamp::to_millis,
+};
+
+pub const DEFAULT_AUTOSAVE_REVIEWS: usize = 5;
+pub const DEFAULT_AUTOSAVE_SECONDS: u64 = 60;
+
+/// How often `langwitch review` saves progress and its session checkpoint, e.g. {"every_reviews": 5, "every_seconds": 60}: whichever comes first. 0 turns that trigger off, and turning both off saves after every review.
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Copy)]
+#[serde(default)]
+pub struct AutosaveOptions {
+    pub every_reviews: usize,
+    pub every_seconds: u64,
+}
+
+impl Default for AutosaveOptions {
+    fn default() -> Self {
+        AutosaveOptions { every_reviews: DEFAULT_AUTOSAVE_REVIEWS, every_seconds: DEFAULT_AUTOSAVE_SECONDS }
+    }
+}
+
+/// Counts reviews and time since the last save.
+pub struct Autosave {
+    options: AutosaveOptions,
+    unsaved: usize,
+    last_save: Instant,
+}
+
+impl Autosave {
+    pub fn new(options: AutosaveOptions) -> Autosave {
+        Autosave { options, unsaved: 0, last_save: Instant::now() }
+    }
+
+    /// Counts one review, returning true if it's time to save. The caller is expected to save when it is.
+    pub fn review(&mut self) -> bool {
+        self.unsaved += 1;
+        let AutosaveOptions { every_reviews, every_seconds } = self.options;
+        let due = (every_reviews == 0 && every_seconds == 0)
+            || (every_reviews > 0 && self.unsaved >= every_reviews)
+            || (every_seconds > 0 && self.last_save.elapsed() >= Duration::from_secs(every_seconds));
+        if due {
+            self.unsaved = 0;
+            self.last_save = Instant::now();
+        }
+        due
+    }
+}
+
+/// A queued-up card in a [`SessionCheckpoint`]. Gems are kept by key, so a checkpoint still makes sense if the deck was reordered.
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct CheckpointCard {
+    pub gem: GemKey,
+    pub facets: Vec<String>,
+}
+
+/// Where a review session had got to when it was last autosaved.
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct SessionCheckpoint {
+    #[serde(with = "crate::timestamp::unix_millis")]
+    pub saved_at: SystemTime,
+    /// Cards reviewed so far in the session.
+    pub reviewed: usize,
+    /// New gems that grading unlocked and that hadn't been shown yet.
+    pub new_cards: Vec<CheckpointCard>,
+    /// The gems shown last, which aren't shown again straight away.
+    pub recent: Vec<GemKey>,
+}
+
+impl SessionCheckpoint {
+    /// The checkpoint at `path`, or None if the last session finished properly.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Option<SessionCheckpoint>> {
+        match fs::read_to_string(path) {
+            Ok(contents) => Ok(Some(serde_json::from_str(&contents)?)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Writes the checkpoint, replacing the old one only once the new one is safely on disk (see [`crate::storage::atomic`]).
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        write_atomically(path, serde_json::to_string(self)?.as_bytes())
+    }
+
+    /// Removes the checkpoint once a session has finished and saved everything.
+    pub fn clear<P: AsRef<Path>>(path: P) -> Result<()> {
+        match fs::remove_file(path) {
+            Err(e) if e.kind() != ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl GemCollection {
+    /// Applies the journaled reviews that never made it into the saved progress, as grading them did, and returns how many there were.
+    /// Every scheduler stamps a reviewed facet with the review's time, so the unsaved reviews are the ones later than the latest stamp in the progress (to the millisecond, as the journal keeps time). Reviews that did get saved are left alone, however many commands have added to the journal since.
+    pub fn recover_reviews(&mut self, events: &[ReviewEvent]) -> Result<usize> {
+        let saved_up_to = self.facet_states.values().filter_map(|state| state.last_seen_date).map(to_millis).max();
+        let mut unsaved: Vec<ReviewEvent> = events.iter().filter(|event| saved_up_to.is_none_or(|saved_up_to| to_millis(event.timestamp) > saved_up_to)).cloned().collect();
+        sort_chronologically(&mut unsaved);
+        let mut newly_known = HashSet::new();
+        for event in unsaved.iter() {
+            newly_known.extend(self.apply_review(event)?);
+        }
+        self.mark_facets_known(&newly_known);
+        Ok(unsaved.len())
+    }
+}
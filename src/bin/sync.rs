@@ -0,0 +1,153 @@
+//`langwitch-sync [--listen <ADDRESS>] [--dir <DIR>]`: a small self-hostable server that keeps one learner's progress in step across devices, for `langwitch push` and `langwitch pull` to talk to. It keeps a copy of the review journal and the known-facet store in DIR (sync/ by default) and knows nothing about decks. See langwitch::sync for what goes over the wire.
+//It listens on 127.0.0.1:8765 unless told otherwise; pass --listen 0.0.0.0:8765 for a phone on the same network to reach it. If LANGWITCH_SYNC_TOKEN is set, every request has to carry it as a bearer token.
+//DIR is written in plaintext whether or not the devices encrypt their own files, so keep it on an encrypted disk.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use axum::{
+    extract::State,
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::get,
+    Json, Router,
+};
+
+use langwitch::{
+    knowledge::KnowledgeStore,
+    sync::{events_from_lines, events_to_lines, merge_events, SyncAdded},
+    Journal, LangwitchError, ReviewEvent,
+};
+
+const USAGE: &str = "usage: langwitch-sync [--listen <ADDRESS>] [--dir <DIR>]";
+
+struct SyncState {
+    journal: Journal,
+    //Every event in the journal, to tell which incoming ones are new.
+    events: Vec<ReviewEvent>,
+    knowledge: KnowledgeStore,
+    knowledge_path: PathBuf,
+}
+
+struct App {
+    state: Mutex<SyncState>,
+    token: Option<String>,
+}
+
+type Shared = Arc<App>;
+
+struct ApiError(StatusCode, String);
+
+impl From<LangwitchError> for ApiError {
+    fn from(e: LangwitchError) -> Self {
+        ApiError(StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.0, Json(serde_json::json!({ "error": self.1 }))).into_response()
+    }
+}
+
+impl App {
+    //The state, once the request has shown the token (if there is one).
+    fn authorize(&self, headers: &HeaderMap) -> Result<MutexGuard<'_, SyncState>, ApiError> {
+        if let Some(token) = &self.token {
+            let sent = headers.get(header::AUTHORIZATION).and_then(|value| value.to_str().ok()).and_then(|value| value.strip_prefix("Bearer "));
+            if sent != Some(token.as_str()) {
+                return Err(ApiError(StatusCode::UNAUTHORIZED, "missing or wrong sync token".to_string()));
+            }
+        }
+        Ok(self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner()))
+    }
+}
+
+async fn get_journal(State(app): State<Shared>, headers: HeaderMap) -> Result<Response, ApiError> {
+    let state = app.authorize(&headers)?;
+    let lines = events_to_lines(&state.events)?;
+    Ok(([(header::CONTENT_TYPE, "application/x-ndjson")], lines).into_response())
+}
+
+async fn post_journal(State(app): State<Shared>, headers: HeaderMap, body: String) -> Result<Json<SyncAdded>, ApiError> {
+    let mut state = app.authorize(&headers)?;
+    let incoming = events_from_lines(&body).map_err(|e| ApiError(StatusCode::BAD_REQUEST, e.to_string()))?;
+    //The server's copy of a review wins a conflict; the device hears how many there were.
+    let merge = merge_events(&state.events, incoming);
+    for event in merge.new_events.iter() {
+        state.journal.append(event)?;
+    }
+    let (added, conflicts) = (merge.new_events.len(), merge.conflicts.len());
+    state.events.extend(merge.new_events);
+    Ok(Json(SyncAdded { added, conflicts }))
+}
+
+async fn get_knowledge(State(app): State<Shared>, headers: HeaderMap) -> Result<Json<KnowledgeStore>, ApiError> {
+    Ok(Json(app.authorize(&headers)?.knowledge.clone()))
+}
+
+async fn post_knowledge(State(app): State<Shared>, headers: HeaderMap, body: String) -> Result<Json<SyncAdded>, ApiError> {
+    let mut state = app.authorize(&headers)?;
+    let incoming: KnowledgeStore = serde_json::from_str(&body).map_err(|e| ApiError(StatusCode::BAD_REQUEST, e.to_string()))?;
+    let added = state.knowledge.merge(&incoming);
+    if added > 0 {
+        state.knowledge.save(&state.knowledge_path)?;
+    }
+    Ok(Json(SyncAdded { added, conflicts: 0 }))
+}
+
+fn load(dir: &Path) -> langwitch::Result<SyncState> {
+    fs::create_dir_all(dir)?;
+    let journal_path = dir.join("journal.ndjson");
+    let knowledge_path = dir.join("knowledge.json");
+    //Opening first drops a line a crash cut short, so reading afterwards sees only whole events.
+    let journal = Journal::open(&journal_path)?;
+    let events = Journal::read_events(&journal_path)?;
+    let knowledge = KnowledgeStore::load(&knowledge_path)?;
+    Ok(SyncState { journal, events, knowledge, knowledge_path })
+}
+
+async fn run(address: &str, dir: &str) -> langwitch::Result<()> {
+    let state = load(Path::new(dir))?;
+    println!("Syncing {} reviews from {}", state.events.len(), dir);
+    let token = std::env::var("LANGWITCH_SYNC_TOKEN").ok().filter(|token| !token.is_empty());
+    if token.is_none() {
+        println!("LANGWITCH_SYNC_TOKEN isn't set, so anyone who can reach the server can sync with it");
+    }
+    let app = Router::new()
+        .route("/journal", get(get_journal).post(post_journal))
+        .route("/knowledge", get(get_knowledge).post(post_knowledge))
+        .with_state(Arc::new(App { state: Mutex::new(state), token }));
+    let listener = tokio::net::TcpListener::bind(address).await?;
+    println!("Serving sync on http://{}", listener.local_addr()?);
+    axum::serve(listener, app)
+        .with_graceful_shutdown(async {
+            let _ = tokio::signal::ctrl_c().await;
+        })
+        .await?;
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let (mut address, mut dir) = ("127.0.0.1:8765", "sync");
+    let mut rest = args.iter().map(String::as_str);
+    while let Some(flag) = rest.next() {
+        match (flag, rest.next()) {
+            ("--listen", Some(value)) => address = value,
+            ("--dir", Some(value)) => dir = value,
+            _ => {
+                eprintln!("{}", USAGE);
+                std::process::exit(2);
+            }
+        }
+    }
+    if let Err(e) = run(address, dir).await {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,104 @@
+//Cloze deletions: a gem's sentence with the facet being learned blanked out, so a review can quiz the facet in context instead of showing the answer along with the question.
+//Words are compared after the collection's normalization, so with a lemma table the facet "run" blanks "running" too. Without one, a word that starts with the facet and only adds a short ending counts as well, which catches most regular inflections in languages that inflect at the end.
+
+use serde::{Serialize, Deserialize};
+
+use crate::{
+    collection::GemCollection,
+    error::Result,
+    gem::GemId,
+    ruby::strip_ruby,
+    tokenize::{tokenize_words, word_spans},
+};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct ClozeOptions {
+    /// Which side of the gem gets blanked.
+    pub side: usize,
+    pub placeholder: String,
+    /// Keeps the first letter of each blanked word, as a hint.
+    pub hint_first_letter: bool,
+    /// Longest ending a word can add to the facet and still count as the facet. 0 turns suffix matching off.
+    pub max_suffix_len: usize,
+}
+
+impl Default for ClozeOptions {
+    fn default() -> Self {
+        ClozeOptions {
+            side: 0,
+            placeholder: "____".to_string(),
+            hint_first_letter: false,
+            max_suffix_len: 4,
+        }
+    }
+}
+
+//Whether a (normalized) word of the sentence counts as a (normalized) word of the facet.
+fn matches(word: &str, facet_word: &str, options: &ClozeOptions) -> bool {
+    if word == facet_word {
+        return true;
+    }
+    //Short facets would match far too much as prefixes ("a" in "about").
+    options.max_suffix_len > 0
+        && facet_word.chars().count() >= 3
+        && word.starts_with(facet_word)
+        && word.chars().count() - facet_word.chars().count() <= options.max_suffix_len
+}
+
+impl GemCollection {
+    /// Blanks every occurrence of each of `facets` in `text`, whole words only. Multi-word facets are blanked as one stretch. Returns None if none of them occur.
+    pub fn cloze_text(&self, text: &str, facets: &[String], options: &ClozeOptions) -> Result<Option<String>> {
+        let spans = word_spans(text);
+        let words: Vec<String> = spans.iter().map(|(_, word)| word.clone()).collect();
+        let words = self.normalize_facet_names(&words)?;
+        //Marks which words get blanked, numbering each match so the words of one multi-word match become a single blank.
+        let mut blanked: Vec<Option<usize>> = vec![None; words.len()];
+        let mut match_number = 0;
+        for facet in facets.iter() {
+            let facet_words = self.normalize_facet_names(&tokenize_words(facet))?;
+            if facet_words.is_empty() || facet_words.len() > words.len() {
+                continue;
+            }
+            for start in 0..=(words.len() - facet_words.len()) {
+                let window = &words[start..start + facet_words.len()];
+                if window.iter().zip(facet_words.iter()).all(|(word, facet_word)| matches(word, facet_word, options)) {
+                    blanked[start..start + facet_words.len()].iter_mut().for_each(|blank| *blank = Some(match_number));
+                    match_number += 1;
+                }
+            }
+        }
+        if match_number == 0 {
+            return Ok(None);
+        }
+        let mut cloze = String::with_capacity(text.len());
+        let mut copied_up_to = 0;
+        for (number, (range, _)) in spans.iter().enumerate() {
+            let blank = match blanked[number] {
+                Some(blank) => blank,
+                None => continue,
+            };
+            if number > 0 && blanked[number - 1] == Some(blank) {
+                copied_up_to = range.end;
+                continue;
+            }
+            cloze.push_str(&text[copied_up_to..range.start]);
+            if options.hint_first_letter {
+                cloze.extend(text[range.clone()].chars().next());
+            }
+            cloze.push_str(&options.placeholder);
+            copied_up_to = range.end;
+        }
+        cloze.push_str(&text[copied_up_to..]);
+        Ok(Some(cloze))
+    }
+
+    /// Gem `gem_id`'s side `options.side` with `facets` blanked, or None if the gem doesn't have that side or none of the facets occur in it.
+    /// Furigana is dropped first: a reading over a blank would give the answer away.
+    pub fn cloze(&self, gem_id: GemId, facets: &[String], options: &ClozeOptions) -> Result<Option<String>> {
+        match self.get(gem_id).and_then(|gem| gem.sides.get(&options.side)) {
+            Some(text) => self.cloze_text(&strip_ruby(text), facets, options),
+            None => Ok(None),
+        }
+    }
+}
@@ -0,0 +1,574 @@
+use std::{
+    collections::{HashSet, HashMap, VecDeque},
+    io::{Read, Write},
+    path::Path,
+};
+
+use rand::{rngs::StdRng, SeedableRng};
+#[cfg(feature = "native")]
+use rayon::prelude::*;
+use serde::{Serialize, Deserialize};
+
+use crate::{
+    error::{LangwitchError, Result},
+    facet::Facet,
+    filter::FacetFilter,
+    gem::{Gem, GemId, GemKey, InternedGem},
+    interner::{FacetId, Interner},
+    knowledge::SharedKnowledge,
+    normalize::{Normalization, TextNormalization},
+    preview::{OutputFormat, DEFAULT_PREVIEW_STEPS},
+    scheduler::SchedulerKind,
+    schema::{read_deck, write_deck, DeckFile},
+    selection::{ScoringConfig, SelectionKind, SelectionStrategy},
+    side::SideRoles,
+    storage::compression::{open_reader, uncompressed_name, DeckWriter},
+    suspend::SetAside,
+};
+
+/// A gem in the curriculum [`GemCollection::order_gems`] works out.
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct OrderingStep {
+    /// The gem that becomes fully known, with nothing left in it to learn.
+    pub gem: GemId,
+    /// The facets learned since the gem before it, sorted. Empty when an earlier gem's facets were all this one needed too.
+    pub introduced: Vec<String>,
+    /// How much of the deck is fully known once this gem is, from 0.0 to 1.0.
+    pub comprehensibility: f64,
+}
+
+/// The curriculum as [`GemCollection::ordering`] works it out, one step per gem.
+pub struct Ordering<'a> {
+    gem_collection: &'a mut GemCollection,
+    selection: SelectionKind,
+    //Gems that aren't set aside, for comprehensibility.
+    gem_count: usize,
+    finished: usize,
+    //Gems finished but not handed out yet: first the ones readable from the start, then whatever the last step unlocked.
+    pending: VecDeque<(GemId, Vec<String>)>,
+    //A step can teach facets without finishing any gem; they go to whichever gem is finished next.
+    introduced: Vec<String>,
+    exhausted: bool,
+}
+
+impl Iterator for Ordering<'_> {
+    type Item = Result<OrderingStep>;
+
+    fn next(&mut self) -> Option<Result<OrderingStep>> {
+        loop {
+            if let Some((gem, introduced)) = self.pending.pop_front() {
+                self.finished += 1;
+                let comprehensibility = self.finished as f64 / self.gem_count as f64;
+                return Some(Ok(OrderingStep { gem, introduced, comprehensibility }));
+            }
+            if self.exhausted {
+                return None;
+            }
+            match self.gem_collection.step_unlocking_with(&mut self.selection) {
+                Ok((facets, unlocked)) => {
+                    self.introduced.extend(facets);
+                    for gem in unlocked {
+                        self.introduced.sort_unstable();
+                        self.pending.push_back((gem, std::mem::take(&mut self.introduced)));
+                    }
+                }
+                Err(LangwitchError::EmptyCollection) => self.exhausted = true,
+                Err(e) => {
+                    self.exhausted = true;
+                    return Some(Err(e));
+                }
+            }
+        }
+    }
+}
+
+//GemCollection: gems_by_size_index indexes gems by the number of facets they have. gems_by_facet_index indexes gems by the facets they have (e.g "physics": set of gem ids here). Gems live in one Vec in the order they were added, and both indices hold GemIds (positions in that Vec) rather than references, so the collection owns everything and can be handed around freely.
+//Facets are interned: everything in here refers to them by FacetId, and `interner` turns those back into names.
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct GemCollection {
+    pub gems: Vec<InternedGem>,
+    //Finds a gem from the stable key a journal or store refers to it by. If two gems share a key, the first one added wins.
+    pub gem_ids_by_key: HashMap<GemKey, GemId>,
+    pub interner: Interner,
+    pub known_facets: HashSet<FacetId>,
+    pub gems_by_size_index: HashMap<usize, HashSet<GemId>>,
+    pub gems_by_facet_index: HashMap<FacetId, HashSet<GemId>>,
+    pub total_frequency_list: HashMap<FacetId, usize>,
+    //Scheduling data for every facet that has been reviewed, keyed by facet name.
+    #[serde(default)]
+    pub facet_states: HashMap<String, Facet>,
+    //Which algorithm reviews go through. This is a setting rather than state, so it isn't saved with the collection.
+    #[serde(skip)]
+    pub scheduler: SchedulerKind,
+    //Which strategy the ordering uses to pick the next gem. Also a setting.
+    #[serde(skip)]
+    pub selection: SelectionKind,
+    //How many buckets above the easiest one feed the frequency weighting. 1 is the classic n+1 lookahead; 0 turns it off, leaving only the global frequencies.
+    #[serde(skip, default = "default_lookahead")]
+    pub lookahead: usize,
+    //How the lookahead-frequency strategy weighs candidate gems.
+    #[serde(skip)]
+    pub scoring: ScoringConfig,
+    //Breaks ties between equally good gems (and drives the random strategy), so the same seed always gives the same curriculum. See GemCollection::seed.
+    #[serde(skip, default = "default_rng")]
+    pub rng: StdRng,
+    //Maps graded facet names onto the collection's normalized facets. Set with GemCollection::set_normalization, which also normalizes what's already loaded.
+    #[serde(skip)]
+    pub normalization: Normalization,
+    //Case folding and the like, applied before `normalization` and also whenever facets are marked known by name.
+    #[serde(skip)]
+    pub facet_normalization: TextNormalization,
+    //Known facets shared with other decks of the same language. See GemCollection::share_knowledge.
+    #[serde(skip)]
+    pub shared_knowledge: Option<SharedKnowledge>,
+    //What each side of the gems holds. A setting, since it describes the deck file rather than the learner's progress.
+    #[serde(skip)]
+    pub side_roles: SideRoles,
+    //Suspended and buried gems, which stay out of the indices. See crate::suspend.
+    #[serde(skip)]
+    pub set_aside: HashMap<GemId, SetAside>,
+    //Set when the gems came from a version 0 deck whose scheduling hasn't been saved to progress yet. Writing the deck would lose it, so write_gems_to_file refuses to. See crate::schema.
+    #[serde(skip)]
+    pub unsaved_migration: bool,
+    //The filter filter_facets last applied, so gems added afterwards go through it too. See GemCollection::add_gems.
+    #[serde(skip)]
+    pub facet_filter: FacetFilter,
+}
+
+pub const DEFAULT_LOOKAHEAD: usize = 1;
+pub const DEFAULT_SEED: u64 = 0;
+
+fn default_lookahead() -> usize {
+    DEFAULT_LOOKAHEAD
+}
+
+fn default_rng() -> StdRng {
+    StdRng::seed_from_u64(DEFAULT_SEED)
+}
+
+impl Default for GemCollection {
+    fn default() -> Self {
+        GemCollection {
+            gems: Vec::new(),
+            gem_ids_by_key: HashMap::new(),
+            interner: Interner::default(),
+            known_facets: HashSet::new(),
+            gems_by_size_index: HashMap::new(),
+            gems_by_facet_index: HashMap::new(),
+            total_frequency_list: HashMap::new(),
+            facet_states: HashMap::new(),
+            scheduler: SchedulerKind::default(),
+            selection: SelectionKind::default(),
+            lookahead: DEFAULT_LOOKAHEAD,
+            scoring: ScoringConfig::default(),
+            rng: default_rng(),
+            normalization: Normalization::default(),
+            facet_normalization: TextNormalization::default(),
+            shared_knowledge: None,
+            side_roles: SideRoles::default(),
+            set_aside: HashMap::new(),
+            unsaved_migration: false,
+            facet_filter: FacetFilter::default(),
+        }
+    }
+}
+
+impl GemCollection {
+    /// Builds a collection from gems that are already in memory. Gems get ids in the order given.
+    pub fn from_gems(gems: Vec<Gem>) -> GemCollection {
+        let mut gem_collection = GemCollection::default();
+        for gem in gems.into_iter() {
+            gem_collection.push_gem(gem);
+        }
+        gem_collection
+    }
+
+    /// Adds a gem to the end of the collection, interning its facets, and returns its id.
+    pub fn push_gem(&mut self, gem: Gem) -> GemId {
+        let gem = InternedGem::intern(gem, &mut self.interner);
+        let gem_id = GemId(self.gems.len());
+        self.gem_ids_by_key.entry(gem.key.clone()).or_insert(gem_id);
+        self.gems.push(gem);
+        gem_id
+    }
+
+    /// The gem a stable key refers to, if it's in this deck.
+    pub fn gem_id(&self, key: &GemKey) -> Option<GemId> {
+        self.gem_ids_by_key.get(key).copied()
+    }
+
+    pub fn key(&self, gem_id: GemId) -> Option<&GemKey> {
+        self.get(gem_id).map(|gem| &gem.key)
+    }
+
+    pub fn get(&self, gem_id: GemId) -> Option<&InternedGem> {
+        self.gems.get(gem_id.0)
+    }
+
+    /// Gem `gem_id` with its facet names spelled out.
+    pub fn gem(&self, gem_id: GemId) -> Option<Gem> {
+        self.get(gem_id).map(|gem| gem.resolve(&self.interner))
+    }
+
+    /// Every gem id, in order.
+    pub fn gem_ids(&self) -> impl Iterator<Item = GemId> {
+        (0..self.gems.len()).map(GemId)
+    }
+
+    pub fn facet_name(&self, id: FacetId) -> &str {
+        self.interner.name(id)
+    }
+
+    /// The names of the facets the learner knows.
+    pub fn known_facet_names(&self) -> HashSet<String> {
+        self.interner.names(self.known_facets.iter())
+    }
+
+    /// Adds facets to `known_facets` by name, without touching the indices (see [`GemCollection::mark_facets_known`] for that).
+    pub fn insert_known_facets<'a, I: IntoIterator<Item = &'a String>>(&mut self, names: I) {
+        let ids = self.interner.intern_all(names);
+        self.known_facets.extend(ids);
+    }
+
+    //Okay, let's use serde to read in a list of gem structs represented in json in this format:
+    //[{"sides":{"0":"In mechanical engineering, the Beale number is a parameter that characterizes the performance of Stirling engines"},"unknown_facets":["mechanical engineering", "Beale number", "Stirling engines"]}...]
+    /// Loads a collection from a JSON array of gems, or from JSON Lines if the file ends in `.jsonl` or `.ndjson`. Either can be zstd-compressed (`gems.json.zst`). The collection still needs to be indexed before it can be ordered.
+    pub fn read_gems_from_file(file_path: &str) -> Result<GemCollection> {
+        Ok(GemCollection::from_deck(GemCollection::read_deck_file(file_path, false)?))
+    }
+
+    //The deck at `file_path` in any of the formats above. A lenient read leaves out gems that don't fit (see crate::schema).
+    pub(crate) fn read_deck_file(file_path: &str, lenient: bool) -> Result<DeckFile> {
+        let format_name = uncompressed_name(file_path);
+        if format_name.ends_with(".jsonl") || format_name.ends_with(".ndjson") {
+            return GemCollection::read_jsonl_deck(file_path, lenient);
+        }
+        let mut file = open_reader(file_path)?;
+        let mut contents = Vec::new();
+        file.read_to_end(&mut contents)?;
+        read_deck(&contents, lenient)
+    }
+
+    /// Writes the gems back out as a JSON array in id order, as JSON Lines if the path ends in `.jsonl`/`.ndjson`, and zstd-compressed if it ends in `.zst`.
+    /// Gems read straight from a version 0 deck can't be written until its scheduling is in progress: load the deck through [`crate::storage::json::JsonStorage`] instead, which saves it there.
+    pub fn write_gems_to_file<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        if self.unsaved_migration {
+            return Err(LangwitchError::UnsavedMigration);
+        }
+        let format_name = path.as_ref().to_string_lossy().into_owned();
+        let format_name = uncompressed_name(&format_name);
+        if format_name.ends_with(".jsonl") || format_name.ends_with(".ndjson") {
+            return self.write_gems_to_jsonl(path);
+        }
+        let gems: Vec<Gem> = self.gems.iter().map(|gem| gem.resolve(&self.interner)).collect();
+        let mut writer = DeckWriter::create(path)?;
+        write_deck(&mut writer, &gems)?;
+        writer.write_all(b"\n")?;
+        writer.finish()
+    }
+
+    /// Restarts the tie-breaking from `seed`. Two copies of the same collection seeded alike order identically.
+    pub fn seed(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+
+    /// Builds `gems_by_size_index`, `gems_by_facet_index` and `total_frequency_list` from the gems' unknown facets, first stripping any facets that are already known (here, or in the shared knowledge store if there is one). Suspended and buried gems are left out of both indices.
+    /// Big decks are indexed in parallel (with the native feature): each rayon worker builds its own shard of both indices and the shards are merged at the end.
+    pub fn index_all_gems_by_number(&mut self) {
+        self.pull_shared_knowledge();
+        if !self.known_facets.is_empty() {
+            let known_facets = &self.known_facets;
+            let strip_known = |gem: &mut InternedGem| gem.unknown_facets.retain(|facet| !known_facets.contains(facet));
+            #[cfg(feature = "native")]
+            self.gems.par_iter_mut().for_each(strip_known);
+            #[cfg(not(feature = "native"))]
+            self.gems.iter_mut().for_each(strip_known);
+        }
+        //Start from scratch, so indexing twice (or after some steps) doesn't leave stale entries behind.
+        let shard = IndexShard::build(&self.gems);
+        self.gems_by_size_index = shard.gems_by_size_index;
+        self.gems_by_facet_index = shard.gems_by_facet_index;
+        self.total_frequency_list = self.create_frequency_hashmap_from_facets_of_n2_gem_indices(&self.gem_ids().collect());
+        let set_aside: Vec<GemId> = self.set_aside.keys().copied().collect();
+        for gem_id in set_aside {
+            self.unindex_gem(gem_id);
+        }
+    }
+
+    /// Runs one step of the ordering: picks the facets of the easiest next gem, strips them from every gem that contains them and returns them.
+    /// Returns [`LangwitchError::EmptyCollection`] once every gem has been unlocked.
+    pub fn step(&mut self) -> Result<HashSet<String>> {
+        let mut selection = self.selection;
+        self.step_with(&mut selection)
+    }
+
+    /// Same as [`GemCollection::step`], picking gems with any selection strategy.
+    pub fn step_with<S: SelectionStrategy>(&mut self, strategy: &mut S) -> Result<HashSet<String>> {
+        let (top_gem_facets, _) = self.step_unlocking_with(strategy)?;
+        Ok(top_gem_facets)
+    }
+
+    /// Same as [`GemCollection::step`], also returning the ids of the gems the step fully unlocked.
+    /// Names are only looked up for the returned facets; the step itself works on ids.
+    pub fn step_unlocking(&mut self) -> Result<(HashSet<String>, Vec<GemId>)> {
+        let mut selection = self.selection;
+        self.step_unlocking_with(&mut selection)
+    }
+
+    pub fn step_unlocking_with<S: SelectionStrategy>(&mut self, strategy: &mut S) -> Result<(HashSet<String>, Vec<GemId>)> {
+        let mut rng = self.rng.clone();
+        let top_gem_facets = self.next_facets_with(strategy, &mut rng);
+        self.rng = rng;
+        let top_gem_facets = top_gem_facets?;
+        let unlocked_gem_indices = self.mark_facet_ids_known(&top_gem_facets);
+        Ok((self.interner.names(top_gem_facets.iter()), unlocked_gem_indices))
+    }
+
+    /// Picks the easiest next gem the way [`GemCollection::step`] does, but without learning anything: returns the gem and the facets it would introduce, and leaves the indices alone so the facets can be learned (or not) once they've been graded.
+    pub fn next_gem(&mut self) -> Result<(GemId, HashSet<String>)> {
+        let mut selection = self.selection;
+        let mut rng = self.rng.clone();
+        let facets = self.next_facets_with(&mut selection, &mut rng);
+        self.rng = rng;
+        let facets = facets?;
+        //The strategy hands back facets rather than a gem, so the gem is the lowest-numbered one in the bucket with exactly those facets.
+        let gem_id = self.gems_by_size_index
+            .get(&facets.len())
+            .and_then(|gem_ids| gem_ids.iter().filter(|gem_id| self.gems[gem_id.0].unknown_facets == facets).min().copied())
+            .ok_or(LangwitchError::EmptyCollection)?;
+        Ok((gem_id, self.interner.names(facets.iter())))
+    }
+
+    //Picks the facets of the easiest next gem without learning them.
+    fn next_facets_with<S: SelectionStrategy>(&self, strategy: &mut S, rng: &mut StdRng) -> Result<HashSet<FacetId>> {
+        //We get the minimum number from the keys of gems_by_size_index, and the second minimum number, filtering out any keys that point to empty hashsets
+        let mut non_empty_keys: Vec<usize> = self.gems_by_size_index
+            .iter()
+            .filter(|(_, indices)| !indices.is_empty())
+            .map(|(key, _)| *key)
+            .collect();
+        non_empty_keys.sort_unstable();
+        let min_number = non_empty_keys.first().ok_or(LangwitchError::EmptyCollection)?;
+        //We fetch all the Gem indices from gems_by_size_index for the minimum number, as HashSets, and then the gems from the next `lookahead` buckets up. When there's only one bucket left, there's nothing above it to look ahead into:
+        let gem_indices_for_n1: HashSet<GemId> = self.gems_by_size_index[min_number].clone();
+        let mut gem_indices_for_n2: HashSet<GemId> = HashSet::new();
+        for min_number_2 in non_empty_keys.iter().skip(1).take(self.lookahead) {
+            gem_indices_for_n2.extend(self.gems_by_size_index[min_number_2].iter().cloned());
+        }
+        //We create a frequency hashmap by counting how many times each facet appears in total for all the lookahead gems:
+        let frequency_hashmap = self.create_frequency_hashmap_from_facets_of_n2_gem_indices(&gem_indices_for_n2);
+        //The strategy picks which of the n_1 gems to learn from:
+        let top_gem_facets: HashSet<FacetId> = strategy.choose(self, &gem_indices_for_n1, &frequency_hashmap, rng);
+        Ok(top_gem_facets)
+    }
+
+    /// Marks `facets` as known and updates both indices incrementally, exactly as one ordering step does: every gem containing them loses them from its unknown facets and is refiled under its new size.
+    /// Returns the ids of the gems that now have no unknown facets left. Facets that don't appear in any gem are simply added to `known_facets`.
+    pub fn mark_facets_known(&mut self, facets: &HashSet<String>) -> Vec<GemId> {
+        let facets: Vec<String> = facets.iter().map(|facet| self.facet_normalization.apply(facet)).collect();
+        let facets = self.interner.intern_all(facets.iter());
+        self.mark_facet_ids_known(&facets)
+    }
+
+    /// Same as [`GemCollection::mark_facets_known`], for facets that are already interned.
+    pub fn mark_facet_ids_known(&mut self, facets: &HashSet<FacetId>) -> Vec<GemId> {
+        self.known_facets.extend(facets.iter().copied());
+        //Most of the time, there's only one facet but sometimes there are up to 7 or 8. So what we want to do now is take the facet names and get the appropriate gem indices from gems_by_facet_index.
+        //We get the indices of the gems that have the given facets:
+        let mut top_gem_indices: HashSet<GemId> = HashSet::new();
+        for facet in facets.iter() {
+            if let Some(facet_indices) = self.gems_by_facet_index.get(facet) {
+                top_gem_indices.extend(facet_indices.iter().cloned());
+            }
+        }
+        //Now all we need to do is go through self.gems and subtract the facets from each gem's unknown_facet field, since now we know them. Before that, we remove the gem's number from gems_by_size_index and refile it under however many unknown facets it has left (e.g if it's currently indexed under '3' and loses one, it goes under '2'). Gems with nothing left to learn drop out of the size index entirely.
+        let mut unlocked_gem_indices = Vec::new();
+        for gem_index in top_gem_indices.iter() {
+            let gem = match self.gems.get_mut(gem_index.0) {
+                Some(gem) => gem,
+                None => continue,
+            };
+            if let Some(indices) = self.gems_by_size_index.get_mut(&gem.unknown_facets.len()) {
+                indices.remove(gem_index);
+            }
+            gem.unknown_facets.retain(|facet| !facets.contains(facet));
+            if gem.unknown_facets.is_empty() {
+                unlocked_gem_indices.push(*gem_index);
+            } else {
+                //The index for the new size might not exist, so we need to create it if it doesn't:
+                self.gems_by_size_index
+                    .entry(gem.unknown_facets.len())
+                    .or_default()
+                    .insert(*gem_index);
+            }
+        }
+        //Now, we remove the top_gem_indices from each facet index in facets. There's no such thing as 'difference with' on a HashSet, so we retain instead:
+        for facet in facets.iter() {
+            if let Some(facet_indices) = self.gems_by_facet_index.get_mut(facet) {
+                facet_indices.retain(|&gem_index| !top_gem_indices.contains(&gem_index));
+            }
+        }
+        unlocked_gem_indices.sort_unstable();
+        unlocked_gem_indices
+    }
+
+    //Files a gem in both indices under the unknown facets it has now. A gem with none left only goes in neither.
+    pub(crate) fn index_gem(&mut self, gem_id: GemId) {
+        let gem = &self.gems[gem_id.0];
+        if !gem.unknown_facets.is_empty() {
+            self.gems_by_size_index.entry(gem.unknown_facets.len()).or_default().insert(gem_id);
+        }
+        for facet in gem.unknown_facets.iter() {
+            self.gems_by_facet_index.entry(*facet).or_default().insert(gem_id);
+        }
+    }
+
+    //Takes a gem out of both indices, leaving its facets alone.
+    pub(crate) fn unindex_gem(&mut self, gem_id: GemId) {
+        let gem = &self.gems[gem_id.0];
+        if let Some(gem_ids) = self.gems_by_size_index.get_mut(&gem.unknown_facets.len()) {
+            gem_ids.remove(&gem_id);
+        }
+        for facet in gem.unknown_facets.iter() {
+            if let Some(gem_ids) = self.gems_by_facet_index.get_mut(facet) {
+                gem_ids.remove(&gem_id);
+            }
+        }
+    }
+
+    /// Indexes the collection and runs the ordering to the end, returning gem ids in the order they become fully known.
+    /// Gems that had no unknown facets to begin with come first. This consumes the collection's unknown facets, so clone it first if you still need them.
+    pub fn difficulty_order(&mut self) -> Result<Vec<GemId>> {
+        let mut selection = self.selection;
+        self.difficulty_order_with(&mut selection)
+    }
+
+    /// Same as [`GemCollection::difficulty_order`], picking gems with any selection strategy.
+    pub fn difficulty_order_with<S: SelectionStrategy>(&mut self, strategy: &mut S) -> Result<Vec<GemId>> {
+        self.index_all_gems_by_number();
+        let mut order: Vec<GemId> = self.gem_ids()
+            .filter(|gem_id| self.gems[gem_id.0].unknown_facets.is_empty())
+            .collect();
+        //The rng is borrowed out of the collection while the strategy looks at it, and put back afterwards so later steps carry on from the same state.
+        let mut rng = self.rng.clone();
+        let ordered = loop {
+            let top_gem_facets = match self.next_facets_with(strategy, &mut rng) {
+                Ok(top_gem_facets) => top_gem_facets,
+                Err(LangwitchError::EmptyCollection) => break Ok(order),
+                Err(e) => break Err(e),
+            };
+            order.extend(self.mark_facet_ids_known(&top_gem_facets));
+        };
+        self.rng = rng;
+        ordered
+    }
+
+    /// Indexes the collection and runs the ordering to the end, returning the curriculum as data: each gem in the order it becomes fully known, with the facets learned to get there.
+    /// Gems that had no unknown facets to begin with come first, introducing nothing. Suspended and buried gems are left out. Like [`GemCollection::difficulty_order`], this consumes the collection's unknown facets.
+    pub fn order_gems(&mut self) -> Result<Vec<OrderingStep>> {
+        self.ordering().collect()
+    }
+
+    /// Indexes the collection and works out the same curriculum as [`GemCollection::order_gems`], one gem at a time, so only as much of the ordering is run as is asked for.
+    pub fn ordering(&mut self) -> Ordering<'_> {
+        self.index_all_gems_by_number();
+        let readable: VecDeque<(GemId, Vec<String>)> = self.gem_ids()
+            .filter(|gem_id| !self.is_set_aside(*gem_id) && self.gems[gem_id.0].unknown_facets.is_empty())
+            .map(|gem_id| (gem_id, Vec::new()))
+            .collect();
+        Ordering {
+            selection: self.selection,
+            gem_count: self.gem_ids().filter(|gem_id| !self.is_set_aside(*gem_id)).count(),
+            finished: 0,
+            pending: readable,
+            introduced: Vec::new(),
+            exhausted: false,
+            gem_collection: self,
+        }
+    }
+
+    /// Indexes the collection and prints the first 200 gems of the curriculum and the facets each introduces, starting from whatever is already known and stopping early if the deck runs out. See [`GemCollection::write_order_preview`] for other formats.
+    pub fn display_all_gems_in_order_of_difficulty(&mut self) -> Result<()> {
+        self.write_order_preview(std::io::stdout().lock(), OutputFormat::Text, DEFAULT_PREVIEW_STEPS)?;
+        Ok(())
+    }
+
+    pub(crate) fn create_frequency_hashmap_from_facets_of_n2_gem_indices(&self, gem_indices_for_n2: &HashSet<GemId>) -> HashMap<FacetId, usize> {
+        let count_facets = |mut frequency_hashmap: HashMap<FacetId, usize>, gem_index: &GemId| {
+            if let Some(gem) = self.get(*gem_index) {
+                for facet in gem.unknown_facets.iter() {
+                    frequency_hashmap.entry(*facet)
+                        .and_modify(|e| *e += 1)
+                        .or_insert(1);
+                }
+            }
+            frequency_hashmap
+        };
+        //Most steps only look at a handful of gems, where handing work out to threads costs more than it saves.
+        #[cfg(feature = "native")]
+        if gem_indices_for_n2.len() >= PARALLEL_THRESHOLD {
+            return gem_indices_for_n2
+                .par_iter()
+                .fold(HashMap::new, count_facets)
+                .reduce(HashMap::new, |mut left, right| {
+                    for (facet, count) in right {
+                        *left.entry(facet).or_insert(0) += count;
+                    }
+                    left
+                });
+        }
+        gem_indices_for_n2.iter().fold(HashMap::new(), count_facets)
+    }
+}
+
+//Below this many gems, indexing and counting stay on the current thread.
+#[cfg(feature = "native")]
+const PARALLEL_THRESHOLD: usize = 4096;
+
+//One worker's part of the size and facet indices.
+#[derive(Default)]
+struct IndexShard {
+    gems_by_size_index: HashMap<usize, HashSet<GemId>>,
+    gems_by_facet_index: HashMap<FacetId, HashSet<GemId>>,
+}
+
+impl IndexShard {
+    fn build(gems: &[InternedGem]) -> IndexShard {
+        #[cfg(feature = "native")]
+        if gems.len() >= PARALLEL_THRESHOLD {
+            return gems
+                .par_iter()
+                .enumerate()
+                .fold(IndexShard::default, |mut shard, (number, gem)| {
+                    shard.add(GemId(number), gem);
+                    shard
+                })
+                .reduce(IndexShard::default, IndexShard::merge);
+        }
+        let mut shard = IndexShard::default();
+        for (number, gem) in gems.iter().enumerate() {
+            shard.add(GemId(number), gem);
+        }
+        shard
+    }
+
+    fn add(&mut self, number: GemId, gem: &InternedGem) {
+        if !gem.unknown_facets.is_empty() {
+            self.gems_by_size_index.entry(gem.unknown_facets.len()).or_default().insert(number);
+        }
+        for facet in gem.unknown_facets.iter() {
+            self.gems_by_facet_index.entry(*facet).or_default().insert(number);
+        }
+    }
+
+    #[cfg(feature = "native")]
+    fn merge(mut self, other: IndexShard) -> IndexShard {
+        for (size, numbers) in other.gems_by_size_index {
+            self.gems_by_size_index.entry(size).or_default().extend(numbers);
+        }
+        for (facet, numbers) in other.gems_by_facet_index {
+            self.gems_by_facet_index.entry(facet).or_default().extend(numbers);
+        }
+        self
+    }
+}
@@ -0,0 +1,168 @@
+//Compacting the review journal. Replaying millions of reviews on every full recompute is slow, so `langwitch compact` folds the oldest ones into a snapshot of the facet state they add up to, and the journal keeps only the newest. Replaying then starts from the snapshot, which gives the same result as replaying the journal from the beginning with the same scheduler.
+//Everything in a snapshot happened at or before its `up_to`, and nothing left in the journal did, so a journal event at or before `up_to` is one the snapshot already holds. That keeps a crash between writing the snapshot and cutting the journal from counting any review twice.
+//Folded reviews are moved to an archive beside the journal rather than thrown away. A review another device made before the snapshot's `up_to` can turn up in a later pull; the archive tells it apart from one already folded in, and a new one is folded in by replaying the archive with it from the start.
+
+use std::{
+    collections::HashMap,
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    collection::GemCollection,
+    error::Result,
+    journal::{sort_chronologically, ReviewEvent},
+    progress::Progress,
+    stats::{add_review_counts, ReviewCount},
+    sync::SyncConflict,
+};
+
+/// How many of the newest reviews `langwitch compact` leaves in the journal unless told otherwise.
+pub const DEFAULT_KEEP: usize = 1000;
+
+/// The facet state the folded part of a journal adds up to.
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct JournalSnapshot {
+    /// The time of the newest review folded in.
+    #[serde(with = "crate::timestamp::unix_millis")]
+    pub up_to: SystemTime,
+    /// How many reviews have been folded in, over every compaction.
+    pub events: usize,
+    /// Known facets and scheduling as replaying the folded reviews left them.
+    pub progress: Progress,
+    /// Reviews and lapses of the folded reviews, by facet, so leeches are still counted after compacting.
+    #[serde(default)]
+    pub review_counts: HashMap<String, ReviewCount>,
+}
+
+/// What folding in reviews from other devices older than the snapshot did.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct LateFold {
+    /// The reviews folded in, oldest first.
+    pub folded: Vec<ReviewEvent>,
+    /// Reviews the archive already had a different record of. The archive's was kept.
+    pub conflicts: Vec<SyncConflict>,
+    /// Reviews that couldn't be checked against the snapshot, because it was compacted before folded reviews were archived. They were left out.
+    pub unchecked: Vec<ReviewEvent>,
+}
+
+/// What compacting did.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Compaction {
+    pub folded: usize,
+    pub kept: usize,
+}
+
+impl Default for JournalSnapshot {
+    fn default() -> Self {
+        JournalSnapshot { up_to: UNIX_EPOCH, events: 0, progress: Progress::default(), review_counts: HashMap::new() }
+    }
+}
+
+impl JournalSnapshot {
+    /// Where the snapshot for the journal at `journal_path` lives: beside it, `journal.ndjson` getting `journal.snapshot.json`.
+    pub fn path_for<P: AsRef<Path>>(journal_path: P) -> PathBuf {
+        let journal_path = journal_path.as_ref();
+        let stem = journal_path.file_stem().unwrap_or_default().to_string_lossy();
+        journal_path.with_file_name(format!("{}.snapshot.json", stem))
+    }
+
+    /// Where the reviews folded into the snapshot for the journal at `journal_path` are kept: beside it, `journal.ndjson` getting `journal.archive.ndjson`.
+    pub fn archive_path_for<P: AsRef<Path>>(journal_path: P) -> PathBuf {
+        let journal_path = journal_path.as_ref();
+        let stem = journal_path.file_stem().unwrap_or_default().to_string_lossy();
+        journal_path.with_file_name(format!("{}.archive.ndjson", stem))
+    }
+
+    /// The snapshot at `path`, or None if the journal was never compacted.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Option<JournalSnapshot>> {
+        match fs::read_to_string(path) {
+            Ok(contents) => Ok(Some(serde_json::from_str(&contents)?)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// True if `event` is already folded into the snapshot.
+    pub fn holds(&self, event: &ReviewEvent) -> bool {
+        event.timestamp <= self.up_to
+    }
+}
+
+/// Splits `events` (in any order) into the ones to fold and the newest `keep` to leave, both oldest first. Reviews at the same moment as the last one folded are folded too, so everything left is strictly newer.
+pub fn split_for_compaction(mut events: Vec<ReviewEvent>, keep: usize) -> (Vec<ReviewEvent>, Vec<ReviewEvent>) {
+    sort_chronologically(&mut events);
+    let mut boundary = events.len().saturating_sub(keep);
+    if boundary > 0 {
+        let up_to = events[boundary - 1].timestamp;
+        while boundary < events.len() && events[boundary].timestamp <= up_to {
+            boundary += 1;
+        }
+    }
+    let kept = events.split_off(boundary);
+    (events, kept)
+}
+
+impl GemCollection {
+    /// Folds `events` (oldest first, all newer than `base`) into `base` with the configured scheduler and normalizer, returning the new snapshot. The collection's own progress is left as it was.
+    pub fn fold_into_snapshot(&mut self, base: Option<JournalSnapshot>, events: &[ReviewEvent]) -> Result<JournalSnapshot> {
+        let base = base.unwrap_or_default();
+        let saved = self.progress();
+        self.set_progress(base.progress);
+        let folded = events.iter().try_for_each(|event| self.apply_review(event).map(|_| ()));
+        let progress = self.progress();
+        self.set_progress(saved);
+        folded?;
+        let up_to = events.last().map_or(base.up_to, |event| event.timestamp.max(base.up_to));
+        let mut review_counts = base.review_counts;
+        add_review_counts(&mut review_counts, events);
+        Ok(JournalSnapshot { up_to, events: base.events + events.len(), progress, review_counts })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::testing::{collection, journal};
+
+    #[test]
+    fn reviews_at_the_same_moment_are_folded_together() {
+        let (folded, kept) = split_for_compaction(journal(), 5);
+        assert_eq!(folded.len(), 3);
+        assert_eq!(kept.len(), 4);
+        assert!(kept.iter().all(|event| event.timestamp > folded.last().unwrap().timestamp));
+    }
+
+    #[test]
+    fn snapshot_plus_the_rest_replays_like_the_whole_journal() {
+        let mut whole = collection();
+        whole.replay_merged(None, &mut journal()).unwrap();
+        for keep in 0..=journal().len() {
+            let mut gem_collection = collection();
+            let (folded, mut kept) = split_for_compaction(journal(), keep);
+            let snapshot = gem_collection.fold_into_snapshot(None, &folded).unwrap();
+            assert_eq!(gem_collection.progress(), Progress::default(), "folding changed the collection's own progress");
+            assert!(kept.iter().all(|event| !snapshot.holds(event)));
+            gem_collection.replay_merged(Some(&snapshot), &mut kept).unwrap();
+            assert_eq!(gem_collection.progress(), whole.progress(), "keeping {}", keep);
+        }
+    }
+
+    #[test]
+    fn folding_in_two_goes_is_folding_in_one() {
+        let mut gem_collection = collection();
+        let (first, second) = split_for_compaction(journal(), 4);
+        let once = gem_collection.fold_into_snapshot(None, &journal()).unwrap();
+        let base = gem_collection.fold_into_snapshot(None, &first).unwrap();
+        let twice = gem_collection.fold_into_snapshot(Some(base), &second).unwrap();
+        assert_eq!(twice, once);
+        assert_eq!(once.events, journal().len());
+        assert_eq!(once.up_to, UNIX_EPOCH + Duration::from_secs(90000));
+    }
+}
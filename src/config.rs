@@ -0,0 +1,215 @@
+//User settings, read from a JSON file. Every field has a default, so a config file only needs to mention what it changes (and no config file at all is fine).
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::ErrorKind,
+    path::Path,
+};
+
+use serde::{Serialize, Deserialize};
+
+use crate::{
+    audio::AudioOptions,
+    autosave::AutosaveOptions,
+    cloze::ClozeOptions,
+    encryption::EncryptionOptions,
+    collection::{GemCollection, DEFAULT_LOOKAHEAD, DEFAULT_SEED},
+    error::{LangwitchError, Result},
+    filter::FacetFilter,
+    image::ImageOptions,
+    leech::LeechOptions,
+    mine::MineOptions,
+    normalize::{NormalizerKind, TextNormalization},
+    review::DailyLimits,
+    scheduler::SchedulerKind,
+    selection::{ScoringConfig, SelectionKind},
+    side::SideRoles,
+    storage::wal::WalOptions,
+    sync::SyncOptions,
+    template::{default_templates, CardTemplate},
+    tokenize::TokenizerKind,
+    typed::TypedAnswerOptions,
+};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct Config {
+    /// Which algorithm schedules facet reviews: "heuristic", "sm2" or {"leitner": {...}}.
+    pub scheduler: SchedulerKind,
+    /// How the ordering picks the next gem: "lookahead_frequency", "pure_frequency", "random", "coverage_greedy" or "shortest_sentence_first".
+    pub selection: SelectionKind,
+    /// How many difficulty buckets beyond the easiest one count towards a gem's weight. Sparse decks tend to order better with 2 or 3.
+    pub lookahead: usize,
+    /// Tunes how "lookahead_frequency" weighs gems. See [`ScoringConfig`].
+    pub scoring: ScoringConfig,
+    /// Seeds the tie-breaking between equally good gems. The same seed always gives the same curriculum.
+    pub seed: u64,
+    /// Maps inflected forms onto one facet: "none", {"lemma_table": "lemmas.tsv"} or {"command": {"program": "...", "args": [...]}}.
+    pub normalizer: NormalizerKind,
+    /// Character-level normalization of facets, e.g. {"lowercase": true, "unicode_form": "nfkc", "strip_diacritics": false}.
+    pub facet_normalization: TextNormalization,
+    /// Which facets to strip out of the deck when it's loaded, e.g. {"digits": true, "punctuation": true, "proper_nouns": true, "patterns": ["^https?://"], "excluded": ["um", "acme"], "min_facet_len": 2, "min_corpus_frequency": 2, "below_threshold": "mark_known", "max_facets_per_gem": 12}. The main binary also adds everything in excluded_facets.txt.
+    pub facet_filter: FacetFilter,
+    /// How sentences are mined out of raw text (feeds, articles, books). See [`MineOptions`].
+    pub mining: MineOptions,
+    /// RSS or Atom feeds that `langwitch feed` keeps the deck supplied from.
+    pub feeds: Vec<String>,
+    /// How long `langwitch feed` waits between fetches.
+    pub feed_interval_minutes: u64,
+    /// Which language the deck is in. Decks of the same language share what's known through src/knowledge.json.
+    pub language: String,
+    /// Named decks for `langwitch decks`, e.g. {"news": "decks/news.json", "novel": "decks/novel.jsonl.zst"}.
+    pub decks: BTreeMap<String, String>,
+    /// How `langwitch review --typed` grades typed answers, e.g. {"prompt_side": 1, "answer_side": 0, "tolerance": 0.25, "ignore_case": true, "ignore_diacritics": true}.
+    pub typed_answer: TypedAnswerOptions,
+    /// How `langwitch review --cloze` blanks facets out of sentences, e.g. {"side": 0, "placeholder": "____", "hint_first_letter": true, "max_suffix_len": 4}.
+    pub cloze: ClozeOptions,
+    /// What each side of the deck holds, e.g. {"0": "text", "1": "translation", "2": "transliteration", "3": "audio", "4": "image", "5": "notes"}. Sides left out are notes.
+    pub side_roles: SideRoles,
+    /// How the audio side is played, e.g. {"media_dir": "src/media", "player": {"program": "mpv", "args": ["--no-video", "--really-quiet"]}, "autoplay": true, "tts": {"command": {"program": "espeak-ng", "args": ["--stdin", "-w"]}}, "tts_extension": "wav"}. The main binary looks in src/media unless media_dir says otherwise.
+    pub audio: AudioOptions,
+    /// How the image side is drawn in the terminal, e.g. {"viewer": {"program": "kitten", "args": ["icat"]}}. Pictures are looked up in the same media directory as audio.
+    pub images: ImageOptions,
+    /// Card templates by name, e.g. {"production": {"front": "{translation}", "back": "{text}\n{audio}"}}. See [`crate::template`] for the placeholders. A config that sets this replaces the built-in recognition, production and listening templates.
+    pub templates: BTreeMap<String, CardTemplate>,
+    /// Which template `langwitch review` and the TUI use unless `--template` picks one. None shows every side as it is.
+    pub template: Option<String>,
+    /// Where `langwitch push` and `langwitch pull` sync progress to, e.g. {"url": "http://192.168.1.10:8765", "token": "..."}. See [`crate::sync`].
+    pub sync: SyncOptions,
+    /// Encrypts progress and the review journal on disk, e.g. {"passphrase_env": "LANGWITCH_PASSPHRASE"} or {"keyfile": "/home/me/.langwitch.key"}. Needs the encryption feature; run `langwitch encrypt` once to convert files written before it was turned on. See [`crate::encryption`].
+    pub encryption: Option<EncryptionOptions>,
+    /// How often `langwitch review` saves progress and a checkpoint to resume from after a crash, e.g. {"every_reviews": 5, "every_seconds": 60}. See [`crate::autosave`].
+    pub autosave: AutosaveOptions,
+    /// Caps on how many brand-new facets review introduces and how many reviews it asks for a day, e.g. {"new_facets": 20, "reviews": 200, "utc_offset_minutes": 60}. Reviews past the cap wait for the next day, most urgent first. Either cap can be left out. See [`crate::review::DailyLimits`].
+    pub daily_limits: DailyLimits,
+    /// How many other cards review puts between gems that share a facet, when there's something else to show. 0 lets them come back to back.
+    pub sibling_spacing: usize,
+    /// When a facet that keeps lapsing is suspended, e.g. {"threshold": 8, "require_note": true}. See [`crate::leech`].
+    pub leeches: LeechOptions,
+    /// Puts a write-ahead log in front of progress and the journal, so they're written in batches instead of on every grade, e.g. {"batch_size": 20, "flush_interval_seconds": 30, "fsync": "batch"}. None writes straight through. See [`crate::storage::wal`].
+    pub wal: Option<WalOptions>,
+    /// Leaves gems the deck can't read out of it instead of refusing to load the deck, and lists what was left out. `--lenient` turns it on for one run.
+    pub lenient: bool,
+    /// Settings that only apply to one language, keyed by language code, e.g. {"ja": {"tokenizer": {"vibrato": {"dictionary": "ipadic.dic"}}}, "es": {"facet_normalization": {"lowercase": true, "strip_diacritics": true}}}. Whatever the profile for `language` sets replaces the setting above.
+    pub profiles: BTreeMap<String, LanguageProfile>,
+}
+
+/// The per-language layer of a [`Config`]. Every field is optional, and unset ones fall through to the config's own.
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct LanguageProfile {
+    /// How text in this language is split into facets when it's mined or imported.
+    pub tokenizer: Option<TokenizerKind>,
+    pub normalizer: Option<NormalizerKind>,
+    pub facet_normalization: Option<TextNormalization>,
+    pub facet_filter: Option<FacetFilter>,
+    pub scheduler: Option<SchedulerKind>,
+    pub selection: Option<SelectionKind>,
+    pub lookahead: Option<usize>,
+    pub scoring: Option<ScoringConfig>,
+}
+
+pub const DEFAULT_FEED_INTERVAL_MINUTES: u64 = 60;
+pub const DEFAULT_LANGUAGE: &str = "default";
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            scheduler: SchedulerKind::default(),
+            selection: SelectionKind::default(),
+            lookahead: DEFAULT_LOOKAHEAD,
+            scoring: ScoringConfig::default(),
+            seed: DEFAULT_SEED,
+            normalizer: NormalizerKind::default(),
+            facet_normalization: TextNormalization::default(),
+            facet_filter: FacetFilter::default(),
+            mining: MineOptions::default(),
+            feeds: Vec::new(),
+            feed_interval_minutes: DEFAULT_FEED_INTERVAL_MINUTES,
+            language: DEFAULT_LANGUAGE.to_string(),
+            decks: BTreeMap::new(),
+            typed_answer: TypedAnswerOptions::default(),
+            cloze: ClozeOptions::default(),
+            side_roles: SideRoles::default(),
+            audio: AudioOptions::default(),
+            images: ImageOptions::default(),
+            templates: default_templates(),
+            template: None,
+            sync: SyncOptions::default(),
+            encryption: None,
+            autosave: AutosaveOptions::default(),
+            daily_limits: DailyLimits::default(),
+            sibling_spacing: 0,
+            leeches: LeechOptions::default(),
+            wal: None,
+            lenient: false,
+            profiles: BTreeMap::new(),
+        }
+    }
+}
+
+impl Config {
+    /// Reads the config at `path`, falling back to the defaults if the file doesn't exist.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Config> {
+        match fs::read_to_string(path) {
+            Ok(contents) => Ok(serde_json::from_str(&contents)?),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(Config::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// The card template called `name`.
+    pub fn card_template(&self, name: &str) -> Result<CardTemplate> {
+        self.templates.get(name).cloned().ok_or_else(|| LangwitchError::MissingTemplate(name.to_string()))
+    }
+
+    /// This config as it applies to `language`: `language` set, and its profile (if there is one) laid over the other settings.
+    pub fn for_language(&self, language: &str) -> Config {
+        let mut config = self.clone();
+        config.language = language.to_string();
+        let profile = match self.profiles.get(language) {
+            Some(profile) => profile.clone(),
+            None => return config,
+        };
+        if let Some(tokenizer) = profile.tokenizer {
+            config.mining.tokenizer = tokenizer;
+        }
+        if let Some(normalizer) = profile.normalizer {
+            config.normalizer = normalizer;
+        }
+        if let Some(facet_normalization) = profile.facet_normalization {
+            config.facet_normalization = facet_normalization;
+        }
+        if let Some(facet_filter) = profile.facet_filter {
+            config.facet_filter = facet_filter;
+        }
+        if let Some(scheduler) = profile.scheduler {
+            config.scheduler = scheduler;
+        }
+        if let Some(selection) = profile.selection {
+            config.selection = selection;
+        }
+        if let Some(lookahead) = profile.lookahead {
+            config.lookahead = lookahead;
+        }
+        if let Some(scoring) = profile.scoring {
+            config.scoring = scoring;
+        }
+        config
+    }
+}
+
+/// Sets `gem_collection` up the way `config` says: scheduler, ordering, side roles, facet normalization and the facet filter, in that order so the filter sees normalized facets. Every front end calls this once the deck and its progress are loaded and before indexing, so a setting (a language profile's included) means the same thing everywhere.
+pub fn apply_config(config: &Config, gem_collection: &mut GemCollection) -> Result<()> {
+    gem_collection.scheduler = config.scheduler.clone();
+    gem_collection.selection = config.selection;
+    gem_collection.lookahead = config.lookahead;
+    gem_collection.scoring = config.scoring.clone();
+    gem_collection.seed(config.seed);
+    gem_collection.side_roles = config.side_roles.clone();
+    gem_collection.facet_normalization = config.facet_normalization.clone();
+    gem_collection.set_normalization(config.normalizer.build()?)?;
+    gem_collection.filter_facets(&config.facet_filter)?;
+    Ok(())
+}
@@ -0,0 +1,128 @@
+//Cramming: drilling a chosen set of facets over and over, for the night before an exam, without touching their scheduling. A facet keeps coming round until it's been got right so many times in a row, by then it's left alone, and a miss starts its run again.
+//Cram grades go into the journal like any other, marked as cram, so the audit trail has them. Replaying, statistics and daily limits all pass over them: a facet crammed ten times tonight is still due exactly when it was.
+
+use std::collections::{HashMap, HashSet};
+use std::time::SystemTime;
+
+use crate::{
+    collection::GemCollection,
+    error::{LangwitchError, Result},
+    gem::GemId,
+    hint::hinted_grade,
+    interner::FacetId,
+    journal::{ReviewEvent, PASSING_GRADE},
+    review::Card,
+};
+
+/// How many times in a row a facet has to be got right before a cram session stops asking about it, unless told otherwise.
+pub const DEFAULT_STREAK: usize = 2;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct CramSession {
+    //Each drilled facet's current run of right answers, and the card it was last asked on.
+    streaks: HashMap<FacetId, usize>,
+    last_asked: HashMap<FacetId, usize>,
+    streak: usize,
+    gems_by_facet: HashMap<FacetId, Vec<GemId>>,
+    //The drilled facets in each gem that has any, and how many facets the gem has altogether.
+    drilled_in_gem: HashMap<GemId, (Vec<FacetId>, usize)>,
+    unmatched: Vec<String>,
+    asked: usize,
+    last_gem: Option<GemId>,
+}
+
+impl CramSession {
+    /// Drills `facets` (normalized as reviews are) in the gems of `gem_collection` until each has been got right `streak` times in a row. Call it before indexing, while the gems still have every facet, known ones included.
+    pub fn new(gem_collection: &GemCollection, facets: &[String], streak: usize) -> Result<CramSession> {
+        let mut drilled = HashSet::new();
+        let mut unmatched = Vec::new();
+        for (facet, name) in gem_collection.normalize_facet_names(facets)?.into_iter().zip(facets.iter()) {
+            match gem_collection.interner.get(&facet) {
+                Some(id) if gem_collection.gems.iter().any(|gem| gem.unknown_facets.contains(&id)) => {
+                    drilled.insert(id);
+                }
+                _ => unmatched.push(name.clone()),
+            }
+        }
+        let mut gems_by_facet: HashMap<FacetId, Vec<GemId>> = HashMap::new();
+        let mut drilled_in_gem = HashMap::new();
+        for (number, gem) in gem_collection.gems.iter().enumerate() {
+            let mut in_gem: Vec<FacetId> = gem.unknown_facets.iter().filter(|facet| drilled.contains(facet)).copied().collect();
+            if in_gem.is_empty() {
+                continue;
+            }
+            in_gem.sort_unstable();
+            for facet in in_gem.iter() {
+                gems_by_facet.entry(*facet).or_default().push(GemId(number));
+            }
+            drilled_in_gem.insert(GemId(number), (in_gem, gem.unknown_facets.len()));
+        }
+        Ok(CramSession {
+            streaks: drilled.into_iter().map(|facet| (facet, 0)).collect(),
+            last_asked: HashMap::new(),
+            streak: streak.max(1),
+            gems_by_facet,
+            drilled_in_gem,
+            unmatched,
+            asked: 0,
+            last_gem: None,
+        })
+    }
+
+    /// The asked-for facets that no gem has, in the order they were asked for.
+    pub fn unmatched(&self) -> &[String] {
+        &self.unmatched
+    }
+
+    /// How many drilled facets still haven't been got right enough times in a row.
+    pub fn remaining(&self) -> usize {
+        self.streaks.values().filter(|streak| **streak < self.streak).count()
+    }
+
+    /// The next card, or None once every facet has its run. It asks about the facet with the shortest run (then the one asked longest ago), in the gem with the fewest facets that has it, avoiding the gem just shown when there's another; and it asks about every other facet in that gem still being drilled.
+    pub fn next_card(&self, gem_collection: &GemCollection) -> Option<Card> {
+        let facet = self
+            .streaks
+            .iter()
+            .filter(|(_, streak)| **streak < self.streak)
+            .min_by_key(|(facet, streak)| (**streak, self.last_asked.get(facet).copied(), **facet))
+            .map(|(facet, _)| *facet)?;
+        let gems = &self.gems_by_facet[&facet];
+        let gem = gems
+            .iter()
+            .min_by_key(|gem_id| (Some(**gem_id) == self.last_gem && gems.len() > 1, self.drilled_in_gem[gem_id].1, **gem_id))?;
+        let mut facets: Vec<String> = self.drilled_in_gem[gem]
+            .0
+            .iter()
+            .filter(|facet| self.streaks[facet] < self.streak)
+            .map(|facet| gem_collection.facet_name(*facet).to_string())
+            .collect();
+        facets.sort();
+        Some(Card { gem: *gem, facets, is_new: false, ahead: false })
+    }
+
+    /// Grades a card at `timestamp`, with hints taken off as [`GemCollection::grade_gem_with_hints_at`] does, and returns the cram event for the journal. Scheduling and known facets are left alone.
+    pub fn grade(&mut self, gem_collection: &GemCollection, card: &Card, grades: HashMap<String, f64>, hints: HashMap<String, u8>, timestamp: SystemTime) -> Result<ReviewEvent> {
+        let gem_key = gem_collection.key(card.gem).cloned().ok_or(LangwitchError::MissingGem(card.gem.0))?;
+        let grades: HashMap<String, f64> = grades
+            .into_iter()
+            .map(|(facet, grade)| {
+                let level = hints.get(&facet).copied().unwrap_or(0);
+                (facet, hinted_grade(grade, level))
+            })
+            .collect();
+        self.asked += 1;
+        for (facet, grade) in grades.iter() {
+            let streak = gem_collection.interner.get(facet).and_then(|id| {
+                self.last_asked.insert(id, self.asked);
+                self.streaks.get_mut(&id)
+            });
+            if let Some(streak) = streak {
+                *streak = if *grade >= PASSING_GRADE { *streak + 1 } else { 0 };
+            }
+        }
+        self.last_gem = Some(card.gem);
+        let hints = hints.into_iter().filter(|(_, level)| *level > 0).collect();
+        Ok(ReviewEvent { gem_key, grades, timestamp, hints, cram: true })
+    }
+}
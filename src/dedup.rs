@@ -0,0 +1,266 @@
+//Corpus-derived decks repeat the same sentence over and over, and every copy counts towards the frequency map as if it were a different gem. Deduplication keeps the first copy of each set of identical sides, gives it the union of every copy's facets, and drops the rest.
+//Near-duplicates ("I have a cat." / "I have a dog.") are found with MinHash over character shingles of each gem's first side: gems whose signatures collide in any LSH band are compared properly, and pairs above the similarity threshold can be flagged, dropped or down-weighted.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    collection::GemCollection,
+    error::Result,
+    filter::{FacetFilter, FilterReport},
+    gem::{GemId, GemKey, InternedGem},
+    schema::DeckError,
+};
+
+#[derive(Debug, Clone, Default)]
+pub struct DeckReadOptions {
+    /// Merge gems whose sides are identical into one.
+    pub deduplicate: bool,
+    /// Look for near-duplicates too, and deal with them as these options say.
+    pub near_duplicates: Option<NearDuplicateOptions>,
+    /// Which facets to strip out of every gem.
+    pub facet_filter: FacetFilter,
+    /// Leave out gems that can't be read instead of failing, listing them in [`ReadReport::skipped`].
+    pub lenient: bool,
+}
+
+/// What happened while reading a deck.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct ReadReport {
+    /// How many gems the file held.
+    pub gems_read: usize,
+    /// How many of those were dropped as duplicates of an earlier gem.
+    pub duplicates_dropped: usize,
+    /// Every near-duplicate found, whatever was done about it.
+    pub near_duplicates: Vec<NearDuplicate>,
+    /// What the facet filter took out.
+    pub facet_filter: FilterReport,
+    /// Gems a lenient read left out, and why.
+    pub skipped: Vec<DeckError>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum NearDuplicateAction {
+    /// Only report them.
+    Flag,
+    /// Drop the later gem of each pair, as long as it doesn't hold a facet that no other gem teaches.
+    Drop,
+    /// Multiply the later gem's weight by this, so the ordering picks it less readily.
+    DownWeight(f64),
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct NearDuplicateOptions {
+    /// How similar (Jaccard similarity of character shingles, 0.0 to 1.0) two gems have to be to count as near-duplicates.
+    pub threshold: f64,
+    pub action: NearDuplicateAction,
+}
+
+impl Default for NearDuplicateOptions {
+    fn default() -> Self {
+        NearDuplicateOptions { threshold: 0.9, action: NearDuplicateAction::Flag }
+    }
+}
+
+/// A gem that's nearly the same as an earlier one. Gems are named by key, since dropping gems hands out new ids.
+#[derive(Debug, PartialEq, Clone)]
+pub struct NearDuplicate {
+    pub gem: GemKey,
+    pub similar_to: GemKey,
+    pub similarity: f64,
+    /// Whether `gem` was dropped from the deck.
+    pub dropped: bool,
+}
+
+//Shingles are runs of this many characters.
+const SHINGLE_LENGTH: usize = 4;
+const BANDS: usize = 16;
+const ROWS_PER_BAND: usize = 4;
+
+//Lowercased, with whitespace squashed, so spacing and capitals don't count as differences.
+fn shingles(text: &str) -> HashSet<String> {
+    let normalized: Vec<char> = text.to_lowercase().split_whitespace().collect::<Vec<&str>>().join(" ").chars().collect();
+    if normalized.len() <= SHINGLE_LENGTH {
+        return [normalized.into_iter().collect()].into_iter().collect();
+    }
+    normalized.windows(SHINGLE_LENGTH).map(|window| window.iter().collect()).collect()
+}
+
+fn jaccard(left: &HashSet<String>, right: &HashSet<String>) -> f64 {
+    let union = left.union(right).count();
+    if union == 0 {
+        return 1.0;
+    }
+    left.intersection(right).count() as f64 / union as f64
+}
+
+//splitmix64, used to turn one shingle hash into BANDS * ROWS_PER_BAND different ones.
+fn mix(mut value: u64) -> u64 {
+    value = value.wrapping_add(0x9e3779b97f4a7c15);
+    value = (value ^ (value >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    value = (value ^ (value >> 27)).wrapping_mul(0x94d049bb133111eb);
+    value ^ (value >> 31)
+}
+
+fn fnv(text: &str) -> u64 {
+    text.bytes().fold(0xcbf29ce484222325, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x100000001b3))
+}
+
+fn min_hash_signature(shingles: &HashSet<String>) -> Vec<u64> {
+    let mut signature = vec![u64::MAX; BANDS * ROWS_PER_BAND];
+    for shingle in shingles {
+        let base = fnv(shingle);
+        for (seed, slot) in signature.iter_mut().enumerate() {
+            *slot = (*slot).min(mix(base ^ mix(seed as u64)));
+        }
+    }
+    signature
+}
+
+impl GemCollection {
+    /// Same as [`GemCollection::read_gems_from_file`], with options, also reporting what was done to the deck along the way.
+    pub fn read_gems_from_file_with(file_path: &str, options: &DeckReadOptions) -> Result<(GemCollection, ReadReport)> {
+        let mut deck = GemCollection::read_deck_file(file_path, options.lenient)?;
+        let skipped = std::mem::take(&mut deck.skipped);
+        let mut gem_collection = GemCollection::from_deck(deck);
+        let mut report = ReadReport {
+            gems_read: gem_collection.gems.len(),
+            skipped,
+            ..ReadReport::default()
+        };
+        report.facet_filter = gem_collection.filter_facets(&options.facet_filter)?;
+        if options.deduplicate {
+            report.duplicates_dropped = gem_collection.deduplicate();
+        }
+        if let Some(near_duplicate_options) = &options.near_duplicates {
+            report.near_duplicates = gem_collection.handle_near_duplicates(near_duplicate_options);
+        }
+        Ok((gem_collection, report))
+    }
+
+    /// Merges gems with identical sides, keeping the first of each and giving it every copy's facets. Returns how many gems were dropped.
+    /// Gem ids are handed out again afterwards, so this belongs straight after loading, before indexing or handing ids to anyone.
+    pub fn deduplicate(&mut self) -> usize {
+        //Bucketed by a hash of the sides; within a bucket the sides themselves are compared, so a hash collision can't merge two different gems.
+        let mut kept_by_hash: HashMap<GemKey, Vec<usize>> = HashMap::new();
+        let mut dropped: HashSet<GemId> = HashSet::new();
+        for gem_id in self.gem_ids() {
+            let same_hash = kept_by_hash.entry(GemKey::from_sides(&self.gems[gem_id.0].sides)).or_default();
+            match same_hash.iter().copied().find(|position| self.gems[*position].sides == self.gems[gem_id.0].sides) {
+                Some(position) => {
+                    let facets = self.gems[gem_id.0].unknown_facets.clone();
+                    self.gems[position].unknown_facets.extend(facets);
+                    dropped.insert(gem_id);
+                }
+                None => same_hash.push(gem_id.0),
+            }
+        }
+        self.drop_gems(&dropped);
+        dropped.len()
+    }
+
+    /// Finds pairs of gems whose text sides are at least `threshold` similar. Each near-duplicate is paired with the most similar gem before it.
+    pub fn find_near_duplicates(&self, threshold: f64) -> Vec<(GemId, GemId, f64)> {
+        let text_side = self.side_roles.text_side();
+        let gem_shingles: Vec<HashSet<String>> = self.gems
+            .iter()
+            .map(|gem| shingles(gem.sides.get(&text_side).map_or("", |side| side.as_str())))
+            .collect();
+        //Gems that agree on every row of some band land in the same bucket, and only gems sharing a bucket are compared.
+        let mut buckets: HashMap<(usize, Vec<u64>), Vec<usize>> = HashMap::new();
+        for (position, shingles) in gem_shingles.iter().enumerate() {
+            let signature = min_hash_signature(shingles);
+            for (band, rows) in signature.chunks(ROWS_PER_BAND).enumerate() {
+                buckets.entry((band, rows.to_vec())).or_default().push(position);
+            }
+        }
+        let mut best_match: HashMap<usize, (usize, f64)> = HashMap::new();
+        let mut compared: HashSet<(usize, usize)> = HashSet::new();
+        for positions in buckets.values() {
+            for (index, earlier) in positions.iter().enumerate() {
+                for later in positions[index + 1..].iter() {
+                    if !compared.insert((*earlier, *later)) {
+                        continue;
+                    }
+                    let similarity = jaccard(&gem_shingles[*earlier], &gem_shingles[*later]);
+                    if similarity < threshold {
+                        continue;
+                    }
+                    let better = best_match.get(later).is_none_or(|(_, best)| similarity > *best);
+                    if better {
+                        best_match.insert(*later, (*earlier, similarity));
+                    }
+                }
+            }
+        }
+        let mut near_duplicates: Vec<(GemId, GemId, f64)> = best_match
+            .into_iter()
+            .map(|(later, (earlier, similarity))| (GemId(later), GemId(earlier), similarity))
+            .collect();
+        near_duplicates.sort_by_key(|(later, _, _)| *later);
+        near_duplicates
+    }
+
+    /// Finds near-duplicates and flags, drops or down-weights them as `options` says. Dropping hands out new gem ids, like [`GemCollection::deduplicate`].
+    pub fn handle_near_duplicates(&mut self, options: &NearDuplicateOptions) -> Vec<NearDuplicate> {
+        let pairs = self.find_near_duplicates(options.threshold);
+        let mut dropped: HashSet<GemId> = HashSet::new();
+        let mut near_duplicates = Vec::with_capacity(pairs.len());
+        //How many kept gems teach each facet, so a drop never takes the last gem that teaches something.
+        let mut teachers: HashMap<_, usize> = HashMap::new();
+        if options.action == NearDuplicateAction::Drop {
+            for gem in self.gems.iter() {
+                for facet in gem.unknown_facets.iter() {
+                    *teachers.entry(*facet).or_insert(0) += 1;
+                }
+            }
+        }
+        for (gem_id, similar_to, similarity) in pairs {
+            let mut was_dropped = false;
+            match options.action {
+                NearDuplicateAction::Flag => {}
+                NearDuplicateAction::DownWeight(factor) => self.gems[gem_id.0].weight *= factor,
+                NearDuplicateAction::Drop => {
+                    let gem: &InternedGem = &self.gems[gem_id.0];
+                    if gem.unknown_facets.iter().all(|facet| teachers[facet] > 1) {
+                        for facet in gem.unknown_facets.iter() {
+                            if let Some(count) = teachers.get_mut(facet) {
+                                *count -= 1;
+                            }
+                        }
+                        dropped.insert(gem_id);
+                        was_dropped = true;
+                    }
+                }
+            }
+            near_duplicates.push(NearDuplicate {
+                gem: self.gems[gem_id.0].key.clone(),
+                similar_to: self.gems[similar_to.0].key.clone(),
+                similarity,
+                dropped: was_dropped,
+            });
+        }
+        self.drop_gems(&dropped);
+        near_duplicates
+    }
+
+    //Removes gems and hands out fresh ids to the rest, which stay set aside if they were. The indices are cleared, since they'd point at the wrong gems now.
+    pub(crate) fn drop_gems(&mut self, dropped: &HashSet<GemId>) {
+        if dropped.is_empty() {
+            return;
+        }
+        let gems = std::mem::take(&mut self.gems);
+        let set_aside = std::mem::take(&mut self.set_aside);
+        for (position, gem) in gems.into_iter().enumerate().filter(|(position, _)| !dropped.contains(&GemId(*position))) {
+            if let Some(why) = set_aside.get(&GemId(position)) {
+                self.set_aside.insert(GemId(self.gems.len()), *why);
+            }
+            self.gems.push(gem);
+        }
+        self.gem_ids_by_key.clear();
+        for gem_id in self.gem_ids() {
+            self.gem_ids_by_key.entry(self.gems[gem_id.0].key.clone()).or_insert(gem_id);
+        }
+        self.gems_by_size_index.clear();
+        self.gems_by_facet_index.clear();
+    }
+}
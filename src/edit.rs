@@ -0,0 +1,125 @@
+//Changing a collection that's already indexed, so fixing a typo or a facet list, or feeding in freshly mined gems, doesn't mean reloading the deck and indexing everything again.
+//An edited gem is taken out of both indices under its old facets and filed again under its new ones, and the global frequency list has the old facets counted out and the new ones counted in. Known facets are stripped from the new list, as indexing would. Suspended and buried gems stay out of the indices but are counted, like indexing counts them.
+//Added gems go on the end, so every gem id handed out before stays put, and are filed straight into the indices (known facets stripped) and counted into the frequency list, which makes them candidates for the very next ordering step. They go through the same normalization, facet filter and shared knowledge as the gems the collection was loaded with. The work is in the new gems only, except that a filter for proper nouns reads the whole deck's text again.
+//A gem without an explicit id is keyed by a hash of its sides, so new sides would give it a new key and cut it off from its journal history and anything stored against it. Its old key becomes its id instead, which keeps it, and writing the deck out saves it.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    collection::GemCollection,
+    error::{LangwitchError, Result},
+    gem::{Gem, GemId},
+};
+
+impl GemCollection {
+    /// Replaces a gem's sides and unknown facets, keeping the indices and `total_frequency_list` up to date without reindexing. `facets` are normalized like the collection's own (see [`GemCollection::normalize_facet_names`]); the facet filter isn't applied again.
+    /// A review session started before the edit still has the gem's old facets.
+    pub fn update_gem(&mut self, gem_id: GemId, sides: HashMap<usize, String>, facets: HashSet<String>) -> Result<()> {
+        if gem_id.0 >= self.gems.len() {
+            return Err(LangwitchError::MissingGem(gem_id.0));
+        }
+        let facets: Vec<String> = facets.into_iter().collect();
+        let mut facets = self.interner.intern_all(self.normalize_facet_names(&facets)?.iter());
+        facets.retain(|facet| !self.known_facets.contains(facet));
+        let indexed = !self.is_set_aside(gem_id);
+        if indexed {
+            self.unindex_gem(gem_id);
+        }
+        let gem = &mut self.gems[gem_id.0];
+        for facet in gem.unknown_facets.iter() {
+            if let Some(count) = self.total_frequency_list.get_mut(facet) {
+                *count -= 1;
+                if *count == 0 {
+                    self.total_frequency_list.remove(facet);
+                }
+            }
+        }
+        for facet in facets.iter() {
+            *self.total_frequency_list.entry(*facet).or_insert(0) += 1;
+        }
+        if gem.id.is_none() && gem.sides != sides {
+            gem.id = Some(gem.key.0.clone());
+        }
+        gem.sides = sides;
+        gem.unknown_facets = facets;
+        if indexed {
+            self.index_gem(gem_id);
+        }
+        Ok(())
+    }
+
+    /// Appends whichever of `gems` the collection doesn't have yet (by key, as [`GemCollection::append_new_gems`] does) and indexes them straight away, returning the ids they were given. They're prepared as [`GemCollection::append_gems`] prepares them.
+    pub fn add_gems(&mut self, gems: Vec<Gem>) -> Result<Vec<GemId>> {
+        let added = self.append_gems(gems)?;
+        self.index_gems(&added);
+        Ok(added)
+    }
+
+    /// Same as [`GemCollection::add_gems`] without the indexing, for a caller that wants the gems' full facets first (see [`GemCollection::index_gems`]). Facets are normalized like the collection's own and put through the facet filter the collection was loaded with, and whatever the shared knowledge store knows about them is pulled in.
+    pub fn append_gems(&mut self, gems: Vec<Gem>) -> Result<Vec<GemId>> {
+        let mut normalized = Vec::with_capacity(gems.len());
+        for mut gem in gems {
+            let facets: Vec<String> = gem.unknown_facets.into_iter().collect();
+            gem.unknown_facets = self.normalize_facet_names(&facets)?.into_iter().collect();
+            normalized.push(gem);
+        }
+        let filtered = self.filter_new_gems(normalized)?;
+        let added = self.append_new_gems(filtered);
+        if !added.is_empty() {
+            self.pull_shared_knowledge();
+        }
+        Ok(added)
+    }
+
+    /// Indexes gems already in the collection that aren't in the indices yet: strips their known facets, files them in both indices and counts their facets into `total_frequency_list`. [`GemCollection::add_gems`] does this for the gems it adds; a caller that wants the gems' full facets first, like [`crate::review::ReviewSession::extend`], can add them with [`GemCollection::append_gems`] and index them afterwards.
+    pub fn index_gems(&mut self, gem_ids: &[GemId]) {
+        let gem_count = self.gems.len();
+        for gem_id in gem_ids.iter().filter(|gem_id| gem_id.0 < gem_count) {
+            let known_facets = &self.known_facets;
+            let gem = &mut self.gems[gem_id.0];
+            gem.unknown_facets.retain(|facet| !known_facets.contains(facet));
+            for facet in gem.unknown_facets.iter() {
+                *self.total_frequency_list.entry(*facet).or_insert(0) += 1;
+            }
+            if !self.is_set_aside(*gem_id) {
+                self.index_gem(*gem_id);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{BTreeMap, BTreeSet};
+
+    use super::*;
+    use crate::testing::{collection, gem};
+
+    type Indices = (BTreeMap<usize, BTreeSet<usize>>, BTreeMap<String, BTreeSet<usize>>, BTreeMap<String, usize>);
+
+    //The three indices by facet name and gem number, so collections that interned facets in another order still compare.
+    fn indices(gem_collection: &GemCollection) -> Indices {
+        let gem_numbers = |gem_ids: &HashSet<GemId>| gem_ids.iter().map(|gem_id| gem_id.0).collect::<BTreeSet<usize>>();
+        (
+            gem_collection.gems_by_size_index.iter().map(|(size, gem_ids)| (*size, gem_numbers(gem_ids))).filter(|(_, gem_ids)| !gem_ids.is_empty()).collect(),
+            gem_collection.gems_by_facet_index.iter().map(|(facet, gem_ids)| (gem_collection.facet_name(*facet).to_string(), gem_numbers(gem_ids))).filter(|(_, gem_ids)| !gem_ids.is_empty()).collect(),
+            gem_collection.total_frequency_list.iter().map(|(facet, count)| (gem_collection.facet_name(*facet).to_string(), *count)).collect(),
+        )
+    }
+
+    #[test]
+    fn adding_gems_indexes_them_as_indexing_everything_would() {
+        let added = vec![gem("der vogel"), gem("die katze"), gem("ein vogel singt"), gem("der hund singt")];
+        let known = ["der".to_string()];
+        let mut incremental = collection();
+        incremental.insert_known_facets(known.iter());
+        incremental.index_all_gems_by_number();
+        assert_eq!(incremental.add_gems(added.clone()).unwrap().len(), 3);
+        let mut whole = collection();
+        whole.append_new_gems(added);
+        whole.insert_known_facets(known.iter());
+        whole.index_all_gems_by_number();
+        assert_eq!(whole.gems.len(), incremental.gems.len());
+        assert_eq!(indices(&incremental), indices(&whole));
+    }
+}
@@ -0,0 +1,71 @@
+//A deck studied from inside another runtime: the browser through wasm/, or a mobile app through the C library in ffi/. The host hands over the deck, its progress and the config as JSON bytes, asks for cards and grades them as JSON, and stores progress_json() wherever it keeps things. Nothing here reads files or the clock, so the host passes the time in.
+//Cards and grades are the same JSON `langwitch serve` uses (see [`crate::wire`]), and the card handed out is kept until it's graded, so asking again gives the same one.
+
+use std::time::SystemTime;
+
+use crate::{
+    collection::GemCollection,
+    config::{apply_config, Config},
+    error::Result,
+    progress::Progress,
+    review::{Card, ReviewSession},
+    schema::parse_deck,
+    wire::{CardJson, GradeRequest, GradeResponse},
+};
+
+pub struct EmbeddedDeck {
+    gem_collection: GemCollection,
+    session: ReviewSession,
+    card: Option<Card>,
+}
+
+impl EmbeddedDeck {
+    /// Loads a deck from the bytes of a gems.json, with the bytes of a progress.json and a config.json if there are any. A normalizer that needs a lemma table file or a program only works where the host has those.
+    pub fn from_json(gems: &[u8], progress: Option<&[u8]>, config: Option<&[u8]>) -> Result<EmbeddedDeck> {
+        let deck = parse_deck(gems)?;
+        let config: Config = match config {
+            Some(config) => serde_json::from_slice(config)?,
+            None => Config::default(),
+        };
+        let config = config.for_language(&config.language);
+        let mut gem_collection = GemCollection::from_deck(deck);
+        if let Some(progress) = progress {
+            let progress: Progress = serde_json::from_slice(progress)?;
+            gem_collection.set_progress_over_deck(progress);
+        }
+        apply_config(&config, &mut gem_collection)?;
+        let mut session = ReviewSession::new(&gem_collection);
+        session.space_siblings(config.sibling_spacing);
+        gem_collection.index_all_gems_by_number();
+        Ok(EmbeddedDeck { gem_collection, session, card: None })
+    }
+
+    /// The card to study as of `now`, as JSON, or "null" once nothing is left.
+    pub fn next_gem_json(&mut self, now: SystemTime) -> Result<String> {
+        if self.card.is_none() {
+            self.card = self.session.next_card(&mut self.gem_collection, now)?;
+        }
+        let card = match &self.card {
+            Some(card) => card,
+            None => return Ok("null".to_string()),
+        };
+        let card = CardJson::new(&self.gem_collection, card)?;
+        Ok(serde_json::to_string(&card)?)
+    }
+
+    /// Grades the card being studied at `now`, from JSON like {"gem": 3, "grades": {"cat": 1.0, "sat": 0.0}}, with "grade" for every facet it doesn't list and "hints" for how many hints each facet needed. Answers with what the grading did, as JSON.
+    pub fn grade_json(&mut self, request: &str, now: SystemTime) -> Result<String> {
+        let request: GradeRequest = serde_json::from_str(request)?;
+        let card = request.card(self.card.as_ref())?;
+        let (grades, hints) = request.into_grades(&card);
+        let result = self.session.grade_with_hints_at(&mut self.gem_collection, &card, grades, hints, now)?;
+        self.card = None;
+        let response = GradeResponse::from(result);
+        Ok(serde_json::to_string(&response)?)
+    }
+
+    /// The progress so far, as the bytes of a progress.json that [`EmbeddedDeck::from_json`] takes back.
+    pub fn progress_json(&self) -> Result<Vec<u8>> {
+        Ok(serde_json::to_vec(&self.gem_collection.progress())?)
+    }
+}
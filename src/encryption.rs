@@ -0,0 +1,192 @@
+//Encryption at rest for the learner's own data, so progress, the review journal and the known-facet store can sit in a synced or cloud-backed folder without being readable there. The deck isn't touched: it's content, not anything about the learner.
+//An encrypted file is a header line, `langwitch-encrypted 1 <salt>`, followed by sealed lines: base64 of a random nonce and the ChaCha20-Poly1305 ciphertext. Progress is one sealed line; the journal seals each event on its own line, so it stays append-only and a crash can still only cut the last line short. The key comes from a passphrase or a keyfile through Argon2id with the file's salt, and a key that's wrong (or a file that's been tampered with) is an error, never garbage.
+//Sync isn't end-to-end: the server has to read reviews to merge them, so `langwitch push` sends them and the known facets decrypted and langwitch-sync keeps them in plaintext. Run it only where the files would be safe unencrypted, on a disk of its own that's encrypted, with LANGWITCH_SYNC_TOKEN set and behind TLS; or leave sync unset and sync the encrypted files themselves through the folder.
+
+use serde::{Deserialize, Serialize};
+
+/// How progress and the journal are encrypted, e.g. {"keyfile": "/home/me/.langwitch.key"} or {"passphrase_env": "LANGWITCH_PASSPHRASE"}. A keyfile wins if both are set.
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct EncryptionOptions {
+    /// A file whose whole contents are the secret. 32 random bytes is plenty.
+    pub keyfile: Option<String>,
+    /// The environment variable holding a passphrase, so it never has to be written into the config.
+    pub passphrase_env: Option<String>,
+}
+
+#[cfg(feature = "encryption")]
+pub use keyring::{is_encrypted, Keyring, Salt};
+
+#[cfg(feature = "encryption")]
+mod keyring {
+    use std::{collections::HashMap, fs};
+
+    use argon2::Argon2;
+    use base64::{engine::general_purpose::STANDARD, Engine};
+    use chacha20poly1305::{aead::Aead, ChaCha20Poly1305, Key, KeyInit, Nonce};
+    use rand::RngCore;
+
+    use crate::error::{LangwitchError, Result};
+
+    use super::EncryptionOptions;
+
+    const HEADER_PREFIX: &str = "langwitch-encrypted 1 ";
+    const NONCE_LEN: usize = 12;
+
+    /// The salt a file's key was derived with.
+    pub type Salt = [u8; 16];
+
+    /// True if `contents` (or just its first line) is an encrypted file's.
+    pub fn is_encrypted(contents: &str) -> bool {
+        contents.starts_with(HEADER_PREFIX)
+    }
+
+    fn refused(reason: &str) -> LangwitchError {
+        LangwitchError::Encryption(reason.to_string())
+    }
+
+    /// The secret, and the keys derived from it so far. Deriving is slow on purpose, so each salt's key is only worked out once.
+    #[derive(Clone)]
+    pub struct Keyring {
+        secret: Vec<u8>,
+        keys: HashMap<Salt, Key>,
+    }
+
+    impl Keyring {
+        pub fn new(secret: Vec<u8>) -> Result<Keyring> {
+            if secret.is_empty() {
+                return Err(refused("the encryption secret is empty"));
+            }
+            Ok(Keyring { secret, keys: HashMap::new() })
+        }
+
+        /// The secret `options` point at: the keyfile's contents, or the passphrase in the environment variable.
+        pub fn from_options(options: &EncryptionOptions) -> Result<Keyring> {
+            if let Some(keyfile) = &options.keyfile {
+                return Keyring::new(fs::read(keyfile)?);
+            }
+            if let Some(variable) = &options.passphrase_env {
+                let passphrase = std::env::var(variable).map_err(|_| LangwitchError::Encryption(format!("set {} to the passphrase", variable)))?;
+                return Keyring::new(passphrase.into_bytes());
+            }
+            Err(refused("encryption needs a keyfile or a passphrase_env"))
+        }
+
+        fn cipher(&mut self, salt: &Salt) -> Result<ChaCha20Poly1305> {
+            if !self.keys.contains_key(salt) {
+                let mut key = Key::default();
+                Argon2::default().hash_password_into(&self.secret, salt, &mut key).map_err(|e| LangwitchError::Encryption(e.to_string()))?;
+                self.keys.insert(*salt, key);
+            }
+            Ok(ChaCha20Poly1305::new(&self.keys[salt]))
+        }
+
+        /// A salt for a new file: one that already has a key worked out if there is one, so a save doesn't have to derive another.
+        pub fn salt(&self) -> Salt {
+            match self.keys.keys().next() {
+                Some(salt) => *salt,
+                None => {
+                    let mut salt = Salt::default();
+                    rand::thread_rng().fill_bytes(&mut salt);
+                    salt
+                }
+            }
+        }
+
+        pub fn header(salt: &Salt) -> String {
+            format!("{}{}", HEADER_PREFIX, STANDARD.encode(salt))
+        }
+
+        /// The salt in a header line.
+        pub fn parse_header(line: &str) -> Result<Salt> {
+            let encoded = line.trim_end().strip_prefix(HEADER_PREFIX).ok_or_else(|| refused("the file isn't encrypted"))?;
+            let salt = STANDARD.decode(encoded).map_err(|_| refused("the header's salt isn't base64"))?;
+            salt.try_into().map_err(|_| refused("the header's salt is the wrong length"))
+        }
+
+        /// `plaintext` as one sealed line, without the newline.
+        pub fn seal_line(&mut self, salt: &Salt, plaintext: &[u8]) -> Result<String> {
+            let mut nonce = [0u8; NONCE_LEN];
+            rand::thread_rng().fill_bytes(&mut nonce);
+            let ciphertext = self.cipher(salt)?.encrypt(Nonce::from_slice(&nonce), plaintext).map_err(|_| refused("couldn't encrypt"))?;
+            let mut sealed = nonce.to_vec();
+            sealed.extend(ciphertext);
+            Ok(STANDARD.encode(sealed))
+        }
+
+        /// The plaintext of a sealed line.
+        pub fn open_line(&mut self, salt: &Salt, line: &str) -> Result<Vec<u8>> {
+            let sealed = STANDARD.decode(line.trim_end()).map_err(|_| refused("a sealed line isn't base64"))?;
+            if sealed.len() < NONCE_LEN {
+                return Err(refused("a sealed line is too short"));
+            }
+            let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+            self.cipher(salt)?
+                .decrypt(Nonce::from_slice(nonce), ciphertext)
+                .map_err(|_| refused("wrong key, or the file has been tampered with"))
+        }
+
+        /// A whole file holding `plaintext`: the header and one sealed line.
+        pub fn seal_file(&mut self, plaintext: &[u8]) -> Result<String> {
+            let salt = self.salt();
+            Ok(format!("{}\n{}\n", Keyring::header(&salt), self.seal_line(&salt, plaintext)?))
+        }
+
+        /// The plaintext of a file written by [`Keyring::seal_file`].
+        pub fn open_file(&mut self, contents: &str) -> Result<Vec<u8>> {
+            let mut lines = contents.lines();
+            let salt = Keyring::parse_header(lines.next().unwrap_or_default())?;
+            let line = lines.next().ok_or_else(|| refused("the file has a header but nothing sealed after it"))?;
+            self.open_line(&salt, line)
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn keyring(secret: &str) -> Keyring {
+            Keyring::new(secret.as_bytes().to_vec()).unwrap()
+        }
+
+        #[test]
+        fn a_sealed_file_opens_with_the_same_secret() {
+            let sealed = keyring("correct horse").seal_file(b"{\"known_facets\": []}").unwrap();
+            assert!(is_encrypted(&sealed));
+            assert!(!sealed.contains("known_facets"));
+            assert_eq!(keyring("correct horse").open_file(&sealed).unwrap(), b"{\"known_facets\": []}");
+        }
+
+        #[test]
+        fn a_wrong_secret_is_refused() {
+            let sealed = keyring("correct horse").seal_file(b"progress").unwrap();
+            assert!(matches!(keyring("battery staple").open_file(&sealed), Err(LangwitchError::Encryption(_))));
+        }
+
+        #[test]
+        fn a_tampered_line_is_refused() {
+            let mut keyring = keyring("correct horse");
+            let salt = keyring.salt();
+            let line = keyring.seal_line(&salt, b"a review").unwrap();
+            let mut sealed = STANDARD.decode(&line).unwrap();
+            *sealed.last_mut().unwrap() ^= 1;
+            assert!(matches!(keyring.open_line(&salt, &STANDARD.encode(sealed)), Err(LangwitchError::Encryption(_))));
+            assert_eq!(keyring.open_line(&salt, &line).unwrap(), b"a review");
+        }
+
+        #[test]
+        fn sealing_the_same_line_twice_differs() {
+            let mut keyring = keyring("correct horse");
+            let salt = keyring.salt();
+            assert_ne!(keyring.seal_line(&salt, b"a review").unwrap(), keyring.seal_line(&salt, b"a review").unwrap());
+        }
+
+        #[test]
+        fn headers_carry_the_salt() {
+            let salt = keyring("correct horse").salt();
+            assert_eq!(Keyring::parse_header(&format!("{}\n", Keyring::header(&salt))).unwrap(), salt);
+            assert!(Keyring::parse_header("[{\"sides\": \"der hund\"}]").is_err());
+            assert!(Keyring::new(Vec::new()).is_err());
+        }
+    }
+}
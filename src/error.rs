@@ -0,0 +1,182 @@
+use std::{fmt, io};
+
+//Everything that can go wrong in the library ends up as one of these, so callers can match on the kind of failure instead of the whole app coming down when a deck is malformed.
+#[derive(Debug)]
+pub enum LangwitchError {
+    /// Reading or writing a file failed.
+    Io(io::Error),
+    /// A deck or progress file isn't valid JSON, or doesn't have the shape we expect.
+    Parse(serde_json::Error),
+    /// There are no gems with unknown facets left to order.
+    EmptyCollection,
+    /// A facet was looked up in an index that doesn't contain it.
+    MissingFacet(String),
+    /// A gem id that doesn't belong to the collection it was used with.
+    MissingGem(usize),
+    /// A card template was asked for by a name the config doesn't have.
+    MissingTemplate(String),
+    /// A grade for a gem that isn't the card being studied.
+    NotCurrentCard(usize),
+    /// A facet's scheduling fields are missing or inconsistent.
+    SchedulingState(String),
+    /// The SQLite store couldn't be opened, read or written.
+    #[cfg(feature = "native")]
+    Sqlite(rusqlite::Error),
+    /// A zip-based deck (like an Anki .apkg) couldn't be read.
+    #[cfg(feature = "native")]
+    Zip(zip::result::ZipError),
+    /// A binary snapshot couldn't be encoded or decoded.
+    #[cfg(feature = "native")]
+    Snapshot(bincode::Error),
+    /// A file from another tool was readable but didn't contain what we needed.
+    Import(String),
+    /// A background task panicked or was cancelled before it could finish.
+    Background(String),
+    /// A tokenizer couldn't be set up, because its dictionary wouldn't load or its cargo feature isn't enabled.
+    Tokenizer(String),
+    /// A normalizer's lemma table was malformed, or its external command failed.
+    Normalizer(String),
+    /// A facet filter pattern isn't a valid regular expression.
+    Pattern(regex::Error),
+    /// Something couldn't be downloaded.
+    #[cfg(feature = "native")]
+    Http(Box<ureq::Error>),
+    /// Audio couldn't be attached to a gem, or speech couldn't be synthesized for one.
+    Audio(String),
+    /// A picture couldn't be shown.
+    Image(String),
+    /// Progress or the journal couldn't be encrypted or decrypted: no secret, the wrong one, a damaged file, or no encryption feature.
+    Encryption(String),
+    /// A deck was written in a newer schema version than this langwitch reads.
+    SchemaVersion(u32),
+    /// A version 0 deck would be written back before the scheduling it holds was saved to progress.
+    UnsavedMigration,
+    /// A deck, or a gem in it, doesn't have the shape we expect. Says which gem and field, and where.
+    Deck(crate::schema::DeckError),
+    /// A leech can't be released: it isn't suspended, or it needs a note first.
+    Leech(String),
+    /// A gem or facet can't be suspended, buried or brought back: it isn't in the deck, or it wasn't set aside.
+    SetAside(String),
+    /// A gem can't be flagged or unflagged: it isn't in the deck, or it has no flags to clear.
+    Flag(String),
+    /// A caller handed over something unusable: a null pointer or text that isn't UTF-8 through the C library, or a config missing a setting the call needs.
+    InvalidArgument(String),
+}
+
+pub type Result<T> = std::result::Result<T, LangwitchError>;
+
+impl fmt::Display for LangwitchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LangwitchError::Io(e) => write!(f, "io error: {}", e),
+            LangwitchError::Parse(e) => write!(f, "parse error: {}", e),
+            LangwitchError::EmptyCollection => write!(f, "no gems with unknown facets are left"),
+            LangwitchError::MissingFacet(facet) => write!(f, "facet {:?} is not in the index", facet),
+            LangwitchError::MissingGem(number) => write!(f, "gem {} is not in the collection", number),
+            LangwitchError::MissingTemplate(name) => write!(f, "there's no card template called {:?}", name),
+            LangwitchError::NotCurrentCard(number) => write!(f, "gem {} isn't the card being studied; ask for the next gem first", number),
+            LangwitchError::SchedulingState(reason) => write!(f, "bad scheduling state: {}", reason),
+            #[cfg(feature = "native")]
+            LangwitchError::Sqlite(e) => write!(f, "sqlite error: {}", e),
+            #[cfg(feature = "native")]
+            LangwitchError::Zip(e) => write!(f, "zip error: {}", e),
+            #[cfg(feature = "native")]
+            LangwitchError::Snapshot(e) => write!(f, "snapshot error: {}", e),
+            LangwitchError::Import(reason) => write!(f, "import error: {}", reason),
+            LangwitchError::Background(reason) => write!(f, "background task failed: {}", reason),
+            LangwitchError::Tokenizer(reason) => write!(f, "tokenizer error: {}", reason),
+            LangwitchError::Normalizer(reason) => write!(f, "normalizer error: {}", reason),
+            LangwitchError::Pattern(e) => write!(f, "bad pattern: {}", e),
+            #[cfg(feature = "native")]
+            LangwitchError::Http(e) => write!(f, "http error: {}", e),
+            LangwitchError::Audio(reason) => write!(f, "audio error: {}", reason),
+            LangwitchError::Image(reason) => write!(f, "image error: {}", reason),
+            LangwitchError::Encryption(reason) => write!(f, "encryption error: {}", reason),
+            LangwitchError::SchemaVersion(version) => write!(f, "the deck is schema version {}, but this langwitch only reads up to version {}", version, crate::schema::SCHEMA_VERSION),
+            LangwitchError::UnsavedMigration => write!(f, "the deck is schema version 0 and holds scheduling that isn't in progress yet, so it can't be rewritten until it's been loaded with its progress once"),
+            LangwitchError::Deck(e) => write!(f, "invalid deck: {}", e),
+            LangwitchError::Leech(reason) => write!(f, "leech: {}", reason),
+            LangwitchError::SetAside(reason) => write!(f, "set aside: {}", reason),
+            LangwitchError::Flag(reason) => write!(f, "flag: {}", reason),
+            LangwitchError::InvalidArgument(reason) => write!(f, "invalid argument: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for LangwitchError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            LangwitchError::Io(e) => Some(e),
+            LangwitchError::Parse(e) => Some(e),
+            #[cfg(feature = "native")]
+            LangwitchError::Sqlite(e) => Some(e),
+            #[cfg(feature = "native")]
+            LangwitchError::Zip(e) => Some(e),
+            #[cfg(feature = "native")]
+            LangwitchError::Snapshot(e) => Some(e),
+            LangwitchError::Pattern(e) => Some(e),
+            #[cfg(feature = "native")]
+            LangwitchError::Http(e) => Some(e.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for LangwitchError {
+    fn from(e: io::Error) -> Self {
+        LangwitchError::Io(e)
+    }
+}
+
+impl From<crate::schema::DeckError> for LangwitchError {
+    fn from(e: crate::schema::DeckError) -> Self {
+        LangwitchError::Deck(e)
+    }
+}
+
+impl From<serde_json::Error> for LangwitchError {
+    fn from(e: serde_json::Error) -> Self {
+        LangwitchError::Parse(e)
+    }
+}
+
+#[cfg(feature = "native")]
+impl From<rusqlite::Error> for LangwitchError {
+    fn from(e: rusqlite::Error) -> Self {
+        LangwitchError::Sqlite(e)
+    }
+}
+
+#[cfg(feature = "native")]
+impl From<zip::result::ZipError> for LangwitchError {
+    fn from(e: zip::result::ZipError) -> Self {
+        LangwitchError::Zip(e)
+    }
+}
+
+#[cfg(feature = "native")]
+impl From<bincode::Error> for LangwitchError {
+    fn from(e: bincode::Error) -> Self {
+        LangwitchError::Snapshot(e)
+    }
+}
+
+impl From<regex::Error> for LangwitchError {
+    fn from(e: regex::Error) -> Self {
+        LangwitchError::Pattern(e)
+    }
+}
+
+#[cfg(feature = "native")]
+impl From<ureq::Error> for LangwitchError {
+    fn from(e: ureq::Error) -> Self {
+        LangwitchError::Http(Box::new(e))
+    }
+}
+
+#[cfg(feature = "native")]
+impl From<tokio::task::JoinError> for LangwitchError {
+    fn from(e: tokio::task::JoinError) -> Self {
+        LangwitchError::Background(e.to_string())
+    }
+}
@@ -0,0 +1,71 @@
+//Writes an ordering out as a tab-separated file Anki's "Import File" dialog understands. Anki gives new cards due positions in the order the rows appear, so the curriculum survives the trip: study the deck in "order added" and the cards come up easiest-first.
+//The header lines tell Anki the separator, that fields are plain text, what each column is called (sides are named after their roles), and which column holds the tags. Each row is the position, every side in order, then the gem's facets as tags.
+
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+use crate::{
+    collection::GemCollection,
+    error::Result,
+    gem::GemId,
+};
+
+//Tabs and newlines would break the row structure, so they're flattened to spaces.
+fn clean_field(field: &str) -> String {
+    field.replace(['\t', '\n', '\r'], " ")
+}
+
+//Anki tags can't contain spaces.
+fn facet_to_tag(facet: &str) -> String {
+    facet.split_whitespace().collect::<Vec<&str>>().join("_")
+}
+
+/// Writes the gems listed in `order` (gem ids, easiest first) to `path`, taking sides and facets from `gem_collection`.
+/// Ids that aren't in the collection are skipped.
+pub fn write_anki_tsv<P: AsRef<Path>>(gem_collection: &GemCollection, order: &[GemId], path: P) -> Result<()> {
+    let side_count = gem_collection.gems
+        .iter()
+        .flat_map(|gem| gem.sides.keys())
+        .max()
+        .map_or(0, |max_side| max_side + 1);
+    let mut columns = vec!["Position".to_string()];
+    for side in 0..side_count {
+        let role = gem_collection.side_roles.role(side);
+        //Anki needs column names to be unique, so a role that's on several sides gets numbered.
+        let shared = (0..side_count).filter(|other| gem_collection.side_roles.role(*other) == role).count() > 1;
+        columns.push(if shared { format!("{} {}", role.label(), side) } else { role.label().to_string() });
+    }
+    columns.push("Tags".to_string());
+    let mut file = BufWriter::new(File::create(path)?);
+    writeln!(file, "#separator:tab")?;
+    writeln!(file, "#html:false")?;
+    writeln!(file, "#columns:{}", columns.join("\t"))?;
+    writeln!(file, "#tags column:{}", side_count + 2)?;
+    for (position, gem_id) in order.iter().enumerate() {
+        let gem = match gem_collection.get(*gem_id) {
+            Some(gem) => gem,
+            None => continue,
+        };
+        let mut row = vec![format!("{:06}", position + 1)];
+        for side in 0..side_count {
+            row.push(gem.sides.get(&side).map(|text| clean_field(text)).unwrap_or_default());
+        }
+        let mut tags: Vec<String> = gem.unknown_facets.iter().map(|facet| facet_to_tag(gem_collection.facet_name(*facet))).collect();
+        tags.sort();
+        row.push(tags.join(" "));
+        writeln!(file, "{}", row.join("\t"))?;
+    }
+    file.flush()?;
+    Ok(())
+}
+
+impl GemCollection {
+    /// Orders a copy of the collection by difficulty and writes it out as an Anki import file. The collection itself is left untouched.
+    pub fn export_anki_tsv<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let order = self.clone().difficulty_order()?;
+        write_anki_tsv(self, &order, path)
+    }
+}
@@ -0,0 +1,131 @@
+//Forgetting-curve data, for anyone who wants to model their own memory from what langwitch recorded: every review of a facet after an earlier one, as (facet, hours since that earlier review, recalled or not), and an exponential decay fitted to them, per facet and over everything.
+//The fitted curve is p(recall after t hours) = exp(-t / stability), with the stability that makes the recorded outcomes most likely. A facet that was always recalled (or always forgotten) pushes that to the edge of the search range, so those fits say more about the data being thin than about memory.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::Result,
+    export::progress::csv_field,
+    journal::ReviewEvent,
+    stats::repeat_reviews,
+};
+
+//The stabilities the fit searches between, in hours: about half a minute to ten years.
+const MIN_STABILITY_IN_HOURS: f64 = 0.01;
+const MAX_STABILITY_IN_HOURS: f64 = 24.0 * 365.0 * 10.0;
+const FIT_ITERATIONS: usize = 100;
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct RecallObservation {
+    pub facet: String,
+    /// Hours since the facet's previous review.
+    pub elapsed_hours: f64,
+    pub recalled: bool,
+}
+
+/// An exponential forgetting curve fitted to some observations.
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct DecayFit {
+    pub observations: usize,
+    /// How many hours it takes recall to fall to 1/e (about 37%).
+    pub stability_hours: f64,
+    /// How many hours it takes recall to fall to 50%.
+    pub half_life_hours: f64,
+    /// Of the observations under the fitted curve. Higher is a better fit.
+    pub log_likelihood: f64,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Default)]
+pub struct ForgettingCurves {
+    pub observations: Vec<RecallObservation>,
+    /// Over every observation, or None if there weren't any.
+    pub overall: Option<DecayFit>,
+    pub facets: BTreeMap<String, DecayFit>,
+}
+
+fn log_likelihood(points: &[(f64, bool)], stability_hours: f64) -> f64 {
+    points
+        .iter()
+        .map(|(elapsed_hours, recalled)| {
+            let log_recall = -elapsed_hours / stability_hours;
+            //Forgetting straight after a review is possible but not under this curve, so it's kept from costing infinitely much.
+            if *recalled { log_recall } else { (-log_recall.exp_m1()).max(1e-12).ln() }
+        })
+        .sum()
+}
+
+/// Fits an exponential forgetting curve to (hours elapsed, recalled) points by maximum likelihood. None without any points.
+pub fn fit_decay(points: &[(f64, bool)]) -> Option<DecayFit> {
+    if points.is_empty() {
+        return None;
+    }
+    //Golden-section search over the log of the stability, where the likelihood has a single peak.
+    let ratio = (5f64.sqrt() - 1.0) / 2.0;
+    let (mut low, mut high) = (MIN_STABILITY_IN_HOURS.ln(), MAX_STABILITY_IN_HOURS.ln());
+    for _ in 0..FIT_ITERATIONS {
+        let left = high - ratio * (high - low);
+        let right = low + ratio * (high - low);
+        if log_likelihood(points, left.exp()) < log_likelihood(points, right.exp()) {
+            low = left;
+        } else {
+            high = right;
+        }
+    }
+    let stability_hours = ((low + high) / 2.0).exp();
+    Some(DecayFit {
+        observations: points.len(),
+        stability_hours,
+        half_life_hours: stability_hours * std::f64::consts::LN_2,
+        log_likelihood: log_likelihood(points, stability_hours),
+    })
+}
+
+/// The observations in `events` and the curves fitted to them.
+pub fn forgetting_curves(events: &[ReviewEvent]) -> ForgettingCurves {
+    let observations: Vec<RecallObservation> = repeat_reviews(events)
+        .into_iter()
+        .map(|review| RecallObservation { facet: review.facet, elapsed_hours: review.elapsed.as_secs_f64() / 3600.0, recalled: review.recalled })
+        .collect();
+    let mut points_by_facet: HashMap<&String, Vec<(f64, bool)>> = HashMap::new();
+    for observation in observations.iter() {
+        points_by_facet.entry(&observation.facet).or_default().push((observation.elapsed_hours, observation.recalled));
+    }
+    let all_points: Vec<(f64, bool)> = observations.iter().map(|observation| (observation.elapsed_hours, observation.recalled)).collect();
+    ForgettingCurves {
+        overall: fit_decay(&all_points),
+        facets: points_by_facet.into_iter().filter_map(|(facet, points)| Some((facet.clone(), fit_decay(&points)?))).collect(),
+        observations,
+    }
+}
+
+/// Writes [`forgetting_curves`] to `path`: as CSV if the path ends in `.csv`, one row per observation with its facet's fitted stability and half-life alongside, and as JSON otherwise.
+pub fn write_forgetting_curves<P: AsRef<Path>>(events: &[ReviewEvent], path: P) -> Result<()> {
+    let curves = forgetting_curves(events);
+    let is_csv = path.as_ref().extension().is_some_and(|extension| extension == "csv");
+    let mut file = BufWriter::new(File::create(path)?);
+    if !is_csv {
+        serde_json::to_writer_pretty(&mut file, &curves)?;
+        writeln!(file)?;
+        return Ok(file.flush()?);
+    }
+    writeln!(file, "facet,elapsed_hours,recalled,stability_hours,half_life_hours")?;
+    for observation in curves.observations.iter() {
+        let fit = curves.facets.get(&observation.facet);
+        let fields = [
+            csv_field(Some(&observation.facet)),
+            csv_field(Some(observation.elapsed_hours)),
+            csv_field(Some(observation.recalled)),
+            csv_field(fit.map(|fit| fit.stability_hours)),
+            csv_field(fit.map(|fit| fit.half_life_hours)),
+        ];
+        writeln!(file, "{}", fields.join(","))?;
+    }
+    Ok(file.flush()?)
+}
@@ -0,0 +1,79 @@
+//Writes an ordering out as one self-contained HTML page, a card per gem in study order, for reading on a phone or printing.
+//Each side is rendered by its role: the text with its furigana as ruby, pictures as <img>, recordings as <audio> players, and translations and notes from Markdown. Media paths are written as they resolve, so the page finds its files when it's saved next to the deck.
+
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    audio::audio_reference,
+    collection::GemCollection,
+    error::Result,
+    gem::GemId,
+    markdown::{is_markdown, markdown_to_html},
+    ruby::{escape_html, ruby_to_html},
+    side::SideRole,
+};
+
+const STYLE: &str = "body{font-family:sans-serif;max-width:40em;margin:auto}.gem{border-bottom:1px solid #ccc;padding:1em 0}.role{color:#888;font-size:small}.side{margin:.5em 0}img{max-width:100%}";
+
+fn media_path(media_dir: Option<&Path>, name: &str) -> PathBuf {
+    match media_dir {
+        Some(media_dir) => media_dir.join(name),
+        None => PathBuf::from(name),
+    }
+}
+
+//One side as block-level HTML, or None for a media side that doesn't name anything.
+fn render_side(role: SideRole, side: &str, media_dir: Option<&Path>) -> Option<String> {
+    let rendered = match role {
+        SideRole::Image => {
+            let name = Some(side.trim()).filter(|name| !name.is_empty())?;
+            format!("<p><img src=\"{}\" alt=\"\"></p>", escape_html(&media_path(media_dir, name).to_string_lossy()))
+        }
+        SideRole::Audio => format!("<p><audio controls src=\"{}\"></audio></p>", escape_html(&media_path(media_dir, audio_reference(side)?).to_string_lossy())),
+        role if is_markdown(role) => markdown_to_html(side),
+        _ => format!("<p>{}</p>", ruby_to_html(side)),
+    };
+    Some(rendered)
+}
+
+/// Writes the gems listed in `order` (gem ids, easiest first) to `path` as an HTML page, rendering sides by the collection's [`crate::side::SideRoles`]. Relative media names are looked up in `media_dir`.
+/// Ids that aren't in the collection are skipped.
+pub fn write_html<P: AsRef<Path>>(gem_collection: &GemCollection, order: &[GemId], media_dir: Option<&Path>, path: P) -> Result<()> {
+    let mut file = BufWriter::new(File::create(path)?);
+    writeln!(file, "<!DOCTYPE html>")?;
+    writeln!(file, "<html><head><meta charset=\"utf-8\"><title>langwitch</title><style>{}</style></head><body>", STYLE)?;
+    for (position, gem_id) in order.iter().enumerate() {
+        let gem = match gem_collection.get(*gem_id) {
+            Some(gem) => gem,
+            None => continue,
+        };
+        writeln!(file, "<div class=\"gem\" id=\"gem-{}\">", position + 1)?;
+        let mut side_numbers: Vec<&usize> = gem.sides.keys().collect();
+        side_numbers.sort_unstable();
+        for side_number in side_numbers {
+            let role = gem_collection.side_roles.role(*side_number);
+            if let Some(rendered) = render_side(role, &gem.sides[side_number], media_dir) {
+                writeln!(file, "<div class=\"side\"><span class=\"role\">{}</span>{}</div>", role.label(), rendered)?;
+            }
+        }
+        let mut facets: Vec<&str> = gem.unknown_facets.iter().map(|facet| gem_collection.facet_name(*facet)).collect();
+        facets.sort_unstable();
+        writeln!(file, "<p class=\"role\">{}</p>", escape_html(&facets.join(", ")))?;
+        writeln!(file, "</div>")?;
+    }
+    writeln!(file, "</body></html>")?;
+    file.flush()?;
+    Ok(())
+}
+
+impl GemCollection {
+    /// Orders a copy of the collection by difficulty and writes it out as an HTML page. The collection itself is left untouched.
+    pub fn export_html<P: AsRef<Path>>(&self, media_dir: Option<&Path>, path: P) -> Result<()> {
+        let order = self.clone().difficulty_order()?;
+        write_html(self, &order, media_dir, path)
+    }
+}
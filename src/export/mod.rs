@@ -0,0 +1,6 @@
+//Exporters that hand an ordered deck over to other flashcard programs, and the learner's own data over to other tools.
+
+pub mod anki;
+pub mod curves;
+pub mod html;
+pub mod progress;
@@ -0,0 +1,102 @@
+//Writes the learner's progress out in a form other tools (and people) can read: one record per facet that's known or has been reviewed, with its scheduling state and timestamps as unix milliseconds. Good for backups, for moving progress between machines, and for seeding another deck (the CSV works as a word list for GemCollection::import_known_words once it's filtered down to the known rows).
+
+use std::{
+    collections::BTreeSet,
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+use serde::{Serialize, Deserialize};
+
+use crate::{
+    collection::GemCollection,
+    error::Result,
+    timestamp::to_millis,
+};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct FacetRecord {
+    pub facet: String,
+    pub known: bool,
+    pub review_date: Option<u64>,
+    pub last_seen_date: Option<u64>,
+    pub lifetime_in_hours: Option<f64>,
+    pub stage: Option<String>,
+    pub ease_factor: Option<f64>,
+    pub repetitions: Option<u32>,
+    pub leitner_box: Option<usize>,
+}
+
+const CSV_HEADER: &str = "facet,known,review_date,last_seen_date,lifetime_in_hours,stage,ease_factor,repetitions,leitner_box";
+
+/// One record per facet that's known or has scheduling state, sorted by facet name.
+pub fn facet_records(gem_collection: &GemCollection) -> Vec<FacetRecord> {
+    let known = gem_collection.known_facet_names();
+    let names: BTreeSet<&String> = known.iter().chain(gem_collection.facet_states.keys()).collect();
+    names
+        .into_iter()
+        .map(|name| {
+            let state = gem_collection.facet_states.get(name);
+            FacetRecord {
+                facet: name.clone(),
+                known: known.contains(name),
+                review_date: state.and_then(|state| state.review_date).map(to_millis),
+                last_seen_date: state.and_then(|state| state.last_seen_date).map(to_millis),
+                lifetime_in_hours: state.and_then(|state| state.lifetime_in_hours),
+                stage: state.and_then(|state| state.stage.clone()),
+                ease_factor: state.and_then(|state| state.ease_factor),
+                repetitions: state.and_then(|state| state.repetitions),
+                leitner_box: state.and_then(|state| state.leitner_box),
+            }
+        })
+        .collect()
+}
+
+//Quotes a CSV field if it needs it. Missing values are empty fields.
+pub(crate) fn csv_field<T: ToString>(value: Option<T>) -> String {
+    let value = match value {
+        Some(value) => value.to_string(),
+        None => return String::new(),
+    };
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value
+    }
+}
+
+/// Writes [`facet_records`] to `path`: as CSV with a header row if the path ends in `.csv`, and as a JSON array otherwise.
+pub fn write_progress_export<P: AsRef<Path>>(gem_collection: &GemCollection, path: P) -> Result<()> {
+    let records = facet_records(gem_collection);
+    let is_csv = path.as_ref().extension().is_some_and(|extension| extension == "csv");
+    let mut file = BufWriter::new(File::create(path)?);
+    if !is_csv {
+        serde_json::to_writer_pretty(&mut file, &records)?;
+        writeln!(file)?;
+        return Ok(file.flush()?);
+    }
+    writeln!(file, "{}", CSV_HEADER)?;
+    for record in records {
+        let fields = [
+            csv_field(Some(record.facet)),
+            csv_field(Some(record.known)),
+            csv_field(record.review_date),
+            csv_field(record.last_seen_date),
+            csv_field(record.lifetime_in_hours),
+            csv_field(record.stage),
+            csv_field(record.ease_factor),
+            csv_field(record.repetitions),
+            csv_field(record.leitner_box),
+        ];
+        writeln!(file, "{}", fields.join(","))?;
+    }
+    Ok(file.flush()?)
+}
+
+impl GemCollection {
+    /// Same as [`write_progress_export`].
+    pub fn export_progress<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        write_progress_export(self, path)
+    }
+}
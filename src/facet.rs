@@ -0,0 +1,90 @@
+//A Facet represents an underlying latent concept (a word, a phrase, a grammar point) along with the scheduling data for it. When a Gem is reviewed, the user marks which of the facets they got correct, and a Scheduler updates each Facet's review dates based on that.
+//Ported from gems_old.rs, with every unwrap turned into a LangwitchError.
+
+use std::{
+    fs::File,
+    io::{Read, Write},
+    time::SystemTime,
+};
+
+use serde::{Serialize, Deserialize};
+
+use crate::{
+    error::Result,
+    scheduler::heuristic::Heuristic,
+};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct Facet {
+    pub name: String,
+    pub review_date: Option<SystemTime>,
+    pub last_seen_date: Option<SystemTime>,
+    pub lifetime_in_hours: Option<f64>,
+    pub stage: Option<String>,
+    //Only used by the SM-2 scheduler.
+    #[serde(default)]
+    pub ease_factor: Option<f64>,
+    #[serde(default)]
+    pub repetitions: Option<u32>,
+    //Only used by the Leitner scheduler.
+    #[serde(default)]
+    pub leitner_box: Option<usize>,
+}
+
+//How long a facet is expected to last after the very first time it's seen.
+pub const INITIAL_LIFETIME_IN_HOURS: f64 = 4.0;
+
+impl Facet {
+    /// A facet being seen for the first time at `now`: due straight away, with a short starting lifetime.
+    pub fn new(name: &str, now: SystemTime) -> Facet {
+        Facet {
+            name: name.to_string(),
+            review_date: Some(now),
+            last_seen_date: Some(now),
+            lifetime_in_hours: Some(INITIAL_LIFETIME_IN_HOURS),
+            stage: Some("new".to_string()),
+            ease_factor: None,
+            repetitions: None,
+            leitner_box: None,
+        }
+    }
+}
+
+//The scheduling updates themselves live in scheduler::heuristic now; these are kept so code written against the old Facet API still works.
+impl Facet {
+    pub fn update_facet_binary(&mut self, correct: bool) -> Result<()> {
+        self.update_facet_binary_at(correct, SystemTime::now())
+    }
+    /// Same as [`Facet::update_facet_binary`], but as if the review happened at `now`.
+    pub fn update_facet_binary_at(&mut self, correct: bool, now: SystemTime) -> Result<()> {
+        *self = Heuristic.review_binary(self, correct, now)?;
+        Ok(())
+    }
+    pub fn update_facet_fuzzy(&mut self, correct: f64) -> Result<()> {
+        self.update_facet_fuzzy_at(correct, SystemTime::now())
+    }
+    pub fn update_facet_fuzzy_at(&mut self, correct: f64, now: SystemTime) -> Result<()> {
+        *self = Heuristic.review_fuzzy(self, correct, now)?;
+        Ok(())
+    }
+    pub fn average_facet_fields(&self, facet_1: Facet, facet_2: Facet, correct: f64) -> Result<Facet> {
+        Heuristic.average_facet_fields(self, facet_1, facet_2, correct, SystemTime::now())
+    }
+}
+
+//Method for reading and writing a list of facets to and from a json file:
+impl Facet {
+    pub fn read_from_file(filename: &str) -> Result<Vec<Facet>> {
+        let mut file = File::open(filename)?;
+        let mut contents = String::new();
+        file.read_to_string(&mut contents)?;
+        let facets: Vec<Facet> = serde_json::from_str(&contents)?;
+        Ok(facets)
+    }
+    pub fn write_to_file(facets: &[Facet], filename: &str) -> Result<()> {
+        let mut file = File::create(filename)?;
+        let contents = serde_json::to_string(facets)?;
+        file.write_all(contents.as_bytes())?;
+        Ok(())
+    }
+}
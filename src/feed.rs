@@ -0,0 +1,105 @@
+//RSS and Atom feeds as a source of fresh sentences. Each item's text is mined into gems, which are appended to the deck unless the deck already has them, so fetching the same feed twice doesn't grow the deck twice.
+
+use regex::Regex;
+
+use crate::{
+    collection::GemCollection,
+    error::Result,
+    gem::{Gem, GemId},
+    import::strip_html,
+    mine::{mine_text_with, MineOptions},
+};
+#[cfg(feature = "native")]
+use crate::fetch::fetch_text;
+
+/// One RSS `<item>` or Atom `<entry>`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct FeedItem {
+    pub title: String,
+    pub link: String,
+    /// The item's description or content, as plain text.
+    pub text: String,
+}
+
+//Feed text is usually HTML that's been escaped (or wrapped in CDATA) to fit inside XML, so it's unwrapped, unescaped and only then stripped.
+fn element_text(xml: &str) -> String {
+    let unwrapped = xml.trim().trim_start_matches("<![CDATA[").trim_end_matches("]]>");
+    let unescaped = unwrapped
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&");
+    strip_html(&unescaped)
+}
+
+//The contents of the first <name>...</name> in `xml`, whatever its namespace prefix and attributes.
+fn element<'a>(xml: &'a str, name: &str) -> Option<&'a str> {
+    let pattern = format!(r"(?is)<(?:[\w-]+:)?{0}\b[^>]*>(.*?)</(?:[\w-]+:)?{0}>", regex::escape(name));
+    Regex::new(&pattern).expect("element pattern is valid").captures(xml).and_then(|captures| captures.get(1)).map(|found| found.as_str())
+}
+
+/// Parses the items out of an RSS or Atom feed.
+pub fn parse_feed(xml: &str) -> Vec<FeedItem> {
+    let items = Regex::new(r"(?is)<item\b[^>]*>(.*?)</item>|<entry\b[^>]*>(.*?)</entry>").expect("item pattern is valid");
+    let atom_link = Regex::new(r#"(?is)<link\b[^>]*href\s*=\s*["']([^"']*)["']"#).expect("link pattern is valid");
+    items
+        .captures_iter(xml)
+        .filter_map(|captures| captures.get(1).or_else(|| captures.get(2)))
+        .map(|item| {
+            let item = item.as_str();
+            //RSS puts the link between the tags, Atom in an href.
+            let link = match element(item, "link").map(element_text).filter(|link| !link.is_empty()) {
+                Some(link) => link,
+                None => atom_link.captures(item).map(|captures| captures[1].to_string()).unwrap_or_default(),
+            };
+            let text = ["content:encoded", "content", "description", "summary"]
+                .iter()
+                .find_map(|name| element(item, name))
+                .map(element_text)
+                .unwrap_or_default();
+            FeedItem {
+                title: element(item, "title").map(element_text).unwrap_or_default(),
+                link,
+                text,
+            }
+        })
+        .collect()
+}
+
+/// Mines a feed's items into gems. Side 0 is the sentence and side 1 the link to the item it came from. Titles are mined too, since headlines are often the best sentences in a feed.
+pub fn mine_feed(items: &[FeedItem], options: &MineOptions) -> Result<Vec<Gem>> {
+    let tokenizer = options.tokenizer.build()?;
+    let mut gems = Vec::new();
+    for item in items {
+        let text = format!("{}\n\n{}", item.title, item.text);
+        for mut gem in mine_text_with(&text, options, tokenizer.as_ref()) {
+            if !item.link.is_empty() {
+                gem.sides.insert(1, item.link.clone());
+            }
+            gems.push(gem);
+        }
+    }
+    Ok(gems)
+}
+
+/// Downloads a feed and mines it into gems.
+#[cfg(feature = "native")]
+pub fn fetch_feed(url: &str, options: &MineOptions) -> Result<Vec<Gem>> {
+    mine_feed(&parse_feed(&fetch_text(url)?), options)
+}
+
+impl GemCollection {
+    /// Appends the gems the collection doesn't already have (by key, so the same sentence from the same link is only added once) and returns the ids of the ones added.
+    /// The indices aren't updated, so index afterwards, or use [`GemCollection::add_gems`] to do both.
+    pub fn append_new_gems(&mut self, gems: Vec<Gem>) -> Vec<GemId> {
+        let mut added = Vec::new();
+        for gem in gems {
+            if self.gem_id(&gem.key()).is_none() {
+                added.push(self.push_gem(gem));
+            }
+        }
+        added
+    }
+}
@@ -0,0 +1,35 @@
+//Fetching over HTTP, for the feed and article importers and for text-to-speech services. Blocking, like the rest of the library; callers in async code should hand it to spawn_blocking.
+
+use crate::error::Result;
+
+const USER_AGENT: &str = concat!("langwitch/", env!("CARGO_PKG_VERSION"));
+
+/// Downloads `url` and returns the body as text.
+pub fn fetch_text(url: &str) -> Result<String> {
+    let mut response = ureq::get(url).header("User-Agent", USER_AGENT).call()?;
+    Ok(response.body_mut().read_to_string()?)
+}
+
+/// Same as [`fetch_text`], sending `token` (if there is one) as a bearer token.
+pub fn fetch_text_with_token(url: &str, token: Option<&str>) -> Result<String> {
+    let mut request = ureq::get(url).header("User-Agent", USER_AGENT);
+    if let Some(token) = token {
+        request = request.header("Authorization", format!("Bearer {}", token));
+    }
+    Ok(request.call()?.body_mut().read_to_string()?)
+}
+
+/// Posts `body` as `content_type` to `url`, sending `token` (if there is one) as a bearer token, and returns the response body as text.
+pub fn post_text_with_token(url: &str, token: Option<&str>, content_type: &str, body: &str) -> Result<String> {
+    let mut request = ureq::post(url).header("User-Agent", USER_AGENT).header("Content-Type", content_type);
+    if let Some(token) = token {
+        request = request.header("Authorization", format!("Bearer {}", token));
+    }
+    Ok(request.send(body)?.body_mut().read_to_string()?)
+}
+
+/// Posts `text` to `url` and returns the response body as it is, e.g. the audio a speech service sends back.
+pub fn post_text(url: &str, text: &str) -> Result<Vec<u8>> {
+    let mut response = ureq::post(url).header("User-Agent", USER_AGENT).header("Content-Type", "text/plain; charset=utf-8").send(text)?;
+    Ok(response.body_mut().read_to_vec()?)
+}
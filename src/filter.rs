@@ -0,0 +1,270 @@
+//Load-time facet filters. Tokenizing raw text turns numbers, stray punctuation and names into facets, and a gem shouldn't count as harder because it mentions "3,000" or "NASA". Filters strip those out of every gem's unknown_facets before indexing, so difficulty counts only cover things worth learning.
+//On top of the built-in classes, users can keep an exclusion list (excluded_facets.txt) of names, brands and interjections they never want scheduled.
+//Thresholds catch the rest: single letters and hapax legomena can be dropped or simply counted as known, and gems with too many facets to be learnable can be left out.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    io::ErrorKind,
+    path::Path,
+};
+
+use regex::Regex;
+use serde::{Serialize, Deserialize};
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::{
+    collection::GemCollection,
+    error::Result,
+    gem::{Gem, GemId},
+    interner::FacetId,
+};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct FacetFilter {
+    /// Drop facets that are numbers: a digit and no letters, like "42" or "3,000".
+    pub digits: bool,
+    /// Drop facets with no letters or digits at all.
+    pub punctuation: bool,
+    /// Drop facets that look like proper nouns: capitalized everywhere they appear mid-sentence, and never written in lowercase mid-sentence.
+    pub proper_nouns: bool,
+    /// Drop facets matching any of these regular expressions.
+    pub patterns: Vec<String>,
+    /// Drop these facets outright. They go through the collection's normalization first, so the list can be written in any inflection or case.
+    pub excluded: HashSet<String>,
+    /// Facets shorter than this many characters are below threshold. 0 turns it off.
+    pub min_facet_len: usize,
+    /// Facets that appear in fewer gems than this are below threshold. 0 turns it off.
+    pub min_corpus_frequency: usize,
+    /// What happens to facets below either threshold.
+    pub below_threshold: ThresholdAction,
+    /// Gems left with more unknown facets than this after filtering are dropped from the deck.
+    pub max_facets_per_gem: Option<usize>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ThresholdAction {
+    /// Take the facet out of every gem, as if it had never been there.
+    #[default]
+    Drop,
+    /// Count the facet as known, so it's saved with the progress and stays out of the queue.
+    MarkKnown,
+}
+
+/// What [`GemCollection::filter_facets`] did.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct FilterReport {
+    /// How many (gem, facet) pairs were taken out of gems.
+    pub facets_removed: usize,
+    /// How many distinct facets were marked known for falling below a threshold.
+    pub facets_marked_known: usize,
+    /// How many gems were dropped for having more than `max_facets_per_gem` facets.
+    pub gems_dropped: usize,
+}
+
+impl FacetFilter {
+    /// Reads an exclusion list: one facet per line, with blank lines and lines starting with `#` skipped. A missing file excludes nothing.
+    pub fn read_exclusions<P: AsRef<Path>>(path: P) -> Result<HashSet<String>> {
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(HashSet::new()),
+            Err(e) => return Err(e.into()),
+        };
+        Ok(contents
+            .lines()
+            .map(|line| line.trim())
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| line.to_string())
+            .collect())
+    }
+}
+
+fn is_number(facet: &str) -> bool {
+    facet.chars().any(|c| c.is_numeric()) && !facet.chars().any(|c| c.is_alphabetic())
+}
+
+fn is_punctuation(facet: &str) -> bool {
+    !facet.chars().any(|c| c.is_alphanumeric())
+}
+
+fn ends_sentence(segment: &str) -> bool {
+    segment.chars().any(|c| matches!(c, '.' | '!' | '?' | '…' | '。' | '！' | '？'))
+}
+
+//Walks every gem's text side and counts, for each lowercased word, how often it's written capitalized or in lowercase when it isn't the first word of a sentence (where everything is capitalized). Words that are only ever capitalized there are taken to be names.
+fn proper_noun_names<'a, I: IntoIterator<Item = &'a HashMap<usize, String>>>(sides: I, text_side: usize) -> HashSet<String> {
+    let mut capitalized: HashMap<String, usize> = HashMap::new();
+    let mut lowercase: HashMap<String, usize> = HashMap::new();
+    for sides in sides {
+        let text = match sides.get(&text_side) {
+            Some(text) => text,
+            None => continue,
+        };
+        let mut sentence_start = true;
+        for segment in text.split_word_bounds() {
+            let first = match segment.chars().next() {
+                Some(first) if first.is_alphabetic() => first,
+                _ => {
+                    if ends_sentence(segment) {
+                        sentence_start = true;
+                    }
+                    continue;
+                }
+            };
+            if !sentence_start {
+                let counts = if first.is_uppercase() { &mut capitalized } else { &mut lowercase };
+                *counts.entry(segment.to_lowercase()).or_insert(0) += 1;
+            }
+            sentence_start = false;
+        }
+    }
+    capitalized
+        .into_keys()
+        .filter(|word| !lowercase.contains_key(word))
+        .collect()
+}
+
+//What the built-in classes, patterns and exclusion list reject, worked out once for a pass over the facets.
+struct Rejection {
+    patterns: Vec<Regex>,
+    proper_nouns: HashSet<String>,
+    excluded: HashSet<String>,
+}
+
+impl Rejection {
+    //Proper nouns are judged from the text sides in `sides`.
+    fn new<'a, I: IntoIterator<Item = &'a HashMap<usize, String>>>(gem_collection: &GemCollection, filter: &FacetFilter, sides: I) -> Result<Rejection> {
+        let patterns = filter
+            .patterns
+            .iter()
+            .map(|pattern| Regex::new(pattern))
+            .collect::<std::result::Result<Vec<Regex>, regex::Error>>()?;
+        let proper_nouns = if filter.proper_nouns {
+            proper_noun_names(sides, gem_collection.side_roles.text_side())
+        } else {
+            HashSet::new()
+        };
+        let excluded: Vec<String> = filter.excluded.iter().cloned().collect();
+        let excluded: HashSet<String> = gem_collection.normalize_facet_names(&excluded)?.into_iter().collect();
+        Ok(Rejection { patterns, proper_nouns, excluded })
+    }
+
+    fn rejects(&self, filter: &FacetFilter, name: &str) -> bool {
+        (filter.digits && is_number(name))
+            || (filter.punctuation && is_punctuation(name))
+            || (filter.proper_nouns && self.proper_nouns.contains(&name.to_lowercase()))
+            || self.patterns.iter().any(|pattern| pattern.is_match(name))
+            || self.excluded.contains(name)
+    }
+}
+
+impl GemCollection {
+    /// Removes every facet `filter` rejects from every gem's unknown facets (or marks it known, for thresholds set to do that) and drops gems that are still too big.
+    /// Meant to run straight after loading: the indices aren't updated and dropping gems hands out new gem ids, so index afterwards.
+    /// The filter is kept on the collection, and [`GemCollection::add_gems`] puts gems added later through it too.
+    pub fn filter_facets(&mut self, filter: &FacetFilter) -> Result<FilterReport> {
+        self.facet_filter = filter.clone();
+        let rejection = Rejection::new(self, filter, self.gems.iter().map(|gem| &gem.sides))?;
+        let rejected: HashSet<FacetId> = (0..self.interner.len())
+            .map(|id| FacetId(id as u32))
+            .filter(|id| rejection.rejects(filter, self.interner.name(*id)))
+            .collect();
+        let mut report = FilterReport {
+            facets_removed: self.remove_facets(&rejected),
+            ..FilterReport::default()
+        };
+
+        //Frequencies are counted after the other filters, over what's actually left in the gems.
+        if filter.min_facet_len > 0 || filter.min_corpus_frequency > 0 {
+            let mut frequencies: HashMap<FacetId, usize> = HashMap::new();
+            for gem in self.gems.iter() {
+                for facet in gem.unknown_facets.iter() {
+                    *frequencies.entry(*facet).or_insert(0) += 1;
+                }
+            }
+            let below: HashSet<FacetId> = frequencies
+                .into_iter()
+                .filter(|(facet, frequency)| {
+                    self.interner.name(*facet).chars().count() < filter.min_facet_len || *frequency < filter.min_corpus_frequency
+                })
+                .map(|(facet, _)| facet)
+                .collect();
+            match filter.below_threshold {
+                ThresholdAction::Drop => report.facets_removed += self.remove_facets(&below),
+                ThresholdAction::MarkKnown => {
+                    report.facets_marked_known = below.iter().filter(|facet| !self.known_facets.contains(facet)).count();
+                    self.known_facets.extend(below);
+                }
+            }
+        }
+
+        if let Some(max_facets_per_gem) = filter.max_facets_per_gem {
+            let known_facets = &self.known_facets;
+            let too_big: HashSet<GemId> = self
+                .gem_ids()
+                .filter(|gem_id| {
+                    let gem = &self.gems[gem_id.0];
+                    gem.unknown_facets.iter().filter(|facet| !known_facets.contains(facet)).count() > max_facets_per_gem
+                })
+                .collect();
+            report.gems_dropped = too_big.len();
+            self.drop_gems(&too_big);
+        }
+        Ok(report)
+    }
+
+    //Puts gems that are about to be added after loading through the filter filter_facets last applied: rejected facets are taken out, facets below a threshold are dropped or marked known, and gems still too big are left out. Proper nouns are judged over the collection's text and the new gems' together, and frequencies are the collection's as indexed plus the new gems'.
+    pub(crate) fn filter_new_gems(&mut self, mut gems: Vec<Gem>) -> Result<Vec<Gem>> {
+        let filter = self.facet_filter.clone();
+        if filter == FacetFilter::default() {
+            return Ok(gems);
+        }
+        let rejection = Rejection::new(self, &filter, self.gems.iter().map(|gem| &gem.sides).chain(gems.iter().map(|gem| &gem.sides)))?;
+        for gem in gems.iter_mut() {
+            gem.unknown_facets.retain(|facet| !rejection.rejects(&filter, facet));
+        }
+        if filter.min_facet_len > 0 || filter.min_corpus_frequency > 0 {
+            let mut frequencies: HashMap<String, usize> = HashMap::new();
+            for facet in gems.iter().flat_map(|gem| gem.unknown_facets.iter()) {
+                *frequencies.entry(facet.clone()).or_insert(0) += 1;
+            }
+            let below: HashSet<String> = frequencies
+                .into_iter()
+                .filter(|(facet, frequency)| {
+                    let indexed = self.interner.get(facet).and_then(|id| self.total_frequency_list.get(&id)).copied().unwrap_or(0);
+                    facet.chars().count() < filter.min_facet_len || frequency + indexed < filter.min_corpus_frequency
+                })
+                .map(|(facet, _)| facet)
+                .collect();
+            match filter.below_threshold {
+                ThresholdAction::Drop => {
+                    for gem in gems.iter_mut() {
+                        gem.unknown_facets.retain(|facet| !below.contains(facet));
+                    }
+                }
+                ThresholdAction::MarkKnown => self.insert_known_facets(below.iter()),
+            }
+        }
+        if let Some(max_facets_per_gem) = filter.max_facets_per_gem {
+            let known = |facet: &String| self.interner.get(facet).is_some_and(|id| self.known_facets.contains(&id));
+            gems.retain(|gem| gem.unknown_facets.iter().filter(|facet| !known(facet)).count() <= max_facets_per_gem);
+        }
+        Ok(gems)
+    }
+
+    //Takes facets out of every gem (without marking them known) and returns how many were taken out.
+    pub(crate) fn remove_facets(&mut self, facets: &HashSet<FacetId>) -> usize {
+        if facets.is_empty() {
+            return 0;
+        }
+        let mut removed = 0;
+        for gem in self.gems.iter_mut() {
+            let before = gem.unknown_facets.len();
+            gem.unknown_facets.retain(|facet| !facets.contains(facet));
+            removed += before - gem.unknown_facets.len();
+        }
+        removed
+    }
+}
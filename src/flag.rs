@@ -0,0 +1,57 @@
+//Flagging problem gems: a bad translation, a typo, a sentence that's too long. A flag is a short reason and an optional free-text note, kept on the gem itself so it travels with the deck, and the flagged gems can be listed later to clean the deck up outside of review.
+//Flags live in the deck file rather than in progress, and a collection loaded for review has had its facets stripped and normalized, so a flag raised during review is written straight into the deck file as it is on disk with flag_in_deck_file, as well as onto the loaded gem.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    collection::GemCollection,
+    error::{LangwitchError, Result},
+    gem::{GemId, GemKey},
+};
+
+/// Something wrong with a gem, e.g. {"reason": "typo", "note": "\"teh\" in the second clause"}.
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone)]
+pub struct GemFlag {
+    pub reason: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+impl GemFlag {
+    pub fn new(reason: &str, note: Option<&str>) -> GemFlag {
+        GemFlag { reason: reason.trim().to_string(), note: note.map(str::trim).filter(|note| !note.is_empty()).map(str::to_string) }
+    }
+}
+
+impl GemCollection {
+    /// Adds a flag to a gem. A flag it already has isn't added twice.
+    pub fn flag(&mut self, gem_id: GemId, flag: GemFlag) -> Result<()> {
+        let gem = self.gems.get_mut(gem_id.0).ok_or(LangwitchError::MissingGem(gem_id.0))?;
+        if !gem.flags.contains(&flag) {
+            gem.flags.push(flag);
+        }
+        Ok(())
+    }
+
+    /// Clears a gem's flags, returning false if it had none.
+    pub fn unflag(&mut self, gem_id: GemId) -> bool {
+        self.gems.get_mut(gem_id.0).is_some_and(|gem| !std::mem::take(&mut gem.flags).is_empty())
+    }
+
+    /// Every flagged gem, in id order.
+    pub fn flagged(&self) -> impl Iterator<Item = GemId> + '_ {
+        self.gem_ids().filter(|gem_id| !self.gems[gem_id.0].flags.is_empty())
+    }
+
+    /// Flags the gem with key `key` in the deck at `deck_path` and writes the deck back, leaving everything else in it as it was. Returns false if the deck has no such gem.
+    pub fn flag_in_deck_file(deck_path: &str, key: &GemKey, flag: GemFlag) -> Result<bool> {
+        let mut deck = GemCollection::read_gems_from_file(deck_path)?;
+        let gem_id = match deck.gem_id(key) {
+            Some(gem_id) => gem_id,
+            None => return Ok(false),
+        };
+        deck.flag(gem_id, flag)?;
+        deck.write_gems_to_file(deck_path)?;
+        Ok(true)
+    }
+}
@@ -0,0 +1,126 @@
+#[allow(unused_imports)]
+use serde::{Serialize, Deserialize};
+use std::{
+    collections::{HashSet, HashMap},
+    fmt,
+};
+
+use crate::{
+    flag::GemFlag,
+    interner::{FacetId, Interner},
+    schema::StoredGem,
+};
+
+//Gem: vec of strings, hashset of facets, hashset of strings
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+#[serde(from = "StoredGem")]
+pub struct Gem {
+    //An explicit stable identifier. Gems without one are identified by a hash of their sides instead (see Gem::key).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub sides: HashMap<usize, String>,
+    pub unknown_facets: HashSet<String>,
+    //What's wrong with the gem, if anything (see crate::flag).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub flags: Vec<GemFlag>,
+}
+
+impl Gem {
+    /// What journals and stores use to refer to this gem: its `id` if it has one, otherwise a hash of its sides. Either way it stays the same when other gems are added to or removed from the deck.
+    pub fn key(&self) -> GemKey {
+        match &self.id {
+            Some(id) => GemKey(id.clone()),
+            None => GemKey::from_sides(&self.sides),
+        }
+    }
+}
+
+/// A gem's stable identifier (see [`Gem::key`]). Unlike a [`GemId`], it doesn't depend on where the gem sits in the deck.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
+pub struct GemKey(pub String);
+
+impl GemKey {
+    //FNV-1a over the sides in side order. It's spelled out here rather than using std's hasher because std's isn't guaranteed to give the same answer across Rust versions, and these end up in files.
+    pub fn from_sides(sides: &HashMap<usize, String>) -> GemKey {
+        let mut side_numbers: Vec<&usize> = sides.keys().collect();
+        side_numbers.sort_unstable();
+        let mut hash: u64 = 0xcbf29ce484222325;
+        let mut feed = |bytes: &[u8]| {
+            for byte in bytes {
+                hash ^= *byte as u64;
+                hash = hash.wrapping_mul(0x100000001b3);
+            }
+        };
+        for side_number in side_numbers {
+            feed(&(*side_number as u64).to_le_bytes());
+            feed(sides[side_number].as_bytes());
+            //A separator, so {"ab", "c"} and {"a", "bc"} don't collide.
+            feed(&[0xff]);
+        }
+        GemKey(format!("{:016x}", hash))
+    }
+}
+
+impl fmt::Display for GemKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+//Journals written before gems had stable keys refer to gems by number, so a number is read as a key too.
+impl<'de> Deserialize<'de> for GemKey {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> std::result::Result<GemKey, D::Error> {
+        struct GemKeyVisitor;
+        impl serde::de::Visitor<'_> for GemKeyVisitor {
+            type Value = GemKey;
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "a gem key string or a legacy gem number")
+            }
+            fn visit_str<E: serde::de::Error>(self, value: &str) -> std::result::Result<GemKey, E> {
+                Ok(GemKey(value.to_string()))
+            }
+            fn visit_u64<E: serde::de::Error>(self, value: u64) -> std::result::Result<GemKey, E> {
+                Ok(GemKey(value.to_string()))
+            }
+        }
+        deserializer.deserialize_any(GemKeyVisitor)
+    }
+}
+
+/// Where a gem sits in its GemCollection. Ids are handed out densely, in the order gems are added, so they're only meaningful for as long as the collection is loaded; anything saved should use a [`GemKey`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct GemId(pub usize);
+
+//How a gem is held inside a GemCollection: the same as a Gem, except its facets are interned and its key is worked out once up front.
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct InternedGem {
+    pub id: Option<String>,
+    pub key: GemKey,
+    pub sides: HashMap<usize, String>,
+    pub unknown_facets: HashSet<FacetId>,
+    //Multiplies the gem's score when the ordering weighs candidates. 1.0 unless something (like near-duplicate detection) has down-weighted it.
+    pub weight: f64,
+    pub flags: Vec<GemFlag>,
+}
+
+impl InternedGem {
+    pub fn intern(gem: Gem, interner: &mut Interner) -> InternedGem {
+        InternedGem {
+            key: gem.key(),
+            unknown_facets: interner.intern_all(gem.unknown_facets.iter()),
+            id: gem.id,
+            sides: gem.sides,
+            weight: 1.0,
+            flags: gem.flags,
+        }
+    }
+
+    pub fn resolve(&self, interner: &Interner) -> Gem {
+        Gem {
+            id: self.id.clone(),
+            sides: self.sides.clone(),
+            unknown_facets: interner.names(self.unknown_facets.iter()),
+            flags: self.flags.clone(),
+        }
+    }
+}
@@ -0,0 +1,82 @@
+//Goal-directed ordering: given target facets ("the 1,000 most frequent words"), find a short path of gems that teaches all of them, still taking the easiest gems it can at each step. Gems that don't lead towards any target are left out, the GoalDirected strategy picks among the ones that do, and the path stops as soon as the targets are all known instead of running through the whole deck.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    collection::GemCollection,
+    error::Result,
+    gem::GemId,
+    interner::FacetId,
+    selection::{GoalDirected, SelectionStrategy},
+};
+
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct GoalPath {
+    /// The gems to study, in order. Each one introduces the facets of one ordering step.
+    pub path: Vec<GemId>,
+    /// Targets the path teaches (or that were already known).
+    pub reached: Vec<String>,
+    /// Targets that never get taught: they aren't in the deck, or the deck runs out before reaching them.
+    pub never_taught: Vec<String>,
+}
+
+impl GemCollection {
+    /// Works out a path of gems that introduces every facet in `targets` (normalized like any other facet name), learning facets as it goes just like [`GemCollection::difficulty_order`] does.
+    /// Gems that have nothing to do with the targets are skipped: each step takes the easiest gem that either holds a target or shares a facet with a gem that does, so the path only grows as hard as the targets need it to.
+    pub fn goal_order(&mut self, targets: &[String]) -> Result<GoalPath> {
+        let targets = self.normalize_facet_names(targets)?;
+        self.index_all_gems_by_number();
+        let target_ids: HashSet<FacetId> = targets.iter().filter_map(|target| self.interner.get(target)).collect();
+        let mut strategy = GoalDirected { targets: target_ids.clone() };
+        let mut path = Vec::new();
+        let mut rng = self.rng.clone();
+        while let Some(candidates) = self.useful_candidates(&target_ids) {
+            let facets = strategy.choose(self, &candidates, &HashMap::new(), &mut rng);
+            if facets.is_empty() {
+                break;
+            }
+            //The gem that was picked is the lowest-numbered candidate whose unknown facets are exactly what's being learned.
+            path.extend(candidates.iter().filter(|gem_id| self.gems[gem_id.0].unknown_facets == facets).min().copied());
+            self.mark_facet_ids_known(&facets);
+        }
+        self.rng = rng;
+        let (reached, never_taught): (Vec<String>, Vec<String>) = targets
+            .into_iter()
+            .collect::<HashSet<String>>()
+            .into_iter()
+            .partition(|target| self.interner.get(target).is_some_and(|target| self.known_facets.contains(&target)));
+        let mut goal_path = GoalPath { path, reached, never_taught };
+        goal_path.reached.sort();
+        goal_path.never_taught.sort();
+        Ok(goal_path)
+    }
+
+    //The easiest bucket's worth of gems that bring the remaining targets closer: ones holding a target, and ones sharing a facet with those. None once every target is known or nothing can reach the rest.
+    fn useful_candidates(&self, targets: &HashSet<FacetId>) -> Option<HashSet<GemId>> {
+        let target_gems: HashSet<GemId> = targets
+            .iter()
+            .filter(|target| !self.known_facets.contains(target))
+            .flat_map(|target| self.gems_by_facet_index.get(target).into_iter().flatten())
+            .filter(|gem_id| !self.gems[gem_id.0].unknown_facets.is_empty())
+            .copied()
+            .collect();
+        if target_gems.is_empty() {
+            return None;
+        }
+        let mut sizes: Vec<&usize> = self.gems_by_size_index.keys().collect();
+        sizes.sort_unstable();
+        sizes.into_iter().find_map(|size| {
+            let useful: HashSet<GemId> = self.gems_by_size_index[size]
+                .iter()
+                .filter(|gem_id| {
+                    target_gems.contains(gem_id)
+                        || self.gems[gem_id.0].unknown_facets.iter().any(|facet| {
+                            self.gems_by_facet_index.get(facet).is_some_and(|gem_ids| gem_ids.iter().any(|gem_id| target_gems.contains(gem_id)))
+                        })
+                })
+                .copied()
+                .collect();
+            (!useful.is_empty()).then_some(useful)
+        })
+    }
+}
@@ -0,0 +1,203 @@
+//`langwitch serve --grpc <ADDRESS>`: the same session as the HTTP API, as the gRPC service in proto/langwitch.proto, for backends that would rather embed langwitch than talk JSON to it. Both share one Server, so a grade over either shows up in both, and WatchStats hears about it like /live does.
+
+use std::{io, net::SocketAddr, pin::Pin};
+
+use tokio::sync::{broadcast, mpsc};
+use tokio_stream::{wrappers::ReceiverStream, Stream};
+use tonic::{Request, Response, Status, Streaming};
+
+use langwitch::{wire, Config, LangwitchError};
+
+use crate::{
+    server::{self, lock, Shared},
+    study,
+};
+
+pub mod proto {
+    tonic::include_proto!("langwitch");
+}
+
+use proto::{
+    langwitch_server::{Langwitch, LangwitchServer},
+    review_update::Update,
+    import_request::Source,
+    Card, Empty, GradeRequest, GradeResponse, ImportRequest, ImportResponse, NextGemResponse, ReviewUpdate, Side, StatsResponse,
+};
+
+//What an engine error is over gRPC. A grade for anything but the current card is the caller's to fix.
+fn status(e: LangwitchError) -> Status {
+    match e {
+        LangwitchError::NotCurrentCard(_) => Status::failed_precondition(e.to_string()),
+        _ => Status::internal(e.to_string()),
+    }
+}
+
+impl From<wire::CardJson> for Card {
+    fn from(card: wire::CardJson) -> Self {
+        Card {
+            gem: card.gem as u64,
+            key: card.key,
+            facets: card.facets,
+            is_new: card.is_new,
+            sides: card
+                .sides
+                .into_iter()
+                .map(|(side_number, side)| (side_number as u32, Side { role: side.role.label().to_lowercase(), text: side.text }))
+                .collect(),
+            front: card.front,
+            back: card.back,
+        }
+    }
+}
+
+impl From<GradeRequest> for wire::GradeRequest {
+    fn from(request: GradeRequest) -> Self {
+        wire::GradeRequest {
+            gem: request.gem as usize,
+            grades: request.grades,
+            grade: request.grade,
+            hints: request.hints.into_iter().map(|(facet, hints)| (facet, hints.min(u8::MAX as u32) as u8)).collect(),
+        }
+    }
+}
+
+impl From<wire::GradeResponse> for GradeResponse {
+    fn from(result: wire::GradeResponse) -> Self {
+        GradeResponse {
+            passed: result.passed,
+            failed: result.failed,
+            newly_known: result.newly_known,
+            unlocked: result.unlocked.into_iter().map(|gem| gem as u64).collect(),
+        }
+    }
+}
+
+impl From<study::Stats> for StatsResponse {
+    fn from(stats: study::Stats) -> Self {
+        StatsResponse {
+            gems: stats.gems as u64,
+            unlocked: stats.unlocked as u64,
+            known_facets: stats.known_facets as u64,
+            scheduled_facets: stats.scheduled_facets as u64,
+            due: stats.due as u64,
+            reviewed: stats.reviewed as u64,
+        }
+    }
+}
+
+//The card to study next, as a Review update.
+fn next_update(state: &Shared) -> Result<ReviewUpdate, Status> {
+    let update = match lock(state).study.current_card().map_err(status)? {
+        Some(card) => Update::Card(card.into()),
+        None => Update::Done(Empty {}),
+    };
+    Ok(ReviewUpdate { update: Some(update) })
+}
+
+struct Service {
+    state: Shared,
+}
+
+type ResponseStream<T> = Pin<Box<dyn Stream<Item = Result<T, Status>> + Send>>;
+
+#[tonic::async_trait]
+impl Langwitch for Service {
+    async fn next_gem(&self, _: Request<Empty>) -> Result<Response<NextGemResponse>, Status> {
+        let card = lock(&self.state).study.current_card().map_err(status)?;
+        Ok(Response::new(NextGemResponse { card: card.map(Card::from) }))
+    }
+
+    async fn grade(&self, request: Request<GradeRequest>) -> Result<Response<GradeResponse>, Status> {
+        let result = lock(&self.state).grade(request.into_inner().into()).map_err(status)?;
+        Ok(Response::new(result.into()))
+    }
+
+    async fn stats(&self, _: Request<Empty>) -> Result<Response<StatsResponse>, Status> {
+        Ok(Response::new(lock(&self.state).study.stats().into()))
+    }
+
+    async fn import(&self, request: Request<ImportRequest>) -> Result<Response<ImportResponse>, Status> {
+        let imported = match request.into_inner().source {
+            Some(Source::GemsJson(gems)) => {
+                let gems = serde_json::from_str(&gems).map_err(|e| Status::invalid_argument(e.to_string()))?;
+                lock(&self.state).import(|study| study.import(gems)).map_err(status)?
+            }
+            Some(Source::Text(text)) => lock(&self.state).import(|study| study.import_text(&text)).map_err(status)?,
+            None => return Err(Status::invalid_argument("send gems_json or text")),
+        };
+        Ok(Response::new(ImportResponse { received: imported.received as u64, added: imported.added as u64 }))
+    }
+
+    type ReviewStream = ResponseStream<ReviewUpdate>;
+
+    async fn review(&self, request: Request<Streaming<GradeRequest>>) -> Result<Response<Self::ReviewStream>, Status> {
+        let mut grades = request.into_inner();
+        let state = self.state.clone();
+        let (updates, received) = mpsc::channel(4);
+        tokio::spawn(async move {
+            if updates.send(next_update(&state)).await.is_err() {
+                return;
+            }
+            while let Ok(Some(request)) = grades.message().await {
+                let graded = lock(&state).grade(request.into());
+                let replies = match graded {
+                    Ok(result) => vec![Ok(ReviewUpdate { update: Some(Update::Graded(result.into())) }), next_update(&state)],
+                    //A grade for the wrong gem ends this stream, not the session; the client can open another.
+                    Err(e) => vec![Err(status(e))],
+                };
+                for reply in replies {
+                    if updates.send(reply).await.is_err() {
+                        return;
+                    }
+                }
+            }
+        });
+        Ok(Response::new(Box::pin(ReceiverStream::new(received))))
+    }
+
+    type WatchStatsStream = ResponseStream<StatsResponse>;
+
+    async fn watch_stats(&self, _: Request<Empty>) -> Result<Response<Self::WatchStatsStream>, Status> {
+        let (mut stats_updates, stats) = {
+            let server = lock(&self.state);
+            (server.stats_updates.subscribe(), server.study.stats())
+        };
+        let (updates, received) = mpsc::channel(4);
+        tokio::spawn(async move {
+            if updates.send(Ok(stats.into())).await.is_err() {
+                return;
+            }
+            loop {
+                let stats = match stats_updates.recv().await {
+                    Ok(stats) => stats,
+                    Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => return,
+                };
+                if updates.send(Ok(stats.into())).await.is_err() {
+                    return;
+                }
+            }
+        });
+        Ok(Response::new(Box::pin(ReceiverStream::new(received))))
+    }
+}
+
+async fn serve(state: Shared, address: SocketAddr) -> langwitch::Result<()> {
+    println!("Serving gRPC on {}", address);
+    tonic::transport::Server::builder()
+        .add_service(LangwitchServer::new(Service { state }))
+        .serve_with_shutdown(address, async {
+            let _ = tokio::signal::ctrl_c().await;
+        })
+        .await
+        .map_err(io::Error::other)?;
+    Ok(())
+}
+
+/// Serves the HTTP API on `http_address` and the gRPC service on `grpc_address` until Ctrl-C, then saves progress and shared knowledge.
+pub async fn run(config: Config, http_address: &str, grpc_address: &str) -> langwitch::Result<()> {
+    let grpc_address: SocketAddr = grpc_address.parse().map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    let state = server::load(config)?;
+    tokio::try_join!(server::serve(state.clone(), http_address), serve(state.clone(), grpc_address))?;
+    server::finish(&state)
+}
@@ -0,0 +1,30 @@
+//Progressive hints during review. Each hint gives away a little more of a facet: first its first letter, then how long it is, then the whole thing. Every hint taken costs some of the grade, so a facet only recalled with help doesn't count as well learned, and one that had to be shown in full can't pass at all.
+
+/// The most hints a facet can get. At this level the facet has been shown outright.
+pub const MAX_HINT_LEVEL: u8 = 3;
+
+/// How much of the grade each hint level takes away. A penalty above 1.0 - PASSING_GRADE keeps even a "right" answer from passing.
+pub const HINT_PENALTIES: [f64; MAX_HINT_LEVEL as usize + 1] = [0.0, 0.2, 0.35, 0.6];
+
+/// The hint for `facet` at `level`: nothing at 0, then "r…", then "r______ (7 letters)", then the facet itself.
+pub fn hint(facet: &str, level: u8) -> String {
+    let mut characters = facet.chars();
+    let first = characters.next().map(String::from).unwrap_or_default();
+    match level {
+        0 => String::new(),
+        1 => format!("{}…", first),
+        2 => {
+            //Spaces and hyphens stay visible, since they give the shape of the facet away anyway.
+            let masked: String = characters.map(|c| if c.is_whitespace() || c == '-' { c } else { '_' }).collect();
+            let letters = facet.chars().filter(|c| c.is_alphanumeric()).count();
+            format!("{}{} ({} letters)", first, masked, letters)
+        }
+        _ => facet.to_string(),
+    }
+}
+
+/// A grade as adjusted for the hints taken before giving it.
+pub fn hinted_grade(grade: f64, level: u8) -> f64 {
+    let penalty = HINT_PENALTIES[level.min(MAX_HINT_LEVEL) as usize];
+    (grade * (1.0 - penalty)).clamp(0.0, 1.0)
+}
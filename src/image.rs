@@ -0,0 +1,61 @@
+//Pictures on cards, for picture-word gems. A side with the image role names a file, looked up in the deck's media directory like audio is.
+//Terminals draw pictures with their own protocols (sixel, kitty's graphics protocol, iTerm's inline images), so showing one is left to a viewer command that knows them all, like chafa or `kitten icat`.
+
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use serde::{Serialize, Deserialize};
+
+use crate::{
+    collection::GemCollection,
+    error::{LangwitchError, Result},
+    gem::GemId,
+    normalize::ExternalCommand,
+    side::SideRole,
+};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct ImageOptions {
+    /// Draws a picture in the terminal. The file's path goes after `args`.
+    pub viewer: ExternalCommand,
+}
+
+impl Default for ImageOptions {
+    fn default() -> Self {
+        ImageOptions {
+            viewer: ExternalCommand {
+                program: "chafa".to_string(),
+                args: ["--size", "60x20"].into_iter().map(String::from).collect(),
+            },
+        }
+    }
+}
+
+impl ImageOptions {
+    /// Draws the picture at `path` in the terminal, returning once the viewer is done.
+    pub fn show(&self, path: &Path) -> Result<()> {
+        let status = Command::new(&self.viewer.program).args(&self.viewer.args).arg(path).status()?;
+        if !status.success() {
+            return Err(LangwitchError::Image(format!("{} exited with {}", self.viewer.program, status)));
+        }
+        Ok(())
+    }
+}
+
+impl GemCollection {
+    /// The picture for gem `gem_id`, if it has an image side naming a file that exists. Relative names are looked up in `media_dir`.
+    pub fn image_path(&self, gem_id: GemId, media_dir: Option<&Path>) -> Option<PathBuf> {
+        let name = self.side(gem_id, SideRole::Image)?.trim();
+        if name.is_empty() {
+            return None;
+        }
+        let path = match media_dir {
+            Some(media_dir) => media_dir.join(name),
+            None => PathBuf::from(name),
+        };
+        path.is_file().then_some(path)
+    }
+}
@@ -0,0 +1,152 @@
+//Reads an Anki .apkg deck. An .apkg is a zip holding a `collection.anki2` (or `collection.anki21`) SQLite database, a `media` JSON file mapping numbered entries to their real filenames, and the numbered media files themselves.
+//Every note becomes a Gem: field n becomes side n (with the HTML stripped), and the target-language field is tokenized into unknown_facets.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fs::{self, File},
+    io::Read,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use rusqlite::Connection;
+
+use crate::{
+    collection::GemCollection,
+    error::{LangwitchError, Result},
+    gem::Gem,
+    import::strip_html,
+    side::{SideRole, SideRoles},
+    tokenize::{Tokenizer, TokenizerKind},
+};
+
+#[derive(Debug, Clone, Default)]
+pub struct AnkiImportOptions {
+    /// Which note field holds the target-language text to take facets from.
+    pub target_field: usize,
+    /// Where to unpack the deck's media files. Media is skipped if this is None.
+    pub media_dir: Option<PathBuf>,
+    /// How the target field is split into facets. Chinese and Japanese decks want one of the dictionary-based tokenizers.
+    pub tokenizer: TokenizerKind,
+}
+
+//Anki separates fields inside a note with the unit separator character.
+const FIELD_SEPARATOR: char = '\u{1f}';
+
+//[sound:file.mp3] references stay on the side so audio can be found later, but they aren't words.
+fn strip_sound_tags(text: &str) -> String {
+    let mut stripped = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("[sound:") {
+        stripped.push_str(&rest[..start]);
+        match rest[start..].find(']') {
+            Some(end) => rest = &rest[start + end + 1..],
+            None => rest = "",
+        }
+    }
+    stripped.push_str(rest);
+    stripped
+}
+
+//Note types don't say what their fields mean, so only what can be told for sure is marked: the target field is the text, and a field that never holds anything but [sound:...] references is audio.
+fn note_side_roles(gems: &[Gem], target_field: usize) -> SideRoles {
+    let field_count = gems.iter().flat_map(|gem| gem.sides.keys()).max().map_or(0, |max_field| max_field + 1);
+    let mut side_roles = SideRoles::new([(target_field, SideRole::Text)]);
+    for field in (0..field_count).filter(|field| *field != target_field) {
+        let mut values = gems.iter().filter_map(|gem| gem.sides.get(&field)).filter(|value| !value.trim().is_empty()).peekable();
+        let has_values = values.peek().is_some();
+        if has_values && values.all(|value| value.contains("[sound:") && strip_sound_tags(value).trim().is_empty()) {
+            side_roles.0.insert(field, SideRole::Audio);
+        }
+    }
+    side_roles
+}
+
+fn note_to_gem(guid: String, fields: &str, target_field: usize, tokenizer: &dyn Tokenizer) -> Gem {
+    let sides: HashMap<usize, String> = fields
+        .split(FIELD_SEPARATOR)
+        .map(strip_html)
+        .enumerate()
+        .collect();
+    let unknown_facets = match sides.get(&target_field) {
+        Some(text) => tokenizer.tokenize(&strip_sound_tags(text)),
+        None => HashSet::new(),
+    };
+    //Anki's note guid survives edits to the note, which makes it a better key than anything derived from the text.
+    Gem { id: Some(guid), sides, unknown_facets, flags: Vec::new() }
+}
+
+/// Turns every note in an .apkg into a gem, optionally unpacking its media into `options.media_dir`.
+pub fn read_apkg<P: AsRef<Path>>(path: P, options: &AnkiImportOptions) -> Result<GemCollection> {
+    let mut archive = zip::ZipArchive::new(File::open(path)?)?;
+    let database_name = ["collection.anki21", "collection.anki2"]
+        .into_iter()
+        .find(|name| archive.index_for_name(name).is_some())
+        .ok_or_else(|| {
+            if archive.index_for_name("collection.anki21b").is_some() {
+                LangwitchError::Import("this deck uses the newer zstd-compressed collection.anki21b format; re-export it from Anki with \"support older Anki versions\" ticked".to_string())
+            } else {
+                LangwitchError::Import("no collection.anki2 in the archive".to_string())
+            }
+        })?;
+    let mut database = Vec::new();
+    archive.by_name(database_name)?.read_to_end(&mut database)?;
+
+    //rusqlite wants an actual file, so the database goes to the temp dir for as long as we're reading it.
+    let stamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos();
+    let database_path = std::env::temp_dir().join(format!("langwitch-{}-{}.anki2", std::process::id(), stamp));
+    fs::write(&database_path, &database)?;
+    let gems = read_notes(&database_path, options);
+    let _ = fs::remove_file(&database_path);
+    let gems = gems?;
+
+    if let Some(media_dir) = &options.media_dir {
+        extract_media(&mut archive, media_dir)?;
+    }
+    let side_roles = note_side_roles(&gems, options.target_field);
+    let mut gem_collection = GemCollection::from_gems(gems);
+    gem_collection.side_roles = side_roles;
+    Ok(gem_collection)
+}
+
+fn read_notes(database_path: &Path, options: &AnkiImportOptions) -> Result<Vec<Gem>> {
+    let tokenizer = options.tokenizer.build()?;
+    let connection = Connection::open(database_path)?;
+    let mut select = connection.prepare("SELECT guid, flds FROM notes ORDER BY id")?;
+    let rows = select.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?;
+    let mut gems = Vec::new();
+    for row in rows {
+        let (guid, fields) = row?;
+        gems.push(note_to_gem(guid, &fields, options.target_field, tokenizer.as_ref()));
+    }
+    Ok(gems)
+}
+
+fn extract_media(archive: &mut zip::ZipArchive<File>, media_dir: &Path) -> Result<()> {
+    let mut media = String::new();
+    match archive.by_name("media") {
+        Ok(mut file) => file.read_to_string(&mut media)?,
+        Err(zip::result::ZipError::FileNotFound) => return Ok(()),
+        Err(e) => return Err(e.into()),
+    };
+    let media: HashMap<String, String> = serde_json::from_str(&media)?;
+    fs::create_dir_all(media_dir)?;
+    for (entry, file_name) in media.iter() {
+        //Filenames come from the deck, so don't let one climb out of the media directory.
+        let file_name = match Path::new(file_name).file_name() {
+            Some(file_name) => file_name,
+            None => continue,
+        };
+        let mut contents = Vec::new();
+        archive.by_name(entry)?.read_to_end(&mut contents)?;
+        fs::write(media_dir.join(file_name), contents)?;
+    }
+    Ok(())
+}
+
+impl GemCollection {
+    /// Imports an Anki .apkg deck with the default options (facets from the first field, no media).
+    pub fn read_gems_from_apkg<P: AsRef<Path>>(path: P) -> Result<GemCollection> {
+        read_apkg(path, &AnkiImportOptions::default())
+    }
+}
@@ -0,0 +1,68 @@
+//Web articles. A page is mostly navigation, ads, share buttons and footers around a few paragraphs of actual writing, so before mining, the page goes through a small readability-style pass: obvious chrome is cut out, the <article> is preferred if there is one, and only paragraphs that read like prose (enough words, not mostly links) are kept.
+
+use regex::Regex;
+
+use crate::{
+    error::Result,
+    fetch::fetch_text,
+    gem::Gem,
+    import::strip_html,
+    mine::{mine_text_with, MineOptions},
+};
+
+//Paragraphs shorter than this are usually bylines, captions or buttons.
+const MIN_PARAGRAPH_WORDS: usize = 6;
+//Paragraphs where more than this share of the text sits inside links are usually lists of related articles.
+const MAX_LINK_DENSITY: f64 = 0.5;
+
+fn link_density(paragraph_html: &str, text_len: usize) -> f64 {
+    let links = Regex::new(r"(?is)<a\b[^>]*>(.*?)</a>").expect("link pattern is valid");
+    let linked: usize = links.captures_iter(paragraph_html).map(|captures| strip_html(&captures[1]).chars().count()).sum();
+    linked as f64 / text_len.max(1) as f64
+}
+
+/// Cuts a page down to its main text, one paragraph per blank-line-separated block.
+pub fn readable_text(html: &str) -> String {
+    let chrome = Regex::new(r"(?is)<(script|style|noscript|nav|header|footer|aside|form|figure|svg)\b.*?</(script|style|noscript|nav|header|footer|aside|form|figure|svg)>")
+        .expect("chrome pattern is valid");
+    let html = chrome.replace_all(html, " ");
+    //The longest <article> is almost always the story; everything outside it can go.
+    let articles = Regex::new(r"(?is)<article\b[^>]*>(.*?)</article>").expect("article pattern is valid");
+    let main = articles
+        .captures_iter(&html)
+        .filter_map(|captures| captures.get(1))
+        .map(|article| article.as_str())
+        .max_by_key(|article| article.len())
+        .unwrap_or(&html);
+    let paragraphs = Regex::new(r"(?is)<p\b[^>]*>(.*?)</p>").expect("paragraph pattern is valid");
+    let kept: Vec<String> = paragraphs
+        .captures_iter(main)
+        .filter_map(|captures| {
+            let paragraph_html = &captures[1];
+            let text = strip_html(paragraph_html);
+            let prose = text.split_whitespace().count() >= MIN_PARAGRAPH_WORDS
+                && link_density(paragraph_html, text.chars().count()) <= MAX_LINK_DENSITY;
+            prose.then_some(text)
+        })
+        .collect();
+    if !kept.is_empty() {
+        return kept.join("\n\n");
+    }
+    //Pages that don't use <p> at all still get something out of them.
+    strip_html(main)
+}
+
+/// Mines the readable text of an already-downloaded page. Every gem gets `url` as side 1.
+pub fn mine_article(html: &str, url: &str, options: &MineOptions) -> Result<Vec<Gem>> {
+    let tokenizer = options.tokenizer.build()?;
+    let mut gems = mine_text_with(&readable_text(html), options, tokenizer.as_ref());
+    for gem in gems.iter_mut() {
+        gem.sides.insert(1, url.to_string());
+    }
+    Ok(gems)
+}
+
+/// Downloads the page at `url` and mines its readable text.
+pub fn fetch_article(url: &str, options: &MineOptions) -> Result<Vec<Gem>> {
+    mine_article(&fetch_text(url)?, url, options)
+}
@@ -0,0 +1,146 @@
+//Reads an EPUB book. An EPUB is a zip: META-INF/container.xml points at an OPF package file, whose manifest lists the book's files and whose spine says what order the chapters are read in. Each chapter is XHTML, which is stripped down to its paragraphs and mined for sentences like any other text.
+//Every gem gets its chapter's title as side 1, so a whole novel becomes one curriculum that still says where each sentence came from.
+
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::Read,
+    path::Path,
+};
+
+use regex::Regex;
+
+use crate::{
+    collection::GemCollection,
+    error::{LangwitchError, Result},
+    gem::Gem,
+    import::strip_html,
+    mine::{mine_text_with, mined_side_roles, MineOptions},
+};
+
+/// One chapter of a book, as text with paragraphs separated by blank lines.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Chapter {
+    pub title: String,
+    pub text: String,
+}
+
+//Pulls every attribute out of one tag, like <item id="c1" href="c1.xhtml"/>.
+fn attributes(tag: &str) -> HashMap<String, String> {
+    let attribute = Regex::new(r#"([\w:-]+)\s*=\s*(?:"([^"]*)"|'([^']*)')"#).expect("attribute pattern is valid");
+    attribute
+        .captures_iter(tag)
+        .map(|captures| {
+            let value = captures.get(2).or_else(|| captures.get(3)).map_or("", |value| value.as_str());
+            (captures[1].to_lowercase(), value.to_string())
+        })
+        .collect()
+}
+
+//Every opening tag called `name` in `xml` (namespace prefixes are ignored, so "rootfile" finds <container:rootfile>).
+fn tags(xml: &str, name: &str) -> Vec<HashMap<String, String>> {
+    let tag = Regex::new(&format!(r"(?is)<(?:[\w-]+:)?{}\b[^>]*>", regex::escape(name))).expect("tag pattern is valid");
+    tag.find_iter(xml).map(|found| attributes(found.as_str())).collect()
+}
+
+fn read_entry(archive: &mut zip::ZipArchive<File>, name: &str) -> Result<String> {
+    let mut contents = String::new();
+    archive.by_name(name)?.read_to_string(&mut contents)?;
+    Ok(contents)
+}
+
+//Hrefs in the package are relative to the package file, may climb out of its directory with "..", and may be percent-encoded.
+fn resolve_href(base_dir: &str, href: &str) -> String {
+    let href = href.split('#').next().unwrap_or(href).replace("%20", " ");
+    let mut parts: Vec<&str> = base_dir.split('/').filter(|part| !part.is_empty()).collect();
+    for part in href.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop();
+            }
+            _ => parts.push(part),
+        }
+    }
+    parts.join("/")
+}
+
+/// Turns one XHTML chapter into plain text, one paragraph per blank-line-separated block, and finds its title.
+pub fn chapter_from_xhtml(xhtml: &str) -> Chapter {
+    //The chapter's first heading says more than its <title>, which is often just the book's name.
+    let title = [r"(?is)<h[1-3]\b[^>]*>(.*?)</h[1-3]>", r"(?is)<title\b[^>]*>(.*?)</title>"]
+        .iter()
+        .filter_map(|pattern| Regex::new(pattern).expect("title pattern is valid").captures(xhtml))
+        .map(|captures| strip_html(&captures[1]))
+        .find(|title| !title.is_empty())
+        .unwrap_or_default();
+    let body = match (xhtml.find("<body"), xhtml.rfind("</body>")) {
+        (Some(start), Some(end)) if start < end => &xhtml[start..end],
+        _ => xhtml,
+    };
+    let body = Regex::new(r"(?is)<script\b.*?</script>|<style\b.*?</style>").expect("script pattern is valid").replace_all(body, "");
+    let blocks = Regex::new(r"(?i)</(?:p|h[1-6]|div|li|blockquote|tr)>|<br\s*/?>").expect("block pattern is valid");
+    let text = blocks
+        .split(&body)
+        .map(strip_html)
+        .filter(|block| !block.is_empty())
+        .collect::<Vec<String>>()
+        .join("\n\n");
+    Chapter { title, text }
+}
+
+/// Reads the chapters of an EPUB in reading order.
+pub fn read_chapters<P: AsRef<Path>>(path: P) -> Result<Vec<Chapter>> {
+    let mut archive = zip::ZipArchive::new(File::open(path)?)?;
+    let container = read_entry(&mut archive, "META-INF/container.xml")?;
+    let package_path = tags(&container, "rootfile")
+        .into_iter()
+        .find_map(|rootfile| rootfile.get("full-path").cloned())
+        .ok_or_else(|| LangwitchError::Import("container.xml doesn't say where the package file is".to_string()))?;
+    let package = read_entry(&mut archive, &package_path)?;
+    let base_dir = package_path.rsplit_once('/').map_or("", |(dir, _)| dir);
+
+    let manifest: HashMap<String, String> = tags(&package, "item")
+        .into_iter()
+        .filter_map(|item| Some((item.get("id")?.clone(), item.get("href")?.clone())))
+        .collect();
+    let mut chapters = Vec::new();
+    for itemref in tags(&package, "itemref") {
+        let href = match itemref.get("idref").and_then(|idref| manifest.get(idref)) {
+            Some(href) => href,
+            None => continue,
+        };
+        let xhtml = read_entry(&mut archive, &resolve_href(base_dir, href))?;
+        let mut chapter = chapter_from_xhtml(&xhtml);
+        if chapter.text.is_empty() {
+            continue;
+        }
+        if chapter.title.is_empty() {
+            chapter.title = format!("Chapter {}", chapters.len() + 1);
+        }
+        chapters.push(chapter);
+    }
+    Ok(chapters)
+}
+
+/// Mines every chapter of an EPUB into gems, in reading order, with the chapter title as side 1.
+pub fn read_epub<P: AsRef<Path>>(path: P, options: &MineOptions) -> Result<GemCollection> {
+    let tokenizer = options.tokenizer.build()?;
+    let mut gems: Vec<Gem> = Vec::new();
+    for chapter in read_chapters(path)? {
+        for mut gem in mine_text_with(&chapter.text, options, tokenizer.as_ref()) {
+            gem.sides.insert(1, chapter.title.clone());
+            gems.push(gem);
+        }
+    }
+    let mut gem_collection = GemCollection::from_gems(gems);
+    gem_collection.side_roles = mined_side_roles();
+    Ok(gem_collection)
+}
+
+impl GemCollection {
+    /// Imports an EPUB book with the default mining options.
+    pub fn read_gems_from_epub<P: AsRef<Path>>(path: P) -> Result<GemCollection> {
+        read_epub(path, &MineOptions::default())
+    }
+}
@@ -0,0 +1,43 @@
+//Importers that turn other people's flashcard formats into GemCollections.
+
+#[cfg(feature = "native")]
+pub mod anki;
+#[cfg(feature = "native")]
+pub mod article;
+#[cfg(feature = "native")]
+pub mod epub;
+pub mod srt;
+pub mod vtt;
+
+//Anki fields (and plenty of other sources) are little HTML fragments. This drops the tags, turns <br> and <div> boundaries into spaces, and decodes the handful of entities that actually show up in decks.
+pub(crate) fn strip_html(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut in_tag = false;
+    let mut tag = String::new();
+    for c in html.chars() {
+        match c {
+            '<' => {
+                in_tag = true;
+                tag.clear();
+            }
+            '>' if in_tag => {
+                in_tag = false;
+                let name = tag.trim_start_matches('/').split_whitespace().next().unwrap_or("").to_lowercase();
+                if matches!(name.as_str(), "br" | "br/" | "div" | "p" | "li" | "tr") {
+                    text.push(' ');
+                }
+            }
+            _ if in_tag => tag.push(c),
+            _ => text.push(c),
+        }
+    }
+    let text = text
+        .replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&");
+    text.split_whitespace().collect::<Vec<&str>>().join(" ")
+}
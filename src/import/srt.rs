@@ -0,0 +1,178 @@
+//Reads .srt subtitle files (and holds what's shared with the WebVTT importer). Subtitles break sentences across cues to fit the screen, so consecutive cues are merged until a sentence ends (or the dialogue pauses), and each sentence becomes a gem: side 0 is the text, side 1 is when it's said.
+
+use std::{collections::HashMap, fs, path::Path, time::Duration};
+
+use crate::{
+    collection::GemCollection,
+    error::{LangwitchError, Result},
+    gem::Gem,
+    import::strip_html,
+    mine::{is_closing, is_terminator, mined_side_roles, split_sentences},
+    tokenize::{Tokenizer, TokenizerKind},
+};
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct SubtitleImportOptions {
+    /// How the subtitle text is split into facets.
+    pub tokenizer: TokenizerKind,
+    /// Cues further apart than this are never merged into one sentence, even if the first doesn't end with punctuation.
+    pub max_gap: Duration,
+    /// Where the video lives. If set, every gem gets a link to the moment its sentence is said as side 2.
+    pub source_url: Option<String>,
+}
+
+impl Default for SubtitleImportOptions {
+    fn default() -> Self {
+        SubtitleImportOptions {
+            tokenizer: TokenizerKind::default(),
+            max_gap: Duration::from_millis(1500),
+            source_url: None,
+        }
+    }
+}
+
+/// One subtitle cue: some text and when it's on screen.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Cue {
+    pub start: Duration,
+    pub end: Duration,
+    pub text: String,
+}
+
+//"00:01:02,345" (or with a period, which some tools write).
+fn parse_timestamp(timestamp: &str) -> Option<Duration> {
+    let (clock, millis) = timestamp.trim().split_once([',', '.'])?;
+    let mut parts = clock.split(':').rev();
+    let seconds: u64 = parts.next()?.parse().ok()?;
+    let minutes: u64 = parts.next().unwrap_or("0").parse().ok()?;
+    let hours: u64 = parts.next().unwrap_or("0").parse().ok()?;
+    let millis: u64 = millis.get(..3).unwrap_or(millis).parse().ok()?;
+    Some(Duration::from_millis(((hours * 60 + minutes) * 60 + seconds) * 1000 + millis))
+}
+
+/// Reads "start --> end" off a cue's timing line, ignoring anything after the end time (VTT puts cue settings there).
+pub(crate) fn parse_timing(line: &str) -> Option<(Duration, Duration)> {
+    let (start, end) = line.split_once("-->")?;
+    let end = end.split_whitespace().next()?;
+    Some((parse_timestamp(start)?, parse_timestamp(end)?))
+}
+
+pub(crate) fn format_timestamp(time: Duration) -> String {
+    let millis = time.as_millis();
+    format!("{:02}:{:02}:{:02},{:03}", millis / 3_600_000, millis / 60_000 % 60, millis / 1000 % 60, millis % 1000)
+}
+
+//Drops ASS-style override tags like {\an8} that some .srt files carry, as well as HTML like <i>.
+pub(crate) fn strip_override_tags(text: &str) -> String {
+    let mut stripped = String::with_capacity(text.len());
+    let mut in_tag = false;
+    for c in text.chars() {
+        match c {
+            '{' => in_tag = true,
+            '}' if in_tag => in_tag = false,
+            _ if in_tag => {}
+            _ => stripped.push(c),
+        }
+    }
+    strip_html(&stripped)
+}
+
+/// Parses the cues out of the contents of an .srt file.
+pub fn parse_srt(contents: &str) -> Result<Vec<Cue>> {
+    let contents = contents.trim_start_matches('\u{feff}').replace("\r\n", "\n");
+    let mut cues = Vec::new();
+    for block in contents.split("\n\n") {
+        let mut lines = block.lines().map(|line| line.trim()).filter(|line| !line.is_empty()).peekable();
+        //The cue number is optional in practice, so skip anything before the timing line.
+        while lines.peek().is_some_and(|line| !line.contains("-->")) {
+            lines.next();
+        }
+        let timing = match lines.next() {
+            Some(timing) => timing,
+            None => continue,
+        };
+        let (start, end) = parse_timing(timing).ok_or_else(|| LangwitchError::Import(format!("bad subtitle timing line {:?}", timing)))?;
+        let text = strip_override_tags(&lines.collect::<Vec<&str>>().join(" "));
+        if !text.is_empty() {
+            cues.push(Cue { start, end, text });
+        }
+    }
+    Ok(cues)
+}
+
+fn ends_sentence(text: &str) -> bool {
+    text.trim_end().chars().rev().find(|c| !is_closing(*c)).is_some_and(is_terminator)
+}
+
+/// Merges cues that continue one another (the first doesn't end a sentence, and the second follows within `max_gap`) and splits the result into sentences. Each sentence keeps the timing of the cues it came from.
+pub fn cues_to_sentences(cues: &[Cue], max_gap: Duration) -> Vec<Cue> {
+    let mut merged: Vec<Cue> = Vec::new();
+    for cue in cues {
+        match merged.last_mut() {
+            Some(last) if !ends_sentence(&last.text) && cue.start.saturating_sub(last.end) <= max_gap => {
+                last.text.push(' ');
+                last.text.push_str(&cue.text);
+                last.end = last.end.max(cue.end);
+            }
+            _ => merged.push(cue.clone()),
+        }
+    }
+    merged
+        .into_iter()
+        .flat_map(|cue| {
+            split_sentences(&cue.text)
+                .into_iter()
+                //Dashes mark a change of speaker, not part of the sentence.
+                .map(|text| text.trim_start_matches(['-', '–', '—', ' ']).to_string())
+                .filter(|text| !text.is_empty())
+                .map(move |text| Cue { start: cue.start, end: cue.end, text })
+        })
+        .collect()
+}
+
+//A link to the moment a sentence is said: YouTube understands a t= query parameter, and everything else gets a media fragment.
+fn link_at(source_url: &str, time: Duration) -> String {
+    let seconds = time.as_secs();
+    if source_url.contains("youtube.com/") || source_url.contains("youtu.be/") {
+        let separator = if source_url.contains('?') { '&' } else { '?' };
+        format!("{}{}t={}s", source_url, separator, seconds)
+    } else {
+        format!("{}#t={}", source_url, seconds)
+    }
+}
+
+/// Turns sentences into gems, with the timing as side 1 and, if there's a `source_url`, a link to the moment as side 2.
+pub(crate) fn sentences_to_gems(sentences: Vec<Cue>, tokenizer: &dyn Tokenizer, source_url: Option<&str>) -> Vec<Gem> {
+    sentences
+        .into_iter()
+        .filter_map(|sentence| {
+            let unknown_facets = tokenizer.tokenize(&sentence.text);
+            if unknown_facets.is_empty() {
+                return None;
+            }
+            let timing = format!("{} --> {}", format_timestamp(sentence.start), format_timestamp(sentence.end));
+            let mut sides: HashMap<usize, String> = [(0, sentence.text), (1, timing)].into_iter().collect();
+            if let Some(source_url) = source_url {
+                sides.insert(2, link_at(source_url, sentence.start));
+            }
+            Some(Gem { id: None, sides, unknown_facets, flags: Vec::new() })
+        })
+        .collect()
+}
+
+/// Reads an .srt file into a collection of one gem per sentence.
+pub fn read_srt<P: AsRef<Path>>(path: P, options: &SubtitleImportOptions) -> Result<GemCollection> {
+    let tokenizer = options.tokenizer.build()?;
+    let cues = parse_srt(&fs::read_to_string(path)?)?;
+    let sentences = cues_to_sentences(&cues, options.max_gap);
+    let mut gem_collection = GemCollection::from_gems(sentences_to_gems(sentences, tokenizer.as_ref(), options.source_url.as_deref()));
+    gem_collection.side_roles = mined_side_roles();
+    Ok(gem_collection)
+}
+
+impl GemCollection {
+    /// Imports an .srt subtitle file with the default options.
+    pub fn read_gems_from_srt<P: AsRef<Path>>(path: P) -> Result<GemCollection> {
+        read_srt(path, &SubtitleImportOptions::default())
+    }
+}
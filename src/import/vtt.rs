@@ -0,0 +1,58 @@
+//Reads WebVTT (.vtt) caption files, including the flavour YouTube generates automatically. Those repeat every line twice (once as it's being typed out, word by word, with inline <00:00:01.234> timing tags, and again as the top line of the next cue) and add 10ms cues that just hold the previous text on screen. So tags are stripped and lines already shown by the previous cue are dropped before cues are merged into sentences like .srt ones.
+
+use std::{fs, path::Path};
+
+use crate::{
+    collection::GemCollection,
+    error::{LangwitchError, Result},
+    import::srt::{cues_to_sentences, parse_timing, sentences_to_gems, strip_override_tags, Cue, SubtitleImportOptions},
+    mine::mined_side_roles,
+};
+
+/// Parses the cues out of the contents of a .vtt file. Lines repeated from the cue just before are left out, so rolling captions come out as one copy of each line.
+pub fn parse_vtt(contents: &str) -> Result<Vec<Cue>> {
+    let contents = contents.trim_start_matches('\u{feff}').replace("\r\n", "\n");
+    let mut cues = Vec::new();
+    let mut previous_lines: Vec<String> = Vec::new();
+    for block in contents.split("\n\n") {
+        let mut lines = block.lines().map(|line| line.trim()).filter(|line| !line.is_empty()).peekable();
+        //The header, comments, and style and region definitions aren't cues.
+        if lines.peek().is_some_and(|line| ["WEBVTT", "NOTE", "STYLE", "REGION"].iter().any(|keyword| line.starts_with(keyword))) {
+            continue;
+        }
+        //Cue identifiers are optional.
+        while lines.peek().is_some_and(|line| !line.contains("-->")) {
+            lines.next();
+        }
+        let timing = match lines.next() {
+            Some(timing) => timing,
+            None => continue,
+        };
+        let (start, end) = parse_timing(timing).ok_or_else(|| LangwitchError::Import(format!("bad caption timing line {:?}", timing)))?;
+        let text_lines: Vec<String> = lines.map(strip_override_tags).filter(|line| !line.is_empty()).collect();
+        let new_lines: Vec<&String> = text_lines.iter().filter(|line| !previous_lines.contains(line)).collect();
+        if !new_lines.is_empty() {
+            let text = new_lines.iter().map(|line| line.as_str()).collect::<Vec<&str>>().join(" ");
+            cues.push(Cue { start, end, text });
+        }
+        previous_lines = text_lines;
+    }
+    Ok(cues)
+}
+
+/// Reads a .vtt file into a collection of one gem per sentence.
+pub fn read_vtt<P: AsRef<Path>>(path: P, options: &SubtitleImportOptions) -> Result<GemCollection> {
+    let tokenizer = options.tokenizer.build()?;
+    let cues = parse_vtt(&fs::read_to_string(path)?)?;
+    let sentences = cues_to_sentences(&cues, options.max_gap);
+    let mut gem_collection = GemCollection::from_gems(sentences_to_gems(sentences, tokenizer.as_ref(), options.source_url.as_deref()));
+    gem_collection.side_roles = mined_side_roles();
+    Ok(gem_collection)
+}
+
+impl GemCollection {
+    /// Imports a .vtt caption file with the default options.
+    pub fn read_gems_from_vtt<P: AsRef<Path>>(path: P) -> Result<GemCollection> {
+        read_vtt(path, &SubtitleImportOptions::default())
+    }
+}
@@ -0,0 +1,73 @@
+//Facet names are interned: each distinct facet string is stored once, and everything inside a GemCollection (gem facet sets, both indices, the frequency maps, known_facets) refers to it by a FacetId. That turns the hot loop's hashing, cloning and set differences into work on plain u32s. Names only come back out as strings at the edges: when showing facets to someone, or writing a deck or progress file.
+
+use std::collections::{HashMap, HashSet};
+
+use serde::{Serialize, Deserialize};
+
+/// A facet name, interned. Only meaningful together with the [`Interner`] that handed it out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct FacetId(pub u32);
+
+//Only the names are saved; the lookup table is rebuilt from them on load.
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Default)]
+#[serde(from = "Vec<String>", into = "Vec<String>")]
+pub struct Interner {
+    ids: HashMap<String, FacetId>,
+    names: Vec<String>,
+}
+
+impl Interner {
+    /// The id for `name`, handing out a new one if it hasn't been seen before.
+    pub fn intern(&mut self, name: &str) -> FacetId {
+        if let Some(id) = self.ids.get(name) {
+            return *id;
+        }
+        let id = FacetId(self.names.len() as u32);
+        self.names.push(name.to_string());
+        self.ids.insert(name.to_string(), id);
+        id
+    }
+
+    /// The id for `name`, if it's ever been interned.
+    pub fn get(&self, name: &str) -> Option<FacetId> {
+        self.ids.get(name).copied()
+    }
+
+    /// The name behind `id`. Panics if `id` came from a different interner.
+    pub fn name(&self, id: FacetId) -> &str {
+        &self.names[id.0 as usize]
+    }
+
+    pub fn intern_all<'a, I: IntoIterator<Item = &'a String>>(&mut self, names: I) -> HashSet<FacetId> {
+        names.into_iter().map(|name| self.intern(name)).collect()
+    }
+
+    pub fn names<'a, I: IntoIterator<Item = &'a FacetId>>(&self, ids: I) -> HashSet<String> {
+        ids.into_iter().map(|id| self.name(*id).to_string()).collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+}
+
+impl From<Vec<String>> for Interner {
+    fn from(names: Vec<String>) -> Self {
+        let ids = names
+            .iter()
+            .enumerate()
+            .map(|(id, name)| (name.clone(), FacetId(id as u32)))
+            .collect();
+        Interner { ids, names }
+    }
+}
+
+impl From<Interner> for Vec<String> {
+    fn from(interner: Interner) -> Self {
+        interner.names
+    }
+}
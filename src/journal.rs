@@ -0,0 +1,344 @@
+//An append-only log of every review, one JSON object per line. Nothing in it is ever rewritten, which gives crash safety (the worst a crash can do is cut the last line short) and a full audit trail that statistics and schedulers can be built on later.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fs::{File, OpenOptions},
+    io::{BufRead, BufReader, ErrorKind, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use serde::{Serialize, Deserialize};
+
+use crate::{
+    collection::GemCollection,
+    error::{LangwitchError, Result},
+    facet::Facet,
+    gem::{GemId, GemKey},
+    hint::hinted_grade,
+    progress::Progress,
+    scheduler::Scheduler,
+    storage::json::JsonStorage,
+};
+#[cfg(feature = "encryption")]
+use crate::encryption::{is_encrypted, Keyring, Salt};
+
+/// Grades at or above this count as remembering the facet, which is what puts it in `known_facets`.
+pub const PASSING_GRADE: f64 = 0.5;
+
+/// One review of one gem: which facets were graded and how well (0.0 is wrong, 1.0 is right) at what time.
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct ReviewEvent {
+    //Which gem was reviewed. Older journals called this gem_id and stored the gem's number.
+    #[serde(alias = "gem_id")]
+    pub gem_key: GemKey,
+    pub grades: HashMap<String, f64>,
+    #[serde(with = "crate::timestamp::unix_millis")]
+    pub timestamp: SystemTime,
+    //How many hints each facet needed (see crate::hint). The grades already have the hints taken off, so this is only a record.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub hints: HashMap<String, u8>,
+    //A cram drill (see crate::cram) rather than a review: kept for the record, but it never changes scheduling or known facets.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub cram: bool,
+}
+
+impl ReviewEvent {
+    /// A review happening right now.
+    pub fn now(gem_key: GemKey, grades: HashMap<String, f64>) -> ReviewEvent {
+        ReviewEvent {
+            gem_key,
+            grades,
+            timestamp: SystemTime::now(),
+            hints: HashMap::new(),
+            cram: false,
+        }
+    }
+}
+
+/// What grading one gem did, facet by facet. See [`GemCollection::grade_gem`].
+#[derive(Debug, PartialEq, Clone)]
+pub struct ReviewResult {
+    /// The review, ready to be appended to a [`Journal`].
+    pub event: ReviewEvent,
+    /// Facets (normalized) graded at least [`PASSING_GRADE`], sorted.
+    pub passed: Vec<String>,
+    pub failed: Vec<String>,
+    /// The passed facets that weren't known before this review.
+    pub newly_known: Vec<String>,
+    /// Gems left with no unknown facets because of this review.
+    pub unlocked: Vec<GemId>,
+}
+
+/// Puts events in the order they're replayed in: by timestamp, then by gem. Every device holding the same events replays them the same way, whatever order they were journaled in.
+pub fn sort_chronologically(events: &mut [ReviewEvent]) {
+    events.sort_by(|a, b| (a.timestamp, &a.gem_key).cmp(&(b.timestamp, &b.gem_key)));
+}
+
+pub struct Journal {
+    path: PathBuf,
+    file: File,
+    //The key events are sealed with, for a journal opened with open_encrypted.
+    #[cfg(feature = "encryption")]
+    sealing: Option<(Keyring, Salt)>,
+}
+
+impl Journal {
+    /// Opens the journal at `path` for appending, creating it if it doesn't exist. If the last line was cut short by a crash it's dropped first, so new events don't get glued onto it.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Journal> {
+        let path = path.as_ref().to_path_buf();
+        let file = OpenOptions::new().create(true).append(true).read(true).open(&path)?;
+        truncate_partial_line(&file)?;
+        Ok(Journal {
+            path,
+            file,
+            #[cfg(feature = "encryption")]
+            sealing: None,
+        })
+    }
+
+    /// Opens a journal whose events are encrypted with `keyring` (see [`crate::encryption`]), starting it with a header if it's new. A journal that already has plaintext events in it has to be converted with `langwitch encrypt` first.
+    #[cfg(feature = "encryption")]
+    pub fn open_encrypted<P: AsRef<Path>>(path: P, keyring: Keyring) -> Result<Journal> {
+        let mut journal = Journal::open(path)?;
+        let mut first_line = String::new();
+        BufReader::new(File::open(&journal.path)?).read_line(&mut first_line)?;
+        let salt = if first_line.is_empty() {
+            let salt = keyring.salt();
+            journal.file.write_all(format!("{}\n", Keyring::header(&salt)).as_bytes())?;
+            journal.file.sync_data()?;
+            salt
+        } else if is_encrypted(&first_line) {
+            Keyring::parse_header(&first_line)?
+        } else {
+            return Err(LangwitchError::Encryption(format!("{} has unencrypted reviews in it; run `langwitch encrypt` to convert it", journal.path.display())));
+        };
+        journal.sealing = Some((keyring, salt));
+        Ok(journal)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Appends one event and syncs it to disk before returning, so an acknowledged review is never lost.
+    pub fn append(&mut self, event: &ReviewEvent) -> Result<()> {
+        self.append_all(std::slice::from_ref(event))
+    }
+
+    /// Appends several events with one write and one sync.
+    pub fn append_all(&mut self, events: &[ReviewEvent]) -> Result<()> {
+        if events.is_empty() {
+            return Ok(());
+        }
+        //The lines go out in a single write so another reader never sees half of one.
+        let mut lines = Vec::new();
+        for event in events {
+            let line = serde_json::to_vec(event)?;
+            #[cfg(feature = "encryption")]
+            let line = match &mut self.sealing {
+                Some((keyring, salt)) => keyring.seal_line(salt, &line)?.into_bytes(),
+                None => line,
+            };
+            lines.extend(line);
+            lines.push(b'\n');
+        }
+        self.file.write_all(&lines)?;
+        self.file.sync_data()?;
+        Ok(())
+    }
+
+    /// Reads every event in the journal at `path`, oldest first. A missing journal has no events. A final line that was cut short by a crash is ignored; a broken line anywhere else is an error.
+    pub fn read_events<P: AsRef<Path>>(path: P) -> Result<Vec<ReviewEvent>> {
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(LangwitchError::Io(e)),
+        };
+        let mut reader = BufReader::new(file);
+        let mut events = Vec::new();
+        let mut line = String::new();
+        while reader.read_line(&mut line)? > 0 {
+            if !line.trim().is_empty() {
+                match serde_json::from_str(&line) {
+                    Ok(event) => events.push(event),
+                    //No newline means this was the last line and it never finished being written.
+                    Err(_) if !line.ends_with('\n') => break,
+                    Err(e) => return Err(e.into()),
+                }
+            }
+            line.clear();
+        }
+        Ok(events)
+    }
+
+    /// Same as [`Journal::read_events`] for a journal encrypted with `keyring`. A journal that isn't encrypted yet is read as it is.
+    #[cfg(feature = "encryption")]
+    pub fn read_events_encrypted<P: AsRef<Path>>(path: P, keyring: &mut Keyring) -> Result<Vec<ReviewEvent>> {
+        let file = match File::open(&path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(LangwitchError::Io(e)),
+        };
+        let mut reader = BufReader::new(file);
+        let mut line = String::new();
+        reader.read_line(&mut line)?;
+        if !is_encrypted(&line) {
+            return Journal::read_events(path);
+        }
+        let salt = Keyring::parse_header(&line)?;
+        let mut events = Vec::new();
+        line.clear();
+        while reader.read_line(&mut line)? > 0 {
+            if !line.trim().is_empty() {
+                let event = keyring.open_line(&salt, &line).and_then(|plaintext| Ok(serde_json::from_slice(&plaintext)?));
+                match event {
+                    Ok(event) => events.push(event),
+                    Err(_) if !line.ends_with('\n') => break,
+                    Err(e) => return Err(e),
+                }
+            }
+            line.clear();
+        }
+        Ok(events)
+    }
+}
+
+impl GemCollection {
+    /// Applies one review to the facet scheduling data with the collection's configured scheduler, as if it happened at the event's timestamp, and returns the facets it made known for the first time. Cram events change nothing.
+    /// Only `known_facets` and `facet_states` change; the indices are left for the caller to update (see [`GemCollection::mark_facets_known`]).
+    pub fn apply_review(&mut self, event: &ReviewEvent) -> Result<HashSet<String>> {
+        let mut scheduler = std::mem::take(&mut self.scheduler);
+        let newly_known = self.apply_review_with(event, &mut scheduler);
+        self.scheduler = scheduler;
+        newly_known
+    }
+
+    /// Same as [`GemCollection::apply_review`], with any scheduler.
+    pub fn apply_review_with<S: Scheduler>(&mut self, event: &ReviewEvent, scheduler: &mut S) -> Result<HashSet<String>> {
+        if event.cram {
+            return Ok(HashSet::new());
+        }
+        //Grades go through the collection's normalizer, so a review of "running" lands on the facet "run".
+        let surface_forms: Vec<String> = event.grades.keys().cloned().collect();
+        let facets = self.normalize_facet_names(&surface_forms)?;
+        let mut newly_known = HashSet::new();
+        for (facet, surface_form) in facets.iter().zip(surface_forms.iter()) {
+            let grade = &event.grades[surface_form];
+            let state = match self.facet_states.get(facet) {
+                Some(state) => scheduler.review(state, *grade, event.timestamp)?,
+                None => scheduler.review(&Facet::new(facet, event.timestamp), *grade, event.timestamp)?,
+            };
+            self.facet_states.insert(facet.clone(), state);
+            if *grade >= PASSING_GRADE && self.known_facets.insert(self.interner.intern(facet)) {
+                newly_known.insert(facet.clone());
+            }
+        }
+        Ok(newly_known)
+    }
+
+    /// Grades gem `gem_id` right now, each facet on its own with a partial-credit score (0.0 is wrong, 1.0 is right): the scores go through the configured scheduler as fuzzy grades, and only the facets scoring at least [`PASSING_GRADE`] move towards known.
+    /// Facets passing for the first time become known, with the indices updated as [`GemCollection::mark_facets_known`] does. Failed facets stay as they were: an unknown one stays in its gems, and a known one stays known but comes due again soon.
+    pub fn grade_gem(&mut self, gem_id: GemId, grades: HashMap<String, f64>) -> Result<ReviewResult> {
+        self.grade_gem_with_hints(gem_id, grades, HashMap::new())
+    }
+
+    /// Same as [`GemCollection::grade_gem`] for a review where facets needed hints: each grade is cut down by [`hinted_grade`] for its facet's hint level before anything else happens, and the levels are kept in the event.
+    pub fn grade_gem_with_hints(&mut self, gem_id: GemId, grades: HashMap<String, f64>, hints: HashMap<String, u8>) -> Result<ReviewResult> {
+        self.grade_gem_with_hints_at(gem_id, grades, hints, SystemTime::now())
+    }
+
+    /// Same as [`GemCollection::grade_gem_with_hints`], for a review that happened at `timestamp`. Platforms without a clock (wasm32 in a browser) pass the time in this way.
+    pub fn grade_gem_with_hints_at(&mut self, gem_id: GemId, grades: HashMap<String, f64>, hints: HashMap<String, u8>, timestamp: SystemTime) -> Result<ReviewResult> {
+        let grades: HashMap<String, f64> = grades
+            .into_iter()
+            .map(|(facet, grade)| {
+                let level = hints.get(&facet).copied().unwrap_or(0);
+                (facet, hinted_grade(grade, level))
+            })
+            .collect();
+        let hints: HashMap<String, u8> = hints.into_iter().filter(|(_, level)| *level > 0).collect();
+        let gem_key = self.key(gem_id).cloned().ok_or(LangwitchError::MissingGem(gem_id.0))?;
+        let surface_forms: Vec<String> = grades.keys().cloned().collect();
+        let facets = self.normalize_facet_names(&surface_forms)?;
+        let (mut passed, mut failed) = (Vec::new(), Vec::new());
+        for (facet, surface_form) in facets.into_iter().zip(surface_forms.iter()) {
+            if grades[surface_form] >= PASSING_GRADE {
+                passed.push(facet);
+            } else {
+                failed.push(facet);
+            }
+        }
+        passed.sort();
+        failed.sort();
+        let event = ReviewEvent { gem_key, grades, timestamp, hints, cram: false };
+        let newly_known = self.apply_review(&event)?;
+        let unlocked = self.mark_facets_known(&newly_known);
+        let mut newly_known: Vec<String> = newly_known.into_iter().collect();
+        newly_known.sort();
+        Ok(ReviewResult {
+            event,
+            passed,
+            failed,
+            newly_known,
+            unlocked,
+        })
+    }
+
+    /// Rebuilds `known_facets` and `facet_states` purely from the journal at `journal_path` using the configured scheduler, then reindexes. Events are replayed in [`sort_chronologically`] order, so reviews pulled in from another device land where they happened.
+    /// A journal that's been compacted is replayed from its snapshot (see [`crate::compact`]), with only the reviews after it applied on top.
+    /// Meant to be called on a freshly loaded deck: the deck file stays immutable content and the journal is the only mutable truth.
+    pub fn replay<P: AsRef<Path>>(&mut self, journal_path: P) -> Result<()> {
+        let mut scheduler = std::mem::take(&mut self.scheduler);
+        let replayed = self.replay_with(journal_path, &mut scheduler);
+        self.scheduler = scheduler;
+        replayed
+    }
+
+    /// Same as [`GemCollection::replay`], with any scheduler.
+    pub fn replay_with<P: AsRef<Path>, S: Scheduler>(&mut self, journal_path: P, scheduler: &mut S) -> Result<()> {
+        //Only the journal and its snapshot are read, so the deck and progress paths are never used.
+        self.replay_from_with(&mut JsonStorage::new("", "", journal_path), scheduler)
+    }
+
+    /// Same as [`GemCollection::replay_with`] for the journal in `storage`, decrypting it and its snapshot if `storage` has a keyring.
+    pub fn replay_from_with<S: Scheduler>(&mut self, storage: &mut JsonStorage, scheduler: &mut S) -> Result<()> {
+        let snapshot = storage.read_snapshot()?;
+        let mut events = storage.read_events()?;
+        match snapshot {
+            Some(snapshot) => {
+                events.retain(|event| !snapshot.holds(event));
+                self.set_progress(snapshot.progress);
+            }
+            None => self.set_progress(Progress::default()),
+        }
+        sort_chronologically(&mut events);
+        for event in events.iter() {
+            self.apply_review_with(event, scheduler)?;
+        }
+        self.index_all_gems_by_number();
+        Ok(())
+    }
+}
+
+//Walks backwards from the end of the file to the last newline and cuts off anything after it.
+fn truncate_partial_line(mut file: &File) -> Result<()> {
+    let length = file.metadata()?.len();
+    let mut end = length;
+    let mut chunk = [0u8; 4096];
+    while end > 0 {
+        let start = end.saturating_sub(chunk.len() as u64);
+        let chunk = &mut chunk[..(end - start) as usize];
+        file.seek(SeekFrom::Start(start))?;
+        file.read_exact(chunk)?;
+        if let Some(position) = chunk.iter().rposition(|byte| *byte == b'\n') {
+            end = start + position as u64 + 1;
+            break;
+        }
+        end = start;
+    }
+    if end != length {
+        file.set_len(end)?;
+    }
+    Ok(())
+}
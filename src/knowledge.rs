@@ -0,0 +1,161 @@
+//Knowledge that outlives any one deck. A word learned from the news deck is just as known when it turns up in a novel, so every language gets one set of known facet names, and every GemCollection for that language can share it through a SharedKnowledge handle.
+//Collections read from the store when they're indexed and write to it when their progress is saved. Steps of the ordering itself don't count: they're often run on throwaway copies just to preview a curriculum.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    fs,
+    io::ErrorKind,
+    path::Path,
+    sync::{Arc, RwLock},
+};
+
+use serde::{Serialize, Deserialize};
+
+use crate::{
+    collection::GemCollection,
+    error::Result,
+    library::Library,
+    storage::atomic::write_atomically,
+};
+#[cfg(feature = "encryption")]
+use crate::encryption::{is_encrypted, Keyring};
+
+/// Known facet names, per language.
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Default)]
+pub struct KnowledgeStore {
+    pub languages: HashMap<String, HashSet<String>>,
+}
+
+impl KnowledgeStore {
+    /// Reads a store from a JSON file. A file that doesn't exist yet gives an empty store.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<KnowledgeStore> {
+        match fs::read_to_string(path) {
+            Ok(contents) => Ok(serde_json::from_str(&contents)?),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(KnowledgeStore::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Writes the store, replacing the old file only once the new one is safely on disk (see [`crate::storage::atomic`]).
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        write_atomically(path, serde_json::to_string(self)?.as_bytes())
+    }
+
+    pub fn known(&self, language: &str) -> Option<&HashSet<String>> {
+        self.languages.get(language)
+    }
+
+    /// Adds facet names to what's known in `language`, returning how many weren't known there before.
+    pub fn learn<'a, I: IntoIterator<Item = &'a String>>(&mut self, language: &str, facets: I) -> usize {
+        let known = self.languages.entry(language.to_string()).or_default();
+        facets.into_iter().filter(|facet| known.insert((*facet).clone())).count()
+    }
+
+    /// Wraps the store up so several collections can share it.
+    pub fn shared(self) -> Arc<RwLock<KnowledgeStore>> {
+        Arc::new(RwLock::new(self))
+    }
+}
+
+#[cfg(feature = "encryption")]
+impl KnowledgeStore {
+    /// Same as [`KnowledgeStore::load`] for a store encrypted with `keyring`. A store that isn't encrypted yet is read as it is; it's encrypted the next time it's saved.
+    pub fn load_encrypted<P: AsRef<Path>>(path: P, keyring: &mut Keyring) -> Result<KnowledgeStore> {
+        match fs::read_to_string(&path) {
+            Ok(contents) if is_encrypted(&contents) => Ok(serde_json::from_slice(&keyring.open_file(&contents)?)?),
+            Ok(contents) => Ok(serde_json::from_str(&contents)?),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(KnowledgeStore::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Same as [`KnowledgeStore::save`], encrypting the file with `keyring`.
+    pub fn save_encrypted<P: AsRef<Path>>(&self, path: P, keyring: &mut Keyring) -> Result<()> {
+        write_atomically(path, keyring.seal_file(&serde_json::to_vec(self)?)?.as_bytes())
+    }
+}
+
+/// One language's slice of a shared [`KnowledgeStore`], as handed to a [`GemCollection`]. Clones share the same store.
+#[derive(Clone)]
+pub struct SharedKnowledge {
+    store: Arc<RwLock<KnowledgeStore>>,
+    language: String,
+}
+
+impl SharedKnowledge {
+    pub fn new(store: Arc<RwLock<KnowledgeStore>>, language: &str) -> SharedKnowledge {
+        SharedKnowledge { store, language: language.to_string() }
+    }
+
+    pub fn language(&self) -> &str {
+        &self.language
+    }
+
+    /// Everything known in this language.
+    pub fn known(&self) -> HashSet<String> {
+        //A writer that panicked can't have left a half-inserted name behind, so a poisoned lock is still safe to read.
+        let store = self.store.read().unwrap_or_else(|e| e.into_inner());
+        store.known(&self.language).cloned().unwrap_or_default()
+    }
+
+    pub fn learn<'a, I: IntoIterator<Item = &'a String>>(&self, facets: I) -> usize {
+        let mut store = self.store.write().unwrap_or_else(|e| e.into_inner());
+        store.learn(&self.language, facets)
+    }
+
+    /// A copy of the whole store, e.g. for saving it.
+    pub fn snapshot(&self) -> KnowledgeStore {
+        self.store.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+}
+
+impl fmt::Debug for SharedKnowledge {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SharedKnowledge").field("language", &self.language).finish_non_exhaustive()
+    }
+}
+
+//Two handles are the same if they point at the same store, not merely at stores that happen to hold the same words.
+impl PartialEq for SharedKnowledge {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.store, &other.store) && self.language == other.language
+    }
+}
+
+impl GemCollection {
+    /// Makes the collection share known facets with every other collection holding the same handle: what they know is pulled in whenever this one is indexed, and what this one knows is pushed out whenever its progress is saved.
+    pub fn share_knowledge(&mut self, knowledge: SharedKnowledge) {
+        self.shared_knowledge = Some(knowledge);
+    }
+
+    /// Adds whatever the shared store knows about this deck's facets to `known_facets`. Facets no gem here mentions are left in the store, so the collection's progress doesn't fill up with other decks' words.
+    /// Called by [`GemCollection::index_all_gems_by_number`]; returns how many facets were new to this collection.
+    pub fn pull_shared_knowledge(&mut self) -> usize {
+        let shared = match &self.shared_knowledge {
+            Some(shared) => shared.known(),
+            None => return 0,
+        };
+        let before = self.known_facets.len();
+        let ids: Vec<_> = shared.iter().filter_map(|facet| self.interner.get(facet)).collect();
+        self.known_facets.extend(ids);
+        self.known_facets.len() - before
+    }
+
+    /// Adds this collection's known facets to the shared store. Called whenever the collection's progress is saved; returns how many facets were new to the store.
+    pub fn push_shared_knowledge(&self) -> usize {
+        match &self.shared_knowledge {
+            Some(shared) => shared.learn(self.known_facet_names().iter()),
+            None => 0,
+        }
+    }
+}
+
+impl Library {
+    /// Has every deck in the library share `store` as decks of `language`.
+    pub fn share_knowledge(&mut self, store: &Arc<RwLock<KnowledgeStore>>, language: &str) {
+        for deck in self.decks.values_mut() {
+            deck.share_knowledge(SharedKnowledge::new(Arc::clone(store), language));
+        }
+    }
+}
@@ -0,0 +1,130 @@
+//Leeches: facets that keep being forgotten after they've been learned. Drilling them again the same way mostly wastes reviews, so once a facet has lapsed `threshold` times it's suspended: every gem that teaches it is left out of review sessions, and `langwitch leeches` lists it.
+//Releasing a leech lets its gems back in, optionally only with a note (a mnemonic, a different way of thinking about it) written first. The store remembers how many lapses it had when it was released, so it's only suspended again after another `threshold` of them.
+//Lapses are counted over every review, the ones folded into a compacted snapshot included (see JsonStorage::review_counts). Leeches are suspended like any other set-aside gem, so `langwitch suspended` lists them too, but only for as long as the collection is loaded: whether a facet is still a leech is worked out afresh every time.
+
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fs,
+    io::ErrorKind,
+    path::Path,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    collection::GemCollection,
+    error::{LangwitchError, Result},
+    gem::GemId,
+    stats::ReviewCount,
+    storage::atomic::write_atomically,
+};
+
+pub const DEFAULT_LEECH_THRESHOLD: usize = 8;
+
+/// When a facet counts as a leech, e.g. {"threshold": 8, "require_note": true}.
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Copy)]
+#[serde(default)]
+pub struct LeechOptions {
+    /// Lapses that make a facet a leech. 0 turns leeches off.
+    pub threshold: usize,
+    /// Only release a leech once it has a note.
+    pub require_note: bool,
+}
+
+impl Default for LeechOptions {
+    fn default() -> Self {
+        LeechOptions { threshold: DEFAULT_LEECH_THRESHOLD, require_note: false }
+    }
+}
+
+/// A leech that's been released.
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Default)]
+pub struct LeechRecord {
+    /// How many lapses it had when it was released.
+    pub released_at_lapses: usize,
+    pub note: Option<String>,
+}
+
+/// Released leeches and their notes, by facet.
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Default)]
+pub struct LeechStore {
+    pub leeches: BTreeMap<String, LeechRecord>,
+}
+
+/// A facet that has lapsed at least the threshold, and what's become of it.
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct Leech {
+    pub facet: String,
+    pub lapses: usize,
+    /// How many gems teach it.
+    pub gems: usize,
+    /// True while its gems are kept out of review.
+    pub suspended: bool,
+    pub note: Option<String>,
+}
+
+impl LeechStore {
+    /// Reads the store from a JSON file. A file that doesn't exist yet gives an empty store.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<LeechStore> {
+        match fs::read_to_string(path) {
+            Ok(contents) => Ok(serde_json::from_str(&contents)?),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(LeechStore::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Writes the store, replacing the old file only once the new one is safely on disk (see [`crate::storage::atomic`]).
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        write_atomically(path, serde_json::to_string_pretty(self)?.as_bytes())
+    }
+
+    /// Lets a suspended leech back into review, with `note` replacing its note if there is one. With `require_note`, a leech that has no note either way stays suspended.
+    pub fn release(&mut self, leech: &Leech, note: Option<String>, require_note: bool) -> Result<()> {
+        if !leech.suspended {
+            return Err(LangwitchError::Leech(format!("{:?} isn't suspended", leech.facet)));
+        }
+        let note = note.or_else(|| leech.note.clone());
+        if require_note && note.is_none() {
+            return Err(LangwitchError::Leech(format!("{:?} needs a note before it's released", leech.facet)));
+        }
+        self.leeches.insert(leech.facet.clone(), LeechRecord { released_at_lapses: leech.lapses, note });
+        Ok(())
+    }
+}
+
+impl GemCollection {
+    /// Every facet `counts` has lapsing at least `options.threshold` times (see [`crate::stats::review_counts`]), most lapses first, with what `store` says about it. Gems are read as they are, so like [`GemCollection::facet_stats`] this belongs before indexing.
+    pub fn leeches(&self, counts: &HashMap<String, ReviewCount>, options: &LeechOptions, store: &LeechStore) -> Vec<Leech> {
+        if options.threshold == 0 {
+            return Vec::new();
+        }
+        let mut leeches: Vec<Leech> = counts
+            .iter()
+            .filter(|(_, count)| count.lapses >= options.threshold)
+            .map(|(facet, count)| {
+                let record = store.leeches.get(facet);
+                let released_at_lapses = record.map_or(0, |record| record.released_at_lapses);
+                let gems = self.interner.get(facet).map_or(0, |id| self.gems.iter().filter(|gem| gem.unknown_facets.contains(&id)).count());
+                Leech {
+                    suspended: count.lapses.saturating_sub(released_at_lapses) >= options.threshold,
+                    note: record.and_then(|record| record.note.clone()),
+                    lapses: count.lapses,
+                    gems,
+                    facet: facet.clone(),
+                }
+            })
+            .collect();
+        leeches.sort_unstable_by(|a, b| b.lapses.cmp(&a.lapses).then_with(|| a.facet.cmp(&b.facet)));
+        leeches
+    }
+
+    /// Suspends every gem that teaches a suspended leech (see [`GemCollection::suspend`]), returning their ids. Do it before starting a review session, so the session never sees them.
+    pub fn suspend_leeches(&mut self, leeches: &[Leech]) -> Vec<GemId> {
+        let suspended: HashSet<_> = leeches.iter().filter(|leech| leech.suspended).filter_map(|leech| self.interner.get(&leech.facet)).collect();
+        let gem_ids: Vec<GemId> = self.gem_ids().filter(|gem_id| !self.gems[gem_id.0].unknown_facets.is_disjoint(&suspended)).collect();
+        for gem_id in gem_ids.iter() {
+            self.suspend(*gem_id);
+        }
+        gem_ids
+    }
+}
@@ -0,0 +1,94 @@
+//! The langwitch ordering engine.
+//!
+//! A deck is a list of [`Gem`]s: flashcards with one or more sides and a set of facets (words, phrases,
+//! concepts) the learner doesn't know yet. [`GemCollection`] indexes gems by how many unknown facets they
+//! have and orders them so that each new card only introduces what the previous ones have made easy.
+//!
+//! ```no_run
+//! use langwitch::GemCollection;
+//!
+//! # fn main() -> langwitch::Result<()> {
+//! let mut collection = GemCollection::read_gems_from_file("src/gems.json")?;
+//! collection.index_all_gems_by_number();
+//! let first_facets = collection.step()?;
+//! println!("{:?}", first_facets);
+//! # Ok(())
+//! # }
+//! ```
+
+//Single object
+//Use slices and references, not copies
+//Facets held in a different structure.
+//Use hashsets not hashmaps for subtraction.
+//Cache n-2 sentences. get top word. only do the n-2 sentences. concurrent execution. if we compute the frequency map only once, we end up losing flexibility essential to the flashcard app. could probably precompute different internal states based on whether the user got the card right or wrong.
+
+pub mod error;
+pub mod config;
+pub mod interner;
+pub mod gem;
+pub mod schema;
+pub mod side;
+pub mod facet;
+pub mod collection;
+pub mod library;
+pub mod knowledge;
+pub mod progress;
+pub mod scheduler;
+pub mod selection;
+pub mod dedup;
+pub mod merge;
+pub mod edit;
+pub mod filter;
+pub mod optimize;
+pub mod goal;
+pub mod placement;
+pub mod analyze;
+pub mod preview;
+pub mod stats;
+pub mod review;
+pub mod leech;
+pub mod suspend;
+pub mod flag;
+pub mod cram;
+pub mod autosave;
+pub mod compact;
+pub mod shift;
+pub mod embed;
+pub mod wire;
+pub mod typed;
+pub mod cloze;
+pub mod hint;
+pub mod ruby;
+pub mod audio;
+pub mod tts;
+pub mod image;
+pub mod markdown;
+pub mod template;
+#[cfg(feature = "native")]
+pub mod stream;
+pub mod journal;
+pub mod sync;
+pub mod timestamp;
+pub mod storage;
+pub mod encryption;
+pub mod tokenize;
+pub mod normalize;
+pub mod mine;
+#[cfg(feature = "native")]
+pub mod fetch;
+pub mod feed;
+pub mod import;
+pub mod export;
+#[cfg(test)]
+mod testing;
+
+pub use error::{LangwitchError, Result};
+pub use config::Config;
+pub use gem::{Gem, GemId};
+pub use side::{SideRole, SideRoles};
+pub use interner::FacetId;
+pub use facet::Facet;
+pub use collection::GemCollection;
+pub use library::Library;
+pub use progress::Progress;
+pub use journal::{Journal, ReviewEvent, ReviewResult};
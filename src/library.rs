@@ -0,0 +1,132 @@
+//A Library holds several named decks ("news", "novel", "Anki import") side by side. Each deck keeps its own GemCollection, indices and all, so it can still be ordered on its own; Library::combined pours them into one collection for ordering or reviewing across everything, and remembers which deck every gem came from.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use crate::{
+    collection::GemCollection,
+    error::Result,
+    facet::Facet,
+    gem::GemId,
+};
+
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Library {
+    pub decks: BTreeMap<String, GemCollection>,
+}
+
+/// Every deck of a [`Library`] in one collection. Gem `n` of `collection` came from deck `origins[n].0`, where it's gem `origins[n].1`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct CombinedDeck {
+    pub collection: GemCollection,
+    pub origins: Vec<(String, GemId)>,
+}
+
+impl CombinedDeck {
+    /// Which deck a gem of the combined collection came from, and its id there.
+    pub fn origin(&self, gem_id: GemId) -> Option<(&str, GemId)> {
+        self.origins.get(gem_id.0).map(|(deck, origin)| (deck.as_str(), *origin))
+    }
+}
+
+impl Library {
+    pub fn new() -> Library {
+        Library::default()
+    }
+
+    /// Reads every deck in `paths` (deck name to deck file, anything [`GemCollection::read_gems_from_file`] understands).
+    pub fn read_decks(paths: &BTreeMap<String, String>) -> Result<Library> {
+        let mut library = Library::new();
+        for (name, path) in paths.iter() {
+            library.insert(name, GemCollection::read_gems_from_file(path)?);
+        }
+        Ok(library)
+    }
+
+    /// Adds a deck, replacing any deck that already has the name.
+    pub fn insert(&mut self, name: &str, deck: GemCollection) -> Option<GemCollection> {
+        self.decks.insert(name.to_string(), deck)
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<GemCollection> {
+        self.decks.remove(name)
+    }
+
+    pub fn deck(&self, name: &str) -> Option<&GemCollection> {
+        self.decks.get(name)
+    }
+
+    pub fn deck_mut(&mut self, name: &str) -> Option<&mut GemCollection> {
+        self.decks.get_mut(name)
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.decks.keys().map(|name| name.as_str())
+    }
+
+    /// Builds each deck's own indices, for ordering decks one at a time.
+    pub fn index_all(&mut self) {
+        for deck in self.decks.values_mut() {
+            deck.index_all_gems_by_number();
+        }
+    }
+
+    /// All the decks as one collection, decks in name order. What's known in any deck is known in the combined one, and when two decks have scheduling data for the same facet the most recently seen wins.
+    /// Settings (scheduler, selection and so on) come from the first deck. The combined collection isn't indexed yet.
+    pub fn combined(&self) -> CombinedDeck {
+        let mut collection = GemCollection::default();
+        if let Some(first) = self.decks.values().next() {
+            collection.scheduler = first.scheduler.clone();
+            collection.selection = first.selection;
+            collection.lookahead = first.lookahead;
+            collection.scoring = first.scoring.clone();
+            collection.rng = first.rng.clone();
+            collection.normalization = first.normalization.clone();
+            collection.facet_normalization = first.facet_normalization.clone();
+            collection.side_roles = first.side_roles.clone();
+            collection.shared_knowledge = first.shared_knowledge.clone();
+        }
+        let mut origins = Vec::new();
+        let mut facet_states: HashMap<String, Facet> = HashMap::new();
+        for (name, deck) in self.decks.iter() {
+            for gem_id in deck.gem_ids() {
+                if let Some(gem) = deck.gem(gem_id) {
+                    collection.push_gem(gem);
+                    origins.push((name.clone(), gem_id));
+                }
+            }
+            collection.insert_known_facets(deck.known_facet_names().iter());
+            for (facet_name, state) in deck.facet_states.iter() {
+                let newer = facet_states.get(facet_name).is_none_or(|existing| state.last_seen_date > existing.last_seen_date);
+                if newer {
+                    facet_states.insert(facet_name.clone(), state.clone());
+                }
+            }
+        }
+        collection.facet_states = facet_states;
+        CombinedDeck { collection, origins }
+    }
+
+    /// Orders the gems of every deck together, as [`GemCollection::difficulty_order`] would if they were one deck, returning each gem as its deck's name and its id there.
+    pub fn difficulty_order(&self) -> Result<Vec<(String, GemId)>> {
+        let mut combined = self.combined();
+        let order = combined.collection.difficulty_order()?;
+        Ok(order
+            .into_iter()
+            .filter_map(|gem_id| combined.origin(gem_id).map(|(deck, origin)| (deck.to_string(), origin)))
+            .collect())
+    }
+
+    /// Hands what was learned while studying a [`CombinedDeck`] back to the decks it came from. Each deck takes the known facets and scheduling data for the facets it has; facets that none of its gems mention stay out of it.
+    pub fn absorb_progress(&mut self, combined: &GemCollection) {
+        let known = combined.known_facet_names();
+        for deck in self.decks.values_mut() {
+            let known_here: HashSet<&String> = known.iter().filter(|facet| deck.interner.get(facet).is_some()).collect();
+            deck.insert_known_facets(known_here);
+            for (facet_name, state) in combined.facet_states.iter() {
+                if deck.interner.get(facet_name).is_some() {
+                    deck.facet_states.insert(facet_name.clone(), state.clone());
+                }
+            }
+        }
+    }
+}
@@ -1,211 +1,1340 @@
-//Single object
-//Use slices and references, not copies
-//Facets held in a different structure.
-//Use hashsets not hashmaps for subtraction.
-//Cache n-2 sentences. get top word. only do the n-2 sentences. concurrent execution. if we compute the frequency map only once, we end up losing flexibility essential to the flashcard app. could probably precompute different internal states based on whether the user got the card right or wrong.
-
-#[allow(unused_imports)]
-use serde::{Serialize, Deserialize};
-//use tokio;
-#[allow(unused_imports)]
 use std::{
-    collections::{HashSet, HashMap},
-    time::{Duration, Instant},
-    sync::{Arc, Mutex},
-    fs::{File},
-    io::{Read},
+    collections::HashMap,
+    io::{self, BufRead, Write},
+    path::{Path, PathBuf},
+    process::Child,
+    time::{Duration, Instant, SystemTime},
 };
 
-//Gem: vec of strings, hashset of facets, hashset of strings
-#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
-pub struct Gem {
-    //pub number: usize,
-    pub sides: HashMap<usize, String>,
-    pub unknown_facets: HashSet<String>,
-}
-//GemCollection: gems_by_size_index indexes borrowed mutable references to gems by the number of facets they have. gems_by_facet_index indexes borrowed mutable references to gems by the facet-strings they have (e.g "physics": vec of gems here). Lifetime references.
-#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
-pub struct GemCollection<'a> {
-    pub gems: HashMap<usize, Gem>,
-    pub known_facets: HashSet<String>,
-    pub gems_by_size_index: HashMap<usize, HashSet<usize>>,
-    pub gems_by_facet_index: HashMap<String, HashSet<usize>>,
-    pub total_frequency_list: HashMap<String, usize>,
-    pub unused_thing: &'a str,
-}
-
-impl<'a> GemCollection<'a> {
-    pub async fn index_all_gems_by_number(&mut self) {
-        for (number, gem) in self.gems.iter_mut() {
-            if gem.unknown_facets.len() > 0 {
-                self.gems_by_size_index
-                .entry(
-                    gem.unknown_facets.len()
-                )
-                .or_insert(HashSet::new())
-                .insert(number.clone());
-            }
-            for facet in gem.unknown_facets.iter() {
-                self.gems_by_facet_index
-                    .entry(
-                        facet.clone()
-                    )
-                    .or_insert(HashSet::new())
-                    .insert(number.clone());
-            }
-        }
-        self.total_frequency_list = self.create_frequency_hashmap_from_facets_of_n2_gem_indices(HashSet::from_iter(0..self.gems.len()));
-        //println!("{:?}", self.gems_by_size_index);
-    }
-    //Okay, let's use serde to read in a list of gem structs represented in json in this format:
-    //[{"sides":{"0":"In mechanical engineering, the Beale number is a parameter that characterizes the performance of Stirling engines"},"unknown_facets":["mechanical engineering", "Beale number", "Stirling engines"]}...]
-    pub async fn read_gems_from_file(file_path: &str) -> Result<GemCollection<'a>, String> {
-        let mut file = File::open(file_path).unwrap();
-        let mut contents = String::new();
-        file.read_to_string(&mut contents).unwrap();
-        //Prints the first 300 chars 
-        println!("{}", &contents[0..1000]);
-        let gems: Vec<Gem> = serde_json::from_str(&contents).map_err(|e| format!("{}", e))?;
-        //println!("{:?}", gems);
-        let mut gem_collection = GemCollection {
-            gems: HashMap::new(),
-            known_facets: HashSet::new(),
-            gems_by_size_index: HashMap::new(),
-            gems_by_facet_index: HashMap::new(),
-            total_frequency_list: HashMap::new(),
-            unused_thing: "",
+use langwitch::{analyze::ListEntryStatus, audio::AudioOptions, autosave::{Autosave, SessionCheckpoint}, compact::DEFAULT_KEEP, cram::{CramSession, DEFAULT_STREAK}, cloze::ClozeOptions, image::ImageOptions, feed::fetch_feed, filter::FacetFilter, flag::GemFlag, gem::GemKey, hint::{hint, MAX_HINT_LEVEL}, import::article::fetch_article, markdown::side_to_plain, knowledge::SharedKnowledge, leech::LeechStore, placement::{Placement, PlacementOptions}, stats::{write_facet_stats_csv, FacetSort, RetentionBucket}, export::curves::write_forgetting_curves, preview::{OutputFormat, DEFAULT_PREVIEW_STEPS}, progress::read_word_list, review::{DailyLimits, ReviewSession}, ruby::ruby_to_plain, shift::ScheduleShift, storage::Storage, suspend::{SetAside, SetAsideStore}, storage::json::JsonStorage, storage::wal::WalStorage, sync::{merge_events, SyncClient}, template::CardTemplate, timestamp::to_millis, config::apply_config, Config, GemCollection, GemId, LangwitchError, Library};
+#[cfg(feature = "encryption")]
+use langwitch::{compact::JournalSnapshot, encryption::{is_encrypted, Keyring}, storage::atomic::{replace_atomically, temporary_path_for}, Journal};
+
+#[cfg(feature = "tui")]
+mod tui;
+#[cfg(feature = "server")]
+mod server;
+#[cfg(feature = "grpc")]
+mod grpc;
+mod study;
+mod rpc;
+
+const GEMS_PATH: &str = "src/gems.json";
+const PROGRESS_PATH: &str = "src/progress.json";
+const CONFIG_PATH: &str = "src/config.json";
+const JOURNAL_PATH: &str = "src/journal.ndjson";
+const EXCLUDED_FACETS_PATH: &str = "src/excluded_facets.txt";
+const KNOWLEDGE_PATH: &str = "src/knowledge.json";
+const SESSION_PATH: &str = "src/session.json";
+const LEECHES_PATH: &str = "src/leeches.json";
+const SUSPENDED_PATH: &str = "src/suspended.json";
+const WAL_PATH: &str = "src/progress.wal";
+const MEDIA_DIR: &str = "src/media";
+
+const USAGE: &str = "usage: langwitch [--language <CODE>] [--lenient] [--output text|ndjson | feed [--once] | mine-url <URL> [--deck <PATH>] | import-known <WORD LIST> | export-progress <PATH.json|PATH.csv> | export-curves <PATH.json|PATH.csv> | encrypt | push | pull | compact [--keep <N>] | shift --days <N> [--spread <DAYS>] | placement | analyze <TEXT FILE> | coverage [--json <PATH>] | stats --facets [--sort frequency|gems|reviews|lapses|name|due] [--csv <PATH>] | stats --retention [--json <PATH>] | stats --forecast <DAYS> | leeches | leeches release <FACET> [--note <TEXT>] | suspend <GEM> | suspend --facet <FACET> | bury <GEM> | bury --facet <FACET> | unsuspend <GEM> | unsuspend --facet <FACET> | suspended | flag <GEM> <REASON> [--note <TEXT>] | unflag <GEM> | list --flagged | rank <DIR> | path <TARGET LIST> | list-coverage <FREQUENCY LIST> [--json <PATH>] | decks | merge <DECK> [--into <PATH>] | review [--typed] [--cloze] [--audio] [--template <NAME>] [--ahead <HOURS|DAYSd>] | cram <FACET LIST> [--streak <N>] | tui | serve [--listen <ADDRESS>] [--grpc <ADDRESS>] | --stdio]";
+
+//Decks of the same language share known facets through the store at KNOWLEDGE_PATH, encrypted along with progress if the config asks for it. The handle is returned so the store can be saved once the collection's progress has been.
+fn share_knowledge(config: &Config, storage: &mut WalStorage<JsonStorage>, gem_collection: &mut GemCollection) -> langwitch::Result<SharedKnowledge> {
+    let knowledge = SharedKnowledge::new(storage.inner_mut().load_knowledge(KNOWLEDGE_PATH)?.shared(), &config.language);
+    gem_collection.share_knowledge(knowledge.clone());
+    Ok(knowledge)
+}
+
+//The deck, progress and journal at their usual paths, with progress and the journal encrypted and behind the write-ahead log at WAL_PATH if the config asks for either.
+fn open_storage(config: &Config) -> langwitch::Result<WalStorage<JsonStorage>> {
+    let storage = JsonStorage::new(GEMS_PATH, PROGRESS_PATH, JOURNAL_PATH).lenient(config.lenient);
+    match (&config.encryption, config.wal) {
+        (None, None) => Ok(WalStorage::direct(storage)),
+        (None, Some(wal)) => WalStorage::open(storage, WAL_PATH, wal),
+        #[cfg(feature = "encryption")]
+        (Some(options), wal) => {
+            let keyring = Keyring::from_options(options)?;
+            let storage = storage.with_encryption(keyring.clone());
+            match wal {
+                None => Ok(WalStorage::direct(storage)),
+                Some(wal) => WalStorage::open_encrypted(storage, WAL_PATH, wal, keyring),
+            }
+        }
+        #[cfg(not(feature = "encryption"))]
+        (Some(_), _) => Err(LangwitchError::Encryption("the config asks for encryption, but langwitch was built without the encryption feature".to_string())),
+    }
+}
+
+//The deck at `deck_path`, for a command that writes it back. The main deck is read through storage, which saves a version 0 deck's scheduling to progress before anything can write over it.
+fn read_deck_to_rewrite(config: &Config, deck_path: &str) -> langwitch::Result<GemCollection> {
+    if deck_path == GEMS_PATH {
+        open_storage(config)?.load_gems()
+    } else {
+        GemCollection::read_gems_from_file(deck_path)
+    }
+}
+
+//The collection in `storage`, telling the learner about any gems a lenient load left out.
+fn load_collection(storage: &mut WalStorage<JsonStorage>) -> langwitch::Result<GemCollection> {
+    let gem_collection = GemCollection::load_from(storage)?;
+    let skipped = storage.inner().skipped();
+    if !skipped.is_empty() {
+        eprintln!("Skipped {} gems that couldn't be read:", skipped.len());
+        for error in skipped.iter().take(10) {
+            eprintln!("  {}", error);
+        }
+        if skipped.len() > 10 {
+            eprintln!("  and {} more", skipped.len() - 10);
+        }
+    }
+    Ok(gem_collection)
+}
+
+//Sets aside what the store at SUSPENDED_PATH says to, dropping burials that are over, and hands the store back for changing. Belongs before indexing.
+fn load_set_aside(gem_collection: &mut GemCollection, now: SystemTime) -> langwitch::Result<SetAsideStore> {
+    let mut store = SetAsideStore::load(SUSPENDED_PATH)?;
+    store.forget_expired(now);
+    gem_collection.apply_set_aside(&store, now);
+    Ok(store)
+}
+
+//With no subcommand: load the deck and progress, preview the ordering, and save. With NDJSON output the timings go to stderr, so stdout is nothing but steps.
+async fn order(config: Config, format: OutputFormat) -> langwitch::Result<()> {
+    let mut storage = open_storage(&config)?;
+    let mut gem_collection = load_collection(&mut storage)?;
+    let knowledge = share_knowledge(&config, &mut storage, &mut gem_collection)?;
+    apply_config(&config, &mut gem_collection)?;
+    let timing = |message: String| match format {
+        OutputFormat::Text => println!("{}", message),
+        OutputFormat::Ndjson => eprintln!("{}", message),
+    };
+    load_set_aside(&mut gem_collection, SystemTime::now())?;
+    let now = Instant::now();
+    gem_collection.index_all_gems_by_number();
+    let elapsed = now.elapsed();
+    timing(format!("Indexing all gems by number took {} microseconds", elapsed.as_micros()));
+    //The ordering preview learns facets as it goes, so it runs on a copy and doesn't leak into the saved progress.
+    let now = Instant::now();
+    gem_collection.clone().write_order_preview(std::io::stdout().lock(), format, DEFAULT_PREVIEW_STEPS)?;
+    let elapsed = now.elapsed();
+    timing(format!("Displaying all gems took {} microseconds", elapsed.as_micros()));
+    gem_collection.save_to(&mut storage)?;
+    storage.flush()?;
+    storage.inner_mut().save_knowledge(KNOWLEDGE_PATH, &knowledge.snapshot())?;
+    Ok(())
+}
+
+//`feed`: fetch every configured feed, append the sentences the deck doesn't have yet, and (unless --once) do it again every feed_interval_minutes.
+async fn feed(config: Config, once: bool) -> langwitch::Result<()> {
+    loop {
+        let mut gem_collection = read_deck_to_rewrite(&config, GEMS_PATH)?;
+        let mut added = 0;
+        for url in config.feeds.iter() {
+            let (fetched_url, mining) = (url.clone(), config.mining.clone());
+            //A feed that's down shouldn't stop the others from being fetched.
+            match tokio::task::spawn_blocking(move || fetch_feed(&fetched_url, &mining)).await? {
+                Ok(gems) => added += gem_collection.append_new_gems(gems).len(),
+                Err(e) => eprintln!("{}: {}", url, e),
+            }
+        }
+        if added > 0 {
+            gem_collection.write_gems_to_file(GEMS_PATH)?;
+        }
+        println!("Added {} new gems from {} feeds", added, config.feeds.len());
+        if once {
+            return Ok(());
+        }
+        tokio::time::sleep(Duration::from_secs(config.feed_interval_minutes * 60)).await;
+    }
+}
+
+//`mine-url`: download an article, mine it, and append whatever the deck doesn't already have. A deck that doesn't exist yet is started from scratch.
+async fn mine_url(config: Config, url: &str, deck_path: &str) -> langwitch::Result<()> {
+    let mut gem_collection = if Path::new(deck_path).exists() {
+        read_deck_to_rewrite(&config, deck_path)?
+    } else {
+        GemCollection::default()
+    };
+    let (fetched_url, mining) = (url.to_string(), config.mining);
+    let gems = tokio::task::spawn_blocking(move || fetch_article(&fetched_url, &mining)).await??;
+    let mined = gems.len();
+    let added = gem_collection.append_new_gems(gems).len();
+    gem_collection.write_gems_to_file(deck_path)?;
+    println!("Mined {} sentences from {}, {} of them new to {}", mined, url, added, deck_path);
+    Ok(())
+}
+
+//`import-known`: mark every word in a word list known and save the progress.
+async fn import_known(config: Config, word_list_path: &str) -> langwitch::Result<()> {
+    let mut storage = open_storage(&config)?;
+    let mut gem_collection = load_collection(&mut storage)?;
+    let knowledge = share_knowledge(&config, &mut storage, &mut gem_collection)?;
+    apply_config(&config, &mut gem_collection)?;
+    let known_before = gem_collection.known_facets.len();
+    gem_collection.index_all_gems_by_number();
+    let unlocked = gem_collection.import_known_words(word_list_path)?;
+    println!(
+        "Marked {} new facets known, which leaves {} gems with nothing to learn",
+        gem_collection.known_facets.len() - known_before,
+        unlocked.len()
+    );
+    gem_collection.save_to(&mut storage)?;
+    storage.flush()?;
+    storage.inner_mut().save_knowledge(KNOWLEDGE_PATH, &knowledge.snapshot())?;
+    Ok(())
+}
+
+//`push`: send the sync server every review and known word it doesn't have yet, including reviews compacted into the snapshot.
+async fn push(config: Config) -> langwitch::Result<()> {
+    let client = SyncClient::new(&config.sync)?;
+    let mut storage = open_storage(&config)?;
+    let mut events = storage.inner_mut().read_archive()?;
+    events.extend(storage.inner_mut().read_events()?);
+    let knowledge = storage.inner_mut().load_knowledge(KNOWLEDGE_PATH)?;
+    let (events_added, known_added) = tokio::task::spawn_blocking(move || -> langwitch::Result<_> {
+        Ok((client.push_events(&events)?, client.push_knowledge(&knowledge)?))
+    })
+    .await??;
+    println!("Pushed {} new reviews and {} new known facets", events_added.added, known_added);
+    if config.encryption.is_some() {
+        println!("Encryption only covers the files on this device: the sync server keeps these in plaintext");
+    }
+    if events_added.conflicts > 0 {
+        println!("The server already had different records of {} of these reviews and kept its own; pull to see them", events_added.conflicts);
+    }
+    Ok(())
+}
+
+//`pull`: bring back the reviews and known words other devices pushed, merge them into this device's journal, recompute scheduling from every review in timestamp order, and save.
+async fn pull(config: Config) -> langwitch::Result<()> {
+    let client = SyncClient::new(&config.sync)?;
+    let (remote_events, remote_knowledge) = tokio::task::spawn_blocking(move || -> langwitch::Result<_> {
+        Ok((client.pull_events()?, client.pull_knowledge()?))
+    })
+    .await??;
+    let mut storage = open_storage(&config)?;
+    let mut gem_collection = load_collection(&mut storage)?;
+    let knowledge = share_knowledge(&config, &mut storage, &mut gem_collection)?;
+    apply_config(&config, &mut gem_collection)?;
+    //Reviews older than the snapshot are checked against what it folded in, and any it's missing are folded into it.
+    let (late, remote_events): (Vec<_>, Vec<_>) = match storage.inner_mut().read_snapshot()? {
+        Some(snapshot) => remote_events.into_iter().partition(|event| snapshot.holds(event)),
+        None => (Vec::new(), remote_events),
+    };
+    let late = storage.inner_mut().fold_late(&mut gem_collection, late)?;
+    let snapshot = storage.inner_mut().read_snapshot()?;
+    let mut events = storage.inner_mut().read_events()?;
+    if let Some(snapshot) = &snapshot {
+        events.retain(|event| !snapshot.holds(event));
+    }
+    let merge = merge_events(&events, remote_events);
+    let mut store = storage.inner_mut().load_knowledge(KNOWLEDGE_PATH)?;
+    let known_added = store.merge(&remote_knowledge);
+    storage.inner_mut().save_knowledge(KNOWLEDGE_PATH, &store)?;
+    for event in merge.new_events.iter() {
+        storage.append_review(event)?;
+    }
+    events.extend(merge.new_events.iter().cloned());
+    gem_collection.replay_merged(snapshot.as_ref(), &mut events)?;
+    gem_collection.pull_shared_knowledge();
+    gem_collection.save_to(&mut storage)?;
+    storage.flush()?;
+    storage.inner_mut().save_knowledge(KNOWLEDGE_PATH, &knowledge.snapshot())?;
+    println!("Pulled {} new reviews and {} new known facets", merge.new_events.len() + late.folded.len(), known_added);
+    if !late.folded.is_empty() {
+        println!("{} of the new reviews were older than the journal's snapshot and were folded into it", late.folded.len());
+    }
+    let conflicts: Vec<_> = late.conflicts.iter().chain(merge.conflicts.iter()).collect();
+    if !conflicts.is_empty() {
+        println!("{} reviews were recorded differently here and on the server; this device's records were kept:", conflicts.len());
+        for conflict in conflicts {
+            println!("  kept    {}", serde_json::to_string(&conflict.kept)?);
+            println!("  dropped {}", serde_json::to_string(&conflict.dropped)?);
+        }
+    }
+    if !late.unchecked.is_empty() {
+        println!(
+            "{} reviews on the server are older than the journal's snapshot, which was compacted before folded reviews were archived, so they couldn't be checked against it and were left out:",
+            late.unchecked.len()
+        );
+        for event in late.unchecked.iter() {
+            println!("  {}", serde_json::to_string(event)?);
+        }
+    }
+    Ok(())
+}
+
+//`encrypt`: rewrite progress, the known-facet store, the journal and its snapshot and archive encrypted, for turning encryption on after they were written in plaintext. A journal that's already encrypted is left as it is.
+#[cfg(feature = "encryption")]
+async fn encrypt(config: Config) -> langwitch::Result<()> {
+    let mut keyring = match &config.encryption {
+        Some(options) => Keyring::from_options(options)?,
+        None => return Err(LangwitchError::Encryption("set \"encryption\" in the config first".to_string())),
+    };
+    let mut storage = open_storage(&config)?;
+    //Straight to the files: the write-ahead log would skip a save that changes nothing.
+    let progress = storage.inner_mut().load_progress()?;
+    storage.inner_mut().save_progress(&progress)?;
+    let knowledge = storage.inner_mut().load_knowledge(KNOWLEDGE_PATH)?;
+    storage.inner_mut().save_knowledge(KNOWLEDGE_PATH, &knowledge)?;
+    if let Some(snapshot) = storage.inner_mut().read_snapshot()? {
+        storage.inner_mut().write_snapshot(&snapshot)?;
+    }
+    encrypt_journal(&JournalSnapshot::archive_path_for(JOURNAL_PATH), &mut keyring)?;
+    match encrypt_journal(Path::new(JOURNAL_PATH), &mut keyring)? {
+        Some(events) => println!("Encrypted progress, known facets and {} reviews", events),
+        None => println!("Encrypted progress and known facets; the journal was already encrypted or empty"),
+    }
+    Ok(())
+}
+
+//Rewrites the plaintext journal at `path` encrypted, returning how many reviews it held, or None if it was already encrypted or empty.
+#[cfg(feature = "encryption")]
+fn encrypt_journal(path: &Path, keyring: &mut Keyring) -> langwitch::Result<Option<usize>> {
+    let already_encrypted = std::fs::read_to_string(path).map_or(true, |contents| contents.is_empty() || is_encrypted(&contents));
+    if already_encrypted {
+        return Ok(None);
+    }
+    let events = Journal::read_events(path)?;
+    //Written beside the old journal and swapped in at the end, so a crash part way leaves the plaintext one whole.
+    let temporary_path = temporary_path_for(path);
+    let _ = std::fs::remove_file(&temporary_path);
+    let mut journal = Journal::open_encrypted(&temporary_path, keyring.clone())?;
+    journal.append_all(&events)?;
+    replace_atomically(&temporary_path, path)?;
+    Ok(Some(events.len()))
+}
+
+//`compact`: fold all but the newest reviews in the journal into its snapshot, so a full replay (after a pull, say) only has the rest to go through. See langwitch::compact.
+async fn compact(config: Config, keep: usize) -> langwitch::Result<()> {
+    let mut storage = open_storage(&config)?;
+    let mut gem_collection = load_collection(&mut storage)?;
+    apply_config(&config, &mut gem_collection)?;
+    let compaction = storage.inner_mut().compact(&mut gem_collection, keep)?;
+    println!("Folded {} reviews into the snapshot; the journal keeps the newest {}", compaction.folded, compaction.kept);
+    Ok(())
+}
+
+//`shift --days <N>`: push the whole schedule N days later, for coming back from a holiday, and with --spread <DAYS> deal out whatever is still due over that many days. See langwitch::shift.
+async fn shift(config: Config, days: u64, spread_days: usize) -> langwitch::Result<()> {
+    let mut storage = open_storage(&config)?;
+    let mut gem_collection = load_collection(&mut storage)?;
+    apply_config(&config, &mut gem_collection)?;
+    let ScheduleShift { shifted, spread } = gem_collection.shift_schedule(Duration::from_secs(days * 86_400), spread_days, SystemTime::now());
+    gem_collection.save_to(&mut storage)?;
+    storage.flush()?;
+    println!("Moved {} facets {} days later", shifted, days);
+    if spread_days > 0 {
+        println!("Spread the {} still due over {} days", spread, spread_days);
+    }
+    Ok(())
+}
+
+//`export-progress`: write known facets and their scheduling state out as JSON or CSV.
+async fn export_progress(config: Config, export_path: &str) -> langwitch::Result<()> {
+    let mut storage = open_storage(&config)?;
+    let mut gem_collection = load_collection(&mut storage)?;
+    apply_config(&config, &mut gem_collection)?;
+    gem_collection.export_progress(export_path)
+}
+
+//`export-curves`: write (facet, hours elapsed, recalled) for every repeat review, and the forgetting curves fitted to them, as JSON or CSV.
+async fn export_curves(config: Config, export_path: &str) -> langwitch::Result<()> {
+    let events = open_storage(&config)?.inner_mut().read_all_events()?;
+    write_forgetting_curves(&events, export_path)
+}
+
+//`placement`: ask "do you know X?" until the placement test has an estimate, then mark everything below it known and save.
+async fn placement(config: Config) -> langwitch::Result<()> {
+    let mut storage = open_storage(&config)?;
+    let mut gem_collection = load_collection(&mut storage)?;
+    let knowledge = share_knowledge(&config, &mut storage, &mut gem_collection)?;
+    apply_config(&config, &mut gem_collection)?;
+    gem_collection.index_all_gems_by_number();
+    let mut test = Placement::new(&gem_collection, PlacementOptions::default());
+    let stdin = io::stdin();
+    let mut line = String::new();
+    while let Some(question) = test.next_question(&gem_collection) {
+        print!("Do you know {:?}? [y/n] ", question);
+        io::stdout().flush()?;
+        line.clear();
+        if stdin.lock().read_line(&mut line)? == 0 {
+            break;
+        }
+        match line.trim().to_lowercase().as_str() {
+            "y" | "yes" => test.answer(true),
+            "n" | "no" => test.answer(false),
+            _ => println!("Please answer y or n."),
+        }
+    }
+    println!("After {} questions, you probably know the {} most common facets.", test.questions_asked(), test.estimated_known());
+    test.finish(&mut gem_collection);
+    gem_collection.save_to(&mut storage)?;
+    storage.flush()?;
+    storage.inner_mut().save_knowledge(KNOWLEDGE_PATH, &knowledge.snapshot())?;
+    Ok(())
+}
+
+//`analyze`: report how much of a text file is readable with what's known so far.
+async fn analyze(config: Config, text_path: &str) -> langwitch::Result<()> {
+    let mut storage = open_storage(&config)?;
+    let mut gem_collection = load_collection(&mut storage)?;
+    apply_config(&config, &mut gem_collection)?;
+    let report = gem_collection.analyze_text(&std::fs::read_to_string(text_path)?)?;
+    println!("{:.1}% of {} words known", report.coverage * 100.0, report.tokens);
+    println!("{} of {} sentences fully readable, {} more with one unknown word", report.known_sentences, report.sentences, report.i_plus_one_sentences);
+    for (word, count) in report.unknown_words.iter().take(20) {
+        println!("{:>6}  {}", count, word);
+    }
+    Ok(())
+}
+
+//`coverage`: how comprehensible the deck's gems already are, as a table and optionally as JSON.
+async fn coverage(config: Config, json_path: Option<&str>) -> langwitch::Result<()> {
+    let mut storage = open_storage(&config)?;
+    let mut gem_collection = load_collection(&mut storage)?;
+    apply_config(&config, &mut gem_collection)?;
+    let coverage = gem_collection.deck_coverage();
+    println!("{} gems, {} facets known, {} to go, {:.1}% average coverage", coverage.gems, coverage.known_facets, coverage.unknown_facets, coverage.mean_coverage * 100.0);
+    for bucket in coverage.buckets.iter() {
+        let share = if coverage.gems == 0 { 0.0 } else { bucket.gems as f64 / coverage.gems as f64 * 100.0 };
+        println!(">= {:>5.1}% known  {:>7} gems  ({:.1}%)", bucket.min_coverage * 100.0, bucket.gems, share);
+    }
+    if let Some(json_path) = json_path {
+        std::fs::write(json_path, serde_json::to_string_pretty(&coverage)?)?;
+    }
+    Ok(())
+}
+
+//`stats --facets`: every facet's frequency, gems, scheduling state, reviews and lapses, as a table or as CSV.
+#[derive(Default)]
+struct StatsOptions<'a> {
+    sort: FacetSort,
+    csv_path: Option<&'a str>,
+}
+
+impl<'a> StatsOptions<'a> {
+    //None if there's a flag stats doesn't know.
+    fn parse(flags: &[&'a str]) -> Option<StatsOptions<'a>> {
+        let mut options = StatsOptions::default();
+        let mut flags = flags.iter();
+        while let Some(flag) = flags.next() {
+            match *flag {
+                "--sort" => options.sort = FacetSort::parse(flags.next()?)?,
+                "--csv" => options.csv_path = Some(flags.next()?),
+                _ => return None,
+            }
+        }
+        Some(options)
+    }
+}
+
+async fn facet_stats(config: Config, options: StatsOptions<'_>) -> langwitch::Result<()> {
+    let mut storage = open_storage(&config)?;
+    let mut gem_collection = load_collection(&mut storage)?;
+    apply_config(&config, &mut gem_collection)?;
+    let events = storage.inner_mut().read_all_events()?;
+    let mut stats = gem_collection.facet_stats(&events)?;
+    options.sort.sort(&mut stats);
+    if let Some(csv_path) = options.csv_path {
+        return write_facet_stats_csv(&stats, csv_path);
+    }
+    let now = to_millis(SystemTime::now()) as f64;
+    println!("{:<30} {:>9} {:>6} {:>5}  {:<10} {:>8} {:>7} {:>6}", "facet", "frequency", "gems", "known", "stage", "due", "reviews", "lapses");
+    for record in stats.iter() {
+        //Days from now, negative if it's overdue.
+        let due = record.review_date.map(|review_date| format!("{:.1}d", (review_date as f64 - now) / 86_400_000.0)).unwrap_or_default();
+        let known = if record.known { "yes" } else { "no" };
+        println!(
+            "{:<30} {:>9} {:>6} {:>5}  {:<10} {:>8} {:>7} {:>6}",
+            record.facet, record.frequency, record.gems, known, record.stage.as_deref().unwrap_or(""), due, record.reviews, record.lapses
+        );
+    }
+    Ok(())
+}
+
+//`stats --retention`: how many reviews were passed, by interval and by frequency band.
+async fn retention_stats(config: Config, json_path: Option<&str>) -> langwitch::Result<()> {
+    let mut storage = open_storage(&config)?;
+    let mut gem_collection = load_collection(&mut storage)?;
+    apply_config(&config, &mut gem_collection)?;
+    let events = storage.inner_mut().read_all_events()?;
+    let report = gem_collection.retention_stats(&events)?;
+    let retention = |bucket: &RetentionBucket| bucket.retention.map(|retention| format!("{:.1}%", retention * 100.0)).unwrap_or_else(|| "-".to_string());
+    println!("{} of {} repeat reviews passed: {}", report.overall.recalled, report.overall.reviews, retention(&report.overall));
+    println!("By days since the last review:");
+    for bucket in report.by_interval.iter() {
+        let range = match bucket.to {
+            Some(to) => format!("{}-{}", bucket.from, to),
+            None => format!("{}+", bucket.from),
+        };
+        println!("  {:>7}  {:>6}  ({} reviews)", range, retention(bucket), bucket.reviews);
+    }
+    println!("By frequency rank:");
+    for bucket in report.by_frequency.iter() {
+        let range = match bucket.to {
+            Some(to) => format!("{}-{}", bucket.from, to - 1.0),
+            None => format!("{}+", bucket.from),
+        };
+        println!("  {:>11}  {:>6}  ({} reviews)", range, retention(bucket), bucket.reviews);
+    }
+    if let Some(json_path) = json_path {
+        std::fs::write(json_path, serde_json::to_string_pretty(&report)?)?;
+    }
+    Ok(())
+}
+
+//`stats --forecast <DAYS>`: how many reviews fall due on each of the coming days, and how they add up.
+async fn forecast(config: Config, days: usize) -> langwitch::Result<()> {
+    let mut storage = open_storage(&config)?;
+    let mut gem_collection = load_collection(&mut storage)?;
+    apply_config(&config, &mut gem_collection)?;
+    let forecast = gem_collection.review_forecast(days, SystemTime::now());
+    println!("{} reviews overdue", forecast.overdue);
+    let busiest = forecast.days.iter().copied().max().unwrap_or(0).max(1);
+    let mut total = 0;
+    for (day, due) in forecast.days.iter().enumerate() {
+        total += due;
+        //Bars scaled to the busiest day, at most 50 wide.
+        let bar = "#".repeat((due * 50).div_ceil(busiest));
+        println!("day {:>3}  {:>6} due  {:>7} total  {}", day, due, total, bar);
+    }
+    Ok(())
+}
+
+//`leeches`: list the facets that have lapsed config.leeches.threshold times or more, and whether their gems are suspended.
+async fn leeches(config: Config) -> langwitch::Result<()> {
+    let mut storage = open_storage(&config)?;
+    let mut gem_collection = load_collection(&mut storage)?;
+    apply_config(&config, &mut gem_collection)?;
+    let leeches = gem_collection.leeches(&storage.inner_mut().review_counts()?, &config.leeches, &LeechStore::load(LEECHES_PATH)?);
+    if leeches.is_empty() {
+        println!("No leeches");
+    }
+    for leech in leeches.iter() {
+        let status = if leech.suspended { "suspended" } else { "released" };
+        println!("{:<30} {:>3} lapses {:>4} gems  {:<9}  {}", leech.facet, leech.lapses, leech.gems, status, leech.note.as_deref().unwrap_or(""));
+    }
+    Ok(())
+}
+
+//`leeches release <FACET>`: let a suspended leech's gems back into review, with --note <TEXT> as its mnemonic (which config.leeches.require_note insists on).
+async fn release_leech(config: Config, facet: &str, note: Option<&str>) -> langwitch::Result<()> {
+    let mut storage = open_storage(&config)?;
+    let mut gem_collection = load_collection(&mut storage)?;
+    apply_config(&config, &mut gem_collection)?;
+    let facet = gem_collection.normalize_facet_names(&[facet.to_string()])?.into_iter().next().unwrap_or_default();
+    let mut store = LeechStore::load(LEECHES_PATH)?;
+    let leeches = gem_collection.leeches(&storage.inner_mut().review_counts()?, &config.leeches, &store);
+    let leech = leeches.iter().find(|leech| leech.facet == facet).ok_or_else(|| LangwitchError::Leech(format!("{:?} isn't a leech", facet)))?;
+    store.release(leech, note.map(str::to_string), config.leeches.require_note)?;
+    store.save(LEECHES_PATH)?;
+    println!("Released {}; its {} gems are back in review", leech.facet, leech.gems);
+    Ok(())
+}
+
+//What `suspend`, `bury` and `unsuspend` act on: a gem by key or number, or with --facet every gem that teaches a facet.
+enum SetAsideTarget<'a> {
+    Gem(&'a str),
+    Facet(&'a str),
+}
+
+//A gem named on the command line, by key or else by its number in the deck.
+fn find_gem(gem_collection: &GemCollection, gem: &str) -> Option<GemId> {
+    let by_number = gem.parse::<usize>().ok().map(GemId).filter(|gem_id| gem_collection.get(*gem_id).is_some());
+    gem_collection.gem_id(&GemKey(gem.to_string())).or(by_number)
+}
+
+fn no_such_gem(gem: &str) -> String {
+    format!("no gem has the key or number {:?}", gem)
+}
+
+//Loads the deck with facet names normalized for `suspend`, `bury`, `unsuspend` and `suspended`.
+fn load_for_set_aside(config: &Config) -> langwitch::Result<GemCollection> {
+    let mut storage = open_storage(config)?;
+    let mut gem_collection = load_collection(&mut storage)?;
+    apply_config(config, &mut gem_collection)?;
+    Ok(gem_collection)
+}
+
+//`suspend <GEM>` / `bury <GEM>`: keep a gem (or with --facet, every gem that teaches the facet) out of the ordering and review, until `unsuspend` or, buried, until the next day starts. Nothing is deleted and scheduling carries on.
+async fn suspend(config: Config, target: SetAsideTarget<'_>, bury: bool) -> langwitch::Result<()> {
+    let gem_collection = load_for_set_aside(&config)?;
+    let mut store = SetAsideStore::load(SUSPENDED_PATH)?;
+    let until = config.daily_limits.next_day_start(SystemTime::now());
+    let what = match target {
+        SetAsideTarget::Gem(gem) => {
+            let key = gem_collection.key(find_gem(&gem_collection, gem).ok_or_else(|| LangwitchError::SetAside(no_such_gem(gem)))?).cloned().unwrap_or(GemKey(gem.to_string()));
+            let what = format!("gem {}", key);
+            if bury { store.bury_gem(key, until) } else { store.suspend_gem(key) }
+            what
+        }
+        SetAsideTarget::Facet(facet) => {
+            let facet = gem_collection.normalize_facet_names(&[facet.to_string()])?.into_iter().next().unwrap_or_default();
+            let id = gem_collection.interner.get(&facet).ok_or_else(|| LangwitchError::SetAside(format!("no gem teaches {:?}", facet)))?;
+            let gems = gem_collection.gems.iter().filter(|gem| gem.unknown_facets.contains(&id)).count();
+            let what = format!("the {} gems that teach {}", gems, facet);
+            if bury { store.bury_facet(facet, until) } else { store.suspend_facet(facet) }
+            what
+        }
+    };
+    store.save(SUSPENDED_PATH)?;
+    println!("{} {}", if bury { "Buried until tomorrow:" } else { "Suspended" }, what);
+    Ok(())
+}
+
+//`unsuspend <GEM>` (or --facet <FACET>): bring back a suspended or buried gem or facet.
+async fn unsuspend(config: Config, target: SetAsideTarget<'_>) -> langwitch::Result<()> {
+    let gem_collection = load_for_set_aside(&config)?;
+    let mut store = SetAsideStore::load(SUSPENDED_PATH)?;
+    let (restored, what) = match target {
+        SetAsideTarget::Gem(gem) => {
+            let key = match find_gem(&gem_collection, gem) {
+                Some(gem_id) => gem_collection.key(gem_id).cloned().unwrap_or(GemKey(gem.to_string())),
+                //A gem that's gone from the deck can still be taken out of the store.
+                None => GemKey(gem.to_string()),
+            };
+            (store.restore_gem(&key), format!("gem {}", key))
+        }
+        SetAsideTarget::Facet(facet) => {
+            let facet = gem_collection.normalize_facet_names(&[facet.to_string()])?.into_iter().next().unwrap_or_default();
+            (store.restore_facet(&facet), facet)
+        }
+    };
+    if !restored {
+        return Err(LangwitchError::SetAside(format!("{} isn't suspended or buried on its own", what)));
+    }
+    store.save(SUSPENDED_PATH)?;
+    println!("Brought back {}", what);
+    Ok(())
+}
+
+//`suspended`: list what's suspended and buried, and for how much longer, leeches included.
+async fn suspended(config: Config) -> langwitch::Result<()> {
+    let gem_collection = load_for_set_aside(&config)?;
+    let mut store = SetAsideStore::load(SUSPENDED_PATH)?;
+    let now = SystemTime::now();
+    store.forget_expired(now);
+    let hours_left = |until: &u64| (*until).saturating_sub(to_millis(now)).div_ceil(3_600_000);
+    let gem_text = |key: &GemKey| gem_collection.gem_id(key).and_then(|gem_id| gem_collection.text(gem_id)).map(ruby_to_plain).unwrap_or_else(|| "(not in the deck)".to_string());
+    let mut leeches = gem_collection.leeches(&open_storage(&config)?.inner_mut().review_counts()?, &config.leeches, &LeechStore::load(LEECHES_PATH)?);
+    leeches.retain(|leech| leech.suspended);
+    if store == SetAsideStore::default() && leeches.is_empty() {
+        println!("Nothing is suspended or buried");
+    }
+    for leech in leeches.iter() {
+        println!("suspended  leech {}  {} gems, {} lapses", leech.facet, leech.gems, leech.lapses);
+    }
+    for key in store.suspended_gems.iter() {
+        println!("suspended  gem   {}  {}", key, gem_text(key));
+    }
+    for facet in store.suspended_facets.iter() {
+        println!("suspended  facet {}", facet);
+    }
+    for (key, until) in store.buried_gems.iter() {
+        println!("buried {:>2}h gem   {}  {}", hours_left(until), key, gem_text(key));
+    }
+    for (facet, until) in store.buried_facets.iter() {
+        println!("buried {:>2}h facet {}", hours_left(until), facet);
+    }
+    Ok(())
+}
+
+//`flag <GEM> <REASON>`: mark a gem in the deck as a problem (a typo, a bad translation...), with --note <TEXT> saying more.
+async fn flag(config: Config, gem: &str, reason: &str, note: Option<&str>) -> langwitch::Result<()> {
+    let mut deck = read_deck_to_rewrite(&config, GEMS_PATH)?;
+    let gem_id = find_gem(&deck, gem).ok_or_else(|| LangwitchError::Flag(no_such_gem(gem)))?;
+    deck.flag(gem_id, GemFlag::new(reason, note))?;
+    deck.write_gems_to_file(GEMS_PATH)?;
+    println!("Flagged gem {}", deck.key(gem_id).map(ToString::to_string).unwrap_or_default());
+    Ok(())
+}
+
+//`unflag <GEM>`: clear a gem's flags once it's been fixed.
+async fn unflag(config: Config, gem: &str) -> langwitch::Result<()> {
+    let mut deck = read_deck_to_rewrite(&config, GEMS_PATH)?;
+    let gem_id = find_gem(&deck, gem).ok_or_else(|| LangwitchError::Flag(no_such_gem(gem)))?;
+    if !deck.unflag(gem_id) {
+        return Err(LangwitchError::Flag(format!("gem {:?} isn't flagged", gem)));
+    }
+    deck.write_gems_to_file(GEMS_PATH)?;
+    println!("Cleared the flags on gem {}", deck.key(gem_id).map(ToString::to_string).unwrap_or_default());
+    Ok(())
+}
+
+//`list --flagged`: every flagged gem, with its number, key, text and flags, for cleaning the deck up.
+async fn list_flagged(config: Config) -> langwitch::Result<()> {
+    let mut storage = open_storage(&config)?;
+    let mut gem_collection = load_collection(&mut storage)?;
+    apply_config(&config, &mut gem_collection)?;
+    let mut flagged = 0;
+    for gem_id in gem_collection.flagged() {
+        flagged += 1;
+        let key = gem_collection.key(gem_id).map(ToString::to_string).unwrap_or_default();
+        println!("{:>6}  {}  {}", gem_id.0, key, gem_collection.text(gem_id).map(ruby_to_plain).unwrap_or_default());
+        for flag in gem_collection.gems[gem_id.0].flags.iter() {
+            match &flag.note {
+                Some(note) => println!("        {}: {}", flag.reason, note),
+                None => println!("        {}", flag.reason),
+            }
+        }
+    }
+    println!("{} flagged gems", flagged);
+    Ok(())
+}
+
+//`rank`: score every .txt and .epub in a folder and list them easiest first.
+async fn rank(config: Config, dir: &str) -> langwitch::Result<()> {
+    let mut storage = open_storage(&config)?;
+    let mut gem_collection = load_collection(&mut storage)?;
+    apply_config(&config, &mut gem_collection)?;
+    for (path, report) in gem_collection.rank_texts(dir)? {
+        println!(
+            "{:>5.1}%  {:>6} unknown words  {:>5} i+1 sentences  {}",
+            report.coverage * 100.0,
+            report.unknown_words.len(),
+            report.i_plus_one_sentences,
+            path.display()
+        );
+    }
+    Ok(())
+}
+
+//`path`: the gems to study, in order, to learn every facet in a target word list.
+async fn goal_path(config: Config, targets_path: &str) -> langwitch::Result<()> {
+    let mut storage = open_storage(&config)?;
+    let mut gem_collection = load_collection(&mut storage)?;
+    apply_config(&config, &mut gem_collection)?;
+    let targets = read_word_list(targets_path)?;
+    let goal = gem_collection.goal_order(&targets)?;
+    for (step, gem_id) in goal.path.iter().enumerate() {
+        println!("{:>5}  {}", step + 1, ruby_to_plain(gem_collection.text(*gem_id).unwrap_or_default()));
+    }
+    println!("{} gems teach {} of {} targets", goal.path.len(), goal.reached.len(), goal.reached.len() + goal.never_taught.len());
+    if !goal.never_taught.is_empty() {
+        println!("Never taught: {}", goal.never_taught.join(", "));
+    }
+    Ok(())
+}
+
+//`list-coverage`: which words of a frequency list the deck would teach, and how, so gaps show up before studying starts.
+async fn list_coverage(config: Config, list_path: &str, json_path: Option<&str>) -> langwitch::Result<()> {
+    let mut storage = open_storage(&config)?;
+    let mut gem_collection = load_collection(&mut storage)?;
+    apply_config(&config, &mut gem_collection)?;
+    let report = gem_collection.frequency_list_coverage(&read_word_list(list_path)?)?;
+    println!(
+        "{} words: {} known, {} taught i+1, {} only taught with other new words, {} not in the deck",
+        report.entries.len(),
+        report.known,
+        report.i_plus_one,
+        report.with_others,
+        report.not_in_deck
+    );
+    let missing: Vec<&str> = report
+        .entries
+        .iter()
+        .filter(|entry| entry.status == ListEntryStatus::NotInDeck)
+        .map(|entry| entry.word.as_str())
+        .collect();
+    if !missing.is_empty() {
+        println!("Most frequent missing: {}", missing.iter().take(30).copied().collect::<Vec<&str>>().join(", "));
+    }
+    if let Some(json_path) = json_path {
+        std::fs::write(json_path, serde_json::to_string_pretty(&report)?)?;
+    }
+    Ok(())
+}
+
+//`decks`: every deck named in the config, on its own and then ordered together, with the main deck's progress and the language's shared known facets applied to all of them.
+async fn decks(config: Config) -> langwitch::Result<()> {
+    let mut library = Library::read_decks(&config.decks)?;
+    let mut storage = open_storage(&config)?;
+    library.share_knowledge(&storage.inner_mut().load_knowledge(KNOWLEDGE_PATH)?.shared(), &config.language);
+    let progress = storage.load_progress()?;
+    for (name, deck) in library.decks.iter_mut() {
+        deck.set_progress(progress.clone());
+        apply_config(&config, deck)?;
+        println!("{}: {} gems", name, deck.gems.len());
+    }
+    let order = library.difficulty_order()?;
+    println!("{} gems across {} decks. The first 20:", order.len(), library.decks.len());
+    for (name, gem_id) in order.iter().take(20) {
+        let side = library.deck(name).and_then(|deck| deck.text(*gem_id)).unwrap_or_default();
+        println!("{:>12}  {}", name, ruby_to_plain(side));
+    }
+    Ok(())
+}
+
+//`merge`: fold another deck into this one, keeping one copy of every gem the two have in common.
+async fn merge(config: Config, other_path: &str, deck_path: &str) -> langwitch::Result<()> {
+    let mut gem_collection = if Path::new(deck_path).exists() {
+        read_deck_to_rewrite(&config, deck_path)?
+    } else {
+        GemCollection::default()
+    };
+    let report = gem_collection.merge(GemCollection::read_gems_from_file(other_path)?);
+    gem_collection.write_gems_to_file(deck_path)?;
+    println!(
+        "Added {} gems, merged {} into existing ones and skipped {} duplicates ({} sides disagreed; the existing text was kept)",
+        report.added, report.merged, report.duplicates, report.conflicting_sides
+    );
+    Ok(())
+}
+
+//Everything a review front end works with: the deck loaded and set up the same way the ordering is, and a review session started on it before indexing.
+struct ReviewSetup {
+    storage: WalStorage<JsonStorage>,
+    gem_collection: GemCollection,
+    knowledge: SharedKnowledge,
+    session: ReviewSession,
+    player: Player,
+    images: ImageOptions,
+    //The card template from config.template, if one is chosen.
+    template: Option<CardTemplate>,
+    cloze: ClozeOptions,
+    //The checkpoint of a `review` session that never finished, if there is one.
+    interrupted: Option<SessionCheckpoint>,
+    //What's suspended and buried, saved to SUSPENDED_PATH whenever a card is set aside.
+    set_aside: SetAsideStore,
+    //Where days start, so a buried card comes back at the next one.
+    daily_limits: DailyLimits,
+}
+
+//Flags a card's gem in the deck file, and on the loaded collection so it shows straight away.
+fn flag_card(gem_collection: &mut GemCollection, gem_id: GemId, flag: GemFlag) -> langwitch::Result<()> {
+    let key = gem_collection.key(gem_id).cloned().ok_or(LangwitchError::MissingGem(gem_id.0))?;
+    if !GemCollection::flag_in_deck_file(GEMS_PATH, &key, flag.clone())? {
+        return Err(LangwitchError::MissingGem(gem_id.0));
+    }
+    gem_collection.flag(gem_id, flag)
+}
+
+//Suspends a card's gem, or buries it until the next day starts, and saves the store straight away.
+fn set_card_aside(gem_collection: &mut GemCollection, store: &mut SetAsideStore, daily_limits: &DailyLimits, gem_id: GemId, bury: bool) -> langwitch::Result<()> {
+    let why = if bury { SetAside::Buried { until: daily_limits.next_day_start(SystemTime::now()) } } else { SetAside::Suspended };
+    gem_collection.set_aside_in(store, gem_id, why)?;
+    store.save(SUSPENDED_PATH)
+}
+
+impl ReviewSetup {
+    fn load(config: Config) -> langwitch::Result<ReviewSetup> {
+        let template = match &config.template {
+            Some(name) => Some(config.card_template(name)?),
+            None => None,
+        };
+        let mut storage = open_storage(&config)?;
+        let mut gem_collection = load_collection(&mut storage)?;
+        let knowledge = share_knowledge(&config, &mut storage, &mut gem_collection)?;
+        apply_config(&config, &mut gem_collection)?;
+        let player = Player::new(config.audio);
+        let images = config.images;
+        let cloze = config.cloze;
+        //Grades an interrupted session journaled but never saved go in before the session is set up around what's still unknown.
+        let interrupted = SessionCheckpoint::load(SESSION_PATH)?;
+        if interrupted.is_some() {
+            let recovered = gem_collection.recover_reviews(&storage.inner_mut().read_events()?)?;
+            if recovered > 0 {
+                gem_collection.save_to(&mut storage)?;
+                println!("Recovered {} reviews that hadn't been saved", recovered);
+            }
+        }
+        if config.leeches.threshold > 0 {
+            let leeches = gem_collection.leeches(&storage.inner_mut().review_counts()?, &config.leeches, &LeechStore::load(LEECHES_PATH)?);
+            gem_collection.suspend_leeches(&leeches);
+        }
+        let set_aside = load_set_aside(&mut gem_collection, SystemTime::now())?;
+        let mut session = ReviewSession::new(&gem_collection);
+        session.space_siblings(config.sibling_spacing);
+        let limits = config.daily_limits;
+        if limits.new_facets.is_some() || limits.reviews.is_some() {
+            let snapshot = storage.inner_mut().read_snapshot()?;
+            session.limit(limits.remaining(&storage.inner_mut().read_events()?, snapshot.as_ref(), SystemTime::now()));
+        }
+        gem_collection.index_all_gems_by_number();
+        Ok(ReviewSetup { storage, gem_collection, knowledge, session, player, images, template, cloze, interrupted, set_aside, daily_limits: limits })
+    }
+}
+
+//Plays cards' audio, one recording at a time: starting another stops whatever was still playing.
+struct Player {
+    options: AudioOptions,
+    playing: Option<Child>,
+}
+
+impl Player {
+    fn new(mut options: AudioOptions) -> Player {
+        options.media_dir.get_or_insert_with(|| PathBuf::from(MEDIA_DIR));
+        Player { options, playing: None }
+    }
+
+    //The gem's recording, or speech for it if a hook is set up.
+    fn audio(&self, gem_collection: &GemCollection, gem_id: GemId) -> langwitch::Result<Option<PathBuf>> {
+        gem_collection.audio_or_speech(gem_id, &self.options)
+    }
+
+    fn stop(&mut self) {
+        if let Some(mut child) = self.playing.take() {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+
+    fn play(&mut self, path: &Path) -> langwitch::Result<()> {
+        self.stop();
+        self.playing = Some(self.options.play(path)?);
+        Ok(())
+    }
+
+    //Plays a card's audio for the command-line review. A missing or broken player shouldn't end the review, so failures are only reported.
+    fn play_or_warn(&mut self, path: &Path) {
+        if let Err(e) = self.play(path) {
+            eprintln!("Couldn't play {} with {}: {}", path.display(), self.options.player.program, e);
+        }
+    }
+}
+
+//Reads one trimmed line of input. None at end of input.
+fn read_answer(question: &str) -> io::Result<Option<String>> {
+    print!("{}", question);
+    io::stdout().flush()?;
+    let mut line = String::new();
+    if io::stdin().lock().read_line(&mut line)? == 0 {
+        return Ok(None);
+    }
+    Ok(Some(line.trim().to_string()))
+}
+
+//Same as read_answer, lowercased, for menu choices.
+fn prompt(question: &str) -> io::Result<Option<String>> {
+    Ok(read_answer(question)?.map(|answer| answer.to_lowercase()))
+}
+
+//Asks about each facet in turn. None if the input ran out or the user quit partway.
+fn grade_facets(facets: &[String]) -> io::Result<Option<HashMap<String, f64>>> {
+    let mut grades = HashMap::new();
+    for facet in facets.iter() {
+        loop {
+            match prompt(&format!("  {}? [y/n] ", facet))?.as_deref() {
+                Some("y") | Some("yes") => grades.insert(facet.clone(), 1.0),
+                Some("n") | Some("no") => grades.insert(facet.clone(), 0.0),
+                Some("q") | None => return Ok(None),
+                _ => continue,
+            };
+            break;
+        }
+    }
+    Ok(Some(grades))
+}
+
+fn print_hints(facets: &[String], level: u8) {
+    for facet in facets.iter() {
+        println!("  hint: {}", hint(facet, level));
+    }
+}
+
+//How `review` was asked to run.
+#[derive(Default)]
+struct ReviewMode {
+    typed: bool,
+    cloze: bool,
+    audio_first: bool,
+    template: Option<String>,
+    ahead: Option<Duration>,
+}
+
+//A review-ahead window: hours, or days with a "d" after them ("12", "12h", "2d").
+fn parse_window(window: &str) -> Option<Duration> {
+    let (number, hours_per_unit) = match window.strip_suffix('d') {
+        Some(days) => (days, 24.0),
+        None => (window.strip_suffix('h').unwrap_or(window), 1.0),
+    };
+    let hours = number.parse::<f64>().ok().filter(|hours| hours.is_finite() && *hours >= 0.0)? * hours_per_unit;
+    Some(Duration::from_secs_f64(hours * 3600.0))
+}
+
+impl ReviewMode {
+    //None if there's a flag review doesn't know.
+    fn parse(flags: &[&str]) -> Option<ReviewMode> {
+        let mut mode = ReviewMode::default();
+        let mut flags = flags.iter();
+        while let Some(flag) = flags.next() {
+            match *flag {
+                "--typed" => mode.typed = true,
+                "--cloze" => mode.cloze = true,
+                "--audio" => mode.audio_first = true,
+                "--template" => mode.template = Some(flags.next()?.to_string()),
+                "--ahead" => mode.ahead = Some(parse_window(flags.next()?)?),
+                _ => return None,
+            }
+        }
+        Some(mode)
+    }
+}
+
+//What came of waiting for the reveal.
+#[derive(PartialEq)]
+enum Reveal {
+    Shown,
+    Quit,
+    //Suspend the card's gem, or bury it if true.
+    SetAside(bool),
+}
+
+//Waits for Enter, giving hints, playing the audio again and flagging the gem (handing the flag to `flag`) on the way. With `can_set_aside`, s suspends the card's gem and b buries it until tomorrow instead.
+fn wait_for_reveal(facets: &[String], hint_level: &mut u8, audio: Option<&Path>, player: &mut Player, can_set_aside: bool, flag: &mut dyn FnMut(GemFlag) -> langwitch::Result<()>) -> langwitch::Result<Reveal> {
+    let question = format!(
+        "(Enter to reveal, h for a hint, ! to flag, {}{}q to quit) ",
+        if audio.is_some() { "a to play again, " } else { "" },
+        if can_set_aside { "s to suspend, b to bury until tomorrow, " } else { "" }
+    );
+    loop {
+        match prompt(&question)?.as_deref() {
+            Some("h") => {
+                *hint_level = (*hint_level + 1).min(MAX_HINT_LEVEL);
+                print_hints(facets, *hint_level);
+            }
+            Some("a") => {
+                if let Some(audio) = audio {
+                    player.play_or_warn(audio);
+                }
+            }
+            Some("!") => {
+                let reason = read_answer("  What's wrong with it (typo, bad translation, too long...)? ")?.unwrap_or_default();
+                if reason.is_empty() {
+                    continue;
+                }
+                let note = read_answer("  Note (Enter for none): ")?;
+                flag(GemFlag::new(&reason, note.as_deref()))?;
+                println!("  Flagged");
+            }
+            Some("s") if can_set_aside => return Ok(Reveal::SetAside(false)),
+            Some("b") if can_set_aside => return Ok(Reveal::SetAside(true)),
+            Some("q") | None => return Ok(Reveal::Quit),
+            _ => return Ok(Reveal::Shown),
+        }
+    }
+}
+
+//Takes a grade for the whole card or facet by facet. None if the user quit.
+fn ask_grades(facets: &[String]) -> io::Result<Option<HashMap<String, f64>>> {
+    println!("Facets: {}", facets.join(", "));
+    loop {
+        match prompt("Right? [y] all, [n] none, [f] facet by facet, [q] quit ")?.as_deref() {
+            Some("y") | Some("yes") => return Ok(Some(facets.iter().map(|facet| (facet.clone(), 1.0)).collect())),
+            Some("n") | Some("no") => return Ok(Some(facets.iter().map(|facet| (facet.clone(), 0.0)).collect())),
+            Some("f") => return grade_facets(facets),
+            Some("q") | None => return Ok(None),
+            _ => continue,
+        }
+    }
+}
+
+fn show_picture(gem_collection: &GemCollection, gem_id: GemId, player: &Player, images: &ImageOptions) {
+    if let Some(picture) = gem_collection.image_path(gem_id, player.options.media_dir.as_deref()) {
+        if let Err(e) = images.show(&picture) {
+            eprintln!("Couldn't show {}: {}", picture.display(), e);
+        }
+    }
+}
+
+//`review`: the flashcard loop. Shows the text side, waits for Enter, shows the other sides labelled with their roles, and takes a grade for the whole card or facet by facet. Hints can be asked for before the reveal, and each one costs some of the grade. Every review goes to the journal straight away; progress and a checkpoint of the session are saved every config.autosave reviews or seconds, and a session that was cut off is offered back next time (see langwitch::autosave).
+//With --typed, cards that have both the prompt and answer sides of config.typed_answer are graded by typing the answer instead. With --cloze, the card's facets are blanked out of the sentence (config.cloze says which side) until it's revealed.
+//Cards with a picture have it drawn under the front by config.images.viewer. Cards with audio (a recording, or speech from config.audio.tts) play it when they're shown (unless config.audio.autoplay is off) and again on 'a'. With --audio, those cards are listening practice: the recording plays and the text stays hidden until the reveal.
+//With --template <NAME> (or config.template), the card template decides what the front and back show and when audio plays instead. Typed cards keep their own prompt and answer sides.
+//With --ahead <WINDOW>, once nothing is due and no new gem can be shown, facets due within the window (hours, or days as "2d") are reviewed early instead of stopping.
+//Before the reveal, s suspends the card's gem and b buries it until tomorrow (see langwitch::suspend); either way it's skipped without a grade. ! flags it as a problem gem, with a reason and a note, for `list --flagged`.
+async fn review(mut config: Config, mode: ReviewMode) -> langwitch::Result<()> {
+    let typed_answer = config.typed_answer.clone();
+    if mode.template.is_some() {
+        config.template = mode.template;
+    }
+    let mut autosave = Autosave::new(config.autosave);
+    let ReviewSetup { mut storage, mut gem_collection, knowledge, mut session, mut player, images, template, cloze: cloze_options, interrupted, mut set_aside, daily_limits } = ReviewSetup::load(config)?;
+    if let Some(window) = mode.ahead {
+        session.review_ahead(window);
+    }
+    let mut reviewed = 0;
+    if let Some(checkpoint) = interrupted {
+        let minutes = SystemTime::now().duration_since(checkpoint.saved_at).unwrap_or_default().as_secs() / 60;
+        println!("The last review session was interrupted {} cards in, {} minutes ago.", checkpoint.reviewed, minutes);
+        if read_answer("Resume it? [Y/n] ")?.is_some_and(|answer| !answer.eq_ignore_ascii_case("n")) {
+            session.resume(&gem_collection, &checkpoint);
+            reviewed = checkpoint.reviewed;
+        }
+    }
+    session.checkpoint(&gem_collection, reviewed, SystemTime::now()).save(SESSION_PATH)?;
+    while let Some(card) = session.next_card(&mut gem_collection, SystemTime::now())? {
+        let gem = match gem_collection.gem(card.gem) {
+            Some(gem) => gem,
+            None => continue,
+        };
+        println!();
+        let label = match (card.is_new, card.ahead) {
+            (true, _) => "[new]",
+            (false, true) => "[ahead]",
+            (false, false) => "[review]",
+        };
+        let mut hint_level = 0;
+        let audio = player.audio(&gem_collection, card.gem).unwrap_or_else(|e| {
+            eprintln!("Couldn't synthesize speech: {}", e);
+            None
+        });
+        let typed_sides = match (gem.sides.get(&typed_answer.prompt_side), gem.sides.get(&typed_answer.answer_side)) {
+            (Some(prompt_side), Some(answer_side)) if mode.typed => Some((prompt_side, answer_side)),
+            _ => None,
+        };
+        let front = match &template {
+            Some(template) if typed_sides.is_none() => Some(gem_collection.render_face(&template.front, card.gem, &card.facets, &cloze_options)?),
+            _ => None,
+        };
+        let listening = mode.audio_first && audio.is_some();
+        let autoplay = match &front {
+            Some(front) => front.audio,
+            None => listening || player.options.autoplay,
         };
-        for (number, gem) in gems.iter().enumerate() {
-            gem_collection.gems.insert(number, gem.clone());
-        }
-        Ok(gem_collection)
-    }
-
-    //Here, will use tokio spawn to run the indexing in parallel.
-    pub async fn display_all_gems_in_order_of_difficulty(&'a mut self) {
-        self.known_facets = HashSet::new();
-        self.index_all_gems_by_number().await;
-
-        for _ in 0..200 {
-            let non_empty_keys = self.gems_by_size_index.keys().filter(|&key| self.gems_by_size_index.get(key).unwrap().len() > 0);
-            //We get the minimum number from the keys of gems_by_size_index, and the second minimum number, filtering out any keys that point to empty hashsets
-            let min_number = &non_empty_keys.clone()
-                                            .min()
-                                            .unwrap();
-            let min_number_2 = &non_empty_keys.clone()
-                                            .skip(1)
-                                            .min()
-                                            .unwrap();
-            //We fetch all the Gem indices from gems_by_size_index for the minimum number, as HashSets:
-            let gem_indices_for_n1: HashSet<usize> = HashSet::from_iter(
-                self.gems_by_size_index
-                    .get(&min_number)
-                    .unwrap()
-                    .clone()
-            );
-            let gem_indices_for_n2: HashSet<usize> = HashSet::from_iter(
-                self.gems_by_size_index
-                    .get(&min_number_2)
-                    .unwrap()
-                    .clone()
-            );
-            //We create a frequency hashmap by counting how many times each facet appears in total for all n_2 gems:
-            let frequency_hashmap = self.create_frequency_hashmap_from_facets_of_n2_gem_indices(gem_indices_for_n2);
-            //We get the facets with the highest frequency, sampling only from n_1 gems:
-            let top_gem_facets: HashSet<String> = self.choose_max_n1_gem_facets_by_frequency_hashmap(gem_indices_for_n1, &frequency_hashmap, 2);
-            println!("{:?}", top_gem_facets);
-            //Most of the time, there's only one facet but sometimes there are up to 7 or 8. So what we want to do now is take the facet names, get the appropriate gem indices from gems_by_facet_index, and find the intersection of those gem indices with the gem indices for n_1, and n_2.
-            //We get the indices of the gems that have the top n1 gem facets:
-            let mut top_gem_indices: HashSet<usize> = HashSet::new();
-            for facet in top_gem_facets.iter() {
-                top_gem_indices = top_gem_indices.union(
-                    self.gems_by_facet_index
-                        .get(facet)
-                        .as_ref()
-                        .clone()
-                        .unwrap()
-                    ).cloned().collect();
-            }
-            //We could do this, but that would be borrowing "self" twice, so we need to edit the gem_collection in place:
-            //self.gems_by_size_index.retain(|_, v| v.intersection(&top_gem_indices).count() > 0);
-            //Now all we need to do is go through self.gems and subtract top_gem_facets from each gem's unknown_facet field, since now we know them. Before that, we remove the gem's number from gems_by_size_index, adding it to the gems_by_size_index "above" it (e.g if it's currently indexed under '3', we add it to '4').
-            for gem_index in top_gem_indices.iter() {
-                let mut gem = self.gems.get_mut(gem_index).unwrap();
-                self.gems_by_size_index
-                    .get_mut(&gem.unknown_facets.len())
-                    .unwrap()
-                    .remove(gem_index);
-                //The index above might not exist, so we need to create it if it doesn't:
-                self.gems_by_size_index
-                    .entry(gem.unknown_facets.len() + 1)
-                    .or_insert(HashSet::new())
-                    .insert(*gem_index);
-                gem.unknown_facets = gem.unknown_facets.difference(&top_gem_facets).cloned().collect();
-            }
-                //Now, we remove the top_gem_indices from each facet index in top_gem_facets via difference, the same as last time. We need to access
-                for facet in top_gem_facets.iter() {
-                //self.gems_by_facet_index.get_mut(facet).unwrap().difference(&top_gem_indices);
-                //Quick sanity check, when we call difference, it returns a new HashSet, so we can't just replace it. And as we noted last time, there's no such thing as 'difference with'. So we need to do this:
-                let facet_indices = self.gems_by_facet_index.get_mut(facet).unwrap();
-                facet_indices.retain(|&gem_index| !top_gem_indices.contains(&gem_index));
-            }
-        }
-    }
-
-    fn create_frequency_hashmap_from_facets_of_n2_gem_indices(&self, gem_indices_for_n2: HashSet<usize>) -> HashMap<String, usize> {
-        let mut frequency_hashmap: HashMap<String, usize> = HashMap::new();
-        for gem_index in gem_indices_for_n2.iter() {
-            let gem = self.gems.get(gem_index).unwrap();
-            for facet in gem.unknown_facets.iter() {
-                frequency_hashmap.entry(facet.clone())
-                    .and_modify(|e| *e += 1)
-                    .or_insert(1);
-            }
-        }
-        frequency_hashmap
-    }
-
-    fn choose_max_n1_gem_facets_by_frequency_hashmap(&self, gem_indices_for_n1: HashSet<usize>, frequency_hashmap: &HashMap<String, usize>, _minimum_viable_hashmap_number: usize) -> HashSet<String> {
-        //Here, we're essentially just going: ok, so I have all of these gem indices. And I have a map that tells me that so-and-so facet occurred 5 or 10 or however many times. Now I just need to look at each gem, and see how often each of its facets occurs in the map. Then I just average out that frequency, call it 'weight', and get the gem with the highest weight.
-        let mut top_gem_facets: HashSet<String> = HashSet::new();
-        //let mut top_gem_sides: HashMap<usize, String> = HashMap::new();
-        let mut max_weight: f64 = 0.0;
-        for gem_index in gem_indices_for_n1.iter() {
-            let gem = self.gems.get(gem_index).unwrap();
-            let mut weight: f64 = 0.0;
-            for facet in gem.unknown_facets.iter() {
-                //There's a possibility the facet might not be in the hashmap, so we need to check for that:
-                if let Some(facet_weight) = frequency_hashmap.get(facet) {
-                    weight += *facet_weight as f64;
+        if let Some(audio) = audio.as_deref().filter(|_| autoplay) {
+            player.play_or_warn(audio);
+        }
+        let grades = if let Some((prompt_side, answer_side)) = typed_sides {
+            println!("{} {}", label, ruby_to_plain(prompt_side));
+            let answer = loop {
+                match read_answer("(? for a hint) > ")? {
+                    Some(answer) if answer == "?" => {
+                        hint_level = (hint_level + 1).min(MAX_HINT_LEVEL);
+                        print_hints(&card.facets, hint_level);
+                    }
+                    answer => break answer,
+                }
+            };
+            let answer = match answer {
+                Some(answer) => answer,
+                None => break,
+            };
+            let grade = typed_answer.grade(answer_side, &answer);
+            println!("{}  ({:.0}%)", ruby_to_plain(answer_side), grade * 100.0);
+            println!("Facets: {}", card.facets.join(", "));
+            card.facets.iter().map(|facet| (facet.clone(), grade)).collect()
+        } else if let (Some(template), Some(front)) = (&template, front) {
+            //A front that came out empty (a listening template on a gem with no audio, say) falls back to the text.
+            let front_text = match front.text.as_str() {
+                "" if front.audio && audio.is_some() => "(listen)".to_string(),
+                "" => gem_collection.text(card.gem).map(ruby_to_plain).unwrap_or_default(),
+                text => text.to_string(),
+            };
+            println!("{} {}", label, front_text.replace('\n', "\n  "));
+            if front.image {
+                show_picture(&gem_collection, card.gem, &player, &images);
+            }
+            match wait_for_reveal(&card.facets, &mut hint_level, audio.as_deref(), &mut player, true, &mut |flag| flag_card(&mut gem_collection, card.gem, flag))? {
+                Reveal::Shown => {}
+                Reveal::Quit => break,
+                Reveal::SetAside(bury) => {
+                    set_card_aside(&mut gem_collection, &mut set_aside, &daily_limits, card.gem, bury)?;
+                    println!("{}", if bury { "Buried until tomorrow" } else { "Suspended" });
+                    continue;
                 }
-                //weight += *frequency_hashmap.get(facet).unwrap() as f64;
             }
-            weight /= gem.unknown_facets.len() as f64;
-            if weight > max_weight && gem.unknown_facets.len() > 0{
-                top_gem_facets = gem.unknown_facets.clone();
-                //top_gem_sides = gem.sides.clone();
-                max_weight = weight;
+            let back = gem_collection.render_face(&template.back, card.gem, &card.facets, &cloze_options)?;
+            if !back.text.is_empty() {
+                println!("  {}", back.text.replace('\n', "\n  "));
+            }
+            if back.image {
+                show_picture(&gem_collection, card.gem, &player, &images);
             }
+            if let Some(audio) = audio.as_deref().filter(|_| back.audio) {
+                player.play_or_warn(audio);
+            }
+            match ask_grades(&card.facets)? {
+                Some(grades) => grades,
+                None => break,
+            }
+        } else {
+            let text_side = gem_collection.side_roles.text_side();
+            let mut side_numbers: Vec<&usize> = gem.sides.keys().collect();
+            side_numbers.sort_unstable();
+            let blanked = if mode.cloze { gem_collection.cloze(card.gem, &card.facets, &cloze_options)? } else { None };
+            let is_cloze = blanked.is_some();
+            let front = if listening {
+                "(listen)".to_string()
+            } else {
+                blanked.unwrap_or_else(|| gem.sides.get(&text_side).map(|side| ruby_to_plain(side)).unwrap_or_default())
+            };
+            println!("{} {}", label, front);
+            show_picture(&gem_collection, card.gem, &player, &images);
+            match wait_for_reveal(&card.facets, &mut hint_level, audio.as_deref(), &mut player, true, &mut |flag| flag_card(&mut gem_collection, card.gem, flag))? {
+                Reveal::Shown => {}
+                Reveal::Quit => break,
+                Reveal::SetAside(bury) => {
+                    set_card_aside(&mut gem_collection, &mut set_aside, &daily_limits, card.gem, bury)?;
+                    println!("{}", if bury { "Buried until tomorrow" } else { "Suspended" });
+                    continue;
+                }
+            }
+            //A cloze or listening card shows every side in full, the hidden one included; otherwise the text side is already all there.
+            for side_number in side_numbers.into_iter().filter(|side_number| is_cloze || listening || **side_number != text_side) {
+                let role = gem_collection.side_roles.role(*side_number);
+                println!("  {}: {}", role.label(), side_to_plain(role, &gem.sides[side_number]).replace('\n', "\n    "));
+            }
+            match ask_grades(&card.facets)? {
+                Some(grades) => grades,
+                None => break,
+            }
+        };
+        let hints = card.facets.iter().map(|facet| (facet.clone(), hint_level)).collect();
+        let result = session.grade_with_hints(&mut gem_collection, &card, grades, hints)?;
+        if !result.failed.is_empty() {
+            println!("Still new: {}", result.failed.join(", "));
         }
-        //println!("{:?}", top_gem_sides);
-        if top_gem_facets.len() == 0 {
-            //Then I can simply call myself again, but with self.total_frequency_list
-            top_gem_facets = self.choose_max_n1_gem_facets_by_frequency_hashmap(gem_indices_for_n1, &self.total_frequency_list.clone(), _minimum_viable_hashmap_number);
+        storage.append_review(&result.event)?;
+        reviewed += 1;
+        //The journal already has the grade; progress and the checkpoint catch up every so often.
+        if autosave.review() {
+            gem_collection.save_to(&mut storage)?;
+            session.checkpoint(&gem_collection, reviewed, SystemTime::now()).save(SESSION_PATH)?;
         }
-        top_gem_facets
     }
+    if session.reached_review_limit() {
+        println!("That's today's reviews done; the rest are put off until tomorrow, most urgent first.");
+    } else if session.reached_new_facet_limit() {
+        println!("That's today's new facets used up; only reviews are left until tomorrow.");
+    }
+    player.stop();
+    gem_collection.save_to(&mut storage)?;
+    storage.flush()?;
+    storage.inner_mut().save_knowledge(KNOWLEDGE_PATH, &knowledge.snapshot())?;
+    SessionCheckpoint::clear(SESSION_PATH)?;
+    println!("Reviewed {} cards", reviewed);
+    Ok(())
 }
 
+//`cram <FACET LIST>`: drills the facets in a word list (see langwitch::cram) until each has been got right `streak` times in a row. Cards show the text side and reveal the rest, as plain `review` does. Grades go into the journal marked as cram; progress isn't touched.
+async fn cram(config: Config, list_path: &str, streak: usize) -> langwitch::Result<()> {
+    let mut storage = open_storage(&config)?;
+    let mut gem_collection = load_collection(&mut storage)?;
+    apply_config(&config, &mut gem_collection)?;
+    let mut session = CramSession::new(&gem_collection, &read_word_list(list_path)?, streak)?;
+    if !session.unmatched().is_empty() {
+        println!("No gem has {}", session.unmatched().join(", "));
+    }
+    let mut player = Player::new(config.audio);
+    let mut drilled = 0;
+    while let Some(card) = session.next_card(&gem_collection) {
+        let gem = match gem_collection.gem(card.gem) {
+            Some(gem) => gem,
+            None => break,
+        };
+        let text_side = gem_collection.side_roles.text_side();
+        println!();
+        println!("[cram] {}", gem.sides.get(&text_side).map(|side| ruby_to_plain(side)).unwrap_or_default());
+        let mut hint_level = 0;
+        if wait_for_reveal(&card.facets, &mut hint_level, None, &mut player, false, &mut |flag| flag_card(&mut gem_collection, card.gem, flag))? == Reveal::Quit {
+            break;
+        }
+        let mut side_numbers: Vec<&usize> = gem.sides.keys().filter(|side_number| **side_number != text_side).collect();
+        side_numbers.sort_unstable();
+        for side_number in side_numbers {
+            let role = gem_collection.side_roles.role(*side_number);
+            println!("  {}: {}", role.label(), side_to_plain(role, &gem.sides[side_number]).replace('\n', "\n    "));
+        }
+        let grades = match ask_grades(&card.facets)? {
+            Some(grades) => grades,
+            None => break,
+        };
+        let hints = card.facets.iter().map(|facet| (facet.clone(), hint_level)).collect();
+        let event = session.grade(&gem_collection, &card, grades, hints, SystemTime::now())?;
+        storage.append_review(&event)?;
+        drilled += 1;
+    }
+    storage.flush()?;
+    println!("Crammed {} cards, with {} facets still to get right; scheduling is as it was", drilled, session.remaining());
+    Ok(())
+}
+
+async fn run() -> langwitch::Result<()> {
+    let config = Config::load(CONFIG_PATH)?;
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let args: Vec<&str> = args.iter().map(|arg| arg.as_str()).collect();
+    //`--language <CODE>` up front picks which language profile applies; otherwise it's the config's own language.
+    let (config, args) = match args.as_slice() {
+        ["--language", language, rest @ ..] => (config.for_language(language), rest),
+        rest => (config.for_language(&config.language), rest),
+    };
+    //`--lenient` after that skips gems the deck can't read instead of refusing it.
+    let (config, args) = match args {
+        ["--lenient", rest @ ..] => (Config { lenient: true, ..config }, rest),
+        rest => (config, rest),
+    };
+    //Words listed in the exclusions file are filtered out on top of whatever the config (or its profile) excludes.
+    let mut config = config;
+    config.facet_filter.excluded.extend(FacetFilter::read_exclusions(EXCLUDED_FACETS_PATH)?);
+    match args {
+        [] => order(config, OutputFormat::Text).await,
+        ["--output", format] if OutputFormat::parse(format).is_some() => order(config, OutputFormat::parse(format).unwrap_or_default()).await,
+        ["feed"] => feed(config, false).await,
+        ["feed", "--once"] => feed(config, true).await,
+        ["mine-url", url] => mine_url(config, url, GEMS_PATH).await,
+        ["mine-url", url, "--deck", deck_path] => mine_url(config, url, deck_path).await,
+        ["import-known", word_list_path] => import_known(config, word_list_path).await,
+        ["export-progress", export_path] => export_progress(config, export_path).await,
+        ["export-curves", export_path] => export_curves(config, export_path).await,
+        #[cfg(feature = "encryption")]
+        ["encrypt"] => encrypt(config).await,
+        ["push"] => push(config).await,
+        ["pull"] => pull(config).await,
+        ["compact"] => compact(config, DEFAULT_KEEP).await,
+        ["compact", "--keep", keep] if keep.parse::<usize>().is_ok() => compact(config, keep.parse().unwrap_or_default()).await,
+        ["shift", "--days", days] if days.parse::<u64>().is_ok() => shift(config, days.parse().unwrap_or_default(), 0).await,
+        ["shift", "--days", days, "--spread", spread_days] if days.parse::<u64>().is_ok() && spread_days.parse::<usize>().is_ok() => shift(config, days.parse().unwrap_or_default(), spread_days.parse().unwrap_or_default()).await,
+        ["placement"] => placement(config).await,
+        ["analyze", text_path] => analyze(config, text_path).await,
+        ["coverage"] => coverage(config, None).await,
+        ["coverage", "--json", json_path] => coverage(config, Some(json_path)).await,
+        ["stats", "--forecast", days] if days.parse::<usize>().is_ok() => forecast(config, days.parse().unwrap_or_default()).await,
+        ["stats", "--retention"] => retention_stats(config, None).await,
+        ["stats", "--retention", "--json", json_path] => retention_stats(config, Some(json_path)).await,
+        ["stats", "--facets", flags @ ..] if StatsOptions::parse(flags).is_some() => facet_stats(config, StatsOptions::parse(flags).unwrap_or_default()).await,
+        ["leeches"] => leeches(config).await,
+        ["suspended"] => suspended(config).await,
+        ["flag", gem, reason] => flag(config, gem, reason, None).await,
+        ["flag", gem, reason, "--note", note] => flag(config, gem, reason, Some(note)).await,
+        ["unflag", gem] => unflag(config, gem).await,
+        ["list", "--flagged"] => list_flagged(config).await,
+        ["suspend", "--facet", facet] => suspend(config, SetAsideTarget::Facet(facet), false).await,
+        ["suspend", gem] => suspend(config, SetAsideTarget::Gem(gem), false).await,
+        ["bury", "--facet", facet] => suspend(config, SetAsideTarget::Facet(facet), true).await,
+        ["bury", gem] => suspend(config, SetAsideTarget::Gem(gem), true).await,
+        ["unsuspend", "--facet", facet] => unsuspend(config, SetAsideTarget::Facet(facet)).await,
+        ["unsuspend", gem] => unsuspend(config, SetAsideTarget::Gem(gem)).await,
+        ["leeches", "release", facet] => release_leech(config, facet, None).await,
+        ["leeches", "release", facet, "--note", note] => release_leech(config, facet, Some(note)).await,
+        ["rank", dir] => rank(config, dir).await,
+        ["path", targets_path] => goal_path(config, targets_path).await,
+        ["list-coverage", list_path] => list_coverage(config, list_path, None).await,
+        ["list-coverage", list_path, "--json", json_path] => list_coverage(config, list_path, Some(json_path)).await,
+        ["decks"] => decks(config).await,
+        ["review", flags @ ..] if ReviewMode::parse(flags).is_some() => review(config, ReviewMode::parse(flags).unwrap_or_default()).await,
+        ["cram", list_path] => cram(config, list_path, DEFAULT_STREAK).await,
+        ["cram", list_path, "--streak", streak] if streak.parse::<usize>().is_ok() => cram(config, list_path, streak.parse().unwrap_or_default()).await,
+        #[cfg(feature = "tui")]
+        ["tui"] => tui::run(ReviewSetup::load(config)?),
+        ["--stdio"] => rpc::run(config),
+        #[cfg(feature = "server")]
+        ["serve"] => server::run(config, "127.0.0.1:8080").await,
+        #[cfg(feature = "server")]
+        ["serve", "--listen", address] => server::run(config, address).await,
+        #[cfg(feature = "grpc")]
+        ["serve", "--grpc", grpc_address] => grpc::run(config, "127.0.0.1:8080", grpc_address).await,
+        #[cfg(feature = "grpc")]
+        ["serve", "--listen", address, "--grpc", grpc_address] => grpc::run(config, address, grpc_address).await,
+        ["merge", other_path] => merge(config, other_path, GEMS_PATH).await,
+        ["merge", other_path, "--into", deck_path] => merge(config, other_path, deck_path).await,
+        _ => {
+            eprintln!("{}", USAGE);
+            std::process::exit(2);
+        }
+    }
+}
 
 #[tokio::main]
 async fn main() {
-    let mut gem_collection = GemCollection::read_gems_from_file("src/gems.json").await.unwrap();
-    let now = Instant::now();
-    gem_collection.index_all_gems_by_number().await;
-    let elapsed = now.elapsed();
-    println!("Indexing all gems by number took {} microseconds", elapsed.as_micros());
-    let now = Instant::now();
-    gem_collection.display_all_gems_in_order_of_difficulty().await;
-    let elapsed = now.elapsed();
-    println!("Displaying all gems took {} microseconds", elapsed.as_micros());
+    if let Err(e) = run().await {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
 }
@@ -0,0 +1,60 @@
+//Markdown on the sides that explain things (translations, notes, grammar), so they can have bold, lists and links. The text side isn't Markdown: it's tokenized into facets and carries furigana, whose brackets Markdown would read as links.
+//Raw HTML in a side is shown as text rather than passed through, and script links are emptied, since decks come from other people.
+
+use pulldown_cmark::{html, Event, Parser, Tag, TagEnd};
+
+use crate::{
+    ruby::ruby_to_plain,
+    side::SideRole,
+};
+
+/// Whether sides with `role` are written in Markdown.
+pub fn is_markdown(role: SideRole) -> bool {
+    matches!(role, SideRole::Translation | SideRole::Transliteration | SideRole::Notes)
+}
+
+/// `text` rendered as HTML.
+pub fn markdown_to_html(text: &str) -> String {
+    let events = Parser::new(text).map(|event| match event {
+        Event::Html(raw) | Event::InlineHtml(raw) => Event::Text(raw),
+        Event::Start(Tag::Link { link_type, dest_url, title, id }) if dest_url.trim_start().to_lowercase().starts_with("javascript:") => {
+            Event::Start(Tag::Link { link_type, dest_url: "#".into(), title, id })
+        }
+        event => event,
+    });
+    let mut rendered = String::with_capacity(text.len() * 3 / 2);
+    html::push_html(&mut rendered, events);
+    rendered
+}
+
+/// `text` with the Markdown taken out, for plain terminals: emphasis is dropped, list items start with a dash and links keep their address in brackets.
+pub fn markdown_to_plain(text: &str) -> String {
+    let mut plain = String::with_capacity(text.len());
+    let mut links: Vec<String> = Vec::new();
+    for event in Parser::new(text) {
+        match event {
+            Event::Text(text) | Event::Code(text) | Event::Html(text) | Event::InlineHtml(text) => plain.push_str(&text),
+            Event::SoftBreak => plain.push(' '),
+            Event::HardBreak | Event::Rule => plain.push('\n'),
+            Event::Start(Tag::Item) => plain.push_str("- "),
+            Event::Start(Tag::Link { dest_url, .. }) => links.push(dest_url.to_string()),
+            Event::End(TagEnd::Link) => {
+                if let Some(url) = links.pop() {
+                    plain.push_str(&format!(" ({})", url));
+                }
+            }
+            Event::End(TagEnd::Paragraph | TagEnd::Heading(_) | TagEnd::Item | TagEnd::CodeBlock | TagEnd::BlockQuote(_)) => plain.push('\n'),
+            _ => {}
+        }
+    }
+    plain.trim_end().to_string()
+}
+
+/// A side with `role` as plain text for a terminal: Markdown sides as [`markdown_to_plain`] gives them, and the rest with their furigana in brackets.
+pub fn side_to_plain(role: SideRole, side: &str) -> String {
+    if is_markdown(role) {
+        markdown_to_plain(side)
+    } else {
+        ruby_to_plain(side)
+    }
+}
@@ -0,0 +1,99 @@
+//Merging one collection into another, so material from several importers (an Anki deck, a feed, a mined novel) can live in one curriculum.
+//Two gems are the same gem if they share a key, or if their first sides are the same sentence. When they are, the gem already in the collection stays where it is and picks up the other's facets, any side it doesn't have yet and any flags; a side both have but disagree on keeps the existing text and is counted as a conflict.
+//A gem without an explicit id that picks up a side gets its old key as its id, so its journal history and anything stored against it still find it.
+
+use std::collections::HashMap;
+
+use crate::{
+    collection::GemCollection,
+    gem::GemId,
+};
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct MergeReport {
+    /// Gems that weren't in the collection yet, and were added.
+    pub added: usize,
+    /// Gems that were already there and picked up facets, sides or flags from the other copy.
+    pub merged: usize,
+    /// Gems that were already there exactly as they were, and were skipped.
+    pub duplicates: usize,
+    /// Sides both copies had with different text. The existing text was kept.
+    pub conflicting_sides: usize,
+}
+
+//Sentences that only differ in surrounding whitespace are still the same sentence.
+fn first_side(sides: &HashMap<usize, String>) -> Option<&str> {
+    sides.get(&0).map(|side| side.trim()).filter(|side| !side.is_empty())
+}
+
+impl GemCollection {
+    /// Adds every gem of `other` to this collection, merging the ones it already has (see the module docs), and takes on what `other` knows: known facets are unioned, and for scheduling data the most recently seen state wins.
+    /// The indices are rebuilt at the end. Facets are read off `other`'s gems as they are, so merge collections before indexing them or facets `other` already knew will be missing from its gems.
+    pub fn merge(&mut self, other: GemCollection) -> MergeReport {
+        let mut report = MergeReport::default();
+        let mut gem_ids_by_sentence: HashMap<String, GemId> = HashMap::new();
+        for gem_id in self.gem_ids() {
+            if let Some(sentence) = first_side(&self.gems[gem_id.0].sides) {
+                gem_ids_by_sentence.entry(sentence.to_string()).or_insert(gem_id);
+            }
+        }
+        for other_gem_id in other.gem_ids() {
+            let gem = match other.gem(other_gem_id) {
+                Some(gem) => gem,
+                None => continue,
+            };
+            let existing = self.gem_id(&gem.key()).or_else(|| first_side(&gem.sides).and_then(|sentence| gem_ids_by_sentence.get(sentence).copied()));
+            let gem_id = match existing {
+                Some(gem_id) => gem_id,
+                None => {
+                    let sentence = first_side(&gem.sides).map(|sentence| sentence.to_string());
+                    let gem_id = self.push_gem(gem);
+                    if let Some(sentence) = sentence {
+                        gem_ids_by_sentence.entry(sentence).or_insert(gem_id);
+                    }
+                    report.added += 1;
+                    continue;
+                }
+            };
+            let facets = self.interner.intern_all(gem.unknown_facets.iter());
+            let existing_gem = &mut self.gems[gem_id.0];
+            let facets_before = existing_gem.unknown_facets.len();
+            existing_gem.unknown_facets.extend(facets);
+            let mut changed = existing_gem.unknown_facets.len() > facets_before;
+            for (side_number, side) in gem.sides {
+                match existing_gem.sides.get(&side_number) {
+                    Some(existing_side) if existing_side.trim() == side.trim() => {}
+                    Some(_) => report.conflicting_sides += 1,
+                    None => {
+                        //A gem keyed by its sides keeps the key it had, as in update_gem.
+                        if existing_gem.id.is_none() {
+                            existing_gem.id = Some(existing_gem.key.0.clone());
+                        }
+                        existing_gem.sides.insert(side_number, side);
+                        changed = true;
+                    }
+                }
+            }
+            for flag in gem.flags {
+                if !existing_gem.flags.contains(&flag) {
+                    existing_gem.flags.push(flag);
+                    changed = true;
+                }
+            }
+            if changed {
+                report.merged += 1;
+            } else {
+                report.duplicates += 1;
+            }
+        }
+        self.insert_known_facets(other.known_facet_names().iter());
+        for (facet_name, state) in other.facet_states {
+            let newer = self.facet_states.get(&facet_name).is_none_or(|existing| state.last_seen_date > existing.last_seen_date);
+            if newer {
+                self.facet_states.insert(facet_name, state);
+            }
+        }
+        self.index_all_gems_by_number();
+        report
+    }
+}
@@ -0,0 +1,140 @@
+//Sentence mining: turning a plain-text file (a book, an article, a transcript) into gems. The text is split into sentences, each sentence is tokenized into facets, and sentences that would make poor flashcards are thrown away.
+
+use std::{collections::HashSet, fs, path::Path};
+
+use serde::{Serialize, Deserialize};
+
+use crate::{
+    collection::GemCollection,
+    error::Result,
+    gem::Gem,
+    side::{SideRole, SideRoles},
+    tokenize::{Tokenizer, TokenizerKind},
+};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct MineOptions {
+    /// Sentences with fewer words than this are dropped. Fragments like "Yes." don't teach much.
+    pub min_words: usize,
+    /// Sentences with more words than this are dropped, since nobody wants to review a paragraph.
+    pub max_words: usize,
+    /// Sentences where less than this share of the characters are letters (tables, numbers, markup) are dropped.
+    pub min_letter_ratio: f64,
+    /// Only keep the first copy of a sentence that appears more than once.
+    pub skip_repeats: bool,
+    /// How sentences are split into facets.
+    pub tokenizer: TokenizerKind,
+}
+
+impl Default for MineOptions {
+    fn default() -> Self {
+        MineOptions {
+            min_words: 3,
+            max_words: 30,
+            min_letter_ratio: 0.6,
+            skip_repeats: true,
+            tokenizer: TokenizerKind::default(),
+        }
+    }
+}
+
+//Closing quotes and brackets straight after a terminator belong to the sentence that just ended.
+pub(crate) fn is_closing(c: char) -> bool {
+    matches!(c, '"' | '\'' | '”' | '’' | '»' | ')' | ']' | '」' | '』')
+}
+
+pub(crate) fn is_terminator(c: char) -> bool {
+    matches!(c, '.' | '!' | '?' | '…' | '。' | '！' | '？')
+}
+
+/// Splits `text` into sentences on terminal punctuation and blank lines. Single line breaks are treated as spaces, since most text files wrap lines mid-sentence.
+pub fn split_sentences(text: &str) -> Vec<String> {
+    let mut sentences = Vec::new();
+    for paragraph in text.split("\n\n") {
+        let chars: Vec<char> = paragraph.chars().collect();
+        let mut sentence = String::new();
+        let mut i = 0;
+        while i < chars.len() {
+            let c = chars[i];
+            sentence.push(if c.is_whitespace() { ' ' } else { c });
+            if is_terminator(c) {
+                while i + 1 < chars.len() && (is_terminator(chars[i + 1]) || is_closing(chars[i + 1])) {
+                    i += 1;
+                    sentence.push(chars[i]);
+                }
+                //"3.5" doesn't end a sentence, and neither does "e.g. a lot": a terminator only counts when followed by a space (or nothing), and not when the next word starts lowercase.
+                let next_word = chars[i + 1..].iter().find(|next| !next.is_whitespace());
+                let ends = chars.get(i + 1).is_none_or(|next| next.is_whitespace())
+                    && !next_word.is_some_and(|next| next.is_lowercase());
+                if ends {
+                    sentences.push(std::mem::take(&mut sentence));
+                }
+            }
+            i += 1;
+        }
+        sentences.push(sentence);
+    }
+    sentences
+        .into_iter()
+        .map(|sentence| sentence.split_whitespace().collect::<Vec<&str>>().join(" "))
+        .filter(|sentence| !sentence.is_empty())
+        .collect()
+}
+
+fn is_junk(sentence: &str, facets: &HashSet<String>, options: &MineOptions) -> bool {
+    let words = sentence.split_whitespace().count();
+    if words < options.min_words || words > options.max_words || facets.is_empty() {
+        return true;
+    }
+    let visible = sentence.chars().filter(|c| !c.is_whitespace()).count();
+    let letters = sentence.chars().filter(|c| c.is_alphabetic()).count();
+    (letters as f64) < (visible as f64) * options.min_letter_ratio
+}
+
+/// Mines gems out of `text`. Each kept sentence becomes a one-sided gem whose facets are its words.
+pub fn mine_text(text: &str, options: &MineOptions) -> Result<Vec<Gem>> {
+    Ok(mine_text_with(text, options, options.tokenizer.build()?.as_ref()))
+}
+
+/// Same as [`mine_text`], with a tokenizer that's already been built (`options.tokenizer` is ignored).
+pub fn mine_text_with(text: &str, options: &MineOptions, tokenizer: &dyn Tokenizer) -> Vec<Gem> {
+    let mut seen = HashSet::new();
+    let mut gems = Vec::new();
+    for sentence in split_sentences(text) {
+        let unknown_facets = tokenizer.tokenize(&sentence);
+        if is_junk(&sentence, &unknown_facets, options) {
+            continue;
+        }
+        if options.skip_repeats && !seen.insert(sentence.clone()) {
+            continue;
+        }
+        gems.push(Gem {
+            id: None,
+            sides: [(0, sentence)].into_iter().collect(),
+            unknown_facets,
+            flags: Vec::new(),
+        });
+    }
+    gems
+}
+
+/// How mined gems are laid out: the sentence is side 0, and anything an importer adds after it (a link, a chapter title) is notes.
+pub fn mined_side_roles() -> SideRoles {
+    SideRoles::new([(0, SideRole::Text)])
+}
+
+/// Reads a plain-text file and mines it into a collection.
+pub fn mine_file<P: AsRef<Path>>(path: P, options: &MineOptions) -> Result<GemCollection> {
+    let text = fs::read_to_string(path)?;
+    let mut gem_collection = GemCollection::from_gems(mine_text(&text, options)?);
+    gem_collection.side_roles = mined_side_roles();
+    Ok(gem_collection)
+}
+
+impl GemCollection {
+    /// Mines a plain-text file into gems with the default options.
+    pub fn read_gems_from_text_file<P: AsRef<Path>>(path: P) -> Result<GemCollection> {
+        mine_file(path, &MineOptions::default())
+    }
+}
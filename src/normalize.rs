@@ -0,0 +1,229 @@
+//Normalizers map the different surface forms of a facet onto one name, so "ran", "runs" and "running" all count as the facet "run" instead of three separate unknowns.
+//They're applied to every facet when the collection is loaded (see GemCollection::set_normalization) and to the facet names in every graded review, so progress always lands on the normalized facet.
+//Before any of that, facets go through the collection's TextNormalization (case folding, Unicode normalization forms, diacritics), which is cheap and can't fail, so it's also applied when facets are marked known by name.
+
+use std::{
+    collections::HashMap,
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+use serde::{Serialize, Deserialize};
+use unicode_normalization::{char::is_combining_mark, UnicodeNormalization};
+
+use crate::{
+    collection::GemCollection,
+    error::{LangwitchError, Result},
+    facet::Facet,
+    interner::FacetId,
+};
+
+/// Something that maps facet names onto their normalized form. Names it doesn't know should come back unchanged.
+pub trait Normalizer {
+    /// Normalizes every name in `facets`, returning them in the same order. Done in bulk so a normalizer that's expensive to start (like an external command) only starts once.
+    fn normalize_all(&self, facets: &[String]) -> Result<Vec<String>>;
+
+    fn normalize(&self, facet: &str) -> Result<String> {
+        let mut normalized = self.normalize_all(&[facet.to_string()])?;
+        Ok(normalized.pop().unwrap_or_else(|| facet.to_string()))
+    }
+}
+
+/// Which Unicode normalization form facets are put into. NFC only merges different encodings of the same text (like a precomposed "ä" and "a" plus a combining diaeresis); NFKC also folds compatibility characters like full-width letters and ligatures.
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum UnicodeForm {
+    #[default]
+    None,
+    Nfc,
+    Nfkc,
+}
+
+/// Character-level facet normalization, so "Häuser" and "häuser" don't count as two different unknowns. Everything is off by default.
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct TextNormalization {
+    pub lowercase: bool,
+    pub unicode_form: UnicodeForm,
+    /// Drops accents and other combining marks, so "café" becomes "cafe". Handy for learners who can't type them, but it does merge words some languages keep apart.
+    pub strip_diacritics: bool,
+}
+
+impl TextNormalization {
+    pub fn apply(&self, facet: &str) -> String {
+        let mut facet = match self.unicode_form {
+            UnicodeForm::None => facet.to_string(),
+            UnicodeForm::Nfc => facet.nfc().collect(),
+            UnicodeForm::Nfkc => facet.nfkc().collect(),
+        };
+        if self.lowercase {
+            facet = facet.to_lowercase();
+        }
+        if self.strip_diacritics {
+            facet = facet.nfd().filter(|c| !is_combining_mark(*c)).nfc().collect();
+        }
+        facet
+    }
+}
+
+impl Normalizer for TextNormalization {
+    fn normalize_all(&self, facets: &[String]) -> Result<Vec<String>> {
+        Ok(facets.iter().map(|facet| self.apply(facet)).collect())
+    }
+}
+
+/// A user-supplied table of inflected forms and their lemmas.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct LemmaTable {
+    pub lemmas: HashMap<String, String>,
+}
+
+impl LemmaTable {
+    /// Reads a table with one `form<TAB>lemma` pair per line (any whitespace works as the separator). Blank lines and lines starting with `#` are skipped.
+    pub fn read_from_file<P: AsRef<Path>>(path: P) -> Result<LemmaTable> {
+        let contents = fs::read_to_string(path)?;
+        let mut lemmas = HashMap::new();
+        for (number, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut columns = line.split_whitespace();
+            match (columns.next(), columns.next()) {
+                (Some(form), Some(lemma)) => {
+                    lemmas.insert(form.to_string(), lemma.to_string());
+                }
+                _ => return Err(LangwitchError::Normalizer(format!("line {} of the lemma table has no lemma", number + 1))),
+            }
+        }
+        Ok(LemmaTable { lemmas })
+    }
+}
+
+impl Normalizer for LemmaTable {
+    fn normalize_all(&self, facets: &[String]) -> Result<Vec<String>> {
+        Ok(facets
+            .iter()
+            .map(|facet| self.lemmas.get(facet).unwrap_or(facet).clone())
+            .collect())
+    }
+}
+
+/// Runs an external lemmatizer. It's given the facets on stdin, one per line, and has to print exactly one normalized facet per line back in the same order.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct ExternalCommand {
+    pub program: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+}
+
+impl Normalizer for ExternalCommand {
+    fn normalize_all(&self, facets: &[String]) -> Result<Vec<String>> {
+        if facets.is_empty() {
+            return Ok(Vec::new());
+        }
+        let mut child = Command::new(&self.program)
+            .args(&self.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+        //Written from another thread, since a command that answers line by line would otherwise fill its stdout pipe while we're still blocked writing its stdin.
+        let mut stdin = child.stdin.take().ok_or_else(|| LangwitchError::Normalizer("couldn't open the command's stdin".to_string()))?;
+        let input = facets.join("\n") + "\n";
+        let writer = std::thread::spawn(move || stdin.write_all(input.as_bytes()));
+        let output = child.wait_with_output()?;
+        writer.join().map_err(|_| LangwitchError::Normalizer("writing to the command panicked".to_string()))??;
+        if !output.status.success() {
+            return Err(LangwitchError::Normalizer(format!("{} exited with {}", self.program, output.status)));
+        }
+        let normalized: Vec<String> = String::from_utf8_lossy(&output.stdout).lines().map(|line| line.trim().to_string()).collect();
+        if normalized.len() != facets.len() {
+            return Err(LangwitchError::Normalizer(format!("{} printed {} lines for {} facets", self.program, normalized.len(), facets.len())));
+        }
+        Ok(normalized)
+    }
+}
+
+/// Which normalizer the config asks for: "none" (the default), {"lemma_table": "path/to/lemmas.tsv"} or {"command": {"program": "...", "args": [...]}}.
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum NormalizerKind {
+    #[default]
+    None,
+    LemmaTable(PathBuf),
+    Command(ExternalCommand),
+}
+
+impl NormalizerKind {
+    /// Loads the normalizer, reading its lemma table if it has one.
+    pub fn build(&self) -> Result<Normalization> {
+        match self {
+            NormalizerKind::None => Ok(Normalization::Identity),
+            NormalizerKind::LemmaTable(path) => Ok(Normalization::LemmaTable(LemmaTable::read_from_file(path)?)),
+            NormalizerKind::Command(command) => Ok(Normalization::Command(command.clone())),
+        }
+    }
+}
+
+/// A loaded normalizer, ready to go. This is what a GemCollection holds on to.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub enum Normalization {
+    /// Leaves every facet alone.
+    #[default]
+    Identity,
+    LemmaTable(LemmaTable),
+    Command(ExternalCommand),
+}
+
+impl Normalizer for Normalization {
+    fn normalize_all(&self, facets: &[String]) -> Result<Vec<String>> {
+        match self {
+            Normalization::Identity => Ok(facets.to_vec()),
+            Normalization::LemmaTable(table) => table.normalize_all(facets),
+            Normalization::Command(command) => command.normalize_all(facets),
+        }
+    }
+}
+
+impl GemCollection {
+    /// Runs `facets` through `facet_normalization` and then `normalization`, the same way every facet in the collection has been.
+    pub fn normalize_facet_names(&self, facets: &[String]) -> Result<Vec<String>> {
+        let facets = self.facet_normalization.normalize_all(facets)?;
+        self.normalization.normalize_all(&facets)
+    }
+
+    /// Sets the normalizer reviews go through from now on, and runs every facet already in the collection through `facet_normalization` and then it: gem facets, `known_facets` and `facet_states`.
+    /// Facets that normalize to the same name merge (a facet's scheduling data comes from whichever form was seen most recently). The indices are cleared, so reindex afterwards.
+    pub fn set_normalization(&mut self, normalization: Normalization) -> Result<()> {
+        let mut names: Vec<String> = (0..self.interner.len()).map(|id| self.interner.name(FacetId(id as u32)).to_string()).collect();
+        let interned = names.len();
+        names.extend(self.facet_states.keys().filter(|name| self.interner.get(name).is_none()).cloned());
+        let normalized = normalization.normalize_all(&self.facet_normalization.normalize_all(&names)?)?;
+
+        let remap: Vec<FacetId> = normalized[..interned].iter().map(|name| self.interner.intern(name)).collect();
+        for gem in self.gems.iter_mut() {
+            gem.unknown_facets = gem.unknown_facets.iter().map(|facet| remap[facet.0 as usize]).collect();
+        }
+        self.known_facets = self.known_facets.iter().map(|facet| remap[facet.0 as usize]).collect();
+
+        let lemmas: HashMap<&String, &String> = names.iter().zip(normalized.iter()).collect();
+        let mut facet_states: HashMap<String, Facet> = HashMap::new();
+        for (name, mut state) in std::mem::take(&mut self.facet_states) {
+            let lemma = lemmas.get(&name).map_or(name.clone(), |lemma| lemma.to_string());
+            state.name = lemma.clone();
+            let newer = facet_states.get(&lemma).is_none_or(|existing| state.last_seen_date > existing.last_seen_date);
+            if newer {
+                facet_states.insert(lemma, state);
+            }
+        }
+        self.facet_states = facet_states;
+
+        self.gems_by_size_index.clear();
+        self.gems_by_facet_index.clear();
+        self.total_frequency_list.clear();
+        self.normalization = normalization;
+        Ok(())
+    }
+}
@@ -0,0 +1,195 @@
+//An exact ordering for small decks, to see how far the greedy ordering is from the best possible one.
+//Learning every facet is unavoidable, so what an ordering actually changes is how long each gem waits: a gem's cost is the number of facets that have been introduced by the time it's fully known, and an ordering's cost is the sum over every gem. The greedy ordering makes locally good choices; this searches every choice (each step learns whatever a still-locked gem doesn't know yet) with branch and bound, and stops early at a node limit so a deck that's too big still gives an answer, just not a proven one.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    collection::GemCollection,
+    error::Result,
+    gem::GemId,
+    interner::FacetId,
+};
+
+#[derive(Debug, Clone)]
+pub struct ExactOptions {
+    /// How many search nodes to expand before giving up on proving optimality and returning the best order found so far.
+    pub node_limit: usize,
+}
+
+impl Default for ExactOptions {
+    fn default() -> Self {
+        ExactOptions { node_limit: 1_000_000 }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct ExactOrder {
+    /// Gem ids in the order they become fully known, like [`GemCollection::difficulty_order`].
+    pub order: Vec<GemId>,
+    /// The cost of `order` (see [`GemCollection::unlock_cost`]).
+    pub cost: usize,
+    /// The cost of the greedy ordering on the same deck, for comparison.
+    pub greedy_cost: usize,
+    /// Whether the search finished, so `cost` really is the minimum. False if it hit the node limit.
+    pub proven_optimal: bool,
+    pub nodes: usize,
+}
+
+//Facet sets as bitsets over facet numbers, so the search can union and count them quickly.
+type Bits = Vec<u64>;
+
+fn count_unknown(facets: &Bits, known: &Bits) -> usize {
+    facets.iter().zip(known.iter()).map(|(facets, known)| (facets & !known).count_ones() as usize).sum()
+}
+
+struct Search {
+    gem_ids: Vec<GemId>,
+    gem_facets: Vec<Bits>,
+    node_limit: usize,
+    nodes: usize,
+    best_cost: usize,
+    best_steps: Vec<Vec<usize>>,
+    //The cheapest way found so far of getting to each set of known facets. What's left to pay from a state doesn't depend on how it was reached, so arriving again at the same cost or more can't help.
+    seen: HashMap<Bits, usize>,
+    exhausted: bool,
+}
+
+impl Search {
+    fn explore(&mut self, known: &Bits, locked: &[usize], known_count: usize, cost: usize, steps: &mut Vec<Vec<usize>>) {
+        if locked.is_empty() {
+            if cost < self.best_cost {
+                self.best_cost = cost;
+                self.best_steps = steps.clone();
+            }
+            return;
+        }
+        if self.nodes >= self.node_limit {
+            self.exhausted = true;
+            return;
+        }
+        self.nodes += 1;
+        //Every locked gem has to wait at least until its own missing facets are in.
+        let bound: usize = cost + locked.iter().map(|gem| known_count + count_unknown(&self.gem_facets[*gem], known)).sum::<usize>();
+        if bound >= self.best_cost {
+            return;
+        }
+        match self.seen.get(known) {
+            Some(seen_cost) if *seen_cost <= cost => return,
+            _ => {
+                self.seen.insert(known.clone(), cost);
+            }
+        }
+        //Gems missing the same facets lead to the same state, so each distinct move is only tried once, cheapest first.
+        let mut moves: Vec<(usize, Bits)> = Vec::new();
+        let mut tried: HashSet<Bits> = HashSet::new();
+        for gem in locked.iter() {
+            let missing: Bits = self.gem_facets[*gem].iter().zip(known.iter()).map(|(facets, known)| facets & !known).collect();
+            if tried.insert(missing.clone()) {
+                moves.push((count_unknown(&missing, &vec![0; missing.len()]), missing));
+            }
+        }
+        moves.sort_by_key(|(size, _)| *size);
+        for (size, missing) in moves {
+            let next_known: Bits = known.iter().zip(missing.iter()).map(|(known, missing)| known | missing).collect();
+            let next_count = known_count + size;
+            let (unlocked, still_locked): (Vec<usize>, Vec<usize>) = locked
+                .iter()
+                .partition(|gem| count_unknown(&self.gem_facets[**gem], &next_known) == 0);
+            let next_cost = cost + unlocked.len() * next_count;
+            steps.push(unlocked);
+            self.explore(&next_known, &still_locked, next_count, next_cost, steps);
+            steps.pop();
+        }
+    }
+}
+
+impl GemCollection {
+    /// What an ordering costs: go through `order` learning each gem's unknown facets, and add up how many facets have been introduced by the time each gem is fully known. Lower is better.
+    /// Gems that are unknown to the collection are skipped; already-known facets count as free.
+    pub fn unlock_cost(&self, order: &[GemId]) -> usize {
+        let mut known: HashSet<FacetId> = self.known_facets.clone();
+        let mut introduced = 0;
+        let mut cost = 0;
+        for gem in order.iter().filter_map(|gem_id| self.get(*gem_id)) {
+            for facet in gem.unknown_facets.iter() {
+                if known.insert(*facet) {
+                    introduced += 1;
+                }
+            }
+            cost += introduced;
+        }
+        cost
+    }
+
+    /// Searches for the ordering with the lowest [`GemCollection::unlock_cost`]. The search is exponential: it can usually prove the optimum on decks of a few dozen gems, and on bigger ones (up to a couple of thousand) it returns the best order it found within `options.node_limit`, which is never worse than greedy.
+    /// Doesn't change the collection.
+    pub fn exact_order(&self, options: &ExactOptions) -> Result<ExactOrder> {
+        let mut greedy = self.clone();
+        let greedy_order = greedy.difficulty_order()?;
+        let greedy_cost = self.unlock_cost(&greedy_order);
+
+        let mut facet_numbers: HashMap<FacetId, usize> = HashMap::new();
+        let mut free_gems = Vec::new();
+        let mut gem_ids = Vec::new();
+        let mut gem_facet_numbers: Vec<Vec<usize>> = Vec::new();
+        for gem_id in self.gem_ids() {
+            let facets: Vec<usize> = self.gems[gem_id.0].unknown_facets
+                .iter()
+                .filter(|facet| !self.known_facets.contains(*facet))
+                .map(|facet| {
+                    let next = facet_numbers.len();
+                    *facet_numbers.entry(*facet).or_insert(next)
+                })
+                .collect();
+            if facets.is_empty() {
+                free_gems.push(gem_id);
+            } else {
+                gem_ids.push(gem_id);
+                gem_facet_numbers.push(facets);
+            }
+        }
+        let words = facet_numbers.len().div_ceil(64).max(1);
+        let gem_facets: Vec<Bits> = gem_facet_numbers
+            .iter()
+            .map(|facets| {
+                let mut bits = vec![0u64; words];
+                for facet in facets {
+                    bits[facet / 64] |= 1 << (facet % 64);
+                }
+                bits
+            })
+            .collect();
+
+        let mut search = Search {
+            gem_ids,
+            gem_facets,
+            node_limit: options.node_limit,
+            nodes: 0,
+            //Anything worse than greedy isn't interesting, so greedy is the order to beat.
+            best_cost: greedy_cost + 1,
+            best_steps: Vec::new(),
+            seen: HashMap::new(),
+            exhausted: false,
+        };
+        let locked: Vec<usize> = (0..search.gem_ids.len()).collect();
+        search.explore(&vec![0; words], &locked, 0, 0, &mut Vec::new());
+
+        let (order, cost) = if search.best_cost <= greedy_cost {
+            let mut order = free_gems;
+            for mut step in search.best_steps.into_iter() {
+                step.sort_unstable();
+                order.extend(step.into_iter().map(|gem| search.gem_ids[gem]));
+            }
+            (order, search.best_cost)
+        } else {
+            (greedy_order, greedy_cost)
+        };
+        Ok(ExactOrder {
+            order,
+            cost,
+            greedy_cost,
+            proven_optimal: !search.exhausted,
+            nodes: search.nodes,
+        })
+    }
+}
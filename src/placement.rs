@@ -0,0 +1,160 @@
+//A placement test, so a learner who already knows thousands of words doesn't have to mark them one at a time. Facets are ranked by how many gems they appear in, and a binary search over those ranks looks for the point where the learner stops knowing words: at each step a few facets are sampled from around the current rank and the learner says whether they know each one. Once the search narrows down, every facet ranked above the estimated frontier is marked known in one go.
+//The test only asks questions and keeps score; showing the questions is up to whoever drives it, which keeps it usable from a terminal, a GUI or a server alike.
+
+use std::collections::{HashMap, HashSet};
+
+use rand::{rngs::StdRng, Rng};
+
+use crate::{
+    collection::GemCollection,
+    gem::GemId,
+    interner::FacetId,
+};
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct PlacementOptions {
+    /// How many facets are asked about at each rank before deciding which way to go.
+    pub samples_per_step: usize,
+    /// The share of those the learner has to know for the rank to count as known.
+    pub known_ratio: f64,
+    /// The search stops once the frontier is pinned down to this many ranks.
+    pub precision: usize,
+    /// The most questions the test asks, however far along the search is.
+    pub max_questions: usize,
+}
+
+impl Default for PlacementOptions {
+    fn default() -> Self {
+        PlacementOptions {
+            samples_per_step: 4,
+            known_ratio: 0.75,
+            precision: 50,
+            max_questions: 60,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Placement {
+    options: PlacementOptions,
+    //Unknown facets, most frequent first.
+    ranked: Vec<FacetId>,
+    //The frontier lies somewhere in low..high: everything below `low` is known, nothing from `high` on is.
+    low: usize,
+    high: usize,
+    //The facets sampled for the current rank that haven't been asked about yet, and what's been said about the current rank so far.
+    pending: Vec<FacetId>,
+    step_known: usize,
+    step_asked: usize,
+    answers: HashMap<FacetId, bool>,
+    rng: StdRng,
+}
+
+impl Placement {
+    /// Starts a placement test over the facets of `gem_collection` that aren't known yet. Sampling uses the collection's seeded rng, so the same seed asks the same questions.
+    pub fn new(gem_collection: &GemCollection, options: PlacementOptions) -> Placement {
+        let mut frequencies: HashMap<FacetId, usize> = HashMap::new();
+        for gem in gem_collection.gems.iter() {
+            for facet in gem.unknown_facets.iter().filter(|facet| !gem_collection.known_facets.contains(facet)) {
+                *frequencies.entry(*facet).or_insert(0) += 1;
+            }
+        }
+        let mut ranked: Vec<(FacetId, usize)> = frequencies.into_iter().collect();
+        //Most frequent first, with ties in interning order so the ranking doesn't depend on hash order.
+        ranked.sort_by(|(facet_a, frequency_a), (facet_b, frequency_b)| frequency_b.cmp(frequency_a).then(facet_a.cmp(facet_b)));
+        let ranked: Vec<FacetId> = ranked.into_iter().map(|(facet, _)| facet).collect();
+        let mut placement = Placement {
+            options,
+            high: ranked.len(),
+            ranked,
+            low: 0,
+            pending: Vec::new(),
+            step_known: 0,
+            step_asked: 0,
+            answers: HashMap::new(),
+            rng: gem_collection.rng.clone(),
+        };
+        placement.sample_step();
+        placement
+    }
+
+    fn midpoint(&self) -> usize {
+        self.low + (self.high - self.low) / 2
+    }
+
+    fn is_done(&self) -> bool {
+        self.high - self.low <= self.options.precision || self.answers.len() >= self.options.max_questions
+    }
+
+    //Picks the facets to ask about at the current midpoint, from a window around it so one odd word doesn't decide the step.
+    fn sample_step(&mut self) {
+        self.pending.clear();
+        self.step_known = 0;
+        self.step_asked = 0;
+        if self.is_done() {
+            return;
+        }
+        let window = ((self.high - self.low) / 8).max(1);
+        let start = self.midpoint().saturating_sub(window).max(self.low);
+        let end = (self.midpoint() + window).min(self.high);
+        let mut candidates: Vec<FacetId> = self.ranked[start..end].iter().filter(|facet| !self.answers.contains_key(facet)).copied().collect();
+        for _ in 0..self.options.samples_per_step.min(candidates.len()) {
+            let picked = self.rng.gen_range(0..candidates.len());
+            self.pending.push(candidates.swap_remove(picked));
+        }
+        //Every word near the midpoint has been asked about already; move on with what's known.
+        if self.pending.is_empty() {
+            self.high = self.midpoint();
+            self.sample_step();
+        }
+    }
+
+    /// The facet to ask about next, or None once the test is over.
+    pub fn next_question<'a>(&self, gem_collection: &'a GemCollection) -> Option<&'a str> {
+        self.pending.last().map(|facet| gem_collection.facet_name(*facet))
+    }
+
+    /// Records whether the learner knows the facet [`Placement::next_question`] last returned.
+    pub fn answer(&mut self, known: bool) {
+        let facet = match self.pending.pop() {
+            Some(facet) => facet,
+            None => return,
+        };
+        self.answers.insert(facet, known);
+        self.step_asked += 1;
+        if known {
+            self.step_known += 1;
+        }
+        if self.pending.is_empty() {
+            let midpoint = self.midpoint();
+            if self.step_known as f64 >= self.step_asked as f64 * self.options.known_ratio {
+                self.low = midpoint.max(self.low + 1);
+            } else {
+                self.high = midpoint;
+            }
+            self.sample_step();
+        }
+    }
+
+    pub fn questions_asked(&self) -> usize {
+        self.answers.len()
+    }
+
+    /// How many of the most frequent facets the learner is estimated to know (the middle of what's left of the search).
+    pub fn estimated_known(&self) -> usize {
+        self.midpoint()
+    }
+
+    /// Marks the estimated-known facets known, along with anything the learner said they knew, and leaves out anything they said they didn't. Returns the ids of the gems left with nothing to learn.
+    pub fn finish(self, gem_collection: &mut GemCollection) -> Vec<GemId> {
+        let mut known: HashSet<FacetId> = self.ranked[..self.estimated_known()].iter().copied().collect();
+        for (facet, knows) in self.answers.iter() {
+            if *knows {
+                known.insert(*facet);
+            } else {
+                known.remove(facet);
+            }
+        }
+        gem_collection.mark_facet_ids_known(&known)
+    }
+}
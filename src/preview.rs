@@ -0,0 +1,60 @@
+//The ordering preview that `langwitch` runs with no subcommand: the first few hundred gems of the curriculum and what each one teaches. As text it's for reading; as NDJSON (`--output ndjson`) each gem is one OrderingStep of JSON on its own line, for scripts to consume.
+
+use std::io::Write;
+
+use crate::{
+    collection::GemCollection,
+    error::Result,
+};
+
+/// How many gems the preview shows unless the deck runs out first.
+pub const DEFAULT_PREVIEW_STEPS: usize = 200;
+
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum OutputFormat {
+    /// A line per step for people.
+    #[default]
+    Text,
+    /// A JSON object per step, one per line.
+    Ndjson,
+}
+
+impl OutputFormat {
+    /// The format called `name` on the command line, if there is one.
+    pub fn parse(name: &str) -> Option<OutputFormat> {
+        match name {
+            "text" => Some(OutputFormat::Text),
+            "ndjson" => Some(OutputFormat::Ndjson),
+            _ => None,
+        }
+    }
+}
+
+impl GemCollection {
+    /// Indexes the collection and writes the first `steps` gems of its curriculum (see [`GemCollection::ordering`]) to `writer` in `format`, starting from whatever is already known. Returns how many were written.
+    pub fn write_order_preview<W: Write>(&mut self, mut writer: W, format: OutputFormat, steps: usize) -> Result<usize> {
+        let mut written = 0;
+        for ordering_step in self.ordering().take(steps) {
+            let ordering_step = ordering_step?;
+            written += 1;
+            //A gem readable from the start, or finished by the facets an earlier one brought, introduces nothing.
+            let introduced = if ordering_step.introduced.is_empty() { "(nothing new)".to_string() } else { ordering_step.introduced.join(", ") };
+            match format {
+                OutputFormat::Text => writeln!(
+                    writer,
+                    "{:>4}  {}  (gem {}; {:.1}% of the deck readable)",
+                    written,
+                    introduced,
+                    ordering_step.gem.0,
+                    ordering_step.comprehensibility * 100.0,
+                )?,
+                OutputFormat::Ndjson => {
+                    serde_json::to_writer(&mut writer, &ordering_step)?;
+                    writer.write_all(b"\n")?;
+                }
+            }
+        }
+        writer.flush()?;
+        Ok(written)
+    }
+}
@@ -0,0 +1,142 @@
+//The learner's progress: which facets are known, plus the scheduling data for each facet that has been reviewed. Kept apart from the deck itself so the deck file never needs rewriting, and saved through a temporary file so a crash mid-write can't leave half a progress file behind.
+
+use std::{
+    collections::{HashMap, HashSet},
+    io::{ErrorKind, Read, Write},
+    path::Path,
+};
+
+use serde::{Serialize, Deserialize};
+
+use crate::{
+    collection::GemCollection,
+    error::{LangwitchError, Result},
+    facet::Facet,
+    gem::GemId,
+    interner::FacetId,
+    storage::{atomic::{replace_atomically, temporary_path_for}, compression::{open_reader, DeckWriter}},
+};
+#[cfg(feature = "encryption")]
+use std::fs;
+#[cfg(feature = "encryption")]
+use crate::{encryption::{is_encrypted, Keyring}, storage::atomic::write_atomically};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Default)]
+pub struct Progress {
+    pub known_facets: HashSet<String>,
+    pub facets: HashMap<String, Facet>,
+}
+
+impl Progress {
+    /// Reads a progress file. A file that doesn't exist yet is just a learner who hasn't started, so that gives an empty Progress rather than an error.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Progress> {
+        let mut reader = match open_reader(&path) {
+            Ok(reader) => reader,
+            Err(LangwitchError::Io(e)) if e.kind() == ErrorKind::NotFound => return Ok(Progress::default()),
+            Err(e) => return Err(e),
+        };
+        let mut contents = String::new();
+        reader.read_to_string(&mut contents)?;
+        Ok(serde_json::from_str(&contents)?)
+    }
+
+    /// Writes the progress file, replacing the old one only once the new one is safely on disk (see [`crate::storage::atomic`]).
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let temporary_path = temporary_path_for(&path);
+        let mut writer = DeckWriter::create(&temporary_path)?;
+        serde_json::to_writer(&mut writer, self)?;
+        writer.write_all(b"\n")?;
+        writer.finish()?;
+        replace_atomically(&temporary_path, path)
+    }
+}
+
+#[cfg(feature = "encryption")]
+impl Progress {
+    /// Same as [`Progress::load`] for a progress file encrypted with `keyring`. A file that isn't encrypted yet is read as it is, so turning encryption on loses nothing; it's encrypted the next time it's saved.
+    pub fn load_encrypted<P: AsRef<Path>>(path: P, keyring: &mut Keyring) -> Result<Progress> {
+        let contents = match fs::read(&path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Progress::default()),
+            Err(e) => return Err(e.into()),
+        };
+        match std::str::from_utf8(&contents) {
+            Ok(contents) if is_encrypted(contents) => Ok(serde_json::from_slice(&keyring.open_file(contents)?)?),
+            _ => Progress::load(path),
+        }
+    }
+
+    /// Same as [`Progress::save`], encrypting the file with `keyring`. Encrypted progress isn't compressed, whatever the path's extension.
+    pub fn save_encrypted<P: AsRef<Path>>(&self, path: P, keyring: &mut Keyring) -> Result<()> {
+        write_atomically(path, keyring.seal_file(&serde_json::to_vec(self)?)?.as_bytes())
+    }
+}
+
+/// Reads a list of words: one per line, or CSV/TSV/semicolon-separated with the word in the first column that has letters in it, so frequency lists like "rank,word,count" work as they are. Blank lines, `#` comments and a header row are skipped.
+pub fn read_word_list<P: AsRef<Path>>(path: P) -> Result<Vec<String>> {
+    let mut reader = open_reader(path)?;
+    let mut contents = String::new();
+    reader.read_to_string(&mut contents)?;
+    let mut words = Vec::new();
+    for (number, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields: Vec<&str> = line.split([',', '\t', ';']).map(|field| field.trim().trim_matches('"')).collect();
+        let header = number == 0
+            && fields.len() > 1
+            && fields.iter().any(|field| matches!(field.to_lowercase().as_str(), "word" | "words" | "lemma" | "facet" | "term"));
+        if header {
+            continue;
+        }
+        let word = match fields.into_iter().find(|field| field.chars().any(|c| c.is_alphabetic())) {
+            Some(word) => word,
+            None => continue,
+        };
+        words.push(word.to_string());
+    }
+    Ok(words)
+}
+
+impl GemCollection {
+    /// Marks every word in the list at `path` (see [`read_word_list`]) as known, for learners who aren't starting from zero. Words go through the collection's normalization first, so the list can use any form.
+    /// The indices are updated incrementally, just like [`GemCollection::mark_facets_known`], and the ids of the gems left with nothing to learn are returned.
+    pub fn import_known_words<P: AsRef<Path>>(&mut self, path: P) -> Result<Vec<GemId>> {
+        self.mark_words_known(&read_word_list(path)?)
+    }
+
+    /// Marks `words` as known, normalizing them first. Returns the ids of the gems left with nothing to learn.
+    pub fn mark_words_known(&mut self, words: &[String]) -> Result<Vec<GemId>> {
+        let words = self.normalize_facet_names(words)?;
+        let facets: HashSet<FacetId> = self.interner.intern_all(words.iter());
+        Ok(self.mark_facet_ids_known(&facets))
+    }
+
+    /// The collection's current progress, ready to be saved.
+    pub fn progress(&self) -> Progress {
+        Progress {
+            known_facets: self.known_facet_names(),
+            facets: self.facet_states.clone(),
+        }
+    }
+
+    /// Loads a progress file into the collection. Known facets are stripped from the gems the next time the collection is indexed.
+    pub fn load_progress<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        self.set_progress(Progress::load(path)?);
+        Ok(())
+    }
+
+    /// Replaces the collection's known facets and scheduling data with `progress`.
+    pub fn set_progress(&mut self, progress: Progress) {
+        self.known_facets.clear();
+        self.insert_known_facets(progress.known_facets.iter());
+        self.facet_states = progress.facets;
+    }
+
+    /// Saves the collection's progress, and shares its known facets if it has a shared knowledge store.
+    pub fn save_progress<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        self.push_shared_knowledge();
+        self.progress().save(path)
+    }
+}
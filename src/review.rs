@@ -0,0 +1,392 @@
+//The flashcard loop itself: which card comes next, and what grading it does. Facets that are due come first, each shown in the easiest gem that contains it; once nothing is due, the ordering picks the next new gem. Its facets are only learned once they've been graded right, so a facet that's failed stays new and its gem comes round again. Suspended and buried gems (see crate::suspend) are never shown.
+//Due facets are taken most urgent first: the furthest overdue for how long they're expected to last, so a fragile facet that's an hour late goes before a sturdy one that's a day late.
+//Daily limits keep a big deck from swamping anyone. A new gem is only shown if its brand-new facets (ones that have never been scheduled) still fit in what's left of the day's new facets, and once they don't the session serves only due reviews. Once the day's reviews are used up the session stops, and the rest stay due, still most urgent first, for tomorrow.
+//Reviewing ahead is for when there's time to spare: once nothing is due and no new gem can be shown, facets due within the window are pulled in, most urgent first, and the schedulers take into account that they were seen early. A short-lived facet reviewed early can come due inside the window again, so nothing the session has already asked about is pulled in ahead a second time.
+//Gems that share a facet are siblings, and showing them back to back mostly tests short-term memory: the second is answered from the first. With sibling spacing, a card whose gem shares a facet with one of the last few shown waits while something else goes first. Only when there's nothing else to show does a sibling come straight after.
+//Indexing strips known facets out of the gems, which would leave nothing to look up a due facet by, so a ReviewSession keeps its own copy of every gem's facets as they were when it started.
+
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    time::{Duration, SystemTime},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    autosave::{CheckpointCard, SessionCheckpoint},
+    collection::GemCollection,
+    compact::JournalSnapshot,
+    error::{LangwitchError, Result},
+    facet::INITIAL_LIFETIME_IN_HOURS,
+    gem::GemId,
+    interner::FacetId,
+    journal::{ReviewEvent, ReviewResult},
+    timestamp::to_millis,
+};
+
+/// What to show next.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Card {
+    pub gem: GemId,
+    /// The facets this card asks about, sorted.
+    pub facets: Vec<String>,
+    /// True for a gem the ordering just unlocked, false for a review of due facets.
+    pub is_new: bool,
+    /// True for a review of facets that aren't due yet, pulled in by [`ReviewSession::review_ahead`].
+    pub ahead: bool,
+}
+
+//A gem that's just been graded isn't shown again for this many cards, even if a failed facet in it is due again right away.
+const RECENT_CARDS: usize = 3;
+
+/// Caps on what review does in a day, e.g. {"new_facets": 20, "reviews": 200, "utc_offset_minutes": 60}. A review is one due facet asked about, so a card can use up several. Days run from midnight to midnight at `utc_offset_minutes` ahead of UTC, since langwitch doesn't know the local time zone.
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Copy, Default)]
+#[serde(default)]
+pub struct DailyLimits {
+    pub new_facets: Option<usize>,
+    pub reviews: Option<usize>,
+    pub utc_offset_minutes: i64,
+}
+
+/// What's left of a day's [`DailyLimits`]. None is unlimited.
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub struct DailyAllowance {
+    pub new_facets: Option<usize>,
+    pub reviews: Option<usize>,
+}
+
+impl DailyLimits {
+    /// When the day that `now` falls on began.
+    pub fn day_start(&self, now: SystemTime) -> SystemTime {
+        let local_millis = to_millis(now) as i64 + self.utc_offset_minutes * 60_000;
+        now - Duration::from_millis(local_millis.rem_euclid(86_400_000) as u64)
+    }
+
+    /// When the day after the one `now` falls on begins, which is when a gem buried today comes back.
+    pub fn next_day_start(&self, now: SystemTime) -> SystemTime {
+        self.day_start(now) + Duration::from_secs(86_400)
+    }
+
+    /// What's left of the limits on the day `now` falls on, given the reviews in `events` (cram drills don't count). A facet's first grade introduces it and every later one is a review, except that a facet `snapshot` holds was already introduced before the journal was compacted.
+    pub fn remaining(&self, events: &[ReviewEvent], snapshot: Option<&JournalSnapshot>, now: SystemTime) -> DailyAllowance {
+        let day_start = self.day_start(now);
+        let mut first_graded: HashMap<&String, SystemTime> = HashMap::new();
+        let mut graded_today = 0;
+        for event in events.iter().filter(|event| !event.cram) {
+            for facet in event.grades.keys() {
+                let first = first_graded.entry(facet).or_insert(event.timestamp);
+                *first = (*first).min(event.timestamp);
+                graded_today += (event.timestamp >= day_start) as usize;
+            }
+        }
+        let introduced = first_graded
+            .into_iter()
+            .filter(|(facet, first)| *first >= day_start && !snapshot.is_some_and(|snapshot| snapshot.progress.facets.contains_key(*facet)))
+            .count();
+        DailyAllowance {
+            new_facets: self.new_facets.map(|limit| limit.saturating_sub(introduced)),
+            reviews: self.reviews.map(|limit| limit.saturating_sub(graded_today - introduced)),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct ReviewSession {
+    facets_by_gem: Vec<HashSet<FacetId>>,
+    gems_by_facet: HashMap<FacetId, Vec<GemId>>,
+    //Gems that grading finished off but that haven't been shown yet, with the facets that taught them.
+    new_cards: VecDeque<(GemId, Vec<String>)>,
+    recent: VecDeque<GemId>,
+    //What's left of today's limits, and whether each has held a card back.
+    allowance: DailyAllowance,
+    new_facets_held_back: bool,
+    reviews_held_back: bool,
+    //How far ahead to look for facets once today's queue is empty. Zero doesn't look ahead at all.
+    ahead: Duration,
+    asked: HashSet<FacetId>,
+    //How many other cards have to come between gems that share a facet.
+    sibling_spacing: usize,
+}
+
+impl ReviewSession {
+    /// Starts a session on `gem_collection`. Call it before indexing the collection, while the gems still have every facet they were loaded with.
+    pub fn new(gem_collection: &GemCollection) -> ReviewSession {
+        let facets_by_gem: Vec<HashSet<FacetId>> = gem_collection.gems.iter().map(|gem| gem.unknown_facets.clone()).collect();
+        let mut gems_by_facet: HashMap<FacetId, Vec<GemId>> = HashMap::new();
+        for (number, facets) in facets_by_gem.iter().enumerate() {
+            for facet in facets.iter() {
+                gems_by_facet.entry(*facet).or_default().push(GemId(number));
+            }
+        }
+        ReviewSession { facets_by_gem, gems_by_facet, new_cards: VecDeque::new(), recent: VecDeque::new(), allowance: DailyAllowance::default(), new_facets_held_back: false, reviews_held_back: false, ahead: Duration::ZERO, asked: HashSet::new(), sibling_spacing: 0 }
+    }
+
+    /// Keeps the session within `allowance`, such as what [`DailyLimits::remaining`] says is left of today's.
+    pub fn limit(&mut self, allowance: DailyAllowance) {
+        self.allowance = allowance;
+    }
+
+    /// Once nothing is due and no new gem can be shown, serves facets that come due within `window` of now instead of stopping.
+    pub fn review_ahead(&mut self, window: Duration) {
+        self.ahead = window;
+    }
+
+    /// Keeps at least `cards` other cards between gems that share a facet (as they were when the session started), as long as there's something else to show.
+    pub fn space_siblings(&mut self, cards: usize) {
+        self.sibling_spacing = cards;
+    }
+
+    /// True if a new gem has been held back because its brand-new facets didn't fit in the allowance.
+    pub fn reached_new_facet_limit(&self) -> bool {
+        self.new_facets_held_back
+    }
+
+    /// True if due facets have been held back because the allowance has no reviews left, which is why [`ReviewSession::next_card`] ran out.
+    pub fn reached_review_limit(&self) -> bool {
+        self.reviews_held_back
+    }
+
+    /// Takes in the gems added to `gem_collection` since the session started. Like [`ReviewSession::new`], call it before they're indexed (see [`GemCollection::index_gems`]).
+    pub fn extend(&mut self, gem_collection: &GemCollection) {
+        for (number, gem) in gem_collection.gems.iter().enumerate().skip(self.facets_by_gem.len()) {
+            for facet in gem.unknown_facets.iter() {
+                self.gems_by_facet.entry(*facet).or_default().push(GemId(number));
+            }
+            self.facets_by_gem.push(gem.unknown_facets.clone());
+        }
+    }
+
+    /// The next card as of `now`, or None once nothing is due (within the review-ahead window, if there is one) and every gem has been unlocked, or the allowance has run out. The collection must be indexed.
+    pub fn next_card(&mut self, gem_collection: &mut GemCollection, now: SystemTime) -> Result<Option<Card>> {
+        gem_collection.unbury_expired(now);
+        //Out of reviews, new gems would only add to tomorrow's.
+        if self.allowance.reviews == Some(0) {
+            self.reviews_held_back = !Self::due_facets(gem_collection, now).is_empty();
+            return Ok(None);
+        }
+        if let Some(card) = self.due_card(gem_collection, now, false, true) {
+            return Ok(Some(self.take_review(card)));
+        }
+        if let Some(card) = self.queued_card(gem_collection, true) {
+            return Ok(Some(card));
+        }
+        if let Some(card) = self.ordered_card(gem_collection)? {
+            return Ok(Some(card));
+        }
+        //Nothing left to put between siblings.
+        if self.sibling_spacing > 0 {
+            if let Some(card) = self.due_card(gem_collection, now, false, false) {
+                return Ok(Some(self.take_review(card)));
+            }
+            if let Some(card) = self.queued_card(gem_collection, false) {
+                return Ok(Some(card));
+            }
+        }
+        Ok(self.ahead_card(gem_collection, now))
+    }
+
+    //The first gem grading unlocked that isn't set aside, leaving any that are siblings of the last few shown for later if `spaced`.
+    fn queued_card(&mut self, gem_collection: &GemCollection, spaced: bool) -> Option<Card> {
+        let position = self.new_cards.iter().position(|(gem, _)| !gem_collection.is_set_aside(*gem) && (!spaced || !self.is_sibling(*gem)))?;
+        let (gem, facets) = self.new_cards.remove(position)?;
+        Some(Card { gem, facets, is_new: true, ahead: false })
+    }
+
+    //The next gem the ordering picks, if there is one and its brand-new facets fit in the allowance.
+    fn ordered_card(&mut self, gem_collection: &mut GemCollection) -> Result<Option<Card>> {
+        let (gem, facets) = match gem_collection.next_gem() {
+            Ok(next) => next,
+            Err(LangwitchError::EmptyCollection) => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        //Picking the next gem doesn't learn anything, so a gem held back now is the one picked again tomorrow.
+        if let Some(allowance) = &mut self.allowance.new_facets {
+            let brand_new = facets.iter().filter(|facet| !gem_collection.facet_states.contains_key(*facet)).count();
+            if brand_new > *allowance {
+                self.new_facets_held_back = true;
+                return Ok(None);
+            }
+            *allowance -= brand_new;
+        }
+        let mut facets: Vec<String> = facets.into_iter().collect();
+        facets.sort();
+        Ok(Some(Card { gem, facets, is_new: true, ahead: false }))
+    }
+
+    //True if `gem` shares a facet with one of the last `sibling_spacing` gems shown. A gem added to the collection since the session last took gems in has no facets here, so it's nobody's sibling.
+    fn is_sibling(&self, gem: GemId) -> bool {
+        let facets = match self.facets_by_gem.get(gem.0) {
+            Some(facets) => facets,
+            None => return false,
+        };
+        self.recent
+            .iter()
+            .rev()
+            .take(self.sibling_spacing)
+            .any(|shown| self.facets_by_gem.get(shown.0).is_some_and(|shown| !shown.is_disjoint(facets)))
+    }
+
+    //Counts a review card against the allowance.
+    fn take_review(&mut self, card: Card) -> Card {
+        if let Some(reviews) = &mut self.allowance.reviews {
+            *reviews = reviews.saturating_sub(card.facets.len());
+        }
+        card
+    }
+
+    //The card due_card would serve at the far end of the review-ahead window, if there is a window.
+    fn ahead_card(&mut self, gem_collection: &GemCollection, now: SystemTime) -> Option<Card> {
+        if self.ahead.is_zero() {
+            return None;
+        }
+        let card = self.due_card(gem_collection, now + self.ahead, true, true).or_else(|| self.due_card(gem_collection, now + self.ahead, true, false))?;
+        Some(self.take_review(Card { ahead: true, ..card }))
+    }
+
+    //Every facet due at `now`, most urgent first: the most overdue in units of its lifetime, then the shortest-lived, then the longest due.
+    fn due_facets(gem_collection: &GemCollection, now: SystemTime) -> Vec<FacetId> {
+        let mut due: Vec<(f64, f64, SystemTime, FacetId)> = gem_collection
+            .facet_states
+            .iter()
+            .filter_map(|(name, state)| {
+                let review_date = state.review_date.filter(|review_date| *review_date <= now)?;
+                let lifetime_in_hours = state.lifetime_in_hours.unwrap_or(INITIAL_LIFETIME_IN_HOURS).max(f64::EPSILON);
+                let overdue_in_hours = now.duration_since(review_date).unwrap_or_default().as_secs_f64() / 3600.0;
+                Some((overdue_in_hours / lifetime_in_hours, lifetime_in_hours, review_date, gem_collection.interner.get(name)?))
+            })
+            .collect();
+        due.sort_unstable_by(|a, b| b.0.total_cmp(&a.0).then(a.1.total_cmp(&b.1)).then(a.2.cmp(&b.2)).then(a.3.cmp(&b.3)));
+        due.into_iter().map(|(_, _, _, facet)| facet).collect()
+    }
+
+    //The most urgent facet that some gem can show, in the gem with the fewest facets still unknown (then the fewest facets), asking about every due facet in that gem as far as the review allowance goes, most urgent first. Looking ahead leaves out facets the session has already asked about, and `spaced` leaves out siblings of the last few gems shown.
+    fn due_card(&self, gem_collection: &GemCollection, now: SystemTime, ahead: bool, spaced: bool) -> Option<Card> {
+        let mut due = Self::due_facets(gem_collection, now);
+        if ahead {
+            due.retain(|facet| !self.asked.contains(facet));
+        }
+        let urgency: HashMap<FacetId, usize> = due.iter().enumerate().map(|(rank, facet)| (*facet, rank)).collect();
+        due.iter().find_map(|facet| {
+            let (gem, facets) = self.gems_by_facet
+                .get(facet)?
+                .iter()
+                .filter(|gem_id| !self.recent.iter().rev().take(RECENT_CARDS).any(|shown| shown == *gem_id))
+                .filter(|gem_id| !gem_collection.is_set_aside(**gem_id) && (!spaced || !self.is_sibling(**gem_id)))
+                .filter_map(|gem_id| Some((*gem_id, self.facets_by_gem.get(gem_id.0)?)))
+                .min_by_key(|(gem_id, facets)| {
+                    let unknown = facets.iter().filter(|facet| !gem_collection.known_facets.contains(facet)).count();
+                    (unknown, facets.len(), *gem_id)
+                })?;
+            let mut due_in_gem: Vec<&FacetId> = facets.iter().filter(|facet| urgency.contains_key(facet)).collect();
+            due_in_gem.sort_unstable_by_key(|facet| urgency[facet]);
+            due_in_gem.truncate(self.allowance.reviews.unwrap_or(usize::MAX));
+            let mut facets: Vec<String> = due_in_gem.into_iter().map(|facet| gem_collection.facet_name(*facet).to_string()).collect();
+            facets.sort();
+            Some(Card { gem, facets, is_new: false, ahead: false })
+        })
+    }
+
+    /// Every facet a gem had when the session started, sorted. Useful for grading a card against its whole gem.
+    pub fn gem_facets(&self, gem_collection: &GemCollection, gem_id: GemId) -> Vec<String> {
+        let mut facets: Vec<String> = self.facets_by_gem
+            .get(gem_id.0)
+            .map(|facets| facets.iter().map(|facet| gem_collection.facet_name(*facet).to_string()).collect())
+            .unwrap_or_default();
+        facets.sort();
+        facets
+    }
+
+    /// Where the session has got to at `now`, `reviewed` cards in, for [`SessionCheckpoint::save`].
+    pub fn checkpoint(&self, gem_collection: &GemCollection, reviewed: usize, now: SystemTime) -> SessionCheckpoint {
+        let new_cards = self
+            .new_cards
+            .iter()
+            .filter_map(|(gem_id, facets)| Some(CheckpointCard { gem: gem_collection.key(*gem_id)?.clone(), facets: facets.clone() }))
+            .collect();
+        let recent = self.recent.iter().filter_map(|gem_id| gem_collection.key(*gem_id).cloned()).collect();
+        SessionCheckpoint { saved_at: now, reviewed, new_cards, recent }
+    }
+
+    /// Picks up the queue of a session that was checkpointed. Gems that are no longer in the deck are skipped.
+    pub fn resume(&mut self, gem_collection: &GemCollection, checkpoint: &SessionCheckpoint) {
+        self.new_cards = checkpoint
+            .new_cards
+            .iter()
+            .filter_map(|card| Some((gem_collection.gem_id(&card.gem)?, card.facets.clone())))
+            .collect();
+        self.recent = checkpoint.recent.iter().filter_map(|key| gem_collection.gem_id(key)).collect();
+    }
+
+    /// Grades a card facet by facet with [`GemCollection::grade_gem`]. Other gems that the passed facets finish off are queued up to be shown next, asking about the facets this card taught.
+    pub fn grade(&mut self, gem_collection: &mut GemCollection, card: &Card, grades: HashMap<String, f64>) -> Result<ReviewResult> {
+        self.grade_with_hints(gem_collection, card, grades, HashMap::new())
+    }
+
+    /// Same as [`ReviewSession::grade`], for a card where facets needed hints. See [`GemCollection::grade_gem_with_hints`].
+    pub fn grade_with_hints(&mut self, gem_collection: &mut GemCollection, card: &Card, grades: HashMap<String, f64>, hints: HashMap<String, u8>) -> Result<ReviewResult> {
+        self.grade_with_hints_at(gem_collection, card, grades, hints, SystemTime::now())
+    }
+
+    /// Same as [`ReviewSession::grade_with_hints`], for a review that happened at `timestamp`. See [`GemCollection::grade_gem_with_hints_at`].
+    pub fn grade_with_hints_at(&mut self, gem_collection: &mut GemCollection, card: &Card, grades: HashMap<String, f64>, hints: HashMap<String, u8>, timestamp: SystemTime) -> Result<ReviewResult> {
+        let result = gem_collection.grade_gem_with_hints_at(card.gem, grades, hints, timestamp)?;
+        let newly_known: HashSet<FacetId> = result.newly_known.iter().filter_map(|facet| gem_collection.interner.get(facet)).collect();
+        for gem_id in result.unlocked.iter().filter(|gem_id| **gem_id != card.gem) {
+            //A gem the session hasn't taken in (see ReviewSession::extend) has no facets of its own to ask about yet.
+            let gem_facets = match self.facets_by_gem.get(gem_id.0) {
+                Some(gem_facets) => gem_facets,
+                None => continue,
+            };
+            let mut facets: Vec<String> = gem_facets
+                .iter()
+                .filter(|facet| newly_known.contains(facet))
+                .map(|facet| gem_collection.facet_name(*facet).to_string())
+                .collect();
+            facets.sort();
+            self.new_cards.push_back((*gem_id, facets));
+        }
+        self.asked.extend(card.facets.iter().filter_map(|facet| gem_collection.interner.get(facet)));
+        self.recent.push_back(card.gem);
+        if self.recent.len() > RECENT_CARDS.max(self.sibling_spacing) {
+            self.recent.pop_front();
+        }
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{collection, gem};
+
+    //Grades every card `session` serves at `now` as passed, returning the gems it showed.
+    fn study(session: &mut ReviewSession, gem_collection: &mut GemCollection, now: SystemTime) -> Vec<GemId> {
+        let mut shown = Vec::new();
+        while let Some(card) = session.next_card(gem_collection, now).unwrap() {
+            let grades = card.facets.iter().map(|facet| (facet.clone(), 1.0)).collect();
+            session.grade_with_hints_at(gem_collection, &card, grades, HashMap::new(), now).unwrap();
+            shown.push(card.gem);
+            assert!(shown.len() < 20, "the session never ran out");
+        }
+        shown
+    }
+
+    #[test]
+    fn gems_added_mid_session_are_served_and_graded() {
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000);
+        let mut gem_collection = collection();
+        gem_collection.index_all_gems_by_number();
+        let mut session = ReviewSession::new(&gem_collection);
+        session.space_siblings(2);
+        let card = session.next_card(&mut gem_collection, now).unwrap().unwrap();
+        let grades = card.facets.iter().map(|facet| (facet.clone(), 1.0)).collect();
+        session.grade_with_hints_at(&mut gem_collection, &card, grades, HashMap::new(), now).unwrap();
+        let added = gem_collection.add_gems(vec![gem("der vogel"), gem("die katze singt"), gem("ein vogel singt")]).unwrap();
+        assert_eq!(added.len(), 3);
+        let shown = study(&mut session, &mut gem_collection, now);
+        assert!(added.iter().any(|gem_id| shown.contains(gem_id)));
+        assert!(gem_collection.known_facets.contains(&gem_collection.interner.get("singt").unwrap()));
+        //A month on everything is due again, with the added gems among the last shown.
+        assert!(!study(&mut session, &mut gem_collection, now + Duration::from_secs(30 * 86_400)).is_empty());
+    }
+}
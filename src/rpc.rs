@@ -0,0 +1,130 @@
+//`langwitch --stdio`: JSON-RPC 2.0 over stdin and stdout, so an editor plugin or a script in any language can drive the engine as a subprocess. Each request is one line of JSON and each response goes back as one line; notifications (requests without an id) get no response. Anything that isn't a response goes to stderr.
+//Methods, with params by name:
+//next_gem: the card to study (the same JSON `serve` gives for /next-gem), or null once nothing is left.
+//grade {"gem", "grades", "grade", "hints"}: grades the card, as `serve`'s /grade does.
+//mark_known {"words": [...]}: marks words known, answering with the ids of the gems that leaves with nothing to learn.
+//analyze_text {"text"}: how readable a text is, the report `analyze` prints.
+//import {"gems": [...]} or {"text"}: adds gems in the deck format, or mines them out of plain text, as `serve`'s /import does.
+//stats: the same summary as `serve`'s /stats.
+//Progress is saved after every grade and mark_known, and shared knowledge once stdin closes.
+
+use std::io::{self, BufRead, Write};
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use langwitch::{wire::GradeRequest, Config, Gem, LangwitchError};
+
+use crate::study::Study;
+
+//The error codes JSON-RPC sets aside, plus one for whatever the engine turned down.
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const ENGINE_ERROR: i64 = -32000;
+
+#[derive(Deserialize)]
+struct Request {
+    method: String,
+    #[serde(default)]
+    params: Value,
+    #[serde(default)]
+    id: Option<Value>,
+}
+
+#[derive(Deserialize)]
+struct MarkKnownParams {
+    words: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct AnalyzeParams {
+    text: String,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ImportParams {
+    Gems { gems: Vec<Gem> },
+    Text { text: String },
+}
+
+struct RpcError(i64, String);
+
+impl From<LangwitchError> for RpcError {
+    fn from(e: LangwitchError) -> Self {
+        RpcError(ENGINE_ERROR, e.to_string())
+    }
+}
+
+fn params<T: for<'de> Deserialize<'de>>(params: Value) -> Result<T, RpcError> {
+    serde_json::from_value(params).map_err(|e| RpcError(INVALID_PARAMS, e.to_string()))
+}
+
+fn call(study: &mut Study, method: &str, params_value: Value) -> Result<Value, RpcError> {
+    let to_value = |value: Result<Value, serde_json::Error>| value.map_err(|e| RpcError(ENGINE_ERROR, e.to_string()));
+    match method {
+        "next_gem" => to_value(serde_json::to_value(study.current_card()?)),
+        "grade" => {
+            let request: GradeRequest = params(params_value)?;
+            to_value(serde_json::to_value(study.grade(request)?))
+        }
+        "mark_known" => {
+            let MarkKnownParams { words } = params(params_value)?;
+            Ok(json!({ "unlocked": study.mark_known(&words)? }))
+        }
+        "analyze_text" => {
+            let AnalyzeParams { text } = params(params_value)?;
+            let report = study.setup.gem_collection.analyze_text(&text)?;
+            to_value(serde_json::to_value(report))
+        }
+        "import" => {
+            let imported = match params(params_value)? {
+                ImportParams::Gems { gems } => study.import(gems)?,
+                ImportParams::Text { text } => study.import_text(&text)?,
+            };
+            to_value(serde_json::to_value(imported))
+        }
+        "stats" => to_value(serde_json::to_value(study.stats())),
+        _ => Err(RpcError(METHOD_NOT_FOUND, format!("there's no method called {:?}", method))),
+    }
+}
+
+//The response to one line, or None for a notification.
+fn respond(study: &mut Study, line: &str) -> Option<Value> {
+    let request: Value = match serde_json::from_str(line) {
+        Ok(request) => request,
+        Err(e) => return Some(json!({ "jsonrpc": "2.0", "id": null, "error": { "code": PARSE_ERROR, "message": e.to_string() } })),
+    };
+    let request: Request = match serde_json::from_value(request) {
+        Ok(request) => request,
+        Err(e) => return Some(json!({ "jsonrpc": "2.0", "id": null, "error": { "code": INVALID_REQUEST, "message": e.to_string() } })),
+    };
+    let result = call(study, &request.method, request.params);
+    let id = request.id?;
+    Some(match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(RpcError(code, message)) => json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } }),
+    })
+}
+
+/// Answers requests on stdin until it closes, then saves progress and shared knowledge.
+pub fn run(config: Config) -> langwitch::Result<()> {
+    let mut study = Study::load(config)?;
+    let stdout = io::stdout();
+    for line in io::stdin().lock().lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        if let Some(response) = respond(&mut study, &line) {
+            let mut stdout = stdout.lock();
+            writeln!(stdout, "{}", response)?;
+            stdout.flush()?;
+        }
+    }
+    let reviewed = study.finish()?;
+    eprintln!("Reviewed {} cards", reviewed);
+    Ok(())
+}
@@ -0,0 +1,100 @@
+//Furigana: a side can carry readings inline as `漢字[かんじ]`, the same syntax Anki's furigana filter uses, so Japanese decks keep their readings without the readings turning into facets.
+//A reading belongs to the run of kanji right before its bracket, so text without spaces works (`漢字[かんじ]を勉強[べんきょう]する`), and words with kana in them are marked kanji by kanji (`食[た]べ物[もの]`). When the text before the bracket isn't kanji, the base runs back to the previous space, as it always does in Anki. Brackets that can't be ruby (nothing before them, no closing bracket, Anki's `[sound:...]`) are left as they are.
+
+use std::fmt::Write;
+
+/// A stretch of a side: plain text, or a base with the reading written over it.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct RubySegment<'a> {
+    pub base: &'a str,
+    pub reading: Option<&'a str>,
+}
+
+fn is_kanji(c: char) -> bool {
+    matches!(c,
+        '々' | '〆' | 'ヶ'
+        | '\u{3400}'..='\u{4DBF}'
+        | '\u{4E00}'..='\u{9FFF}'
+        | '\u{F900}'..='\u{FAFF}'
+        | '\u{20000}'..='\u{2FA1F}')
+}
+
+//Where the base of a reading starts, given the text between the end of the last ruby and the bracket.
+fn base_start(before: &str) -> usize {
+    let ends_in_kanji = before.chars().next_back().is_some_and(is_kanji);
+    before
+        .char_indices()
+        .rev()
+        .find(|(_, c)| if ends_in_kanji { !is_kanji(*c) } else { c.is_whitespace() })
+        .map_or(0, |(offset, c)| offset + c.len_utf8())
+}
+
+/// Splits `text` into plain and ruby segments, in order. Joining every base gives the text without readings.
+pub fn parse_ruby(text: &str) -> Vec<RubySegment<'_>> {
+    let mut segments = Vec::new();
+    let mut plain_start = 0;
+    let mut search_from = 0;
+    while let Some(open) = text[search_from..].find('[').map(|offset| search_from + offset) {
+        let close = match text[open..].find(']') {
+            Some(offset) => open + offset,
+            None => break,
+        };
+        let reading = &text[open + 1..close];
+        let base = plain_start + base_start(&text[plain_start..open]);
+        if reading.is_empty() || reading.contains('[') || reading.starts_with("sound:") || base == open {
+            search_from = open + 1;
+            continue;
+        }
+        if base > plain_start {
+            segments.push(RubySegment { base: &text[plain_start..base], reading: None });
+        }
+        segments.push(RubySegment { base: &text[base..open], reading: Some(reading) });
+        plain_start = close + 1;
+        search_from = plain_start;
+    }
+    if plain_start < text.len() {
+        segments.push(RubySegment { base: &text[plain_start..], reading: None });
+    }
+    segments
+}
+
+/// `text` with its readings taken out, which is what gets tokenized into facets.
+pub fn strip_ruby(text: &str) -> String {
+    if !text.contains('[') {
+        return text.to_string();
+    }
+    parse_ruby(text).into_iter().map(|segment| segment.base).collect()
+}
+
+/// `text` with each reading in brackets after its base, like `漢字(かんじ)`, for terminals that can't draw ruby.
+pub fn ruby_to_plain(text: &str) -> String {
+    if !text.contains('[') {
+        return text.to_string();
+    }
+    let mut plain = String::with_capacity(text.len());
+    for segment in parse_ruby(text) {
+        plain.push_str(segment.base);
+        if let Some(reading) = segment.reading {
+            let _ = write!(plain, "({})", reading);
+        }
+    }
+    plain
+}
+
+pub(crate) fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// `text` as HTML, with each reading in a `<ruby>` element. Everything else is escaped.
+pub fn ruby_to_html(text: &str) -> String {
+    let mut html = String::with_capacity(text.len());
+    for segment in parse_ruby(text) {
+        match segment.reading {
+            Some(reading) => {
+                let _ = write!(html, "<ruby>{}<rt>{}</rt></ruby>", escape_html(segment.base), escape_html(reading));
+            }
+            None => html.push_str(&escape_html(segment.base)),
+        }
+    }
+    html
+}
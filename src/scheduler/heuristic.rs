@@ -0,0 +1,85 @@
+//The original lifetime-in-hours heuristic, moved here from Facet so it's just one Scheduler among several.
+//The binary method for updating a Facet based on whether a user's response was right or wrong is simple. The 'fuzzy' method, which receives a number between 0 and 1, runs the binary update twice (once right, once wrong) and takes a weighted average of the two results.
+//A right answer already grows the lifetime by however long the facet actually waited when that's less than the lifetime, so reviewing ahead earns less than waiting would have.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::{
+    error::{LangwitchError, Result},
+    facet::Facet,
+    scheduler::Scheduler,
+};
+
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub struct Heuristic;
+
+//Hours from `earlier` to `later`, or zero if `earlier` is actually in the future (e.g a review date that hasn't come up yet).
+fn hours_between(earlier: SystemTime, later: SystemTime) -> f64 {
+    later.duration_since(earlier).unwrap_or_default().as_secs() as f64 / 3600.0
+}
+
+fn seconds_since_epoch(date: SystemTime, field: &str) -> Result<f64> {
+    date.duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs() as f64)
+        .map_err(|_| LangwitchError::SchedulingState(format!("{} is before the unix epoch", field)))
+}
+
+impl Heuristic {
+    pub fn review_binary(&self, facet: &Facet, correct: bool, now: SystemTime) -> Result<Facet> {
+        //First, we want to return an error if any of the fields are None:
+        let (review_date, last_seen_date, lifetime_in_hours) = match (facet.review_date, facet.last_seen_date, facet.lifetime_in_hours) {
+            (Some(review_date), Some(last_seen_date), Some(lifetime_in_hours)) => (review_date, last_seen_date, lifetime_in_hours),
+            _ => return Err(LangwitchError::SchedulingState(format!("facet {} has a None value in one of its fields", facet.name))),
+        };
+        //Now we calculate the number of hours since last_seen_date and now:
+        let hours_since_last_seen = hours_between(last_seen_date, now);
+        //We then check if hours_since_last_seen is greater than lifetime_in_hours. If it is, we set the lifetime_in_hours to 3 * hours_since_last_seen.
+        let new_lifetime_in_hours = if correct {
+            if hours_since_last_seen > lifetime_in_hours {
+                3.0 * hours_since_last_seen
+            } else if lifetime_in_hours > (0.05 * 27.0) - 1.0 {
+                lifetime_in_hours + hours_since_last_seen
+            } else {
+                hours_since_last_seen * 3.0
+            }
+        } else {
+            lifetime_in_hours / 3.0
+        };
+        //Then, we move forward the review date, and finally we set last_seen to now. A review that came early (reviewing ahead) counts from now rather than from the review date it beat:
+        let hours_since_review = hours_between(review_date, now);
+        let new_hours_since_review = hours_since_review + new_lifetime_in_hours;
+        Ok(Facet {
+            lifetime_in_hours: Some(new_lifetime_in_hours),
+            review_date: Some(review_date.min(now) + Duration::from_secs((new_hours_since_review * 3600.0) as u64)),
+            last_seen_date: Some(now),
+            ..facet.clone()
+        })
+    }
+
+    pub fn review_fuzzy(&self, facet: &Facet, correct: f64, now: SystemTime) -> Result<Facet> {
+        let facet_1 = self.review_binary(facet, true, now)?;
+        let facet_2 = self.review_binary(facet, false, now)?;
+        self.average_facet_fields(facet, facet_1, facet_2, correct, now)
+    }
+
+    pub fn average_facet_fields(&self, facet: &Facet, facet_1: Facet, facet_2: Facet, correct: f64, now: SystemTime) -> Result<Facet> {
+        let missing = || LangwitchError::SchedulingState(format!("facet {} can't be averaged before it has been reviewed", facet.name));
+        let ratios = [correct, 1.0 - correct];
+        let review_date = seconds_since_epoch(facet_1.review_date.ok_or_else(missing)?, "review_date")? * ratios[0]
+            + seconds_since_epoch(facet_2.review_date.ok_or_else(missing)?, "review_date")? * ratios[1];
+        let lifetime_in_hours = facet_1.lifetime_in_hours.ok_or_else(missing)? * ratios[0]
+            + facet_2.lifetime_in_hours.ok_or_else(missing)? * ratios[1];
+        Ok(Facet {
+            review_date: Some(UNIX_EPOCH + Duration::from_secs(review_date as u64)),
+            last_seen_date: Some(now),
+            lifetime_in_hours: Some(lifetime_in_hours),
+            ..facet.clone()
+        })
+    }
+}
+
+impl Scheduler for Heuristic {
+    fn review(&mut self, facet: &Facet, grade: f64, now: SystemTime) -> Result<Facet> {
+        self.review_fuzzy(facet, grade, now)
+    }
+}
@@ -0,0 +1,62 @@
+//Leitner boxes: deterministic and easy to reason about, for people who'd rather not have continuous intervals. Every facet sits in one of N boxes. Getting it right moves it up a box; getting it wrong sends it back to the first box (or just down one, if reset_on_failure is off). Each box has a fixed interval, doubling by default: 1, 2, 4, 8, 16... days.
+//A pass ahead of the review date only earns promotion if the facet waited at least half its box's interval; otherwise it stays where it is, rescheduled from now.
+
+use std::time::{Duration, SystemTime};
+
+use serde::{Serialize, Deserialize};
+
+use crate::{error::Result, facet::Facet, journal::PASSING_GRADE, scheduler::{elapsed_share, Scheduler}};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct Leitner {
+    pub boxes: usize,
+    /// Interval for each box in days. Boxes past the end of the list reuse its last entry; an empty list means doubling from one day.
+    pub intervals_in_days: Vec<f64>,
+    pub reset_on_failure: bool,
+}
+
+//How much of its interval a facet has to have waited for an early pass to promote it.
+const EARLY_PROMOTION_SHARE: f64 = 0.5;
+
+impl Default for Leitner {
+    fn default() -> Self {
+        Leitner {
+            boxes: 5,
+            intervals_in_days: Vec::new(),
+            reset_on_failure: true,
+        }
+    }
+}
+
+impl Leitner {
+    pub fn interval_in_days(&self, leitner_box: usize) -> f64 {
+        match self.intervals_in_days.get(leitner_box).or(self.intervals_in_days.last()) {
+            Some(interval) => *interval,
+            None => 2f64.powi(leitner_box as i32),
+        }
+    }
+}
+
+//Moves the facet between boxes and schedules it by its new box's interval.
+impl Scheduler for Leitner {
+    fn review(&mut self, facet: &Facet, grade: f64, now: SystemTime) -> Result<Facet> {
+        let last_box = self.boxes.max(1) - 1;
+        let leitner_box = match facet.leitner_box {
+            //The first review places the facet in the first box; it has to earn promotion from there.
+            None => 0,
+            Some(leitner_box) if grade >= PASSING_GRADE && elapsed_share(facet, now) < EARLY_PROMOTION_SHARE => leitner_box.min(last_box),
+            Some(leitner_box) if grade >= PASSING_GRADE => (leitner_box + 1).min(last_box),
+            Some(_) if self.reset_on_failure => 0,
+            Some(leitner_box) => leitner_box.saturating_sub(1),
+        };
+        let lifetime_in_hours = self.interval_in_days(leitner_box) * 24.0;
+        Ok(Facet {
+            leitner_box: Some(leitner_box),
+            lifetime_in_hours: Some(lifetime_in_hours),
+            review_date: Some(now + Duration::from_secs((lifetime_in_hours * 3600.0) as u64)),
+            last_seen_date: Some(now),
+            ..facet.clone()
+        })
+    }
+}
@@ -0,0 +1,57 @@
+//The algorithms that decide when a facet is next due. Each one implements Scheduler, so the review code never needs to know which it's talking to; SchedulerKind is the config-friendly way of picking one.
+//A facet reviewed ahead of its review date hasn't been tested over its whole interval, so each scheduler grows it less for an early pass; elapsed_share says how much of the interval it did wait.
+
+use std::time::SystemTime;
+
+use serde::{Serialize, Deserialize};
+
+use crate::{error::Result, facet::Facet};
+
+pub mod heuristic;
+pub mod leitner;
+pub mod sm2;
+
+use heuristic::Heuristic;
+use leitner::Leitner;
+use sm2::Sm2;
+
+pub trait Scheduler {
+    /// Returns the facet's new scheduling state after a review at `now` with a fuzzy grade between 0.0 (wrong) and 1.0 (right).
+    fn review(&mut self, facet: &Facet, grade: f64, now: SystemTime) -> Result<Facet>;
+}
+
+/// How much of its planned interval (last seen to review date) a facet had waited by `now`, from 0.0 to 1.0. Anything under 1.0 is an early review; a facet that was never scheduled has waited all of it.
+pub fn elapsed_share(facet: &Facet, now: SystemTime) -> f64 {
+    let (last_seen_date, review_date) = match (facet.last_seen_date, facet.review_date) {
+        (Some(last_seen_date), Some(review_date)) => (last_seen_date, review_date),
+        _ => return 1.0,
+    };
+    let planned = review_date.duration_since(last_seen_date).unwrap_or_default().as_secs_f64();
+    if planned <= 0.0 {
+        return 1.0;
+    }
+    let waited = now.duration_since(last_seen_date).unwrap_or_default().as_secs_f64();
+    (waited / planned).clamp(0.0, 1.0)
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SchedulerKind {
+    /// The lifetime-in-hours heuristic from the original design.
+    #[default]
+    Heuristic,
+    /// Classic SM-2, for anyone used to Anki's intervals.
+    Sm2,
+    /// Fixed Leitner boxes, written as `{"leitner": {"boxes": 5}}` in the config.
+    Leitner(Leitner),
+}
+
+impl Scheduler for SchedulerKind {
+    fn review(&mut self, facet: &Facet, grade: f64, now: SystemTime) -> Result<Facet> {
+        match self {
+            SchedulerKind::Heuristic => Heuristic.review(facet, grade, now),
+            SchedulerKind::Sm2 => Sm2.review(facet, grade, now),
+            SchedulerKind::Leitner(leitner) => leitner.review(facet, grade, now),
+        }
+    }
+}
@@ -0,0 +1,51 @@
+//Classic SuperMemo-2, the algorithm Anki grew out of: every facet carries an ease factor, a repetition count and an interval. A passing grade grows the interval (1 day, then 6 days, then multiplied by the ease); a failing one starts it over. The ease drifts up with easy answers and down with hard ones, never below 1.3.
+//Intervals are kept in lifetime_in_hours so the rest of the engine (which thinks in hours) sees SM-2 facets the same way as heuristic ones.
+//A facet passed ahead of its review date gets only the share of the interval's growth that it waited for, so reviewing a day early doesn't earn the full jump.
+
+use std::time::{Duration, SystemTime};
+
+use crate::{error::Result, facet::Facet, scheduler::{elapsed_share, Scheduler}};
+
+pub const INITIAL_EASE_FACTOR: f64 = 2.5;
+pub const MINIMUM_EASE_FACTOR: f64 = 1.3;
+const HOURS_PER_DAY: f64 = 24.0;
+
+/// Fuzzy grades (0.0 to 1.0) map onto SM-2's 0 to 5 quality scale.
+pub fn quality_from_grade(grade: f64) -> u8 {
+    (grade.clamp(0.0, 1.0) * 5.0).round() as u8
+}
+
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub struct Sm2;
+
+impl Scheduler for Sm2 {
+    fn review(&mut self, facet: &Facet, grade: f64, now: SystemTime) -> Result<Facet> {
+        let quality = quality_from_grade(grade) as f64;
+        let ease_factor = facet.ease_factor.unwrap_or(INITIAL_EASE_FACTOR);
+        let repetitions = facet.repetitions.unwrap_or(0);
+        let interval_in_days = facet.lifetime_in_hours.unwrap_or(0.0) / HOURS_PER_DAY;
+
+        let (repetitions, interval_in_days) = if quality >= 3.0 {
+            let grown = match repetitions {
+                0 => 1.0,
+                1 => 6.0,
+                _ => (interval_in_days * ease_factor).round(),
+            };
+            let share = elapsed_share(facet, now);
+            (repetitions + 1, interval_in_days + (grown - interval_in_days) * share)
+        } else {
+            (0, 1.0)
+        };
+        let ease_factor = (ease_factor + (0.1 - (5.0 - quality) * (0.08 + (5.0 - quality) * 0.02))).max(MINIMUM_EASE_FACTOR);
+
+        let lifetime_in_hours = interval_in_days * HOURS_PER_DAY;
+        Ok(Facet {
+            ease_factor: Some(ease_factor),
+            repetitions: Some(repetitions),
+            lifetime_in_hours: Some(lifetime_in_hours),
+            review_date: Some(now + Duration::from_secs((lifetime_in_hours * 3600.0) as u64)),
+            last_seen_date: Some(now),
+            ..facet.clone()
+        })
+    }
+}
@@ -0,0 +1,526 @@
+//Versions of the deck format, and loading the older ones without anyone having to convert them by hand.
+//Version 0 is the original layout (see gems_old.rs): a bare array of gems whose unknown_facets is a map from facet name to a whole Facet, scheduling included. Version 1 is the same array with unknown_facets as a plain list of names, scheduling having moved out to progress. Version 2, written from now on, wraps that array as {"schema_version": 2, "gems": [...]}; a JSON Lines deck starts with a {"schema_version": 2} line instead.
+//Reading a version 0 deck keeps the scheduling it carried: it comes back in the collection's facet_states, and loading progress on top of it only fills in the facets the progress file has nothing on. Since decks are written back as version 2, a collection holding scheduling that isn't in progress yet refuses to be written (see GemCollection::write_gems_to_file); JsonStorage saves it to progress and upgrades the deck file the first time it reads one. A deck from a newer version than this one is refused rather than half read.
+//Gems exported from other tools are taken as they come, too: sides as an object keyed by side number (as a string or an integer), a list in side order, or a lone string for side 0; unknown facets as a list, a whitespace-separated string, or left out to be worked out from side 0.
+//Each gem is parsed from its own slice of the file, so a gem that doesn't fit says which one it was, which field, and where in the file, and a lenient read can leave it out and carry on with the rest.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    io::Write,
+};
+
+use serde::{
+    de::{self, MapAccess, SeqAccess, Visitor},
+    Deserialize, Deserializer, Serialize,
+};
+use serde_json::value::RawValue;
+
+use crate::{
+    collection::GemCollection,
+    error::{LangwitchError, Result},
+    facet::Facet,
+    flag::GemFlag,
+    gem::Gem,
+    progress::Progress,
+    tokenize::tokenize,
+};
+
+/// The deck format this version of langwitch writes, and the newest it reads.
+pub const SCHEMA_VERSION: u32 = 2;
+
+//A gem as a deck file has it, in any version. Decks are allowed to leave unknown_facets out, in which case they're worked out from the first side.
+#[derive(Deserialize)]
+pub(crate) struct StoredGem {
+    #[serde(default)]
+    id: Option<String>,
+    sides: StoredSides,
+    #[serde(default)]
+    unknown_facets: Option<StoredFacets>,
+    #[serde(default)]
+    flags: Vec<GemFlag>,
+}
+
+//The sides in whichever shape they came in, already numbered.
+struct StoredSides(HashMap<usize, String>);
+
+impl<'de> Deserialize<'de> for StoredSides {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<StoredSides, D::Error> {
+        struct StoredSidesVisitor;
+
+        impl<'de> Visitor<'de> for StoredSidesVisitor {
+            type Value = StoredSides;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a map from side numbers to sides, a list of sides, or a single side")
+            }
+
+            fn visit_str<E: de::Error>(self, side: &str) -> std::result::Result<StoredSides, E> {
+                Ok(StoredSides(HashMap::from([(0, side.to_string())])))
+            }
+
+            //Nulls hold a side's place without filling it, as spreadsheet exports do for empty cells.
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> std::result::Result<StoredSides, A::Error> {
+                let mut sides = HashMap::new();
+                let mut side_number = 0;
+                while let Some(side) = seq.next_element::<Option<String>>()? {
+                    if let Some(side) = side {
+                        sides.insert(side_number, side);
+                    }
+                    side_number += 1;
+                }
+                Ok(StoredSides(sides))
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> std::result::Result<StoredSides, A::Error> {
+                let mut sides = HashMap::new();
+                while let Some((SideNumber(side_number), side)) = map.next_entry::<SideNumber, String>()? {
+                    sides.insert(side_number, side);
+                }
+                Ok(StoredSides(sides))
+            }
+        }
+
+        deserializer.deserialize_any(StoredSidesVisitor)
+    }
+}
+
+//A side number as a map key: an integer, or a string holding one.
+struct SideNumber(usize);
+
+impl<'de> Deserialize<'de> for SideNumber {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<SideNumber, D::Error> {
+        struct SideNumberVisitor;
+
+        impl<'de> Visitor<'de> for SideNumberVisitor {
+            type Value = SideNumber;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a side number")
+            }
+
+            fn visit_u64<E: de::Error>(self, number: u64) -> std::result::Result<SideNumber, E> {
+                usize::try_from(number).map(SideNumber).map_err(|_| E::invalid_value(de::Unexpected::Unsigned(number), &self))
+            }
+
+            fn visit_str<E: de::Error>(self, number: &str) -> std::result::Result<SideNumber, E> {
+                number.trim().parse().map(SideNumber).map_err(|_| E::invalid_value(de::Unexpected::Str(number), &self))
+            }
+        }
+
+        deserializer.deserialize_any(SideNumberVisitor)
+    }
+}
+
+//A list of facet names, a whitespace-separated string of them, or (version 0) a map from names to facets with their scheduling.
+enum StoredFacets {
+    Names(HashSet<String>),
+    Embedded(Vec<Facet>),
+}
+
+impl<'de> Deserialize<'de> for StoredFacets {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<StoredFacets, D::Error> {
+        struct StoredFacetsVisitor;
+
+        impl<'de> Visitor<'de> for StoredFacetsVisitor {
+            type Value = StoredFacets;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a list of facet names, a string of them separated by spaces, or a map from facet names to facets")
+            }
+
+            fn visit_str<E: de::Error>(self, names: &str) -> std::result::Result<StoredFacets, E> {
+                Ok(StoredFacets::Names(names.split_whitespace().map(str::to_string).collect()))
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> std::result::Result<StoredFacets, A::Error> {
+                let mut names = HashSet::new();
+                while let Some(name) = seq.next_element()? {
+                    names.insert(name);
+                }
+                Ok(StoredFacets::Names(names))
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> std::result::Result<StoredFacets, A::Error> {
+                let mut facets = Vec::new();
+                while let Some((name, mut facet)) = map.next_entry::<String, Facet>()? {
+                    //The key is what the gem called the facet, whatever the facet's own copy says.
+                    facet.name = name;
+                    facets.push(facet);
+                }
+                Ok(StoredFacets::Embedded(facets))
+            }
+        }
+
+        deserializer.deserialize_any(StoredFacetsVisitor)
+    }
+}
+
+impl StoredGem {
+    //The gem, and the scheduling a version 0 deck kept in it. Facets that were never reviewed had nothing worth keeping.
+    fn split(self) -> (Gem, Vec<Facet>) {
+        let (unknown_facets, states) = match self.unknown_facets {
+            Some(StoredFacets::Names(names)) => (names, Vec::new()),
+            Some(StoredFacets::Embedded(facets)) => {
+                let names = facets.iter().map(|facet| facet.name.clone()).collect();
+                (names, facets.into_iter().filter(|facet| facet.review_date.is_some() || facet.last_seen_date.is_some()).collect())
+            }
+            None => (self.sides.0.get(&0).map(|side| tokenize(side)).unwrap_or_default(), Vec::new()),
+        };
+        (Gem { id: self.id, sides: self.sides.0, unknown_facets, flags: self.flags }, states)
+    }
+
+    fn is_embedded(&self) -> bool {
+        matches!(self.unknown_facets, Some(StoredFacets::Embedded(_)))
+    }
+}
+
+impl From<StoredGem> for Gem {
+    fn from(stored: StoredGem) -> Self {
+        stored.split().0
+    }
+}
+
+#[derive(Deserialize)]
+struct Envelope<'a> {
+    schema_version: u32,
+    #[serde(borrow)]
+    gems: Vec<&'a RawValue>,
+}
+
+#[derive(Serialize)]
+struct EnvelopeRef<'a> {
+    schema_version: u32,
+    gems: &'a [Gem],
+}
+
+//The first line of a versioned JSON Lines deck. A gem line has sides, so it never parses as one.
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct JsonlHeader {
+    schema_version: u32,
+}
+
+fn check_version(schema_version: u32) -> Result<()> {
+    if schema_version > SCHEMA_VERSION {
+        return Err(LangwitchError::SchemaVersion(schema_version));
+    }
+    Ok(())
+}
+
+/// Why a deck, or one gem in it, couldn't be read, and where.
+#[derive(Debug, PartialEq, Clone)]
+pub struct DeckError {
+    /// Which gem, counting from 0 in file order. None if the problem isn't in any one gem.
+    pub gem: Option<usize>,
+    /// Where in the gem (or the file, without a gem) it went wrong, like `sides.0` or `unknown_facets.cat.review_date`. Empty if it's the gem as a whole.
+    pub field: String,
+    /// Line and column in the file, counting from 1. Columns count bytes.
+    pub line: usize,
+    pub column: usize,
+    pub reason: String,
+}
+
+impl fmt::Display for DeckError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.gem {
+            Some(gem) => write!(f, "gem {}", gem)?,
+            None => write!(f, "the deck")?,
+        }
+        if !self.field.is_empty() {
+            write!(f, ", field {}", self.field)?;
+        }
+        write!(f, " (line {}, column {}): {}", self.line, self.column, self.reason)
+    }
+}
+
+//The line and column of byte `offset` in `text`.
+fn position(text: &[u8], offset: usize) -> (usize, usize) {
+    let before = &text[..offset];
+    let line_start = before.iter().rposition(|byte| *byte == b'\n').map_or(0, |newline| newline + 1);
+    (before.iter().filter(|byte| **byte == b'\n').count() + 1, offset - line_start + 1)
+}
+
+impl DeckError {
+    //`error` came from parsing text that starts at `start` in the file.
+    fn from_json(error: &serde_json::Error, gem: Option<usize>, field: String, start: (usize, usize)) -> DeckError {
+        //serde_json puts the position at the end of its message, and it's relative to the slice, not the file.
+        let message = error.to_string();
+        let reason = message.strip_suffix(&format!(" at line {} column {}", error.line(), error.column())).unwrap_or(&message).to_string();
+        let (line, column) = match error.line() {
+            1 => (start.0, start.1 + error.column().saturating_sub(1)),
+            line => (start.0 + line - 1, error.column()),
+        };
+        DeckError { gem, field, line, column, reason }
+    }
+
+    //Something wrong with the file around the gems. A syntax error inside the gems array is put down to the gem it was found in.
+    fn in_file(error: &serde_path_to_error::Error<serde_json::Error>) -> DeckError {
+        let gem = error.path().iter().find_map(|segment| match segment {
+            serde_path_to_error::Segment::Seq { index } => Some(*index),
+            _ => None,
+        });
+        let field = if gem.is_some() { String::new() } else { error.path().to_string() };
+        DeckError::from_json(error.inner(), gem, field, (1, 1))
+    }
+}
+
+//Parses one gem, gem number `index`, from `raw`, which starts at `start` in the file.
+fn read_gem(raw: &str, index: usize, start: (usize, usize)) -> std::result::Result<StoredGem, DeckError> {
+    let mut deserializer = serde_json::Deserializer::from_str(raw);
+    let stored = serde_path_to_error::deserialize(&mut deserializer).map_err(|e| {
+        let field = e.path().to_string();
+        DeckError::from_json(e.inner(), Some(index), if field == "." { String::new() } else { field }, start)
+    })?;
+    deserializer.end().map_err(|e| DeckError::from_json(&e, Some(index), String::new(), start))?;
+    Ok(stored)
+}
+
+//Parses the whole of `text` as a `T`.
+fn read_whole<'a, T: Deserialize<'a>>(text: &'a str) -> std::result::Result<T, DeckError> {
+    let mut deserializer = serde_json::Deserializer::from_str(text);
+    let value = serde_path_to_error::deserialize(&mut deserializer).map_err(|e| DeckError::in_file(&e))?;
+    deserializer.end().map_err(|e| DeckError::from_json(&e, None, String::new(), (1, 1)))?;
+    Ok(value)
+}
+
+/// A deck file as it was read.
+#[derive(Debug, PartialEq, Clone)]
+pub struct DeckFile {
+    /// The version the file was in.
+    pub schema_version: u32,
+    pub gems: Vec<Gem>,
+    /// Scheduling that a version 0 deck kept inside its gems, by facet.
+    pub migrated: HashMap<String, Facet>,
+    /// Gems a lenient read left out, and why.
+    pub skipped: Vec<DeckError>,
+}
+
+impl DeckFile {
+    fn empty(schema_version: u32) -> DeckFile {
+        DeckFile { schema_version, gems: Vec::new(), migrated: HashMap::new(), skipped: Vec::new() }
+    }
+
+    fn push(&mut self, stored: StoredGem) {
+        if stored.is_embedded() {
+            self.schema_version = 0;
+        }
+        let (gem, states) = stored.split();
+        self.gems.push(gem);
+        //A facet in several gems keeps the copy that was seen last.
+        for state in states {
+            let newer = self.migrated.get(&state.name).is_none_or(|existing| state.last_seen_date > existing.last_seen_date);
+            if newer {
+                self.migrated.insert(state.name.clone(), state);
+            }
+        }
+    }
+
+    fn push_or_skip(&mut self, gem: std::result::Result<StoredGem, DeckError>, lenient: bool) -> Result<()> {
+        match gem {
+            Ok(stored) => self.push(stored),
+            Err(e) if lenient => self.skipped.push(e),
+            Err(e) => return Err(e.into()),
+        }
+        Ok(())
+    }
+}
+
+/// Reads a JSON deck in any version up to [`SCHEMA_VERSION`]. A gem that doesn't fit fails the whole read with a [`DeckError`] saying which gem and field it was.
+pub fn parse_deck(json: &[u8]) -> Result<DeckFile> {
+    read_deck(json, false)
+}
+
+/// Same as [`parse_deck`], but gems that don't fit are left out and listed in [`DeckFile::skipped`]. A file that isn't JSON, or is broken outside any one gem, still fails.
+pub fn parse_deck_leniently(json: &[u8]) -> Result<DeckFile> {
+    read_deck(json, true)
+}
+
+pub(crate) fn read_deck(json: &[u8], lenient: bool) -> Result<DeckFile> {
+    let text = std::str::from_utf8(json).map_err(|e| {
+        let (line, column) = position(json, e.valid_up_to());
+        DeckError { gem: None, field: String::new(), line, column, reason: "the file isn't valid UTF-8".to_string() }
+    })?;
+    let versioned = text.trim_start().starts_with('{');
+    let (schema_version, raw_gems) = if versioned {
+        let envelope: Envelope = read_whole(text)?;
+        check_version(envelope.schema_version)?;
+        (envelope.schema_version, envelope.gems)
+    } else {
+        (1, read_whole(text)?)
+    };
+    let mut deck = DeckFile::empty(schema_version);
+    deck.gems.reserve(raw_gems.len());
+    for (index, raw) in raw_gems.into_iter().enumerate() {
+        //Borrowed straight out of `text`, so where it starts in the file is where it starts in memory.
+        let start = position(json, raw.get().as_ptr() as usize - text.as_ptr() as usize);
+        deck.push_or_skip(read_gem(raw.get(), index, start), lenient)?;
+    }
+    Ok(deck)
+}
+
+/// Writes `gems` as a current-version JSON deck.
+pub fn write_deck<W: Write>(writer: W, gems: &[Gem]) -> Result<()> {
+    serde_json::to_writer(writer, &EnvelopeRef { schema_version: SCHEMA_VERSION, gems })?;
+    Ok(())
+}
+
+/// The line a current-version JSON Lines deck starts with, without the newline.
+pub fn jsonl_header() -> Result<String> {
+    Ok(serde_json::to_string(&JsonlHeader { schema_version: SCHEMA_VERSION })?)
+}
+
+/// A JSON Lines deck being read a line at a time. Lines before the first gem may be a version header, and blank lines are skipped.
+pub(crate) struct JsonlDeck {
+    deck: DeckFile,
+    lenient: bool,
+    lines_read: usize,
+}
+
+impl JsonlDeck {
+    pub(crate) fn new(lenient: bool) -> JsonlDeck {
+        JsonlDeck { deck: DeckFile::empty(1), lenient, lines_read: 0 }
+    }
+
+    pub(crate) fn read_line(&mut self, line: &str) -> Result<()> {
+        self.lines_read += 1;
+        if line.trim().is_empty() {
+            return Ok(());
+        }
+        let index = self.deck.gems.len() + self.deck.skipped.len();
+        if index == 0 {
+            if let Ok(header) = serde_json::from_str::<JsonlHeader>(line) {
+                check_version(header.schema_version)?;
+                self.deck.schema_version = header.schema_version;
+                return Ok(());
+            }
+        }
+        self.deck.push_or_skip(read_gem(line, index, (self.lines_read, 1)), self.lenient)
+    }
+
+    pub(crate) fn finish(self) -> DeckFile {
+        self.deck
+    }
+}
+
+impl GemCollection {
+    /// A collection of the gems in `deck`, holding any scheduling it migrated in `facet_states`.
+    pub fn from_deck(deck: DeckFile) -> GemCollection {
+        let mut gem_collection = GemCollection::from_gems(deck.gems);
+        gem_collection.unsaved_migration = !deck.migrated.is_empty();
+        gem_collection.facet_states = deck.migrated;
+        gem_collection
+    }
+
+    /// Same as [`GemCollection::set_progress`] for a freshly read deck, keeping the scheduling a version 0 deck brought with it for any facet `progress` has nothing on.
+    pub fn set_progress_over_deck(&mut self, mut progress: Progress) {
+        for (name, state) in std::mem::take(&mut self.facet_states) {
+            progress.facets.entry(name).or_insert(state);
+        }
+        self.set_progress(progress);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use super::*;
+
+    fn names(facets: &[&str]) -> HashSet<String> {
+        facets.iter().map(|facet| facet.to_string()).collect()
+    }
+
+    fn reviewed(name: &str, seconds: u64) -> Facet {
+        Facet::new(name, UNIX_EPOCH + Duration::from_secs(seconds))
+    }
+
+    fn deck_error(json: &str) -> DeckError {
+        match parse_deck(json.as_bytes()) {
+            Err(LangwitchError::Deck(e)) => e,
+            other => panic!("expected a deck error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn version_0_moves_scheduling_out_of_the_gems() {
+        let unreviewed = Facet { review_date: None, last_seen_date: None, ..reviewed("der", 0) };
+        let json = serde_json::json!([
+            {"sides": {"0": "der hund", "1": "the dog"}, "unknown_facets": {"der": unreviewed, "hund": reviewed("hund", 10)}},
+            {"sides": {"0": "der hund schläft"}, "unknown_facets": {"hund": reviewed("hund", 20), "schläft": reviewed("schläft", 5)}},
+        ]);
+        let deck = parse_deck(json.to_string().as_bytes()).unwrap();
+        assert_eq!(deck.schema_version, 0);
+        assert_eq!(deck.gems[0].unknown_facets, names(&["der", "hund"]));
+        assert_eq!(deck.gems[1].sides, HashMap::from([(0, "der hund schläft".to_string())]));
+        assert_eq!(deck.migrated, HashMap::from([("hund".to_string(), reviewed("hund", 20)), ("schläft".to_string(), reviewed("schläft", 5))]));
+        let mut gem_collection = GemCollection::from_deck(deck);
+        assert!(gem_collection.unsaved_migration);
+        let progress = Progress { known_facets: HashSet::new(), facets: HashMap::from([("hund".to_string(), reviewed("hund", 99))]) };
+        gem_collection.set_progress_over_deck(progress);
+        assert_eq!(gem_collection.progress().facets, HashMap::from([("hund".to_string(), reviewed("hund", 99)), ("schläft".to_string(), reviewed("schläft", 5))]));
+    }
+
+    #[test]
+    fn version_1_is_a_bare_array_of_names() {
+        let deck = parse_deck(br#"[{"sides": ["der hund", "the dog"], "unknown_facets": ["der", "hund"]}, {"sides": "die katze"}]"#).unwrap();
+        assert_eq!(deck.schema_version, 1);
+        assert_eq!(deck.gems[0].sides, HashMap::from([(0, "der hund".to_string()), (1, "the dog".to_string())]));
+        assert_eq!(deck.gems[1].unknown_facets, tokenize("die katze"));
+        assert!(deck.migrated.is_empty());
+    }
+
+    #[test]
+    fn version_2_round_trips() {
+        let deck = parse_deck(br#"{"schema_version": 2, "gems": [{"id": "dog", "sides": {"0": "der hund"}, "unknown_facets": "der hund"}]}"#).unwrap();
+        assert_eq!(deck.schema_version, 2);
+        assert_eq!(deck.gems[0].id.as_deref(), Some("dog"));
+        let mut written = Vec::new();
+        write_deck(&mut written, &deck.gems).unwrap();
+        assert_eq!(parse_deck(&written).unwrap(), deck);
+    }
+
+    #[test]
+    fn a_newer_version_is_refused() {
+        assert!(matches!(parse_deck(br#"{"schema_version": 3, "gems": []}"#), Err(LangwitchError::SchemaVersion(3))));
+        let mut jsonl = JsonlDeck::new(false);
+        assert!(matches!(jsonl.read_line(r#"{"schema_version": 3}"#), Err(LangwitchError::SchemaVersion(3))));
+    }
+
+    #[test]
+    fn a_bad_gem_says_where_it_is_in_the_file() {
+        let error = deck_error("{\"schema_version\": 2, \"gems\": [\n  {\"sides\": \"der hund\"},\n  {\"sides\": \"die katze\", \"unknown_facets\": 7}\n]}");
+        assert_eq!(error.gem, Some(1));
+        assert_eq!(error.field, "unknown_facets");
+        assert_eq!((error.line, error.column), (3, 44));
+    }
+
+    #[test]
+    fn a_syntax_error_is_put_down_to_its_gem() {
+        let error = deck_error("[\n  {\"sides\": \"der hund\"},\n  {\"sides\": \"die katze\",,}\n]");
+        assert_eq!(error.gem, Some(1));
+        assert_eq!((error.line, error.column), (3, 25));
+    }
+
+    #[test]
+    fn a_lenient_read_skips_bad_gems_and_keeps_the_rest() {
+        let deck = parse_deck_leniently(b"[{\"sides\": \"der hund\"}, {\"sides\": 5}, {\"sides\": \"die katze\"}]").unwrap();
+        assert_eq!(deck.gems.len(), 2);
+        assert_eq!(deck.skipped.len(), 1);
+        assert_eq!((deck.skipped[0].gem, deck.skipped[0].field.as_str(), deck.skipped[0].line), (Some(1), "sides", 1));
+    }
+
+    #[test]
+    fn jsonl_lines_count_from_the_header() {
+        let mut jsonl = JsonlDeck::new(false);
+        jsonl.read_line(&jsonl_header().unwrap()).unwrap();
+        jsonl.read_line("").unwrap();
+        jsonl.read_line(r#"{"sides": "der hund"}"#).unwrap();
+        let error = match jsonl.read_line(r#"{"sides": {"zero": "die katze"}}"#) {
+            Err(LangwitchError::Deck(e)) => e,
+            other => panic!("expected a deck error, got {:?}", other),
+        };
+        assert_eq!((error.gem, error.line), (Some(1), 4));
+        assert_eq!(jsonl.finish().schema_version, 2);
+    }
+}
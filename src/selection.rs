@@ -0,0 +1,271 @@
+//How the ordering decides which gem to teach next. Each ordering step hands a SelectionStrategy the gems in the easiest non-empty bucket (the n+1 candidates), plus how often each facet turns up in the buckets after that, and the strategy picks the candidate whose facets get learned. SelectionKind is the config-friendly way of choosing one.
+
+use std::collections::{HashMap, HashSet};
+
+use rand::{rngs::StdRng, Rng};
+use serde::{Serialize, Deserialize};
+
+use crate::{
+    collection::GemCollection,
+    gem::{GemId, InternedGem},
+    interner::FacetId,
+};
+
+pub trait SelectionStrategy {
+    /// Returns the unknown facets of the chosen candidate. `lookahead_frequencies` counts how many gems in the next few buckets up (see `GemCollection::lookahead`) contain each facet.
+    /// Any randomness, including breaking ties, should come from `rng` so the same seed always gives the same curriculum.
+    fn choose(&mut self, gem_collection: &GemCollection, candidates: &HashSet<GemId>, lookahead_frequencies: &HashMap<FacetId, usize>, rng: &mut StdRng) -> HashSet<FacetId>;
+}
+
+//Candidates in id order, so nothing depends on how the HashSet happens to iterate.
+fn candidate_gems<'a>(gem_collection: &'a GemCollection, candidates: &HashSet<GemId>) -> Vec<&'a InternedGem> {
+    let mut gem_ids: Vec<GemId> = candidates.iter().copied().collect();
+    gem_ids.sort_unstable();
+    gem_ids
+        .into_iter()
+        .filter_map(|gem_id| gem_collection.get(gem_id))
+        .filter(|gem| !gem.unknown_facets.is_empty())
+        .collect()
+}
+
+//Keeps the highest-scoring gem seen so far. When several gems tie, each of them is equally likely to be the one kept (reservoir sampling over the ties).
+struct TopGem<'a> {
+    gem: Option<&'a InternedGem>,
+    max_score: f64,
+    ties: u32,
+}
+
+impl<'a> TopGem<'a> {
+    fn new() -> TopGem<'a> {
+        TopGem { gem: None, max_score: f64::NEG_INFINITY, ties: 0 }
+    }
+
+    fn offer(&mut self, gem: &'a InternedGem, score: f64, rng: &mut StdRng) {
+        if score > self.max_score {
+            self.gem = Some(gem);
+            self.max_score = score;
+            self.ties = 1;
+        } else if score == self.max_score {
+            self.ties += 1;
+            if rng.gen_range(0..self.ties) == 0 {
+                self.gem = Some(gem);
+            }
+        }
+    }
+
+    fn facets(&self) -> HashSet<FacetId> {
+        self.gem.map(|gem| gem.unknown_facets.clone()).unwrap_or_default()
+    }
+}
+
+//The candidate with the highest score, or nothing if there are no candidates with unknown facets.
+fn best_gem_facets<F: FnMut(&InternedGem) -> f64>(gem_collection: &GemCollection, candidates: &HashSet<GemId>, rng: &mut StdRng, mut score: F) -> HashSet<FacetId> {
+    let mut top_gem = TopGem::new();
+    for gem in candidate_gems(gem_collection, candidates) {
+        top_gem.offer(gem, score(gem), rng);
+    }
+    top_gem.facets()
+}
+
+/// The original picker: the candidate whose facets show up most often (on average) among the gems a few steps harder, falling back to frequency across the whole deck.
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub struct LookaheadFrequency;
+
+/// What "easiest next gem" means to [`LookaheadFrequency`]. The defaults weigh gems exactly as the original ordering did: by the average lookahead frequency of their facets and nothing else.
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct ScoringConfig {
+    /// Multiplies each facet's frequency in the lookahead buckets.
+    pub frequency_weight: f64,
+    /// Subtracted from a gem's weight for every character of its first side, so shorter sentences win ties (and more, if it's large).
+    pub length_penalty: f64,
+    /// Multiplies each facet's frequency across the whole deck. At 0 the global frequencies are only a fallback for when nothing scores in the lookahead.
+    pub global_frequency_weight: f64,
+    /// Extra weight for particular facets, e.g. to pull a word the learner needs soon forward. Facets that aren't listed get nothing extra.
+    pub facet_priors: HashMap<String, f64>,
+}
+
+impl Default for ScoringConfig {
+    fn default() -> Self {
+        ScoringConfig {
+            frequency_weight: 1.0,
+            length_penalty: 0.0,
+            global_frequency_weight: 0.0,
+            facet_priors: HashMap::new(),
+        }
+    }
+}
+
+impl LookaheadFrequency {
+    fn heaviest_gem_facets(&self, gem_collection: &GemCollection, gem_indices_for_n1: &HashSet<GemId>, frequency_hashmap: &HashMap<FacetId, usize>, rng: &mut StdRng) -> HashSet<FacetId> {
+        //Here, we're essentially just going: ok, so I have all of these gem indices. And I have a map that tells me that so-and-so facet occurred 5 or 10 or however many times. Now I just need to look at each gem, and see how often each of its facets occurs in the map. Then I just average out that frequency (plus whatever else the scoring config adds in), call it 'weight', and get the gem with the highest weight.
+        let scoring = &gem_collection.scoring;
+        let text_side = gem_collection.side_roles.text_side();
+        let mut top_gem = TopGem::new();
+        for gem in candidate_gems(gem_collection, gem_indices_for_n1) {
+            let mut weight: f64 = 0.0;
+            //A gem none of whose facets are in the map (or have a prior) has nothing to go on, so it's left for the fallback.
+            let mut scored = false;
+            for facet in gem.unknown_facets.iter() {
+                //There's a possibility the facet might not be in the hashmap, so we need to check for that:
+                if let Some(facet_weight) = frequency_hashmap.get(facet) {
+                    weight += scoring.frequency_weight * *facet_weight as f64;
+                    scored = true;
+                }
+                //Priors are keyed by name, so they're only looked up when there are any.
+                if !scoring.facet_priors.is_empty() {
+                    if let Some(prior) = scoring.facet_priors.get(gem_collection.facet_name(*facet)) {
+                        weight += prior;
+                        scored = true;
+                    }
+                }
+                if let Some(global_weight) = gem_collection.total_frequency_list.get(facet) {
+                    weight += scoring.global_frequency_weight * *global_weight as f64;
+                }
+            }
+            if !scored {
+                continue;
+            }
+            weight /= gem.unknown_facets.len() as f64;
+            weight *= gem.weight;
+            if scoring.length_penalty != 0.0 {
+                let length = gem.sides.get(&text_side).map_or(0, |side| side.chars().count());
+                weight -= scoring.length_penalty * length as f64;
+            }
+            top_gem.offer(gem, weight, rng);
+        }
+        top_gem.facets()
+    }
+}
+
+impl SelectionStrategy for LookaheadFrequency {
+    fn choose(&mut self, gem_collection: &GemCollection, candidates: &HashSet<GemId>, lookahead_frequencies: &HashMap<FacetId, usize>, rng: &mut StdRng) -> HashSet<FacetId> {
+        let mut top_gem_facets = self.heaviest_gem_facets(gem_collection, candidates, lookahead_frequencies, rng);
+        if top_gem_facets.is_empty() {
+            //Then I can simply try again, but with the total frequency list
+            top_gem_facets = self.heaviest_gem_facets(gem_collection, candidates, &gem_collection.total_frequency_list, rng);
+        }
+        if top_gem_facets.is_empty() {
+            //Every candidate weighed nothing even globally, so just take whichever one comes first rather than going round in circles.
+            if let Some(gem) = candidate_gems(gem_collection, candidates).first() {
+                top_gem_facets = gem.unknown_facets.clone();
+            }
+        }
+        top_gem_facets
+    }
+}
+
+/// The candidate whose facets are most frequent across the whole deck, ignoring what's one step ahead.
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub struct PureFrequency;
+
+impl SelectionStrategy for PureFrequency {
+    fn choose(&mut self, gem_collection: &GemCollection, candidates: &HashSet<GemId>, _lookahead_frequencies: &HashMap<FacetId, usize>, rng: &mut StdRng) -> HashSet<FacetId> {
+        best_gem_facets(gem_collection, candidates, rng, |gem| {
+            let total: usize = gem.unknown_facets
+                .iter()
+                .map(|facet| gem_collection.total_frequency_list.get(facet).copied().unwrap_or(0))
+                .sum();
+            total as f64 / gem.unknown_facets.len() as f64
+        })
+    }
+}
+
+/// Any candidate at all. Still respects the i+1 buckets, so the curriculum stays learnable, just not optimised.
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub struct Random;
+
+impl SelectionStrategy for Random {
+    fn choose(&mut self, gem_collection: &GemCollection, candidates: &HashSet<GemId>, _lookahead_frequencies: &HashMap<FacetId, usize>, rng: &mut StdRng) -> HashSet<FacetId> {
+        //Every candidate ties, so the tie-breaking alone picks one uniformly.
+        best_gem_facets(gem_collection, candidates, rng, |_| 0.0)
+    }
+}
+
+/// The candidate whose new facets appear in the most gems that are still locked, per facet introduced, so each introduction moves as much of the deck forward as possible.
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub struct CoverageGreedy;
+
+impl SelectionStrategy for CoverageGreedy {
+    fn choose(&mut self, gem_collection: &GemCollection, candidates: &HashSet<GemId>, _lookahead_frequencies: &HashMap<FacetId, usize>, rng: &mut StdRng) -> HashSet<FacetId> {
+        best_gem_facets(gem_collection, candidates, rng, |gem| {
+            let mut touched: HashSet<GemId> = HashSet::new();
+            for facet in gem.unknown_facets.iter() {
+                if let Some(gem_indices) = gem_collection.gems_by_facet_index.get(facet) {
+                    touched.extend(gem_indices.iter().copied());
+                }
+            }
+            touched.len() as f64 / gem.unknown_facets.len() as f64
+        })
+    }
+}
+
+/// The candidate with the shortest text side, so early cards are quick to read.
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub struct ShortestSentenceFirst;
+
+impl SelectionStrategy for ShortestSentenceFirst {
+    fn choose(&mut self, gem_collection: &GemCollection, candidates: &HashSet<GemId>, _lookahead_frequencies: &HashMap<FacetId, usize>, rng: &mut StdRng) -> HashSet<FacetId> {
+        let text_side = gem_collection.side_roles.text_side();
+        best_gem_facets(gem_collection, candidates, rng, |gem| {
+            let length = gem.sides.get(&text_side).map_or(0, |side| side.chars().count());
+            -(length as f64)
+        })
+    }
+}
+
+/// Heads for a set of target facets instead of following global frequency: a candidate teaching a target wins outright, and otherwise the candidate whose facets do the most to bring target-holding gems within reach (each such gem counting more the fewer unknowns it has left) is picked.
+/// Used by [`GemCollection::goal_order`]. It needs its targets, so it isn't one of the configurable [`SelectionKind`]s.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct GoalDirected {
+    pub targets: HashSet<FacetId>,
+}
+
+//Teaching a target is always worth more than any amount of getting closer to one.
+const TARGET_HIT_SCORE: f64 = 1_000_000.0;
+
+impl SelectionStrategy for GoalDirected {
+    fn choose(&mut self, gem_collection: &GemCollection, candidates: &HashSet<GemId>, _lookahead_frequencies: &HashMap<FacetId, usize>, rng: &mut StdRng) -> HashSet<FacetId> {
+        let mut target_gem_weights: HashMap<GemId, f64> = HashMap::new();
+        for target in self.targets.iter().filter(|target| !gem_collection.known_facets.contains(target)) {
+            for gem_id in gem_collection.gems_by_facet_index.get(target).into_iter().flatten() {
+                let unknown = gem_collection.gems[gem_id.0].unknown_facets.len();
+                if unknown > 0 {
+                    target_gem_weights.insert(*gem_id, 1.0 / unknown as f64);
+                }
+            }
+        }
+        best_gem_facets(gem_collection, candidates, rng, |gem| {
+            let hits = gem.unknown_facets.iter().filter(|facet| self.targets.contains(facet)).count();
+            let progress: f64 = gem.unknown_facets
+                .iter()
+                .flat_map(|facet| gem_collection.gems_by_facet_index.get(facet).into_iter().flatten())
+                .filter_map(|gem_id| target_gem_weights.get(gem_id))
+                .sum();
+            hits as f64 * TARGET_HIT_SCORE + progress / gem.unknown_facets.len() as f64
+        })
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum SelectionKind {
+    #[default]
+    LookaheadFrequency,
+    PureFrequency,
+    Random,
+    CoverageGreedy,
+    ShortestSentenceFirst,
+}
+
+impl SelectionStrategy for SelectionKind {
+    fn choose(&mut self, gem_collection: &GemCollection, candidates: &HashSet<GemId>, lookahead_frequencies: &HashMap<FacetId, usize>, rng: &mut StdRng) -> HashSet<FacetId> {
+        match self {
+            SelectionKind::LookaheadFrequency => LookaheadFrequency.choose(gem_collection, candidates, lookahead_frequencies, rng),
+            SelectionKind::PureFrequency => PureFrequency.choose(gem_collection, candidates, lookahead_frequencies, rng),
+            SelectionKind::Random => Random.choose(gem_collection, candidates, lookahead_frequencies, rng),
+            SelectionKind::CoverageGreedy => CoverageGreedy.choose(gem_collection, candidates, lookahead_frequencies, rng),
+            SelectionKind::ShortestSentenceFirst => ShortestSentenceFirst.choose(gem_collection, candidates, lookahead_frequencies, rng),
+        }
+    }
+}
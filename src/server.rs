@@ -0,0 +1,224 @@
+//`langwitch serve`: the review engine over HTTP, so a web or mobile front end can be built on it without linking any Rust. Everything goes back and forth as JSON:
+//GET /next-gem hands out the card to study (204 once nothing is left), and keeps handing out the same one until it's graded.
+//POST /grade takes {"gem": 3, "grades": {"cat": 1.0, "sat": 0.0}}, optionally with "grade" for every facet it doesn't list and "hints" for how many hints each facet needed, and answers with what the grading did.
+//GET /stats is the same summary the TUI's stats pane shows.
+//POST /import adds gems: a JSON array in the deck format, or (with any other content type) plain text to mine. New gems go into the deck file as well as the running session.
+//GET /live opens a WebSocket for a browser UI that wants to feel instant. It sends {"type": "stats", ...} and then the card to study ({"type": "card", ...}, or {"type": "done"}). Each grade sent up (the same JSON /grade takes) is answered with {"type": "graded", ...} and the next card straight away, and fresh stats are pushed whenever they change, whoever changed them.
+//There's one learner and one session, so the engine sits behind a single lock. Progress is saved after every grade, like `review` does, and shared knowledge when the server is stopped with Ctrl-C.
+
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use axum::{
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        State,
+    },
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use serde::Serialize;
+use tokio::sync::broadcast;
+
+use langwitch::{schema::parse_deck, wire::{CardJson, GradeRequest, GradeResponse}, Config, LangwitchError};
+
+use crate::study::{ImportResponse, Stats, Study};
+
+pub struct Server {
+    pub study: Study,
+    //Fresh stats for the live sessions, sent whenever grading or importing changes them.
+    pub stats_updates: broadcast::Sender<Stats>,
+}
+
+pub type Shared = Arc<Mutex<Server>>;
+
+//A request that failed, sent back as {"error": "..."}.
+struct ApiError(StatusCode, String);
+
+impl From<LangwitchError> for ApiError {
+    fn from(e: LangwitchError) -> Self {
+        let status = match e {
+            LangwitchError::NotCurrentCard(_) => StatusCode::CONFLICT,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        ApiError(status, e.to_string())
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.0, Json(serde_json::json!({ "error": self.1 }))).into_response()
+    }
+}
+
+//A handler that panicked mid-request can't have left the engine any worse than a crash would, so carry on with it.
+pub fn lock(state: &Shared) -> MutexGuard<'_, Server> {
+    state.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+impl Server {
+    pub fn grade(&mut self, request: GradeRequest) -> langwitch::Result<GradeResponse> {
+        let result = self.study.grade(request)?;
+        self.publish_stats();
+        Ok(result)
+    }
+
+    //Imports through `import` (one of Study's import methods), telling the live sessions if anything was added.
+    pub fn import(&mut self, import: impl FnOnce(&mut Study) -> langwitch::Result<ImportResponse>) -> langwitch::Result<ImportResponse> {
+        let imported = import(&mut self.study)?;
+        if imported.added > 0 {
+            self.publish_stats();
+        }
+        Ok(imported)
+    }
+
+    //Tells every live session the numbers changed. Nobody listening isn't an error.
+    fn publish_stats(&self) {
+        let _ = self.stats_updates.send(self.study.stats());
+    }
+}
+
+async fn next_gem(State(state): State<Shared>) -> Result<Response, ApiError> {
+    match lock(&state).study.current_card()? {
+        Some(card) => Ok(Json(card).into_response()),
+        None => Ok(StatusCode::NO_CONTENT.into_response()),
+    }
+}
+
+async fn grade(State(state): State<Shared>, Json(request): Json<GradeRequest>) -> Result<Json<GradeResponse>, ApiError> {
+    Ok(Json(lock(&state).grade(request)?))
+}
+
+async fn stats(State(state): State<Shared>) -> Json<Stats> {
+    Json(lock(&state).study.stats())
+}
+
+async fn import(State(state): State<Shared>, headers: HeaderMap, body: String) -> Result<Json<ImportResponse>, ApiError> {
+    let is_json = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|content_type| content_type.to_str().ok())
+        .is_some_and(|content_type| content_type.starts_with("application/json"));
+    let imported = if is_json {
+        let gems = parse_deck(body.as_bytes()).map_err(|e| ApiError(StatusCode::BAD_REQUEST, e.to_string()))?.gems;
+        lock(&state).import(|study| study.import(gems))?
+    } else {
+        lock(&state).import(|study| study.import_text(&body))?
+    };
+    Ok(Json(imported))
+}
+
+//What a live session sends. Every message says what it is in "type".
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum LiveMessage {
+    Card { card: CardJson },
+    Done,
+    Graded { result: GradeResponse },
+    Stats { stats: Stats },
+    Error { error: String },
+}
+
+//The card to study next, for a live session.
+fn card_message(server: &mut Server) -> LiveMessage {
+    match server.study.current_card() {
+        Ok(Some(card)) => LiveMessage::Card { card },
+        Ok(None) => LiveMessage::Done,
+        Err(e) => LiveMessage::Error { error: e.to_string() },
+    }
+}
+
+//False once the socket has gone away.
+async fn send_all(socket: &mut WebSocket, messages: Vec<LiveMessage>) -> bool {
+    for message in messages {
+        let text = match serde_json::to_string(&message) {
+            Ok(text) => text,
+            Err(_) => continue,
+        };
+        if socket.send(Message::Text(text.into())).await.is_err() {
+            return false;
+        }
+    }
+    true
+}
+
+async fn live(State(state): State<Shared>, upgrade: WebSocketUpgrade) -> Response {
+    upgrade.on_upgrade(move |socket| live_session(socket, state))
+}
+
+async fn live_session(mut socket: WebSocket, state: Shared) {
+    let (mut stats_updates, greeting) = {
+        let mut server = lock(&state);
+        (server.stats_updates.subscribe(), vec![LiveMessage::Stats { stats: server.study.stats() }, card_message(&mut server)])
+    };
+    if !send_all(&mut socket, greeting).await {
+        return;
+    }
+    loop {
+        let replies = tokio::select! {
+            incoming = socket.recv() => match incoming {
+                Some(Ok(Message::Text(text))) => match serde_json::from_str::<GradeRequest>(&text) {
+                    Ok(request) => {
+                        let mut server = lock(&state);
+                        match server.grade(request) {
+                            Ok(result) => vec![LiveMessage::Graded { result }, card_message(&mut server)],
+                            Err(e) => vec![LiveMessage::Error { error: e.to_string() }],
+                        }
+                    }
+                    Err(e) => vec![LiveMessage::Error { error: e.to_string() }],
+                },
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => return,
+                //Pings are answered by axum itself.
+                Some(Ok(_)) => continue,
+            },
+            update = stats_updates.recv() => match update {
+                Ok(stats) => vec![LiveMessage::Stats { stats }],
+                //Only the latest numbers matter, so ones that were missed can be skipped.
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => return,
+            },
+        };
+        if !send_all(&mut socket, replies).await {
+            return;
+        }
+    }
+}
+
+/// Loads the session the HTTP API (and the gRPC service, if it's on) work on.
+pub fn load(config: Config) -> langwitch::Result<Shared> {
+    let (stats_updates, _) = broadcast::channel(16);
+    Ok(Arc::new(Mutex::new(Server { study: Study::load(config)?, stats_updates })))
+}
+
+/// Serves the HTTP API on `address` until Ctrl-C.
+pub async fn serve(state: Shared, address: &str) -> langwitch::Result<()> {
+    let app = Router::new()
+        .route("/next-gem", get(next_gem))
+        .route("/grade", post(grade))
+        .route("/stats", get(stats))
+        .route("/import", post(import))
+        .route("/live", get(live))
+        .with_state(state);
+    let listener = tokio::net::TcpListener::bind(address).await?;
+    println!("Serving on http://{}", listener.local_addr()?);
+    axum::serve(listener, app)
+        .with_graceful_shutdown(async {
+            let _ = tokio::signal::ctrl_c().await;
+        })
+        .await?;
+    Ok(())
+}
+
+/// Saves progress and shared knowledge once serving has stopped.
+pub fn finish(state: &Shared) -> langwitch::Result<()> {
+    let reviewed = lock(state).study.finish()?;
+    println!("Reviewed {} cards", reviewed);
+    Ok(())
+}
+
+/// Serves the engine on `address` until Ctrl-C, then saves progress and shared knowledge.
+pub async fn run(config: Config, address: &str) -> langwitch::Result<()> {
+    let state = load(config)?;
+    serve(state.clone(), address).await?;
+    finish(&state)
+}
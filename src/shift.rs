@@ -0,0 +1,53 @@
+//Moving the whole schedule, for coming back from a holiday. Shifting pushes every facet's review date (and when it was last seen, so the schedulers don't count the break as time it was remembered for) later by the same amount, which keeps everything in the same order. Whatever is still due straight away after that, the pile that built up before the break, can be spread over the coming days, most urgent on the first.
+//The shift goes into progress, not the journal, so a `pull` (which recomputes scheduling from every review) undoes it for the facets it replays. Pull before shifting.
+
+use std::time::{Duration, SystemTime};
+
+use crate::{collection::GemCollection, facet::INITIAL_LIFETIME_IN_HOURS};
+
+/// What [`GemCollection::shift_schedule`] did.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct ScheduleShift {
+    /// Facets whose review date moved.
+    pub shifted: usize,
+    /// Facets still due after the shift that were spread over the coming days.
+    pub spread: usize,
+}
+
+impl GemCollection {
+    /// Pushes every scheduled facet `by` later as of `now`. With `spread_days` above zero, the facets still due at `now` afterwards are dealt out over that many days, the same number each day, the most overdue for their lifetime first.
+    pub fn shift_schedule(&mut self, by: Duration, spread_days: usize, now: SystemTime) -> ScheduleShift {
+        let mut shifted = 0;
+        for state in self.facet_states.values_mut() {
+            if let Some(review_date) = &mut state.review_date {
+                *review_date += by;
+                shifted += 1;
+            }
+            //Never later than now, or the break would count as negative time.
+            if let Some(last_seen_date) = &mut state.last_seen_date {
+                *last_seen_date = (*last_seen_date + by).min(now);
+            }
+        }
+        if spread_days == 0 {
+            return ScheduleShift { shifted, spread: 0 };
+        }
+        let mut pile: Vec<(f64, String)> = self
+            .facet_states
+            .iter()
+            .filter_map(|(name, state)| {
+                let review_date = state.review_date.filter(|review_date| *review_date <= now)?;
+                let lifetime_in_hours = state.lifetime_in_hours.unwrap_or(INITIAL_LIFETIME_IN_HOURS).max(f64::EPSILON);
+                let overdue_in_hours = now.duration_since(review_date).unwrap_or_default().as_secs_f64() / 3600.0;
+                Some((overdue_in_hours / lifetime_in_hours, name.clone()))
+            })
+            .collect();
+        pile.sort_unstable_by(|a, b| b.0.total_cmp(&a.0).then(a.1.cmp(&b.1)));
+        let per_day = pile.len().div_ceil(spread_days).max(1);
+        for (position, (_, name)) in pile.iter().enumerate() {
+            if let Some(state) = self.facet_states.get_mut(name) {
+                state.review_date = Some(now + Duration::from_secs(86_400 * (position / per_day) as u64));
+            }
+        }
+        ScheduleShift { shifted, spread: pile.len() }
+    }
+}
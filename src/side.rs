@@ -0,0 +1,86 @@
+//What each side of a deck's gems holds. Sides are just numbered strings, so the numbers need a key: which one is the target-language sentence the facets come from, which is its translation, which is a path to a recording, and so on.
+//Roles belong to the deck rather than to each gem, since every gem an importer writes has the same layout.
+
+use std::collections::BTreeMap;
+
+use serde::{Serialize, Deserialize};
+
+use crate::{
+    collection::GemCollection,
+    gem::GemId,
+};
+
+#[derive(Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum SideRole {
+    /// The sentence in the language being learned.
+    Text,
+    Translation,
+    /// The text in another script or with its pronunciation spelled out, like pinyin or romaji.
+    Transliteration,
+    /// A path to an audio file of the text.
+    Audio,
+    /// A path to a picture of what the text means.
+    Image,
+    /// Anything else: a source link, a timestamp, a chapter title.
+    Notes,
+}
+
+impl SideRole {
+    /// The role's name, for headings and column names.
+    pub fn label(&self) -> &'static str {
+        match self {
+            SideRole::Text => "Text",
+            SideRole::Translation => "Translation",
+            SideRole::Transliteration => "Transliteration",
+            SideRole::Audio => "Audio",
+            SideRole::Image => "Image",
+            SideRole::Notes => "Notes",
+        }
+    }
+}
+
+/// Which role each side number plays, e.g. {"0": "text", "1": "translation", "2": "audio", "3": "image"}. Sides it doesn't mention are notes.
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone)]
+#[serde(transparent)]
+pub struct SideRoles(pub BTreeMap<usize, SideRole>);
+
+//Side 0 is the text and side 1 its translation, which is how hand-written decks are usually laid out.
+impl Default for SideRoles {
+    fn default() -> Self {
+        SideRoles::new([(0, SideRole::Text), (1, SideRole::Translation)])
+    }
+}
+
+impl SideRoles {
+    pub fn new<I: IntoIterator<Item = (usize, SideRole)>>(roles: I) -> SideRoles {
+        SideRoles(roles.into_iter().collect())
+    }
+
+    pub fn role(&self, side_number: usize) -> SideRole {
+        self.0.get(&side_number).copied().unwrap_or(SideRole::Notes)
+    }
+
+    /// The lowest-numbered side with `role`, if there is one.
+    pub fn side(&self, role: SideRole) -> Option<usize> {
+        self.0.iter().find(|(_, side_role)| **side_role == role).map(|(side_number, _)| *side_number)
+    }
+
+    /// Which side the facets come from: the text side, or side 0 if no side is marked as text.
+    pub fn text_side(&self) -> usize {
+        self.side(SideRole::Text).unwrap_or(0)
+    }
+}
+
+impl GemCollection {
+    /// Gem `gem_id`'s side with `role`, by the collection's [`SideRoles`]. None if the gem doesn't have it.
+    pub fn side(&self, gem_id: GemId, role: SideRole) -> Option<&str> {
+        let side_number = self.side_roles.side(role)?;
+        self.get(gem_id)?.sides.get(&side_number).map(|side| side.as_str())
+    }
+
+    /// Gem `gem_id`'s target-language sentence (see [`SideRoles::text_side`]).
+    pub fn text(&self, gem_id: GemId) -> Option<&str> {
+        self.get(gem_id)?.sides.get(&self.side_roles.text_side()).map(|side| side.as_str())
+    }
+}
@@ -0,0 +1,310 @@
+//Per-facet statistics for `langwitch stats --facets`: how common each facet is in the deck, where it stands in the schedule, and how its reviews have gone. Reviews are counted from the journal and the archive compaction moves them to (see crate::compact), so compacting doesn't change them.
+//The review forecast for `langwitch stats --forecast <DAYS>` counts how many facets fall due on each coming day as things stand. It only knows about facets that have been seen, so it leaves out whatever new facets the coming reviews introduce.
+//True retention for `langwitch stats --retention`: of the reviews where a facet came back after an earlier one, how many were passed, by how long it had been and by how common the facet is. A scheduler aiming for about 90% recall should show about 90% in every interval bucket; a bucket well under that means its intervals are growing too fast.
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    time::{Duration, SystemTime},
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    collection::GemCollection,
+    error::Result,
+    export::progress::csv_field,
+    journal::{sort_chronologically, ReviewEvent, PASSING_GRADE},
+    timestamp::to_millis,
+    tokenize::tokenize_words,
+};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct FacetStats {
+    pub facet: String,
+    /// How many times the facet turns up in the deck's text, normalized like the facets are. Never less than `gems`, so facets the tokenizer doesn't produce on its own (phrases, grammar points) still count once per gem.
+    pub frequency: usize,
+    /// How many gems teach it.
+    pub gems: usize,
+    pub known: bool,
+    pub stage: Option<String>,
+    /// When it's next due, as unix milliseconds.
+    pub review_date: Option<u64>,
+    pub lifetime_in_hours: Option<f64>,
+    /// How many times it's been graded.
+    pub reviews: usize,
+    /// How many times it was failed after having passed before.
+    pub lapses: usize,
+}
+
+/// What [`FacetStats`] are sorted by. Counts sort largest first, names alphabetically, due dates soonest first with facets that aren't scheduled last.
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum FacetSort {
+    #[default]
+    Frequency,
+    Gems,
+    Reviews,
+    Lapses,
+    Name,
+    Due,
+}
+
+impl FacetSort {
+    /// The sort called `name` on the command line, if there is one.
+    pub fn parse(name: &str) -> Option<FacetSort> {
+        match name {
+            "frequency" => Some(FacetSort::Frequency),
+            "gems" => Some(FacetSort::Gems),
+            "reviews" => Some(FacetSort::Reviews),
+            "lapses" => Some(FacetSort::Lapses),
+            "name" => Some(FacetSort::Name),
+            "due" => Some(FacetSort::Due),
+            _ => None,
+        }
+    }
+
+    /// Sorts `stats` this way. Ties are broken by name, so the order is always the same.
+    pub fn sort(&self, stats: &mut [FacetStats]) {
+        stats.sort_by(|a, b| {
+            let by_key = match self {
+                FacetSort::Frequency => b.frequency.cmp(&a.frequency),
+                FacetSort::Gems => b.gems.cmp(&a.gems),
+                FacetSort::Reviews => b.reviews.cmp(&a.reviews),
+                FacetSort::Lapses => b.lapses.cmp(&a.lapses),
+                FacetSort::Name => std::cmp::Ordering::Equal,
+                FacetSort::Due => a.review_date.is_none().cmp(&b.review_date.is_none()).then(a.review_date.cmp(&b.review_date)),
+            };
+            by_key.then_with(|| a.facet.cmp(&b.facet))
+        });
+    }
+}
+
+/// How many times a facet has been reviewed, and how many of those it failed after having passed before.
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Copy, Default)]
+pub struct ReviewCount {
+    pub reviews: usize,
+    pub lapses: usize,
+    /// True once it's been passed, after which every failed grade is a lapse.
+    #[serde(default)]
+    pub passed: bool,
+}
+
+/// Reviews and lapses for every facet graded in `events`, cram drills aside.
+pub fn review_counts(events: &[ReviewEvent]) -> HashMap<String, ReviewCount> {
+    let mut counts = HashMap::new();
+    add_review_counts(&mut counts, events);
+    counts
+}
+
+/// Adds the reviews and lapses in `events` (all newer than whatever `counts` was counted from) to `counts`, cram drills aside.
+pub fn add_review_counts(counts: &mut HashMap<String, ReviewCount>, events: &[ReviewEvent]) {
+    //Replayed in order, since a lapse depends on what came before it.
+    let mut events: Vec<ReviewEvent> = events.iter().filter(|event| !event.cram).cloned().collect();
+    sort_chronologically(&mut events);
+    for event in events.iter() {
+        for (facet, grade) in event.grades.iter() {
+            let count = counts.entry(facet.clone()).or_default();
+            count.reviews += 1;
+            if *grade >= PASSING_GRADE {
+                count.passed = true;
+            } else if count.passed {
+                count.lapses += 1;
+            }
+        }
+    }
+}
+
+const CSV_HEADER: &str = "facet,frequency,gems,known,stage,review_date,lifetime_in_hours,reviews,lapses";
+
+impl GemCollection {
+    /// Statistics for every facet in the deck's gems or with scheduling state, given the reviews in `events` (cram drills aside), sorted by name. Gems are read as they are, so this belongs before indexing strips known facets out of them.
+    pub fn facet_stats(&self, events: &[ReviewEvent]) -> Result<Vec<FacetStats>> {
+        let mut occurrences: HashMap<String, usize> = HashMap::new();
+        for gem_id in self.gem_ids() {
+            for word in self.normalize_facet_names(&tokenize_words(self.text(gem_id).unwrap_or_default()))? {
+                *occurrences.entry(word).or_insert(0) += 1;
+            }
+        }
+        let mut gem_counts: HashMap<String, usize> = HashMap::new();
+        for gem in self.gems.iter() {
+            for facet in self.interner.names(gem.unknown_facets.iter()) {
+                *gem_counts.entry(facet).or_insert(0) += 1;
+            }
+        }
+        let reviews = review_counts(events);
+        let known = self.known_facet_names();
+        let names: BTreeSet<&String> = gem_counts.keys().chain(known.iter()).chain(self.facet_states.keys()).collect();
+        Ok(names
+            .into_iter()
+            .map(|name| {
+                let state = self.facet_states.get(name);
+                let gems = gem_counts.get(name).copied().unwrap_or(0);
+                let ReviewCount { reviews: review_count, lapses, .. } = reviews.get(name).copied().unwrap_or_default();
+                FacetStats {
+                    facet: name.clone(),
+                    frequency: occurrences.get(name).copied().unwrap_or(0).max(gems),
+                    gems,
+                    known: known.contains(name),
+                    stage: state.and_then(|state| state.stage.clone()),
+                    review_date: state.and_then(|state| state.review_date).map(to_millis),
+                    lifetime_in_hours: state.and_then(|state| state.lifetime_in_hours),
+                    reviews: review_count,
+                    lapses,
+                }
+            })
+            .collect())
+    }
+}
+
+/// Writes `stats` to `path` as CSV with a header row, in the order given.
+pub fn write_facet_stats_csv<P: AsRef<Path>>(stats: &[FacetStats], path: P) -> Result<()> {
+    let mut file = BufWriter::new(File::create(path)?);
+    writeln!(file, "{}", CSV_HEADER)?;
+    for record in stats {
+        let fields = [
+            csv_field(Some(&record.facet)),
+            csv_field(Some(record.frequency)),
+            csv_field(Some(record.gems)),
+            csv_field(Some(record.known)),
+            csv_field(record.stage.as_ref()),
+            csv_field(record.review_date),
+            csv_field(record.lifetime_in_hours),
+            csv_field(Some(record.reviews)),
+            csv_field(Some(record.lapses)),
+        ];
+        writeln!(file, "{}", fields.join(","))?;
+    }
+    Ok(file.flush()?)
+}
+
+/// A facet's review, after an earlier one of the same facet.
+#[derive(Debug, PartialEq, Clone)]
+pub struct RepeatReview {
+    pub facet: String,
+    /// Since the facet's previous review.
+    pub elapsed: Duration,
+    /// Whether it was graded at least [`PASSING_GRADE`].
+    pub recalled: bool,
+}
+
+/// Every review in `events` (cram drills aside) that had an earlier review of the same facet to measure from, oldest first.
+pub fn repeat_reviews(events: &[ReviewEvent]) -> Vec<RepeatReview> {
+    let mut events: Vec<ReviewEvent> = events.iter().filter(|event| !event.cram).cloned().collect();
+    sort_chronologically(&mut events);
+    let mut last_reviewed: HashMap<&String, SystemTime> = HashMap::new();
+    let mut reviews = Vec::new();
+    for event in events.iter() {
+        //Sorted so facets graded together come out in the same order every time.
+        let mut grades: Vec<(&String, &f64)> = event.grades.iter().collect();
+        grades.sort_unstable_by_key(|(facet, _)| *facet);
+        for (facet, grade) in grades {
+            if let Some(previous) = last_reviewed.insert(facet, event.timestamp) {
+                reviews.push(RepeatReview {
+                    facet: facet.clone(),
+                    elapsed: event.timestamp.duration_since(previous).unwrap_or(Duration::ZERO),
+                    recalled: *grade >= PASSING_GRADE,
+                });
+            }
+        }
+    }
+    reviews
+}
+
+//The bucket bounds: days since the facet's previous review, and places in the deck's frequency ranking (1 is the most frequent).
+const INTERVAL_BOUNDS_IN_DAYS: [f64; 6] = [0.0, 1.0, 3.0, 7.0, 30.0, 90.0];
+const FREQUENCY_RANK_BOUNDS: [usize; 5] = [1, 101, 1001, 5001, 20001];
+
+/// Reviews passed out of reviews made, over one range: `from` up to but not including `to`, or with no upper end.
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct RetentionBucket {
+    pub from: f64,
+    pub to: Option<f64>,
+    pub reviews: usize,
+    pub recalled: usize,
+    /// `recalled / reviews`, from 0.0 to 1.0. None with no reviews to go on.
+    pub retention: Option<f64>,
+}
+
+impl RetentionBucket {
+    fn new(from: f64, to: Option<f64>) -> RetentionBucket {
+        RetentionBucket { from, to, reviews: 0, recalled: 0, retention: None }
+    }
+
+    fn count(&mut self, recalled: bool) {
+        self.reviews += 1;
+        self.recalled += recalled as usize;
+        self.retention = Some(self.recalled as f64 / self.reviews as f64);
+    }
+}
+
+//Buckets running from each bound to the next, the last with no upper end.
+fn buckets(bounds: &[f64]) -> Vec<RetentionBucket> {
+    bounds.iter().enumerate().map(|(position, from)| RetentionBucket::new(*from, bounds.get(position + 1).copied())).collect()
+}
+
+//The bucket `value` falls in. Values under the first bound go in the first.
+fn bucket_for(buckets: &mut [RetentionBucket], value: f64) -> &mut RetentionBucket {
+    let position = buckets.iter().rposition(|bucket| value >= bucket.from).unwrap_or(0);
+    &mut buckets[position]
+}
+
+/// How well reviewed facets were remembered. A facet's first review in the journal has nothing to measure from, so only the ones after it are counted.
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct RetentionReport {
+    pub overall: RetentionBucket,
+    /// By days since the facet's previous review.
+    pub by_interval: Vec<RetentionBucket>,
+    /// By the facet's place in the deck's frequency ranking, most frequent first. Facets the deck doesn't have any more go in the last band.
+    pub by_frequency: Vec<RetentionBucket>,
+}
+
+impl GemCollection {
+    /// Retention over the reviews in `events`, bucketed by interval and by frequency band. Like [`GemCollection::facet_stats`], this belongs before indexing.
+    pub fn retention_stats(&self, events: &[ReviewEvent]) -> Result<RetentionReport> {
+        let mut stats = self.facet_stats(&[])?;
+        FacetSort::Frequency.sort(&mut stats);
+        let ranks: HashMap<String, usize> = stats.into_iter().enumerate().map(|(rank, record)| (record.facet, rank + 1)).collect();
+        let mut report = RetentionReport {
+            overall: RetentionBucket::new(0.0, None),
+            by_interval: buckets(&INTERVAL_BOUNDS_IN_DAYS),
+            by_frequency: buckets(&FREQUENCY_RANK_BOUNDS.map(|bound| bound as f64)),
+        };
+        for review in repeat_reviews(events) {
+            report.overall.count(review.recalled);
+            bucket_for(&mut report.by_interval, review.elapsed.as_secs_f64() / 86_400.0).count(review.recalled);
+            bucket_for(&mut report.by_frequency, ranks.get(&review.facet).copied().unwrap_or(usize::MAX) as f64).count(review.recalled);
+        }
+        Ok(report)
+    }
+}
+
+/// How many facet reviews fall due on each of the coming days.
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct ReviewForecast {
+    /// Facets already due.
+    pub overdue: usize,
+    /// Facets due on each day, day 0 being the next 24 hours and including everything overdue.
+    pub days: Vec<usize>,
+}
+
+impl GemCollection {
+    /// Which of the next `days` days every scheduled facet falls due on, counting from `now`. Facets due later than that aren't counted.
+    pub fn review_forecast(&self, days: usize, now: SystemTime) -> ReviewForecast {
+        let mut forecast = ReviewForecast { overdue: 0, days: vec![0; days] };
+        for review_date in self.facet_states.values().filter_map(|state| state.review_date) {
+            let day = match review_date.duration_since(now) {
+                Ok(until_due) => (until_due.as_secs() / 86_400) as usize,
+                Err(_) => {
+                    forecast.overdue += 1;
+                    0
+                }
+            };
+            if let Some(due) = forecast.days.get_mut(day) {
+                *due += 1;
+            }
+        }
+        forecast
+    }
+}
@@ -0,0 +1,49 @@
+//Replacing a file so a crash or a power cut leaves either the old one or the new one whole, never half of each. The new contents go to a temporary file beside the old one, are flushed to disk, and only then renamed over it; the directory is flushed after the rename, so the rename itself is on disk too.
+
+use std::{
+    fs::{self, File, OpenOptions},
+    path::{Path, PathBuf},
+};
+
+use crate::error::Result;
+
+/// Where the new version of `path` is written before it replaces the old: beside it, with `.tmp` on the end. A `.zst` path keeps that suffix last, so the temporary file is compressed the same way (see [`crate::storage::compression`]).
+pub fn temporary_path_for<P: AsRef<Path>>(path: P) -> PathBuf {
+    let path = path.as_ref();
+    match path.extension() {
+        Some(extension) if extension == "zst" => path.with_extension("tmp.zst"),
+        _ => {
+            let mut temporary_name = path.as_os_str().to_owned();
+            temporary_name.push(".tmp");
+            PathBuf::from(temporary_name)
+        }
+    }
+}
+
+/// Replaces the file at `path` with `contents`, by way of [`temporary_path_for`] and [`replace_atomically`].
+pub fn write_atomically<P: AsRef<Path>>(path: P, contents: &[u8]) -> Result<()> {
+    let path = path.as_ref();
+    let temporary_path = temporary_path_for(path);
+    fs::write(&temporary_path, contents)?;
+    replace_atomically(&temporary_path, path)
+}
+
+/// Flushes the finished file at `temporary_path` to disk and renames it over `path`, for writers that can't hand over their contents in one go.
+pub fn replace_atomically<P: AsRef<Path>, Q: AsRef<Path>>(temporary_path: P, path: Q) -> Result<()> {
+    let path = path.as_ref();
+    OpenOptions::new().write(true).open(&temporary_path)?.sync_all()?;
+    fs::rename(&temporary_path, path)?;
+    sync_directory(path);
+    Ok(())
+}
+
+//Only some platforms can open a directory to flush it. Where it can't be done the rename is still atomic, just not yet durable, so failing here would only lose a save that worked.
+fn sync_directory(path: &Path) {
+    let directory = match path.parent() {
+        Some(directory) if !directory.as_os_str().is_empty() => directory,
+        _ => Path::new("."),
+    };
+    if let Ok(directory) = File::open(directory) {
+        let _ = directory.sync_all();
+    }
+}
@@ -0,0 +1,95 @@
+//Transparent zstd compression for deck files. Sentence decks compress around 8x, so anything read through here is sniffed for the zstd magic bytes and decompressed on the fly, and anything written to a path ending in `.zst` is compressed.
+//zstd is C, so it comes with the native feature; without it, compressed files are an error rather than garbage.
+
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    path::Path,
+};
+
+use crate::error::Result;
+
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+#[cfg(feature = "native")]
+const COMPRESSION_LEVEL: i32 = 3;
+
+#[cfg(not(feature = "native"))]
+fn zstd_unavailable() -> io::Error {
+    io::Error::new(io::ErrorKind::Unsupported, "zstd-compressed decks need the native feature")
+}
+
+/// Opens `path` for buffered reading, decompressing it if it starts with the zstd magic bytes (whatever its extension says).
+pub fn open_reader<P: AsRef<Path>>(path: P) -> Result<Box<dyn BufRead>> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let compressed = reader.fill_buf()?.starts_with(&ZSTD_MAGIC);
+    if compressed {
+        #[cfg(feature = "native")]
+        return Ok(Box::new(BufReader::new(zstd::Decoder::with_buffer(reader)?)));
+        #[cfg(not(feature = "native"))]
+        return Err(zstd_unavailable().into());
+    } else {
+        Ok(Box::new(reader))
+    }
+}
+
+/// True if the path asks for zstd output.
+pub fn is_compressed_path<P: AsRef<Path>>(path: P) -> bool {
+    path.as_ref().extension().is_some_and(|extension| extension == "zst")
+}
+
+/// The file name with any `.zst` suffix removed, so the format can be picked from the extension underneath (`gems.jsonl.zst` reads as JSON Lines).
+pub fn uncompressed_name(path: &str) -> &str {
+    path.strip_suffix(".zst").unwrap_or(path)
+}
+
+/// A buffered file writer that compresses when the path ends in `.zst`. Call [`DeckWriter::finish`] when done, so the zstd frame gets closed and any error surfaces.
+pub enum DeckWriter {
+    Plain(BufWriter<File>),
+    #[cfg(feature = "native")]
+    Zstd(zstd::Encoder<'static, BufWriter<File>>),
+}
+
+impl DeckWriter {
+    pub fn create<P: AsRef<Path>>(path: P) -> Result<DeckWriter> {
+        let compress = is_compressed_path(&path);
+        let writer = BufWriter::new(File::create(path)?);
+        if compress {
+            #[cfg(feature = "native")]
+            return Ok(DeckWriter::Zstd(zstd::Encoder::new(writer, COMPRESSION_LEVEL)?));
+            #[cfg(not(feature = "native"))]
+            return Err(zstd_unavailable().into());
+        } else {
+            Ok(DeckWriter::Plain(writer))
+        }
+    }
+
+    //Without zstd there's only the one kind of writer to take apart.
+    #[cfg_attr(not(feature = "native"), allow(clippy::infallible_destructuring_match))]
+    pub fn finish(self) -> Result<()> {
+        let mut writer = match self {
+            DeckWriter::Plain(writer) => writer,
+            #[cfg(feature = "native")]
+            DeckWriter::Zstd(encoder) => encoder.finish()?,
+        };
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+impl Write for DeckWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            DeckWriter::Plain(writer) => writer.write(buf),
+            #[cfg(feature = "native")]
+            DeckWriter::Zstd(encoder) => encoder.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            DeckWriter::Plain(writer) => writer.flush(),
+            #[cfg(feature = "native")]
+            DeckWriter::Zstd(encoder) => encoder.flush(),
+        }
+    }
+}
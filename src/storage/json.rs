@@ -0,0 +1,367 @@
+//The original layout: the deck as a JSON (or JSON Lines) file, progress in its own JSON file and reviews in an NDJSON journal next to them. A version 0 deck is upgraded the first time it's loaded, its scheduling moving to progress (see crate::schema).
+//With a keyring (the encryption feature), progress, the journal and the known-facet store are encrypted on disk and the deck is left as it is. So are the journal's snapshot and archive once it's been compacted (see crate::compact).
+
+use std::{
+    collections::HashMap,
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    collection::GemCollection,
+    compact::{split_for_compaction, Compaction, JournalSnapshot, LateFold},
+    error::Result,
+    journal::{sort_chronologically, Journal, ReviewEvent},
+    knowledge::KnowledgeStore,
+    progress::Progress,
+    schema::DeckError,
+    stats::{add_review_counts, ReviewCount},
+    storage::{atomic::{replace_atomically, temporary_path_for, write_atomically}, Storage},
+    sync::merge_events,
+};
+#[cfg(feature = "encryption")]
+use crate::encryption::{is_encrypted, Keyring};
+
+pub struct JsonStorage {
+    gems_path: PathBuf,
+    progress_path: PathBuf,
+    journal_path: PathBuf,
+    //Opened the first time a review comes in, so read-only use never creates a journal file.
+    journal: Option<Journal>,
+    #[cfg(feature = "encryption")]
+    keyring: Option<Keyring>,
+    lenient: bool,
+    skipped: Vec<DeckError>,
+}
+
+impl JsonStorage {
+    pub fn new<P: AsRef<Path>, Q: AsRef<Path>, R: AsRef<Path>>(gems_path: P, progress_path: Q, journal_path: R) -> JsonStorage {
+        JsonStorage {
+            gems_path: gems_path.as_ref().to_path_buf(),
+            progress_path: progress_path.as_ref().to_path_buf(),
+            journal_path: journal_path.as_ref().to_path_buf(),
+            journal: None,
+            #[cfg(feature = "encryption")]
+            keyring: None,
+            lenient: false,
+            skipped: Vec::new(),
+        }
+    }
+
+    /// With `lenient`, loading leaves out gems the deck can't read instead of failing, and lists them in [`JsonStorage::skipped`].
+    pub fn lenient(mut self, lenient: bool) -> JsonStorage {
+        self.lenient = lenient;
+        self
+    }
+
+    /// The gems the last load of the deck left out, and why.
+    pub fn skipped(&self) -> &[DeckError] {
+        &self.skipped
+    }
+
+    /// Encrypts progress and the journal with `keyring`. See [`crate::encryption`].
+    #[cfg(feature = "encryption")]
+    pub fn with_encryption(mut self, keyring: Keyring) -> JsonStorage {
+        self.keyring = Some(keyring);
+        self
+    }
+
+    fn open_journal(&self) -> Result<Journal> {
+        self.open_journal_at(&self.journal_path)
+    }
+
+    fn open_journal_at(&self, path: &Path) -> Result<Journal> {
+        #[cfg(feature = "encryption")]
+        if let Some(keyring) = &self.keyring {
+            return Journal::open_encrypted(path, keyring.clone());
+        }
+        Journal::open(path)
+    }
+
+    pub fn journal_path(&self) -> &Path {
+        &self.journal_path
+    }
+
+    /// Every review in the journal, oldest first, decrypted if need be.
+    pub fn read_events(&mut self) -> Result<Vec<ReviewEvent>> {
+        #[cfg(feature = "encryption")]
+        if let Some(keyring) = &mut self.keyring {
+            return Journal::read_events_encrypted(&self.journal_path, keyring);
+        }
+        Journal::read_events(&self.journal_path)
+    }
+
+    /// The snapshot the journal was compacted into, decrypted if need be, or None if it never was.
+    pub fn read_snapshot(&mut self) -> Result<Option<JournalSnapshot>> {
+        let contents = match fs::read_to_string(JournalSnapshot::path_for(&self.journal_path)) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        #[cfg(feature = "encryption")]
+        if let (Some(keyring), true) = (&mut self.keyring, is_encrypted(&contents)) {
+            return Ok(Some(serde_json::from_slice(&keyring.open_file(&contents)?)?));
+        }
+        Ok(Some(serde_json::from_str(&contents)?))
+    }
+
+    //Saves the scheduling a version 0 deck brought with it to progress, for the facets progress has nothing on, then writes the deck back as the current version so it's only done once. Progress goes first: a crash in between leaves the old deck to be migrated again, which fills in nothing new. A deck the lenient load left gems out of stays as it is.
+    fn save_migration(&mut self, gem_collection: &mut GemCollection) -> Result<()> {
+        let mut progress = self.load_progress()?;
+        for (name, state) in gem_collection.facet_states.iter() {
+            progress.facets.entry(name.clone()).or_insert_with(|| state.clone());
+        }
+        self.save_progress(&progress)?;
+        gem_collection.unsaved_migration = false;
+        if self.skipped.is_empty() {
+            gem_collection.write_gems_to_file(&self.gems_path)?;
+        }
+        Ok(())
+    }
+
+    /// Reviews and lapses for every facet, counting the reviews folded into the snapshot as well as the journal's.
+    pub fn review_counts(&mut self) -> Result<HashMap<String, ReviewCount>> {
+        let snapshot = self.read_snapshot()?;
+        let mut events = self.read_events()?;
+        let mut counts = match snapshot {
+            Some(snapshot) => {
+                events.retain(|event| !snapshot.holds(event));
+                snapshot.review_counts
+            }
+            None => HashMap::new(),
+        };
+        add_review_counts(&mut counts, &events);
+        Ok(counts)
+    }
+
+    /// The known-facet store at `path`, decrypted if need be. It isn't part of any one deck, so its path comes from the caller.
+    pub fn load_knowledge<P: AsRef<Path>>(&mut self, path: P) -> Result<KnowledgeStore> {
+        #[cfg(feature = "encryption")]
+        if let Some(keyring) = &mut self.keyring {
+            return KnowledgeStore::load_encrypted(path, keyring);
+        }
+        KnowledgeStore::load(path)
+    }
+
+    /// Saves `store` to `path`, encrypting it if need be.
+    pub fn save_knowledge<P: AsRef<Path>>(&mut self, path: P, store: &KnowledgeStore) -> Result<()> {
+        #[cfg(feature = "encryption")]
+        if let Some(keyring) = &mut self.keyring {
+            return store.save_encrypted(path, keyring);
+        }
+        store.save(path)
+    }
+
+    /// Every review folded into the journal's snapshot, oldest first, decrypted if need be. Copies left by a compaction that crashed part way are only listed once.
+    pub fn read_archive(&mut self) -> Result<Vec<ReviewEvent>> {
+        let path = JournalSnapshot::archive_path_for(&self.journal_path);
+        #[cfg(feature = "encryption")]
+        let events = match &mut self.keyring {
+            Some(keyring) => Journal::read_events_encrypted(&path, keyring)?,
+            None => Journal::read_events(&path)?,
+        };
+        #[cfg(not(feature = "encryption"))]
+        let events = Journal::read_events(&path)?;
+        Ok(merge_events(&[], events).new_events)
+    }
+
+    /// Every review on record, oldest first: the archive of what compaction folded away, then the journal. Anything counted over the whole history (stats, forgetting curves, sync) reads this, so compacting doesn't change it. A review left in both by a compaction that crashed part way is only listed once.
+    pub fn read_all_events(&mut self) -> Result<Vec<ReviewEvent>> {
+        let mut events = self.read_archive()?;
+        events.extend(self.read_events()?);
+        Ok(merge_events(&[], events).new_events)
+    }
+
+    /// Folds `late` (reviews from other devices, all at or before the snapshot's `up_to`) into the journal's snapshot. The ones the archive lacks are added to it and the snapshot is rebuilt by replaying the whole archive with `gem_collection`'s scheduler and normalizer, so they count as if they'd been there before compacting.
+    pub fn fold_late(&mut self, gem_collection: &mut GemCollection, late: Vec<ReviewEvent>) -> Result<LateFold> {
+        let snapshot = match self.read_snapshot()? {
+            Some(snapshot) => snapshot,
+            None => return Ok(LateFold::default()),
+        };
+        let mut archive = self.read_archive()?;
+        if archive.len() < snapshot.events {
+            return Ok(LateFold { unchecked: late, ..LateFold::default() });
+        }
+        let merge = merge_events(&archive, late);
+        //Archived before the snapshot is rebuilt. An archive ahead of its snapshot (a crash in between, here or in compact) is caught up the next time round.
+        self.open_journal_at(&JournalSnapshot::archive_path_for(&self.journal_path))?.append_all(&merge.new_events)?;
+        archive.extend(merge.new_events.iter().cloned());
+        if archive.len() > snapshot.events {
+            sort_chronologically(&mut archive);
+            let mut rebuilt = gem_collection.fold_into_snapshot(None, &archive)?;
+            rebuilt.up_to = rebuilt.up_to.max(snapshot.up_to);
+            self.write_snapshot(&rebuilt)?;
+        }
+        Ok(LateFold { folded: merge.new_events, conflicts: merge.conflicts, unchecked: Vec::new() })
+    }
+
+    /// Replaces the journal's snapshot, encrypting it if need be. It's written beside the old one and swapped in, so a crash leaves one or the other whole.
+    pub fn write_snapshot(&mut self, snapshot: &JournalSnapshot) -> Result<()> {
+        let contents = serde_json::to_string(snapshot)?;
+        #[cfg(feature = "encryption")]
+        let contents = match &mut self.keyring {
+            Some(keyring) => keyring.seal_file(contents.as_bytes())?,
+            None => contents,
+        };
+        let path = JournalSnapshot::path_for(&self.journal_path);
+        write_atomically(path, contents.as_bytes())
+    }
+
+    /// Folds all but the newest `keep` reviews into the journal's snapshot with `gem_collection`'s scheduler and normalizer, then cuts the journal down to the rest. See [`crate::compact`].
+    pub fn compact(&mut self, gem_collection: &mut GemCollection, keep: usize) -> Result<Compaction> {
+        let snapshot = self.read_snapshot()?;
+        let mut events = self.read_events()?;
+        //Left over from a compaction that crashed before the journal was cut.
+        if let Some(snapshot) = &snapshot {
+            events.retain(|event| !snapshot.holds(event));
+        }
+        let (folded, kept) = split_for_compaction(events, keep);
+        let compaction = Compaction { folded: folded.len(), kept: kept.len() };
+        if folded.is_empty() {
+            return Ok(compaction);
+        }
+        //Archived before the snapshot moves on, so the archive never falls behind it.
+        self.open_journal_at(&JournalSnapshot::archive_path_for(&self.journal_path))?.append_all(&folded)?;
+        let snapshot = gem_collection.fold_into_snapshot(snapshot, &folded)?;
+        self.write_snapshot(&snapshot)?;
+        //The new journal is written beside the old one and swapped in, like the snapshot.
+        self.journal = None;
+        let temporary_path = temporary_path_for(&self.journal_path);
+        let _ = fs::remove_file(&temporary_path);
+        let mut journal = self.open_journal_at(&temporary_path)?;
+        journal.append_all(&kept)?;
+        replace_atomically(&temporary_path, &self.journal_path)?;
+        Ok(compaction)
+    }
+}
+
+impl Storage for JsonStorage {
+    fn load_gems(&mut self) -> Result<GemCollection> {
+        let mut deck = GemCollection::read_deck_file(&self.gems_path.to_string_lossy(), self.lenient)?;
+        self.skipped = std::mem::take(&mut deck.skipped);
+        let mut gem_collection = GemCollection::from_deck(deck);
+        if gem_collection.unsaved_migration {
+            self.save_migration(&mut gem_collection)?;
+        }
+        Ok(gem_collection)
+    }
+
+    fn load_progress(&mut self) -> Result<Progress> {
+        #[cfg(feature = "encryption")]
+        if let Some(keyring) = &mut self.keyring {
+            return Progress::load_encrypted(&self.progress_path, keyring);
+        }
+        Progress::load(&self.progress_path)
+    }
+
+    fn save_progress(&mut self, progress: &Progress) -> Result<()> {
+        #[cfg(feature = "encryption")]
+        if let Some(keyring) = &mut self.keyring {
+            return progress.save_encrypted(&self.progress_path, keyring);
+        }
+        progress.save(&self.progress_path)
+    }
+
+    fn read_reviews(&mut self) -> Result<Vec<ReviewEvent>> {
+        self.read_events()
+    }
+
+    fn append_review(&mut self, event: &ReviewEvent) -> Result<()> {
+        self.append_reviews(std::slice::from_ref(event))
+    }
+
+    fn append_reviews(&mut self, events: &[ReviewEvent]) -> Result<()> {
+        if events.is_empty() {
+            return Ok(());
+        }
+        let journal = match &mut self.journal {
+            Some(journal) => journal,
+            None => {
+                let journal = self.open_journal()?;
+                self.journal.insert(journal)
+            }
+        };
+        journal.append_all(events)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{collection, journal, scratch_dir};
+
+    fn storage(name: &str) -> (PathBuf, JsonStorage) {
+        let directory = scratch_dir(&format!("json-{}", name));
+        let storage = JsonStorage::new(directory.join("gems.json"), directory.join("progress.json"), directory.join("journal.ndjson"));
+        (directory, storage)
+    }
+
+    fn replayed(events: &[ReviewEvent]) -> Progress {
+        let mut gem_collection = collection();
+        gem_collection.replay_merged(None, &mut events.to_vec()).unwrap();
+        gem_collection.progress()
+    }
+
+    #[test]
+    fn a_compacted_journal_replays_like_the_whole_one() {
+        let (directory, mut storage) = storage("compact");
+        storage.append_reviews(&journal()).unwrap();
+        let mut gem_collection = collection();
+        assert_eq!(storage.compact(&mut gem_collection, 4).unwrap(), Compaction { folded: 3, kept: 4 });
+        assert_eq!(storage.compact(&mut gem_collection, 1).unwrap(), Compaction { folded: 3, kept: 1 });
+        assert_eq!(storage.read_events().unwrap(), journal()[6..].to_vec());
+        assert_eq!(storage.read_archive().unwrap(), journal()[..6].to_vec());
+        assert_eq!(storage.read_snapshot().unwrap().unwrap().events, 6);
+        let mut from_disk = collection();
+        from_disk.replay(directory.join("journal.ndjson")).unwrap();
+        assert_eq!(from_disk.progress(), replayed(&journal()));
+        assert!(!directory.join("journal.ndjson.tmp").exists());
+        assert!(!directory.join("journal.snapshot.json.tmp").exists());
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn a_late_review_is_folded_in_where_it_happened() {
+        let (directory, mut storage) = storage("late");
+        let mut events = journal();
+        let late = events.remove(2);
+        storage.append_reviews(&events).unwrap();
+        let mut gem_collection = collection();
+        storage.compact(&mut gem_collection, 2).unwrap();
+        let fold = storage.fold_late(&mut gem_collection, vec![late.clone(), events[0].clone()]).unwrap();
+        assert_eq!(fold.folded, vec![late]);
+        assert!(fold.conflicts.is_empty() && fold.unchecked.is_empty());
+        let mut from_disk = collection();
+        from_disk.replay(directory.join("journal.ndjson")).unwrap();
+        assert_eq!(from_disk.progress(), replayed(&journal()));
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn compacting_leaves_facet_review_counts_as_they_were() {
+        let (directory, mut storage) = storage("facet-stats");
+        storage.append_reviews(&journal()).unwrap();
+        let mut gem_collection = collection();
+        let review_counts = |gem_collection: &GemCollection, events: &[ReviewEvent]| -> Vec<(String, usize, usize)> {
+            gem_collection.facet_stats(events).unwrap().into_iter().map(|stats| (stats.facet, stats.reviews, stats.lapses)).collect()
+        };
+        let before = review_counts(&gem_collection, &storage.read_all_events().unwrap());
+        assert!(before.iter().any(|(_, reviews, lapses)| *reviews > 1 && *lapses > 0));
+        storage.compact(&mut gem_collection, 2).unwrap();
+        assert_eq!(review_counts(&gem_collection, &storage.read_all_events().unwrap()), before);
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn compacting_leaves_retention_as_it_was() {
+        let (directory, mut storage) = storage("retention");
+        storage.append_reviews(&journal()).unwrap();
+        let mut gem_collection = collection();
+        let before = gem_collection.retention_stats(&storage.read_all_events().unwrap()).unwrap();
+        assert!(before.overall.reviews > 0);
+        storage.compact(&mut gem_collection, 2).unwrap();
+        assert_eq!(gem_collection.retention_stats(&storage.read_all_events().unwrap()).unwrap(), before);
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
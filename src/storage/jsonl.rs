@@ -0,0 +1,44 @@
+//JSON Lines decks: one Gem per line. The file is read through a buffered reader a line at a time, so even a multi-gigabyte corpus never has to sit in memory as one giant string next to the gems parsed out of it.
+
+use std::{
+    io::Write,
+    path::Path,
+};
+
+use crate::{
+    collection::GemCollection,
+    error::Result,
+    schema::{jsonl_header, DeckFile, JsonlDeck},
+    storage::compression::{open_reader, DeckWriter},
+};
+
+impl GemCollection {
+    /// Loads a collection from a `.jsonl` file with one gem per line, after a schema version line if it has one (see [`crate::schema`]). Blank lines are skipped. zstd-compressed files are decompressed as they're read.
+    pub fn read_gems_from_jsonl<P: AsRef<Path>>(path: P) -> Result<GemCollection> {
+        Ok(GemCollection::from_deck(GemCollection::read_jsonl_deck(path, false)?))
+    }
+
+    //A lenient read skips lines that aren't gems, whatever is wrong with them.
+    pub(crate) fn read_jsonl_deck<P: AsRef<Path>>(path: P, lenient: bool) -> Result<DeckFile> {
+        let mut reader = open_reader(path)?;
+        let mut deck = JsonlDeck::new(lenient);
+        let mut line = String::new();
+        //Reusing one line buffer keeps allocations down to roughly one per gem.
+        while reader.read_line(&mut line)? > 0 {
+            deck.read_line(&line)?;
+            line.clear();
+        }
+        Ok(deck.finish())
+    }
+
+    /// Writes a schema version line, then every gem as one line of JSON, in id order. A path ending in `.zst` gets compressed.
+    pub fn write_gems_to_jsonl<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let mut writer = DeckWriter::create(path)?;
+        writeln!(writer, "{}", jsonl_header()?)?;
+        for gem in self.gems.iter() {
+            serde_json::to_writer(&mut writer, &gem.resolve(&self.interner))?;
+            writer.write_all(b"\n")?;
+        }
+        writer.finish()
+    }
+}
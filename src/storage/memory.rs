@@ -0,0 +1,49 @@
+//Keeps everything in memory and never touches the disk. Handy as a fixture, or for a throwaway session.
+
+use crate::{
+    collection::GemCollection,
+    error::Result,
+    journal::ReviewEvent,
+    progress::Progress,
+    storage::Storage,
+};
+
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct MemoryStorage {
+    pub gems: GemCollection,
+    pub progress: Progress,
+    pub reviews: Vec<ReviewEvent>,
+}
+
+impl MemoryStorage {
+    pub fn new(gems: GemCollection) -> MemoryStorage {
+        MemoryStorage {
+            gems,
+            ..MemoryStorage::default()
+        }
+    }
+}
+
+impl Storage for MemoryStorage {
+    fn load_gems(&mut self) -> Result<GemCollection> {
+        Ok(self.gems.clone())
+    }
+
+    fn load_progress(&mut self) -> Result<Progress> {
+        Ok(self.progress.clone())
+    }
+
+    fn save_progress(&mut self, progress: &Progress) -> Result<()> {
+        self.progress = progress.clone();
+        Ok(())
+    }
+
+    fn read_reviews(&mut self) -> Result<Vec<ReviewEvent>> {
+        Ok(self.reviews.clone())
+    }
+
+    fn append_review(&mut self, event: &ReviewEvent) -> Result<()> {
+        self.reviews.push(event.clone());
+        Ok(())
+    }
+}
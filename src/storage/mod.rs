@@ -0,0 +1,55 @@
+//Alternative places to keep a deck and the learner's progress, for when one giant JSON blob stops being good enough.
+//The Storage trait is what the engine talks to, so the same review loop runs against flat JSON files, an SQLite store or plain memory, with or without a write-ahead log in front (see wal).
+
+pub mod atomic;
+pub mod compression;
+pub mod json;
+pub mod jsonl;
+pub mod memory;
+#[cfg(feature = "native")]
+pub mod snapshot;
+#[cfg(feature = "native")]
+pub mod sqlite;
+pub mod wal;
+
+use crate::{
+    collection::GemCollection,
+    error::Result,
+    journal::ReviewEvent,
+    progress::Progress,
+};
+
+pub trait Storage {
+    /// The deck, with every gem's original unknown facets. It still needs indexing before it can be ordered.
+    fn load_gems(&mut self) -> Result<GemCollection>;
+    /// The learner's progress. A learner who hasn't started yet gets an empty Progress.
+    fn load_progress(&mut self) -> Result<Progress>;
+    /// Replaces the stored progress with `progress`.
+    fn save_progress(&mut self, progress: &Progress) -> Result<()>;
+    /// Records one review. Reviews are only ever added, never changed.
+    fn append_review(&mut self, event: &ReviewEvent) -> Result<()>;
+    /// Every review recorded so far, oldest first.
+    fn read_reviews(&mut self) -> Result<Vec<ReviewEvent>>;
+    /// Records several reviews, oldest first. Backends that can write them all in one go (one sync, one transaction) do.
+    fn append_reviews(&mut self, events: &[ReviewEvent]) -> Result<()> {
+        for event in events {
+            self.append_review(event)?;
+        }
+        Ok(())
+    }
+}
+
+impl GemCollection {
+    /// Loads the deck and the learner's progress out of any storage backend. The collection still needs to be indexed before it can be ordered.
+    pub fn load_from<S: Storage>(storage: &mut S) -> Result<GemCollection> {
+        let mut gem_collection = storage.load_gems()?;
+        gem_collection.set_progress_over_deck(storage.load_progress()?);
+        Ok(gem_collection)
+    }
+
+    /// Saves the collection's progress to any storage backend, and shares its known facets if it has a shared knowledge store.
+    pub fn save_to<S: Storage>(&self, storage: &mut S) -> Result<()> {
+        self.push_shared_knowledge();
+        storage.save_progress(&self.progress())
+    }
+}
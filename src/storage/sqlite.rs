@@ -0,0 +1,411 @@
+//SQLite store for a GemCollection. Gems, their facets, the known facets, per-facet scheduling and every review live in their own tables, so a huge deck can be read a page at a time and a session that dies halfway through only loses whatever wasn't committed yet.
+
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use rusqlite::{params, Connection, OptionalExtension};
+
+use crate::{
+    collection::GemCollection,
+    error::Result,
+    facet::Facet,
+    gem::{Gem, GemKey},
+    journal::ReviewEvent,
+    progress::Progress,
+    storage::Storage,
+};
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS gems (
+        id INTEGER PRIMARY KEY,
+        sides TEXT NOT NULL,
+        stable_id TEXT,
+        flags TEXT
+    );
+    CREATE TABLE IF NOT EXISTS gem_facets (
+        gem_id INTEGER NOT NULL REFERENCES gems(id) ON DELETE CASCADE,
+        facet TEXT NOT NULL,
+        PRIMARY KEY (gem_id, facet)
+    );
+    CREATE INDEX IF NOT EXISTS gem_facets_by_facet ON gem_facets(facet);
+    CREATE TABLE IF NOT EXISTS known_facets (
+        facet TEXT PRIMARY KEY
+    );
+    CREATE TABLE IF NOT EXISTS facets (
+        name TEXT PRIMARY KEY,
+        review_date INTEGER,
+        last_seen_date INTEGER,
+        lifetime_in_hours REAL,
+        stage TEXT,
+        ease_factor REAL,
+        repetitions INTEGER,
+        leitner_box INTEGER
+    );
+    CREATE TABLE IF NOT EXISTS reviews (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        gem_key TEXT NOT NULL,
+        facet TEXT NOT NULL,
+        grade REAL NOT NULL,
+        reviewed_at INTEGER NOT NULL,
+        cram INTEGER NOT NULL DEFAULT 0
+    );
+";
+
+//Dates are stored as whole seconds since the unix epoch.
+fn to_seconds(date: Option<SystemTime>) -> Option<i64> {
+    date.and_then(|date| date.duration_since(UNIX_EPOCH).ok())
+        .map(|duration| duration.as_secs() as i64)
+}
+
+fn from_seconds(seconds: Option<i64>) -> Option<SystemTime> {
+    seconds.map(|seconds| UNIX_EPOCH + Duration::from_secs(seconds.max(0) as u64))
+}
+
+//Columns added to existing tables after the first release. CREATE TABLE IF NOT EXISTS won't touch a table that's already there, so databases made by older versions get them added here.
+const ADDED_COLUMNS: &[(&str, &str, &str)] = &[
+    ("facets", "ease_factor", "REAL"),
+    ("facets", "repetitions", "INTEGER"),
+    ("facets", "leitner_box", "INTEGER"),
+    ("gems", "stable_id", "TEXT"),
+    ("gems", "flags", "TEXT"),
+    ("reviews", "cram", "INTEGER NOT NULL DEFAULT 0"),
+];
+
+fn add_missing_columns(connection: &Connection) -> Result<()> {
+    for (table, column, column_type) in ADDED_COLUMNS {
+        if !column_names(connection, table)?.contains(*column) {
+            connection.execute_batch(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, column_type))?;
+        }
+    }
+    Ok(())
+}
+
+fn column_names(connection: &Connection, table: &str) -> Result<HashSet<String>> {
+    let mut table_info = connection.prepare(&format!("PRAGMA table_info({})", table))?;
+    let columns = table_info
+        .query_map([], |row| row.get::<_, String>(1))?
+        .collect::<rusqlite::Result<HashSet<String>>>()?;
+    Ok(columns)
+}
+
+//Reviews used to point at gems by their number, which changes whenever the deck does. Old review tables are rebuilt to point at gem keys instead; the old numbers are kept as the key, since that's all there is to go on.
+fn migrate_reviews_to_keys(connection: &Connection) -> Result<()> {
+    let columns = column_names(connection, "reviews")?;
+    if !columns.contains("gem_id") || columns.contains("gem_key") {
+        return Ok(());
+    }
+    connection.execute_batch("
+        BEGIN;
+        ALTER TABLE reviews RENAME TO reviews_by_number;
+        CREATE TABLE reviews (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            gem_key TEXT NOT NULL,
+            facet TEXT NOT NULL,
+            grade REAL NOT NULL,
+            reviewed_at INTEGER NOT NULL
+        );
+        INSERT INTO reviews (id, gem_key, facet, grade, reviewed_at)
+            SELECT id, CAST(gem_id AS TEXT), facet, grade, reviewed_at FROM reviews_by_number;
+        DROP TABLE reviews_by_number;
+        COMMIT;
+    ")?;
+    Ok(())
+}
+
+/// A single review as stored in the `reviews` table.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ReviewRecord {
+    pub gem_key: GemKey,
+    pub facet: String,
+    pub grade: f64,
+    pub reviewed_at: SystemTime,
+    /// True for a cram drill, which never changed scheduling.
+    pub cram: bool,
+}
+
+pub struct SqliteStore {
+    connection: Connection,
+}
+
+impl SqliteStore {
+    /// Opens (or creates) the database at `path` and makes sure all the tables exist.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<SqliteStore> {
+        let connection = Connection::open(path)?;
+        connection.execute_batch("PRAGMA foreign_keys = ON; PRAGMA journal_mode = WAL;")?;
+        connection.execute_batch(SCHEMA)?;
+        migrate_reviews_to_keys(&connection)?;
+        add_missing_columns(&connection)?;
+        Ok(SqliteStore { connection })
+    }
+
+    /// An in-memory database, mostly useful for trying things out.
+    pub fn open_in_memory() -> Result<SqliteStore> {
+        let connection = Connection::open_in_memory()?;
+        connection.execute_batch(SCHEMA)?;
+        Ok(SqliteStore { connection })
+    }
+
+    /// Writes every gem (with its current unknown facets) and the known facets in one transaction, replacing whatever was stored before.
+    pub fn save_collection(&mut self, gem_collection: &GemCollection) -> Result<()> {
+        let transaction = self.connection.transaction()?;
+        transaction.execute_batch("DELETE FROM gem_facets; DELETE FROM gems; DELETE FROM known_facets;")?;
+        {
+            let mut insert_gem = transaction.prepare("INSERT INTO gems (id, sides, stable_id, flags) VALUES (?1, ?2, ?3, ?4)")?;
+            let mut insert_facet = transaction.prepare("INSERT INTO gem_facets (gem_id, facet) VALUES (?1, ?2)")?;
+            for (number, gem) in gem_collection.gems.iter().enumerate() {
+                //Flags are stored as JSON, and left null for the gems that have none.
+                let flags = if gem.flags.is_empty() { None } else { Some(serde_json::to_string(&gem.flags)?) };
+                insert_gem.execute(params![number as i64, serde_json::to_string(&gem.sides)?, gem.id, flags])?;
+                for facet in gem.unknown_facets.iter() {
+                    insert_facet.execute(params![number as i64, gem_collection.facet_name(*facet)])?;
+                }
+            }
+            let mut insert_known = transaction.prepare("INSERT INTO known_facets (facet) VALUES (?1)")?;
+            for facet in gem_collection.known_facets.iter() {
+                insert_known.execute(params![gem_collection.facet_name(*facet)])?;
+            }
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+
+    /// Reads the whole store back into a collection. It still needs to be indexed before it can be ordered.
+    pub fn load_collection(&self) -> Result<GemCollection> {
+        let mut gem_collection = GemCollection::default();
+        let gem_count = self.gem_count()?;
+        let page_size = 10_000;
+        let mut offset = 0;
+        while offset < gem_count {
+            //Rows come back keyed by id; pushing them in id order keeps each gem's GemId the same as its row id.
+            let mut page: Vec<(usize, Gem)> = self.load_gems(offset, page_size)?.into_iter().collect();
+            page.sort_unstable_by_key(|(id, _)| *id);
+            for (_, gem) in page {
+                gem_collection.push_gem(gem);
+            }
+            offset += page_size;
+        }
+        gem_collection.insert_known_facets(self.load_known_facets()?.iter());
+        Ok(gem_collection)
+    }
+
+    pub fn gem_count(&self) -> Result<usize> {
+        let count: i64 = self.connection.query_row("SELECT COUNT(*) FROM gems", [], |row| row.get(0))?;
+        Ok(count as usize)
+    }
+
+    /// Loads one page of gems ordered by id, so a caller can walk a large deck without holding it all in memory at once.
+    pub fn load_gems(&self, offset: usize, limit: usize) -> Result<HashMap<usize, Gem>> {
+        let mut gems = HashMap::new();
+        let mut select_gems = self.connection.prepare("SELECT id, sides, stable_id, flags FROM gems ORDER BY id LIMIT ?1 OFFSET ?2")?;
+        let rows = select_gems.query_map(params![limit as i64, offset as i64], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?, row.get::<_, Option<String>>(2)?, row.get::<_, Option<String>>(3)?))
+        })?;
+        for row in rows {
+            let (id, sides, stable_id, flags) = row?;
+            gems.insert(id as usize, Gem {
+                id: stable_id,
+                sides: serde_json::from_str(&sides)?,
+                unknown_facets: HashSet::new(),
+                flags: match flags {
+                    Some(flags) => serde_json::from_str(&flags)?,
+                    None => Vec::new(),
+                },
+            });
+        }
+        let mut select_facets = self.connection.prepare(
+            "SELECT gem_id, facet FROM gem_facets WHERE gem_id IN (SELECT id FROM gems ORDER BY id LIMIT ?1 OFFSET ?2)"
+        )?;
+        let rows = select_facets.query_map(params![limit as i64, offset as i64], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+        })?;
+        for row in rows {
+            let (gem_id, facet) = row?;
+            if let Some(gem) = gems.get_mut(&(gem_id as usize)) {
+                gem.unknown_facets.insert(facet);
+            }
+        }
+        Ok(gems)
+    }
+
+    pub fn load_known_facets(&self) -> Result<HashSet<String>> {
+        let mut select = self.connection.prepare("SELECT facet FROM known_facets")?;
+        let rows = select.query_map([], |row| row.get::<_, String>(0))?;
+        Ok(rows.collect::<rusqlite::Result<HashSet<String>>>()?)
+    }
+
+    /// Adds facets to the known set without rewriting anything else.
+    pub fn insert_known_facets(&mut self, facets: &HashSet<String>) -> Result<()> {
+        let transaction = self.connection.transaction()?;
+        {
+            let mut insert = transaction.prepare("INSERT OR IGNORE INTO known_facets (facet) VALUES (?1)")?;
+            for facet in facets.iter() {
+                insert.execute(params![facet])?;
+            }
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+
+    /// Inserts or replaces the scheduling data for a facet.
+    pub fn save_facet(&mut self, facet: &Facet) -> Result<()> {
+        insert_facet(&self.connection, facet)
+    }
+
+    pub fn load_facet(&self, name: &str) -> Result<Option<Facet>> {
+        let facet = self.connection.query_row(
+            "SELECT name, review_date, last_seen_date, lifetime_in_hours, stage, ease_factor, repetitions, leitner_box FROM facets WHERE name = ?1",
+            params![name],
+            facet_from_row,
+        ).optional()?;
+        Ok(facet)
+    }
+
+    pub fn load_facets(&self) -> Result<HashMap<String, Facet>> {
+        let mut select = self.connection.prepare("SELECT name, review_date, last_seen_date, lifetime_in_hours, stage, ease_factor, repetitions, leitner_box FROM facets")?;
+        let rows = select.query_map([], facet_from_row)?;
+        let mut facets = HashMap::new();
+        for facet in rows {
+            let facet = facet?;
+            facets.insert(facet.name.clone(), facet);
+        }
+        Ok(facets)
+    }
+
+    /// Appends a review to the history table. Reviews are never updated or deleted.
+    pub fn record_review(&mut self, review: &ReviewRecord) -> Result<()> {
+        insert_review(&self.connection, review)
+    }
+
+    /// Every review in the order it was recorded.
+    pub fn load_reviews(&self) -> Result<Vec<ReviewRecord>> {
+        let mut select = self.connection.prepare("SELECT gem_key, facet, grade, reviewed_at, cram FROM reviews ORDER BY id")?;
+        let rows = select.query_map([], |row| {
+            Ok(ReviewRecord {
+                gem_key: GemKey(row.get(0)?),
+                facet: row.get(1)?,
+                grade: row.get(2)?,
+                reviewed_at: from_seconds(Some(row.get(3)?)).unwrap_or(UNIX_EPOCH),
+                cram: row.get(4)?,
+            })
+        })?;
+        Ok(rows.collect::<rusqlite::Result<Vec<ReviewRecord>>>()?)
+    }
+}
+
+fn insert_facet(connection: &Connection, facet: &Facet) -> Result<()> {
+    connection.execute(
+        "INSERT OR REPLACE INTO facets (name, review_date, last_seen_date, lifetime_in_hours, stage, ease_factor, repetitions, leitner_box) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        params![facet.name, to_seconds(facet.review_date), to_seconds(facet.last_seen_date), facet.lifetime_in_hours, facet.stage, facet.ease_factor, facet.repetitions, facet.leitner_box.map(|leitner_box| leitner_box as i64)],
+    )?;
+    Ok(())
+}
+
+fn insert_review(connection: &Connection, review: &ReviewRecord) -> Result<()> {
+    connection.execute(
+        "INSERT INTO reviews (gem_key, facet, grade, reviewed_at, cram) VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![review.gem_key.0, review.facet, review.grade, to_seconds(Some(review.reviewed_at)), review.cram],
+    )?;
+    Ok(())
+}
+
+fn facet_from_row(row: &rusqlite::Row) -> rusqlite::Result<Facet> {
+    Ok(Facet {
+        name: row.get(0)?,
+        review_date: from_seconds(row.get(1)?),
+        last_seen_date: from_seconds(row.get(2)?),
+        lifetime_in_hours: row.get(3)?,
+        stage: row.get(4)?,
+        ease_factor: row.get(5)?,
+        repetitions: row.get(6)?,
+        leitner_box: row.get::<_, Option<i64>>(7)?.map(|leitner_box| leitner_box as usize),
+    })
+}
+
+impl Storage for SqliteStore {
+    fn load_gems(&mut self) -> Result<GemCollection> {
+        let mut gem_collection = self.load_collection()?;
+        //Known facets are progress, not part of the deck.
+        gem_collection.known_facets.clear();
+        Ok(gem_collection)
+    }
+
+    fn load_progress(&mut self) -> Result<Progress> {
+        Ok(Progress {
+            known_facets: self.load_known_facets()?,
+            facets: self.load_facets()?,
+        })
+    }
+
+    fn save_progress(&mut self, progress: &Progress) -> Result<()> {
+        let transaction = self.connection.transaction()?;
+        transaction.execute_batch("DELETE FROM known_facets; DELETE FROM facets;")?;
+        {
+            let mut insert_known = transaction.prepare("INSERT INTO known_facets (facet) VALUES (?1)")?;
+            for facet in progress.known_facets.iter() {
+                insert_known.execute(params![facet])?;
+            }
+        }
+        for facet in progress.facets.values() {
+            insert_facet(&transaction, facet)?;
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+
+    //Rows from one review share its gem and timestamp, so they're put back together into one event. Hints aren't kept.
+    fn read_reviews(&mut self) -> Result<Vec<ReviewEvent>> {
+        let mut events: Vec<ReviewEvent> = Vec::new();
+        for record in self.load_reviews()? {
+            match events.last_mut() {
+                Some(event) if event.gem_key == record.gem_key && event.timestamp == record.reviewed_at && event.cram == record.cram => {
+                    event.grades.insert(record.facet, record.grade);
+                }
+                _ => events.push(ReviewEvent {
+                    gem_key: record.gem_key,
+                    grades: HashMap::from([(record.facet, record.grade)]),
+                    timestamp: record.reviewed_at,
+                    hints: HashMap::new(),
+                    cram: record.cram,
+                }),
+            }
+        }
+        Ok(events)
+    }
+
+    fn append_review(&mut self, event: &ReviewEvent) -> Result<()> {
+        self.append_reviews(std::slice::from_ref(event))
+    }
+
+    //Each graded facet becomes its own row in `reviews`, all written in one transaction.
+    fn append_reviews(&mut self, events: &[ReviewEvent]) -> Result<()> {
+        let transaction = self.connection.transaction()?;
+        for event in events {
+            for (facet, grade) in event.grades.iter() {
+                insert_review(&transaction, &ReviewRecord {
+                    gem_key: event.gem_key.clone(),
+                    facet: facet.clone(),
+                    grade: *grade,
+                    reviewed_at: event.timestamp,
+                    cram: event.cram,
+                })?;
+            }
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+}
+
+impl GemCollection {
+    /// Opens a collection from an SQLite file written by [`GemCollection::write_gems_to_sqlite`].
+    pub fn read_gems_from_sqlite<P: AsRef<Path>>(path: P) -> Result<GemCollection> {
+        SqliteStore::open(path)?.load_collection()
+    }
+
+    /// Persists the gems and known facets to an SQLite file, creating it if needed.
+    pub fn write_gems_to_sqlite<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        SqliteStore::open(path)?.save_collection(self)
+    }
+}
@@ -0,0 +1,452 @@
+//A write-ahead log in front of any storage backend, for when saving progress after every grade is too slow (progress on a network filesystem, say) but saving only at exit would lose a whole session to a crash.
+//Reviews and progress go into the log first, one line each: a review as it is, and progress as just the facets that changed since the line before. The backend hears about them in batches, once enough have built up or enough time has gone by, and the log is emptied after each one. Opening a log that still has lines in it (the process died between batches) hands them to the backend before anything else.
+//A crash after a batch reached the backend but before the log was emptied leaves reviews in the log that the backend already has. Recovery skips those: a logged review whose gem and timestamp (to the second, which is as fine as the SQLite store keeps them) the backend already holds isn't handed over again.
+
+use std::{
+    collections::HashSet,
+    fs::{File, OpenOptions},
+    io::{BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    collection::GemCollection,
+    error::{LangwitchError, Result},
+    facet::Facet,
+    gem::GemKey,
+    journal::ReviewEvent,
+    progress::Progress,
+    storage::Storage,
+    timestamp::to_millis,
+};
+#[cfg(feature = "encryption")]
+use crate::encryption::{is_encrypted, Keyring, Salt};
+
+pub const DEFAULT_BATCH_SIZE: usize = 20;
+pub const DEFAULT_FLUSH_INTERVAL_SECONDS: u64 = 30;
+
+/// When the log is synced to disk.
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum FsyncPolicy {
+    /// After every line, so nothing acknowledged is ever lost.
+    #[default]
+    Always,
+    /// Before each batch is handed over, so a crash can lose what came in since the last one.
+    Batch,
+    /// Never explicitly; the operating system writes it out when it likes.
+    Never,
+}
+
+/// How the write-ahead log batches, e.g. {"batch_size": 20, "flush_interval_seconds": 30, "fsync": "batch"}. A batch is handed over at `batch_size` lines or once `flush_interval_seconds` have passed since the last one, whichever comes first; 0 turns the interval off. The interval is only checked when something is written, so an idle session leaves its last lines in the log until the next write or the end.
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Copy)]
+#[serde(default)]
+pub struct WalOptions {
+    pub batch_size: usize,
+    pub flush_interval_seconds: u64,
+    pub fsync: FsyncPolicy,
+}
+
+impl Default for WalOptions {
+    fn default() -> Self {
+        WalOptions { batch_size: DEFAULT_BATCH_SIZE, flush_interval_seconds: DEFAULT_FLUSH_INTERVAL_SECONDS, fsync: FsyncPolicy::default() }
+    }
+}
+
+//One line of the log.
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "snake_case")]
+enum WalRecord {
+    Review(ReviewEvent),
+    Progress(ProgressChange),
+}
+
+//How progress changed from one save to the next.
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Default)]
+struct ProgressChange {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    known: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    forgotten: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    facets: Vec<Facet>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    dropped: Vec<String>,
+}
+
+impl ProgressChange {
+    fn between(before: &Progress, after: &Progress) -> ProgressChange {
+        ProgressChange {
+            known: after.known_facets.difference(&before.known_facets).cloned().collect(),
+            forgotten: before.known_facets.difference(&after.known_facets).cloned().collect(),
+            facets: after.facets.values().filter(|facet| before.facets.get(&facet.name) != Some(*facet)).cloned().collect(),
+            dropped: before.facets.keys().filter(|name| !after.facets.contains_key(*name)).cloned().collect(),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.known.is_empty() && self.forgotten.is_empty() && self.facets.is_empty() && self.dropped.is_empty()
+    }
+
+    fn apply(self, progress: &mut Progress) {
+        for facet in self.forgotten.iter() {
+            progress.known_facets.remove(facet);
+        }
+        progress.known_facets.extend(self.known);
+        for name in self.dropped.iter() {
+            progress.facets.remove(name);
+        }
+        progress.facets.extend(self.facets.into_iter().map(|facet| (facet.name.clone(), facet)));
+    }
+}
+
+struct Log {
+    path: PathBuf,
+    file: File,
+    options: WalOptions,
+    //Lines written since the last batch was handed over.
+    unflushed: usize,
+    last_flush: Instant,
+    #[cfg(feature = "encryption")]
+    sealing: Option<(Keyring, Salt)>,
+}
+
+impl Log {
+    fn open(path: &Path, options: WalOptions) -> Result<Log> {
+        Ok(Log {
+            path: path.to_path_buf(),
+            file: OpenOptions::new().create(true).append(true).read(true).open(path)?,
+            options,
+            unflushed: 0,
+            last_flush: Instant::now(),
+            #[cfg(feature = "encryption")]
+            sealing: None,
+        })
+    }
+
+    fn write(&mut self, record: &WalRecord) -> Result<()> {
+        let mut line = serde_json::to_vec(record)?;
+        #[cfg(feature = "encryption")]
+        if let Some((keyring, salt)) = &mut self.sealing {
+            line = keyring.seal_line(salt, &line)?.into_bytes();
+        }
+        line.push(b'\n');
+        self.file.write_all(&line)?;
+        if self.options.fsync == FsyncPolicy::Always {
+            self.file.sync_data()?;
+        }
+        self.unflushed += 1;
+        Ok(())
+    }
+
+    fn due(&self) -> bool {
+        let WalOptions { batch_size, flush_interval_seconds, .. } = self.options;
+        self.unflushed >= batch_size.max(1) || (flush_interval_seconds > 0 && self.last_flush.elapsed() >= Duration::from_secs(flush_interval_seconds))
+    }
+
+    //Empties the log once everything in it is safely in the backend.
+    fn clear(&mut self) -> Result<()> {
+        self.file.set_len(0)?;
+        #[cfg(feature = "encryption")]
+        if let Some((_, salt)) = &self.sealing {
+            self.file.write_all(format!("{}\n", Keyring::header(salt)).as_bytes())?;
+        }
+        if self.options.fsync != FsyncPolicy::Never {
+            self.file.sync_data()?;
+        }
+        self.unflushed = 0;
+        self.last_flush = Instant::now();
+        Ok(())
+    }
+
+    //Every record in the log, sealed or not. A last line cut short by a crash is left out.
+    fn read_records(&self) -> Result<Vec<WalRecord>> {
+        let mut reader = BufReader::new(File::open(&self.path)?);
+        let mut records = Vec::new();
+        let mut line = String::new();
+        #[cfg(feature = "encryption")]
+        let mut opening: Option<(Keyring, Salt)> = None;
+        while reader.read_line(&mut line)? > 0 {
+            #[cfg(feature = "encryption")]
+            if records.is_empty() && opening.is_none() && is_encrypted(&line) {
+                let keyring = self.sealing.as_ref().map(|(keyring, _)| keyring.clone());
+                let keyring = keyring.ok_or_else(|| LangwitchError::Encryption(format!("{} is encrypted; set \"encryption\" in the config", self.path.display())))?;
+                opening = Some((keyring, Keyring::parse_header(&line)?));
+                line.clear();
+                continue;
+            }
+            if !line.trim().is_empty() {
+                #[cfg(feature = "encryption")]
+                let record = match &mut opening {
+                    Some((keyring, salt)) => keyring.open_line(salt, &line).and_then(|plaintext| Ok(serde_json::from_slice(&plaintext)?)),
+                    None => serde_json::from_str(&line).map_err(LangwitchError::from),
+                };
+                #[cfg(not(feature = "encryption"))]
+                let record = serde_json::from_str(&line).map_err(LangwitchError::from);
+                match record {
+                    Ok(record) => records.push(record),
+                    Err(_) if !line.ends_with('\n') => break,
+                    Err(e) => return Err(e),
+                }
+            }
+            line.clear();
+        }
+        Ok(records)
+    }
+}
+
+//What recovery matches a logged review against the backend's on.
+fn recorded_review(event: &ReviewEvent) -> (GemKey, u64) {
+    (event.gem_key.clone(), to_millis(event.timestamp) / 1000)
+}
+
+/// A storage backend with an optional write-ahead log in front of it. See the module comment for what the log guarantees.
+pub struct WalStorage<S: Storage> {
+    inner: S,
+    log: Option<Log>,
+    //Reviews in the log that the backend hasn't had yet.
+    pending_reviews: Vec<ReviewEvent>,
+    //The progress last saved, which the next save is logged as a change from.
+    logged: Progress,
+    pending_progress: bool,
+}
+
+impl<S: Storage> WalStorage<S> {
+    /// `inner` with no log in front of it: everything goes straight through.
+    pub fn direct(inner: S) -> WalStorage<S> {
+        WalStorage { inner, log: None, pending_reviews: Vec::new(), logged: Progress::default(), pending_progress: false }
+    }
+
+    /// `inner` behind the log at `path`, which is created if it doesn't exist. Anything a crash left in the log goes to `inner` first.
+    pub fn open<P: AsRef<Path>>(inner: S, path: P, options: WalOptions) -> Result<WalStorage<S>> {
+        WalStorage::recover(inner, Log::open(path.as_ref(), options)?)
+    }
+
+    /// Same as [`WalStorage::open`] with the log's lines encrypted with `keyring` (see [`crate::encryption`]). A log left in plaintext is still recovered, and encrypted from then on.
+    #[cfg(feature = "encryption")]
+    pub fn open_encrypted<P: AsRef<Path>>(inner: S, path: P, options: WalOptions, keyring: Keyring) -> Result<WalStorage<S>> {
+        let mut log = Log::open(path.as_ref(), options)?;
+        let mut first_line = String::new();
+        BufReader::new(File::open(&log.path)?).read_line(&mut first_line)?;
+        let salt = if is_encrypted(&first_line) { Keyring::parse_header(&first_line)? } else { keyring.salt() };
+        log.sealing = Some((keyring, salt));
+        WalStorage::recover(inner, log)
+    }
+
+    fn recover(mut inner: S, mut log: Log) -> Result<WalStorage<S>> {
+        let mut reviews = Vec::new();
+        let mut changes = Vec::new();
+        for record in log.read_records()? {
+            match record {
+                WalRecord::Review(event) => reviews.push(event),
+                WalRecord::Progress(change) => changes.push(change),
+            }
+        }
+        if !reviews.is_empty() {
+            let recorded: HashSet<(GemKey, u64)> = inner.read_reviews()?.iter().map(recorded_review).collect();
+            reviews.retain(|event| !recorded.contains(&recorded_review(event)));
+            inner.append_reviews(&reviews)?;
+        }
+        if !changes.is_empty() {
+            let mut progress = inner.load_progress()?;
+            for change in changes {
+                change.apply(&mut progress);
+            }
+            inner.save_progress(&progress)?;
+        }
+        log.clear()?;
+        Ok(WalStorage { inner, log: Some(log), pending_reviews: Vec::new(), logged: Progress::default(), pending_progress: false })
+    }
+
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    /// The backend itself. Anything still in the log hasn't reached it yet; [`WalStorage::flush`] first to read from it.
+    pub fn inner_mut(&mut self) -> &mut S {
+        &mut self.inner
+    }
+
+    /// Hands everything in the log to the backend and empties it.
+    pub fn flush(&mut self) -> Result<()> {
+        let log = match &mut self.log {
+            Some(log) if log.unflushed > 0 => log,
+            _ => return Ok(()),
+        };
+        if log.options.fsync == FsyncPolicy::Batch {
+            log.file.sync_data()?;
+        }
+        self.inner.append_reviews(&self.pending_reviews)?;
+        self.pending_reviews.clear();
+        if self.pending_progress {
+            self.inner.save_progress(&self.logged)?;
+            self.pending_progress = false;
+        }
+        log.clear()
+    }
+
+    fn flush_if_due(&mut self) -> Result<()> {
+        match &self.log {
+            Some(log) if log.due() => self.flush(),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl<S: Storage> Storage for WalStorage<S> {
+    fn load_gems(&mut self) -> Result<GemCollection> {
+        self.inner.load_gems()
+    }
+
+    fn load_progress(&mut self) -> Result<Progress> {
+        if self.pending_progress {
+            return Ok(self.logged.clone());
+        }
+        let progress = self.inner.load_progress()?;
+        if self.log.is_some() {
+            self.logged = progress.clone();
+        }
+        Ok(progress)
+    }
+
+    fn save_progress(&mut self, progress: &Progress) -> Result<()> {
+        let log = match &mut self.log {
+            Some(log) => log,
+            None => return self.inner.save_progress(progress),
+        };
+        let change = ProgressChange::between(&self.logged, progress);
+        if change.is_empty() {
+            return Ok(());
+        }
+        log.write(&WalRecord::Progress(change))?;
+        self.logged = progress.clone();
+        self.pending_progress = true;
+        self.flush_if_due()
+    }
+
+    //What the backend has, and what's still waiting in the log for it.
+    fn read_reviews(&mut self) -> Result<Vec<ReviewEvent>> {
+        let mut reviews = self.inner.read_reviews()?;
+        reviews.extend(self.pending_reviews.iter().cloned());
+        Ok(reviews)
+    }
+
+    fn append_review(&mut self, event: &ReviewEvent) -> Result<()> {
+        let log = match &mut self.log {
+            Some(log) => log,
+            None => return self.inner.append_review(event),
+        };
+        log.write(&WalRecord::Review(event.clone()))?;
+        self.pending_reviews.push(event.clone());
+        self.flush_if_due()
+    }
+}
+
+impl<S: Storage> Drop for WalStorage<S> {
+    //A last flush on the way out. An error here has nowhere to go, so anything that needs to know flushes itself first; the log still has the lines for next time.
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, fs};
+
+    use super::*;
+    use crate::{
+        storage::memory::MemoryStorage,
+        testing::{review, scratch_dir},
+    };
+
+    fn log_path(name: &str) -> PathBuf {
+        scratch_dir(&format!("wal-{}", name)).join("wal.ndjson")
+    }
+
+    fn known(facets: &[&str]) -> Progress {
+        Progress { known_facets: facets.iter().map(|facet| facet.to_string()).collect(), facets: HashMap::new() }
+    }
+
+    fn write_lines(path: &Path, records: &[WalRecord]) {
+        let lines: Vec<String> = records.iter().map(|record| serde_json::to_string(record).unwrap() + "\n").collect();
+        fs::write(path, lines.concat()).unwrap();
+    }
+
+    #[test]
+    fn reviews_reach_the_backend_a_batch_at_a_time() {
+        let path = log_path("batch");
+        let options = WalOptions { batch_size: 3, flush_interval_seconds: 0, fsync: FsyncPolicy::Never };
+        let mut wal = WalStorage::open(MemoryStorage::default(), &path, options).unwrap();
+        wal.append_review(&review("a", 1.0, 1)).unwrap();
+        wal.append_review(&review("b", 1.0, 2)).unwrap();
+        assert!(wal.inner().reviews.is_empty());
+        assert_eq!(wal.read_reviews().unwrap(), vec![review("a", 1.0, 1), review("b", 1.0, 2)]);
+        wal.append_review(&review("c", 1.0, 3)).unwrap();
+        assert_eq!(wal.inner().reviews, vec![review("a", 1.0, 1), review("b", 1.0, 2), review("c", 1.0, 3)]);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "");
+        drop(wal);
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn flushing_hands_over_reviews_and_the_latest_progress() {
+        let path = log_path("flush");
+        let mut wal = WalStorage::open(MemoryStorage::default(), &path, WalOptions::default()).unwrap();
+        wal.append_review(&review("a", 1.0, 1)).unwrap();
+        wal.save_progress(&known(&["a"])).unwrap();
+        wal.save_progress(&known(&["a", "b"])).unwrap();
+        assert_eq!(wal.inner().progress, Progress::default());
+        assert_eq!(wal.load_progress().unwrap(), known(&["a", "b"]));
+        wal.flush().unwrap();
+        assert_eq!(wal.inner().reviews, vec![review("a", 1.0, 1)]);
+        assert_eq!(wal.inner().progress, known(&["a", "b"]));
+        assert_eq!(fs::read_to_string(&path).unwrap(), "");
+        drop(wal);
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn recovery_replays_what_a_crash_left_behind() {
+        let path = log_path("recover");
+        write_lines(&path, &[
+            WalRecord::Review(review("a", 1.0, 1)),
+            WalRecord::Progress(ProgressChange::between(&Progress::default(), &known(&["a"]))),
+            WalRecord::Review(review("b", 1.0, 2)),
+            WalRecord::Progress(ProgressChange::between(&known(&["a"]), &known(&["b"]))),
+        ]);
+        let wal = WalStorage::open(MemoryStorage::default(), &path, WalOptions::default()).unwrap();
+        assert_eq!(wal.inner().reviews, vec![review("a", 1.0, 1), review("b", 1.0, 2)]);
+        assert_eq!(wal.inner().progress, known(&["b"]));
+        assert_eq!(fs::read_to_string(&path).unwrap(), "");
+        drop(wal);
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn recovery_skips_reviews_the_backend_already_has() {
+        let path = log_path("handed-over");
+        write_lines(&path, &[WalRecord::Review(review("a", 1.0, 1)), WalRecord::Review(review("b", 1.0, 2))]);
+        let inner = MemoryStorage { reviews: vec![review("a", 1.0, 1)], ..MemoryStorage::default() };
+        let wal = WalStorage::open(inner, &path, WalOptions::default()).unwrap();
+        assert_eq!(wal.inner().reviews, vec![review("a", 1.0, 1), review("b", 1.0, 2)]);
+        drop(wal);
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn recovery_leaves_out_a_last_line_cut_short() {
+        let path = log_path("torn");
+        write_lines(&path, &[WalRecord::Review(review("a", 1.0, 1))]);
+        let torn = serde_json::to_string(&WalRecord::Review(review("b", 1.0, 2))).unwrap();
+        let mut lines = fs::read_to_string(&path).unwrap();
+        lines.push_str(&torn[..torn.len() / 2]);
+        fs::write(&path, lines).unwrap();
+        let wal = WalStorage::open(MemoryStorage::default(), &path, WalOptions::default()).unwrap();
+        assert_eq!(wal.inner().reviews, vec![review("a", 1.0, 1)]);
+        drop(wal);
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+}
@@ -0,0 +1,131 @@
+//Works out the next few cards ahead of time on a tokio blocking task, so the user never waits on the ordering between cards. The buffer is a guess at what comes next: it assumes the only facets to become known are the ones each card introduces. A review that teaches anything else throws the guess away and the prefetch starts again from the real state.
+
+use std::collections::{HashSet, VecDeque};
+
+use tokio::task::JoinHandle;
+
+use crate::{
+    collection::GemCollection,
+    error::{LangwitchError, Result},
+    gem::GemId,
+    journal::ReviewEvent,
+};
+
+/// One ordering step: the facets it introduces and the gems that become fully known because of them.
+#[derive(Debug, PartialEq, Clone)]
+pub struct UpcomingGems {
+    pub facets: HashSet<String>,
+    pub gems: Vec<GemId>,
+}
+
+//What a prefetch task hands back: the steps it worked out, the collection as it stands after them (next prefetch carries on from there), and whether the deck ran out.
+type Prefetched = (Vec<UpcomingGems>, GemCollection, bool);
+
+pub struct GemStream {
+    //The state as of the cards handed out so far and the reviews that came in. This is the truth.
+    collection: GemCollection,
+    buffer: VecDeque<UpcomingGems>,
+    //The state after everything in the buffer, for the next prefetch to start from. None while a prefetch is running with it.
+    frontier: Option<GemCollection>,
+    pending: Option<JoinHandle<Result<Prefetched>>>,
+    buffer_size: usize,
+    exhausted: bool,
+}
+
+impl GemStream {
+    /// Starts streaming from an indexed collection, keeping up to `buffer_size` steps worked out ahead. Must be called from inside a tokio runtime.
+    pub fn new(collection: GemCollection, buffer_size: usize) -> GemStream {
+        let mut gem_stream = GemStream {
+            frontier: Some(collection.clone()),
+            collection,
+            buffer: VecDeque::new(),
+            pending: None,
+            buffer_size: buffer_size.max(1),
+            exhausted: false,
+        };
+        gem_stream.refill();
+        gem_stream
+    }
+
+    /// The collection as of everything handed out and graded so far.
+    pub fn collection(&self) -> &GemCollection {
+        &self.collection
+    }
+
+    /// The next step's gems, waiting for the prefetch only if it hasn't caught up yet. None once every gem has been unlocked.
+    pub async fn next(&mut self) -> Result<Option<UpcomingGems>> {
+        //Take in a prefetch that's finished, or wait for one if there's nothing else to hand out.
+        let finished = self.pending.as_ref().is_some_and(|pending| pending.is_finished());
+        if finished || self.buffer.is_empty() {
+            if let Some(pending) = self.pending.take() {
+                match pending.await {
+                    Ok(Ok(prefetched)) => self.receive(prefetched),
+                    Ok(Err(e)) => return Err(self.restart_after(e)),
+                    Err(e) => return Err(self.restart_after(e.into())),
+                }
+            }
+        }
+        let upcoming = match self.buffer.pop_front() {
+            Some(upcoming) => upcoming,
+            None => return Ok(None),
+        };
+        self.collection.mark_facets_known(&upcoming.facets);
+        self.refill();
+        Ok(Some(upcoming))
+    }
+
+    /// Applies a review. If it made facets known that the buffered steps didn't expect, the buffer is dropped and prefetching restarts from the new state.
+    pub fn grade(&mut self, event: &ReviewEvent) -> Result<()> {
+        let newly_known = self.collection.apply_review(event)?;
+        if newly_known.is_empty() {
+            return Ok(());
+        }
+        self.collection.mark_facets_known(&newly_known);
+        self.restart();
+        Ok(())
+    }
+
+    //Forgets every guess and prefetches again from the real state. A prefetch that's still running was working from the old state; dropping its handle detaches it, and its answer is never looked at.
+    fn restart(&mut self) {
+        self.pending = None;
+        self.buffer.clear();
+        self.frontier = Some(self.collection.clone());
+        self.exhausted = false;
+        self.refill();
+    }
+
+    //A failed prefetch took its frontier with it, so the buffer can't be continued. Start over and hand the error back.
+    fn restart_after(&mut self, e: LangwitchError) -> LangwitchError {
+        self.restart();
+        e
+    }
+
+    fn receive(&mut self, (steps, frontier, exhausted): Prefetched) {
+        self.buffer.extend(steps);
+        self.frontier = Some(frontier);
+        self.exhausted = exhausted;
+    }
+
+    //Starts another prefetch if none is running and the buffer is running low.
+    fn refill(&mut self) {
+        if self.pending.is_some() || self.exhausted || self.buffer.len() >= self.buffer_size {
+            return;
+        }
+        let mut frontier = match self.frontier.take() {
+            Some(frontier) => frontier,
+            None => self.collection.clone(),
+        };
+        let wanted = self.buffer_size - self.buffer.len();
+        self.pending = Some(tokio::task::spawn_blocking(move || {
+            let mut steps = Vec::with_capacity(wanted);
+            while steps.len() < wanted {
+                match frontier.step_unlocking() {
+                    Ok((facets, gems)) => steps.push(UpcomingGems { facets, gems }),
+                    Err(LangwitchError::EmptyCollection) => return Ok((steps, frontier, true)),
+                    Err(e) => return Err(e),
+                }
+            }
+            Ok((steps, frontier, false))
+        }));
+    }
+}
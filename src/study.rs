@@ -0,0 +1,129 @@
+//A study session for front ends that drive the engine from outside the process (`serve`, `--stdio`): the card being studied, grading it, marking words known, importing gems and stats, all in and out as serde types.
+//The card handed out is kept until it's graded, so asking again gives the same one.
+
+use std::{path::Path, time::SystemTime};
+
+use serde::Serialize;
+
+use langwitch::{mine::{mine_text, MineOptions}, review::Card, storage::Storage, wire::{CardJson, GradeRequest, GradeResponse}, Config, Gem, GemCollection};
+
+use crate::{ReviewSetup, GEMS_PATH, KNOWLEDGE_PATH};
+
+pub struct Study {
+    pub setup: ReviewSetup,
+    //How plain text sent in to be imported is mined.
+    mining: MineOptions,
+    card: Option<Card>,
+    reviewed: usize,
+}
+
+#[derive(Serialize, Clone)]
+pub struct Stats {
+    pub gems: usize,
+    //Gems with nothing new left in them.
+    pub unlocked: usize,
+    pub known_facets: usize,
+    pub scheduled_facets: usize,
+    pub due: usize,
+    pub reviewed: usize,
+}
+
+#[derive(Serialize)]
+pub struct ImportResponse {
+    pub received: usize,
+    pub added: usize,
+}
+
+impl Study {
+    pub fn load(config: Config) -> langwitch::Result<Study> {
+        let mining = config.mining.clone();
+        Ok(Study { setup: ReviewSetup::load(config)?, mining, card: None, reviewed: 0 })
+    }
+
+    fn card_json(&self, card: &Card) -> langwitch::Result<CardJson> {
+        let gem_collection = &self.setup.gem_collection;
+        let mut card_json = CardJson::new(gem_collection, card)?;
+        if let Some(template) = &self.setup.template {
+            card_json.front = Some(gem_collection.render_face(&template.front, card.gem, &card.facets, &self.setup.cloze)?.text);
+            card_json.back = Some(gem_collection.render_face(&template.back, card.gem, &card.facets, &self.setup.cloze)?.text);
+        }
+        Ok(card_json)
+    }
+
+    /// The card being studied, picking the next one if the last was graded. None once nothing is left.
+    pub fn current_card(&mut self) -> langwitch::Result<Option<CardJson>> {
+        if self.card.is_none() {
+            self.card = self.setup.session.next_card(&mut self.setup.gem_collection, SystemTime::now())?;
+        }
+        match self.card.clone() {
+            Some(card) => Ok(Some(self.card_json(&card)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Grades the card being studied, journals the review and saves progress.
+    pub fn grade(&mut self, request: GradeRequest) -> langwitch::Result<GradeResponse> {
+        let card = request.card(self.card.as_ref())?;
+        let (grades, hints) = request.into_grades(&card);
+        let setup = &mut self.setup;
+        let result = setup.session.grade_with_hints(&mut setup.gem_collection, &card, grades, hints)?;
+        setup.storage.append_review(&result.event)?;
+        setup.gem_collection.save_to(&mut setup.storage)?;
+        self.card = None;
+        self.reviewed += 1;
+        Ok(GradeResponse::from(result))
+    }
+
+    /// Marks `words` known and saves progress, returning the ids of the gems left with nothing to learn.
+    pub fn mark_known(&mut self, words: &[String]) -> langwitch::Result<Vec<usize>> {
+        let setup = &mut self.setup;
+        let unlocked = setup.gem_collection.mark_words_known(words)?;
+        setup.gem_collection.save_to(&mut setup.storage)?;
+        Ok(unlocked.into_iter().map(|gem_id| gem_id.0).collect())
+    }
+
+    /// Adds whichever of `gems` are new, to the deck file and to the running session.
+    pub fn import(&mut self, gems: Vec<Gem>) -> langwitch::Result<ImportResponse> {
+        let received = gems.len();
+        //The deck file keeps gems as they were written; the running collection gets them normalized and filtered like everything else in it.
+        let mut deck = if Path::new(GEMS_PATH).exists() { self.setup.storage.load_gems()? } else { GemCollection::default() };
+        if !deck.append_new_gems(gems.clone()).is_empty() {
+            deck.write_gems_to_file(GEMS_PATH)?;
+        }
+        let gem_collection = &mut self.setup.gem_collection;
+        let added = gem_collection.append_gems(gems)?;
+        if !added.is_empty() {
+            self.setup.session.extend(gem_collection);
+            gem_collection.index_gems(&added);
+        }
+        Ok(ImportResponse { received, added: added.len() })
+    }
+
+    /// Mines `text` with `mining` and imports whatever it turns up.
+    pub fn import_text(&mut self, text: &str) -> langwitch::Result<ImportResponse> {
+        let gems = mine_text(text, &self.mining)?;
+        self.import(gems)
+    }
+
+    pub fn stats(&self) -> Stats {
+        let gem_collection = &self.setup.gem_collection;
+        let now = SystemTime::now();
+        Stats {
+            gems: gem_collection.gems.len(),
+            unlocked: gem_collection.gems.iter().filter(|gem| gem.unknown_facets.is_empty()).count(),
+            known_facets: gem_collection.known_facets.len(),
+            scheduled_facets: gem_collection.facet_states.len(),
+            due: gem_collection.facet_states.values().filter(|state| state.review_date.is_some_and(|review_date| review_date <= now)).count(),
+            reviewed: self.reviewed,
+        }
+    }
+
+    /// Saves progress and shared knowledge at the end of the session, returning how many cards were reviewed.
+    pub fn finish(&mut self) -> langwitch::Result<usize> {
+        let setup = &mut self.setup;
+        setup.player.stop();
+        setup.gem_collection.save_to(&mut setup.storage)?;
+        setup.storage.inner_mut().save_knowledge(KNOWLEDGE_PATH, &setup.knowledge.snapshot())?;
+        Ok(self.reviewed)
+    }
+}
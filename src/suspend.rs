@@ -0,0 +1,189 @@
+//Setting gems aside without deleting them. A suspended gem stays out of the ordering and out of review until it's unsuspended; a buried one only until a given time, usually the start of the next day, for a card there's no time for today. Either way the gem keeps its facets, and their scheduling carries on as it was: a due facet that only set-aside gems teach just waits.
+//The collection takes set-aside gems out of both indices and keeps them out when it's indexed again. Facets learned in the meantime are stripped from a gem when it comes back, so it's filed under what it really has left.
+//What's set aside is kept in a SetAsideStore by gem key (and by facet name, for setting aside every gem that teaches a facet), so it survives the deck being reordered, and applied to each freshly loaded collection with GemCollection::apply_set_aside.
+
+use std::{
+    collections::{BTreeMap, BTreeSet, HashSet},
+    fs,
+    io::ErrorKind,
+    path::Path,
+    time::SystemTime,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    collection::GemCollection,
+    error::{LangwitchError, Result},
+    gem::{GemId, GemKey},
+    storage::atomic::write_atomically,
+    timestamp::{from_millis, to_millis},
+};
+
+/// Why a gem is out of the queue.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum SetAside {
+    /// Until it's unsuspended.
+    Suspended,
+    /// Until `until`.
+    Buried { until: SystemTime },
+}
+
+/// Suspended and buried gems and facets. Burials are kept as unix milliseconds.
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct SetAsideStore {
+    pub suspended_gems: BTreeSet<GemKey>,
+    pub suspended_facets: BTreeSet<String>,
+    pub buried_gems: BTreeMap<GemKey, u64>,
+    pub buried_facets: BTreeMap<String, u64>,
+}
+
+impl SetAsideStore {
+    /// Reads the store from a JSON file. A file that doesn't exist yet gives an empty store.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<SetAsideStore> {
+        match fs::read_to_string(path) {
+            Ok(contents) => Ok(serde_json::from_str(&contents)?),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(SetAsideStore::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Writes the store, replacing the old file only once the new one is safely on disk (see [`crate::storage::atomic`]).
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        write_atomically(path, serde_json::to_string_pretty(self)?.as_bytes())
+    }
+
+    pub fn suspend_gem(&mut self, key: GemKey) {
+        self.buried_gems.remove(&key);
+        self.suspended_gems.insert(key);
+    }
+
+    pub fn suspend_facet(&mut self, facet: String) {
+        self.buried_facets.remove(&facet);
+        self.suspended_facets.insert(facet);
+    }
+
+    pub fn bury_gem(&mut self, key: GemKey, until: SystemTime) {
+        self.buried_gems.insert(key, to_millis(until));
+    }
+
+    pub fn bury_facet(&mut self, facet: String, until: SystemTime) {
+        self.buried_facets.insert(facet, to_millis(until));
+    }
+
+    /// Unsuspends and unburies a gem, returning false if it wasn't set aside on its own.
+    pub fn restore_gem(&mut self, key: &GemKey) -> bool {
+        self.suspended_gems.remove(key) | self.buried_gems.remove(key).is_some()
+    }
+
+    /// Unsuspends and unburies a facet, returning false if it wasn't set aside.
+    pub fn restore_facet(&mut self, facet: &str) -> bool {
+        self.suspended_facets.remove(facet) | self.buried_facets.remove(facet).is_some()
+    }
+
+    /// Drops burials that are over by `now`.
+    pub fn forget_expired(&mut self, now: SystemTime) {
+        let now = to_millis(now);
+        self.buried_gems.retain(|_, until| *until > now);
+        self.buried_facets.retain(|_, until| *until > now);
+    }
+}
+
+impl GemCollection {
+    /// Takes a gem out of the ordering and review until [`GemCollection::restore`].
+    pub fn suspend(&mut self, gem_id: GemId) {
+        self.set_gem_aside(gem_id, SetAside::Suspended);
+    }
+
+    /// Takes a gem out of the ordering and review until `until`, or until [`GemCollection::restore`]. A gem that's suspended stays suspended.
+    pub fn bury(&mut self, gem_id: GemId, until: SystemTime) {
+        if self.set_aside.get(&gem_id) != Some(&SetAside::Suspended) {
+            self.set_gem_aside(gem_id, SetAside::Buried { until });
+        }
+    }
+
+    fn set_gem_aside(&mut self, gem_id: GemId, why: SetAside) {
+        if gem_id.0 < self.gems.len() {
+            self.set_aside.insert(gem_id, why);
+            self.unindex_gem(gem_id);
+        }
+    }
+
+    /// True if the gem is suspended or buried.
+    pub fn is_set_aside(&self, gem_id: GemId) -> bool {
+        self.set_aside.contains_key(&gem_id)
+    }
+
+    /// Brings a suspended or buried gem back, filed under the facets it still doesn't know. Returns false if it wasn't set aside.
+    pub fn restore(&mut self, gem_id: GemId) -> bool {
+        if self.set_aside.remove(&gem_id).is_none() {
+            return false;
+        }
+        let known_facets = &self.known_facets;
+        self.gems[gem_id.0].unknown_facets.retain(|facet| !known_facets.contains(facet));
+        self.index_gem(gem_id);
+        true
+    }
+
+    /// Brings back every buried gem whose time is up at `now`, returning their ids.
+    pub fn unbury_expired(&mut self, now: SystemTime) -> Vec<GemId> {
+        let mut expired: Vec<GemId> = self
+            .set_aside
+            .iter()
+            .filter(|(_, why)| matches!(why, SetAside::Buried { until } if *until <= now))
+            .map(|(gem_id, _)| *gem_id)
+            .collect();
+        expired.sort_unstable();
+        for gem_id in expired.iter() {
+            self.restore(*gem_id);
+        }
+        expired
+    }
+
+    /// Suspends or buries a gem and notes it in `store` by key, so it stays set aside in the collections loaded after this one.
+    pub fn set_aside_in(&mut self, store: &mut SetAsideStore, gem_id: GemId, why: SetAside) -> Result<()> {
+        let key = self.key(gem_id).cloned().ok_or(LangwitchError::MissingGem(gem_id.0))?;
+        match why {
+            SetAside::Suspended => {
+                store.suspend_gem(key);
+                self.suspend(gem_id);
+            }
+            SetAside::Buried { until } => {
+                store.bury_gem(key, until);
+                self.bury(gem_id, until);
+            }
+        }
+        Ok(())
+    }
+
+    /// Suspends and buries what `store` says to as of `now`: its gems by key, and every gem that teaches one of its facets. Returns how many gems were set aside. Burials that are already over are skipped.
+    pub fn apply_set_aside(&mut self, store: &SetAsideStore, now: SystemTime) -> usize {
+        let facet_gems = |gem_collection: &GemCollection, facets: Vec<&String>| -> HashSet<GemId> {
+            let facets: HashSet<_> = facets.into_iter().filter_map(|facet| gem_collection.interner.get(facet)).collect();
+            gem_collection.gem_ids().filter(|gem_id| !gem_collection.gems[gem_id.0].unknown_facets.is_disjoint(&facets)).collect()
+        };
+        let mut suspended: HashSet<GemId> = store.suspended_gems.iter().filter_map(|key| self.gem_id(key)).collect();
+        suspended.extend(facet_gems(self, store.suspended_facets.iter().collect()));
+        let mut buried: Vec<(GemId, SystemTime)> = Vec::new();
+        for (key, until) in store.buried_gems.iter() {
+            if let Some(gem_id) = self.gem_id(key) {
+                buried.push((gem_id, from_millis(*until)));
+            }
+        }
+        for (facet, until) in store.buried_facets.iter() {
+            buried.extend(facet_gems(self, vec![facet]).into_iter().map(|gem_id| (gem_id, from_millis(*until))));
+        }
+        for gem_id in suspended.iter() {
+            self.suspend(*gem_id);
+        }
+        for (gem_id, until) in buried.into_iter().filter(|(_, until)| *until > now) {
+            //A gem buried twice comes back when the later burial is over.
+            match self.set_aside.get(&gem_id) {
+                Some(SetAside::Buried { until: already }) if *already >= until => {}
+                _ => self.bury(gem_id, until),
+            }
+        }
+        self.set_aside.len()
+    }
+}
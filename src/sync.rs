@@ -0,0 +1,221 @@
+//Keeping one learner's progress the same across devices through a `langwitch-sync` server. The server holds nothing but a copy of the review journal and the known-facet store: `langwitch push` sends up whatever it's missing, and `langwitch pull` brings back the reviews this device hasn't seen and the words other devices learned.
+//Reviews done offline on two devices don't overwrite each other: merging keeps every review from both, and facet state is then recomputed by replaying the lot in timestamp order (see journal::sort_chronologically), so every device that has the same reviews ends up in the same state. The only thing that can't be settled that way is two different records of the same review (same gem, same moment); the copy already held is kept and the other is reported as a conflict.
+//The wire format is the files' own: GET and POST /journal carry journal lines (one ReviewEvent of JSON per line), GET and POST /knowledge a KnowledgeStore. Both POSTs answer with {"added": n}, and /journal with "conflicts" as well. If the server was given a token, every request needs it as `Authorization: Bearer <token>`.
+//None of it is encrypted, even with encryption on: see crate::encryption for what that means for where the server runs.
+
+use std::{
+    collections::HashMap,
+    time::SystemTime,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    collection::GemCollection,
+    compact::JournalSnapshot,
+    error::Result,
+    gem::GemKey,
+    journal::{sort_chronologically, ReviewEvent},
+    knowledge::KnowledgeStore,
+};
+
+/// Where `langwitch push` and `langwitch pull` sync to, e.g. {"url": "https://sync.example.org", "token": "..."}.
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct SyncOptions {
+    pub url: Option<String>,
+    pub token: Option<String>,
+}
+
+/// What the server answers a POST with.
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Copy, Default)]
+pub struct SyncAdded {
+    pub added: usize,
+    #[serde(default)]
+    pub conflicts: usize,
+}
+
+/// Two different records of the same review. The one already held was kept.
+#[derive(Debug, PartialEq, Clone)]
+pub struct SyncConflict {
+    pub kept: ReviewEvent,
+    pub dropped: ReviewEvent,
+}
+
+/// What merging another device's reviews into ours turned up.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct JournalMerge {
+    /// The reviews we didn't have, oldest first.
+    pub new_events: Vec<ReviewEvent>,
+    pub conflicts: Vec<SyncConflict>,
+}
+
+//Two copies of the same review have the same gem and the same timestamp.
+fn review_id(event: &ReviewEvent) -> (GemKey, SystemTime) {
+    (event.gem_key.clone(), event.timestamp)
+}
+
+/// Merges `incoming` into `have`: every review `have` lacks is new, an exact copy of one it has is skipped, and a different record of one it has is a conflict.
+pub fn merge_events(have: &[ReviewEvent], incoming: Vec<ReviewEvent>) -> JournalMerge {
+    let mut seen: HashMap<(GemKey, SystemTime), ReviewEvent> = have.iter().map(|event| (review_id(event), event.clone())).collect();
+    let mut merge = JournalMerge::default();
+    for event in incoming {
+        match seen.get(&review_id(&event)) {
+            Some(kept) if *kept == event => {}
+            Some(kept) => merge.conflicts.push(SyncConflict { kept: kept.clone(), dropped: event }),
+            None => {
+                seen.insert(review_id(&event), event.clone());
+                merge.new_events.push(event);
+            }
+        }
+    }
+    sort_chronologically(&mut merge.new_events);
+    merge
+}
+
+impl GemCollection {
+    /// Recomputes scheduling by replaying `events` (every review this device now has, in any order) in [`sort_chronologically`] order with the configured scheduler, on top of the journal's snapshot if it's been compacted.
+    /// Facets known some other way than by review (marked known, placement, shared knowledge) stay known, and so does the state of any facet no review mentions. The indices are left for the caller to rebuild.
+    pub fn replay_merged(&mut self, snapshot: Option<&JournalSnapshot>, events: &mut [ReviewEvent]) -> Result<()> {
+        sort_chronologically(events);
+        let known_before = std::mem::take(&mut self.known_facets);
+        let states_before = std::mem::take(&mut self.facet_states);
+        if let Some(snapshot) = snapshot {
+            self.set_progress(snapshot.progress.clone());
+        }
+        for event in events.iter() {
+            self.apply_review(event)?;
+        }
+        self.known_facets.extend(known_before);
+        for (facet, state) in states_before {
+            self.facet_states.entry(facet).or_insert(state);
+        }
+        Ok(())
+    }
+}
+
+/// Journal lines for `events`, as the server sends and takes them.
+pub fn events_to_lines(events: &[ReviewEvent]) -> Result<String> {
+    let mut lines = String::new();
+    for event in events {
+        lines.push_str(&serde_json::to_string(event)?);
+        lines.push('\n');
+    }
+    Ok(lines)
+}
+
+/// The events in journal lines. Blank lines are skipped.
+pub fn events_from_lines(lines: &str) -> Result<Vec<ReviewEvent>> {
+    let mut events = Vec::new();
+    for line in lines.lines().filter(|line| !line.trim().is_empty()) {
+        events.push(serde_json::from_str(line)?);
+    }
+    Ok(events)
+}
+
+impl KnowledgeStore {
+    /// Adds everything `other` knows, returning how many facets were new here (across every language).
+    pub fn merge(&mut self, other: &KnowledgeStore) -> usize {
+        other.languages.iter().map(|(language, facets)| self.learn(language, facets.iter())).sum()
+    }
+}
+
+#[cfg(feature = "native")]
+pub use client::SyncClient;
+
+#[cfg(feature = "native")]
+mod client {
+    use crate::{
+        error::{LangwitchError, Result},
+        fetch::{fetch_text_with_token, post_text_with_token},
+        journal::ReviewEvent,
+        knowledge::KnowledgeStore,
+    };
+
+    use super::{events_from_lines, events_to_lines, SyncAdded, SyncOptions};
+
+    /// Talks to a `langwitch-sync` server.
+    pub struct SyncClient {
+        url: String,
+        token: Option<String>,
+    }
+
+    impl SyncClient {
+        /// A client for the server `options` points at.
+        pub fn new(options: &SyncOptions) -> Result<SyncClient> {
+            let url = options.url.clone().ok_or_else(|| LangwitchError::InvalidArgument("set sync.url in the config to the sync server's address".to_string()))?;
+            Ok(SyncClient { url: url.trim_end_matches('/').to_string(), token: options.token.clone() })
+        }
+
+        /// Every review the server has.
+        pub fn pull_events(&self) -> Result<Vec<ReviewEvent>> {
+            events_from_lines(&fetch_text_with_token(&format!("{}/journal", self.url), self.token.as_deref())?)
+        }
+
+        /// Sends reviews up, returning how many the server didn't have yet and how many it had different records of.
+        pub fn push_events(&self, events: &[ReviewEvent]) -> Result<SyncAdded> {
+            let response = post_text_with_token(&format!("{}/journal", self.url), self.token.as_deref(), "application/x-ndjson", &events_to_lines(events)?)?;
+            Ok(serde_json::from_str(&response)?)
+        }
+
+        /// What the server knows about every language.
+        pub fn pull_knowledge(&self) -> Result<KnowledgeStore> {
+            Ok(serde_json::from_str(&fetch_text_with_token(&format!("{}/knowledge", self.url), self.token.as_deref())?)?)
+        }
+
+        /// Sends known facets up, returning how many the server didn't have yet.
+        pub fn push_knowledge(&self, knowledge: &KnowledgeStore) -> Result<usize> {
+            let response = post_text_with_token(&format!("{}/knowledge", self.url), self.token.as_deref(), "application/json", &serde_json::to_string(knowledge)?)?;
+            Ok(serde_json::from_str::<SyncAdded>(&response)?.added)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{collection, review};
+
+    #[test]
+    fn merge_keeps_new_reviews_in_order_and_skips_copies() {
+        let have = vec![review("der hund", 1.0, 10)];
+        let merge = merge_events(&have, vec![review("die katze", 1.0, 30), review("der hund", 1.0, 10), review("der hund schläft", 0.0, 20), review("die katze", 1.0, 30)]);
+        assert_eq!(merge.new_events, vec![review("der hund schläft", 0.0, 20), review("die katze", 1.0, 30)]);
+        assert!(merge.conflicts.is_empty());
+    }
+
+    #[test]
+    fn merge_reports_a_different_record_of_a_held_review() {
+        let have = vec![review("der hund", 1.0, 10)];
+        let merge = merge_events(&have, vec![review("der hund", 0.0, 10)]);
+        assert!(merge.new_events.is_empty());
+        assert_eq!(merge.conflicts, vec![SyncConflict { kept: review("der hund", 1.0, 10), dropped: review("der hund", 0.0, 10) }]);
+    }
+
+    #[test]
+    fn replaying_merged_reviews_ignores_the_order_they_arrived_in() {
+        let events = vec![review("der hund", 1.0, 10), review("die katze", 0.0, 20), review("der hund schläft", 1.0, 30), review("die katze", 1.0, 40)];
+        let mut in_order = collection();
+        for event in events.iter() {
+            in_order.apply_review(event).unwrap();
+        }
+        let mut shuffled = events.clone();
+        shuffled.reverse();
+        let mut merged = collection();
+        merged.replay_merged(None, &mut shuffled).unwrap();
+        assert_eq!(merged.progress(), in_order.progress());
+    }
+
+    #[test]
+    fn replaying_merged_reviews_keeps_what_no_review_mentions() {
+        let mut gem_collection = collection();
+        gem_collection.insert_known_facets([&"katze".to_string()]);
+        gem_collection.apply_review(&review("schläft", 0.0, 5)).unwrap();
+        let unreviewed = gem_collection.progress().facets["schläft"].clone();
+        gem_collection.replay_merged(None, &mut [review("der hund", 1.0, 10)]).unwrap();
+        let progress = gem_collection.progress();
+        assert!(progress.known_facets.contains("katze"));
+        assert!(progress.known_facets.contains("hund"));
+        assert_eq!(progress.facets["schläft"], unreviewed);
+    }
+}
@@ -0,0 +1,119 @@
+//Card templates: what the front and back of a card show, so one gem can be studied as a recognition card (text on the front), a production card (translation on the front) or a listening card (only the recording on the front) without being copied.
+//A template is text with placeholders: {side:N} for a side by number, {text}, {translation}, {transliteration} and {notes} for a side by role, {cloze} for the text with the card's facets blanked, and {facets}. {audio} and {image} don't stand for any text; they say the face plays the recording or shows the picture. A line whose placeholders all come out empty is left out, so one template works for gems that don't have every side.
+
+use std::collections::BTreeMap;
+
+use serde::{Serialize, Deserialize};
+
+use crate::{
+    cloze::ClozeOptions,
+    collection::GemCollection,
+    error::Result,
+    gem::GemId,
+    markdown::side_to_plain,
+    side::SideRole,
+};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+pub struct CardTemplate {
+    pub front: String,
+    pub back: String,
+}
+
+/// One face of a card, filled in.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct RenderedFace {
+    pub text: String,
+    /// The face asked for the gem's audio.
+    pub audio: bool,
+    /// The face asked for the gem's picture.
+    pub image: bool,
+}
+
+/// The templates every deck starts with: "recognition", "production" and "listening".
+pub fn default_templates() -> BTreeMap<String, CardTemplate> {
+    let template = |front: &str, back: &str| CardTemplate { front: front.to_string(), back: back.to_string() };
+    [
+        ("recognition".to_string(), template("{text}\n{image}", "{translation}\n{transliteration}\n{notes}\n{audio}")),
+        ("production".to_string(), template("{translation}\n{image}", "{text}\n{transliteration}\n{audio}")),
+        ("listening".to_string(), template("{audio}", "{text}\n{translation}\n{image}")),
+    ]
+    .into_iter()
+    .collect()
+}
+
+//The part of a placeholder between the braces, as a role, if it names one.
+fn role_named(name: &str) -> Option<SideRole> {
+    match name {
+        "text" => Some(SideRole::Text),
+        "translation" => Some(SideRole::Translation),
+        "transliteration" => Some(SideRole::Transliteration),
+        "notes" => Some(SideRole::Notes),
+        _ => None,
+    }
+}
+
+impl GemCollection {
+    /// Fills in one face of a template for gem `gem_id`, asking about `facets`. Unknown placeholders are left as they are.
+    pub fn render_face(&self, face: &str, gem_id: GemId, facets: &[String], cloze: &ClozeOptions) -> Result<RenderedFace> {
+        let gem = match self.get(gem_id) {
+            Some(gem) => gem,
+            None => return Ok(RenderedFace::default()),
+        };
+        let mut rendered = RenderedFace::default();
+        let mut lines: Vec<String> = Vec::new();
+        for line in face.lines() {
+            let mut text = String::new();
+            let mut rest = line;
+            let mut placeholders = 0;
+            let mut filled = false;
+            while let Some(open) = rest.find('{') {
+                let close = match rest[open..].find('}') {
+                    Some(offset) => open + offset,
+                    None => break,
+                };
+                text.push_str(&rest[..open]);
+                let name = &rest[open + 1..close];
+                let value = match name {
+                    "audio" => {
+                        rendered.audio = true;
+                        Some(String::new())
+                    }
+                    "image" => {
+                        rendered.image = true;
+                        Some(String::new())
+                    }
+                    "cloze" => Some(self.cloze(gem_id, facets, cloze)?.unwrap_or_default()),
+                    "facets" => Some(facets.join(", ")),
+                    //A role no side of the deck has comes out empty, like a side the gem doesn't have.
+                    _ => {
+                        let side_number = match name.strip_prefix("side:") {
+                            Some(number) => number.trim().parse().ok().map(Some),
+                            None => role_named(name).map(|role| self.side_roles.side(role)),
+                        };
+                        side_number.map(|side_number| {
+                            side_number
+                                .and_then(|side_number| gem.sides.get(&side_number).map(|side| side_to_plain(self.side_roles.role(side_number), side)))
+                                .unwrap_or_default()
+                        })
+                    }
+                };
+                match value {
+                    Some(value) => {
+                        placeholders += 1;
+                        filled |= !value.trim().is_empty();
+                        text.push_str(&value);
+                    }
+                    None => text.push_str(&rest[open..=close]),
+                }
+                rest = &rest[close + 1..];
+            }
+            text.push_str(rest);
+            if placeholders == 0 || filled {
+                lines.push(text);
+            }
+        }
+        rendered.text = lines.join("\n").trim_end().to_string();
+        Ok(rendered)
+    }
+}
@@ -0,0 +1,58 @@
+//Fixtures the unit tests share: a three-gem German deck, reviews of its gems, and scratch directories.
+
+use std::{
+    collections::HashMap,
+    fs,
+    path::PathBuf,
+    time::{Duration, UNIX_EPOCH},
+};
+
+use crate::{
+    collection::GemCollection,
+    gem::Gem,
+    journal::{sort_chronologically, ReviewEvent},
+};
+
+/// A gem with `side` as side 0 and each of its words as an unknown facet.
+pub(crate) fn gem(side: &str) -> Gem {
+    Gem { id: None, sides: HashMap::from([(0, side.to_string())]), unknown_facets: side.split_whitespace().map(str::to_string).collect(), flags: Vec::new() }
+}
+
+/// A review of the gem [`gem`] makes of `side`, grading each of its words `grade`, `seconds` after the epoch.
+pub(crate) fn review(side: &str, grade: f64, seconds: u64) -> ReviewEvent {
+    ReviewEvent {
+        gem_key: gem(side).key(),
+        grades: side.split_whitespace().map(|facet| (facet.to_string(), grade)).collect(),
+        timestamp: UNIX_EPOCH + Duration::from_secs(seconds),
+        hints: HashMap::new(),
+        cram: false,
+    }
+}
+
+/// "der hund", "die katze" and "der hund schläft", unindexed.
+pub(crate) fn collection() -> GemCollection {
+    GemCollection::from_gems(vec![gem("der hund"), gem("die katze"), gem("der hund schläft")])
+}
+
+/// An empty directory of its own for test `name`, so tests touching files can run side by side.
+pub(crate) fn scratch_dir(name: &str) -> PathBuf {
+    let directory = std::env::temp_dir().join(format!("langwitch-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&directory);
+    fs::create_dir_all(&directory).expect("the temporary directory is writable");
+    directory
+}
+
+/// Seven reviews of [`collection`]'s gems over a day, two of them at the same moment, in [`sort_chronologically`] order.
+pub(crate) fn journal() -> Vec<ReviewEvent> {
+    let mut events = vec![
+        review("der hund", 1.0, 10),
+        review("die katze", 0.0, 20),
+        review("der hund schläft", 0.3, 20),
+        review("die katze", 1.0, 3600),
+        review("der hund", 0.0, 7200),
+        review("der hund schläft", 1.0, 86400),
+        review("die katze", 0.6, 90000),
+    ];
+    sort_chronologically(&mut events);
+    events
+}
@@ -0,0 +1,26 @@
+//Serde helpers for writing SystemTimes as plain milliseconds since the unix epoch, which is what anything reading our files from another language will expect (serde's default is a {secs, nanos} object).
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Deserializer, Serializer};
+
+pub fn to_millis(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).unwrap_or_default().as_millis() as u64
+}
+
+pub fn from_millis(millis: u64) -> SystemTime {
+    UNIX_EPOCH + Duration::from_millis(millis)
+}
+
+/// Use with `#[serde(with = "crate::timestamp::unix_millis")]`.
+pub mod unix_millis {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(time: &SystemTime, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(to_millis(*time))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<SystemTime, D::Error> {
+        Ok(from_millis(u64::deserialize(deserializer)?))
+    }
+}
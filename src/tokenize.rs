@@ -0,0 +1,145 @@
+//Splitting a sentence into facets, for decks that don't come with facets attached.
+
+use std::{collections::HashSet, path::PathBuf};
+#[cfg(feature = "vibrato")]
+use std::{fs::File, io::BufReader};
+
+use serde::{Serialize, Deserialize};
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::{
+    error::{LangwitchError, Result},
+    ruby::strip_ruby,
+};
+
+//Unicode word boundaries keep "don't" and "l'homme" together but split on hyphens, and a hyphenated compound is usually one thing to learn.
+fn is_hyphen(segment: &str) -> bool {
+    matches!(segment, "-" | "‐" | "‑")
+}
+
+fn is_word(segment: &str) -> bool {
+    segment.chars().any(|c| c.is_alphanumeric())
+}
+
+/// Splits `text` into lowercase word facets along Unicode word boundaries (UAX #29), so apostrophes inside words and scripts other than Latin are handled properly. Words joined by a hyphen (with nothing else between them) stay one facet.
+pub fn tokenize(text: &str) -> HashSet<String> {
+    tokenize_words(text).into_iter().collect()
+}
+
+/// Same as [`tokenize`], but keeps every occurrence of every word, in order. Furigana readings are left out (see [`crate::ruby`]).
+pub fn tokenize_words(text: &str) -> Vec<String> {
+    word_spans(&strip_ruby(text)).into_iter().map(|(_, word)| word).collect()
+}
+
+/// Same as [`tokenize_words`], with where each word sits in `text` as a byte range. Readings aren't stripped, since the ranges have to point into `text` as it is.
+pub fn word_spans(text: &str) -> Vec<(std::ops::Range<usize>, String)> {
+    let segments: Vec<(usize, &str)> = text.split_word_bound_indices().collect();
+    let mut spans = Vec::new();
+    let mut word = String::new();
+    let mut start = 0;
+    let mut end = 0;
+    for (i, (offset, segment)) in segments.iter().enumerate() {
+        let joins = is_hyphen(segment) && !word.is_empty() && segments.get(i + 1).is_some_and(|(_, next)| is_word(next));
+        if is_word(segment) || joins {
+            if word.is_empty() {
+                start = *offset;
+            }
+            word.push_str(&segment.to_lowercase());
+            end = offset + segment.len();
+        } else if !word.is_empty() {
+            spans.push((start..end, std::mem::take(&mut word)));
+        }
+    }
+    if !word.is_empty() {
+        spans.push((start..end, word));
+    }
+    spans
+}
+
+/// Something that can split text into facets. Languages written without spaces between words need a dictionary-based segmenter instead of [`tokenize`].
+pub trait Tokenizer {
+    fn tokenize(&self, text: &str) -> HashSet<String>;
+}
+
+/// Splits on Unicode word boundaries, like [`tokenize`].
+pub struct UnicodeWords;
+
+impl Tokenizer for UnicodeWords {
+    fn tokenize(&self, text: &str) -> HashSet<String> {
+        tokenize(text)
+    }
+}
+
+/// Segments Chinese with jieba's built-in dictionary.
+#[cfg(feature = "jieba")]
+pub struct JiebaWords(jieba_rs::Jieba);
+
+#[cfg(feature = "jieba")]
+impl Tokenizer for JiebaWords {
+    fn tokenize(&self, text: &str) -> HashSet<String> {
+        self.0
+            .cut(&strip_ruby(text), true)
+            .into_iter()
+            .filter(|token| is_word(token.word))
+            .map(|token| token.word.to_lowercase())
+            .collect()
+    }
+}
+
+/// Segments Japanese with vibrato and a MeCab-style system dictionary.
+#[cfg(feature = "vibrato")]
+pub struct VibratoWords(vibrato::Tokenizer);
+
+#[cfg(feature = "vibrato")]
+impl Tokenizer for VibratoWords {
+    fn tokenize(&self, text: &str) -> HashSet<String> {
+        let mut worker = self.0.new_worker();
+        worker.reset_sentence(strip_ruby(text));
+        worker.tokenize();
+        worker
+            .token_iter()
+            .map(|token| token.surface().to_lowercase())
+            .filter(|word| is_word(word))
+            .collect()
+    }
+}
+
+/// Which tokenizer a deck's facets come from: "unicode" (the default), "jieba", or {"vibrato": {"dictionary": "path/to/system.dic"}}.
+/// The dictionary-based ones need the crate built with their cargo feature of the same name.
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenizerKind {
+    #[default]
+    Unicode,
+    Jieba,
+    Vibrato {
+        /// A compiled vibrato dictionary, optionally zstd-compressed (ending in `.zst`) as they're distributed.
+        dictionary: PathBuf,
+    },
+}
+
+impl TokenizerKind {
+    /// Sets the tokenizer up, loading its dictionary if it has one. Worth doing once per deck rather than once per sentence.
+    pub fn build(&self) -> Result<Box<dyn Tokenizer + Send + Sync>> {
+        match self {
+            TokenizerKind::Unicode => Ok(Box::new(UnicodeWords)),
+            #[cfg(feature = "jieba")]
+            TokenizerKind::Jieba => Ok(Box::new(JiebaWords(jieba_rs::Jieba::new()))),
+            #[cfg(not(feature = "jieba"))]
+            TokenizerKind::Jieba => Err(LangwitchError::Tokenizer("langwitch was built without the \"jieba\" feature".to_string())),
+            #[cfg(feature = "vibrato")]
+            TokenizerKind::Vibrato { dictionary } => {
+                let file = BufReader::new(File::open(dictionary)?);
+                let read = if dictionary.extension().is_some_and(|extension| extension == "zst") {
+                    vibrato::Dictionary::read(zstd::Decoder::new(file)?)
+                } else {
+                    vibrato::Dictionary::read(file)
+                };
+                let dictionary = read.map_err(|e| LangwitchError::Tokenizer(e.to_string()))?;
+                Ok(Box::new(VibratoWords(vibrato::Tokenizer::new(dictionary))))
+            }
+            #[cfg(not(feature = "vibrato"))]
+            TokenizerKind::Vibrato { .. } => Err(LangwitchError::Tokenizer("langwitch was built without the \"vibrato\" feature".to_string())),
+        }
+    }
+}
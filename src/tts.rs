@@ -0,0 +1,106 @@
+//Speech for gems that don't come with a recording, so decks mined from plain text still get listening practice. A gem's text is only synthesized the first time its audio is asked for, and the result is cached in the media directory.
+//The hook is either a command (given the text on stdin and the file to write as its last argument, which suits `espeak-ng --stdin -w` and most command-line engines) or an HTTP endpoint the text is posted to, which has to answer with the audio itself.
+
+use std::{
+    collections::HashMap,
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+use serde::{Serialize, Deserialize};
+
+use crate::{
+    audio::AudioOptions,
+    collection::GemCollection,
+    error::{LangwitchError, Result},
+    gem::{GemId, GemKey},
+    normalize::ExternalCommand,
+    ruby::strip_ruby,
+};
+#[cfg(feature = "native")]
+use crate::fetch::post_text;
+
+/// Where speech comes from: {"command": {"program": "espeak-ng", "args": ["-v", "es", "--stdin", "-w"]}} or {"http": "http://localhost:5002/api/tts"}.
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum TtsHook {
+    Command(ExternalCommand),
+    Http(String),
+}
+
+impl TtsHook {
+    /// Synthesizes `text` into the file at `path`.
+    pub fn synthesize(&self, text: &str, path: &Path) -> Result<()> {
+        match self {
+            TtsHook::Command(command) => {
+                let mut child = Command::new(&command.program)
+                    .args(&command.args)
+                    .arg(path)
+                    .stdin(Stdio::piped())
+                    .stdout(Stdio::null())
+                    .spawn()?;
+                //Dropped straight after writing, so the engine sees the end of its input.
+                {
+                    let mut stdin = child.stdin.take().ok_or_else(|| LangwitchError::Audio("couldn't open the speech command's stdin".to_string()))?;
+                    stdin.write_all(text.as_bytes())?;
+                }
+                let status = child.wait()?;
+                if !status.success() {
+                    return Err(LangwitchError::Audio(format!("{} exited with {}", command.program, status)));
+                }
+                if !path.is_file() {
+                    return Err(LangwitchError::Audio(format!("{} didn't write {}", command.program, path.display())));
+                }
+                Ok(())
+            }
+            #[cfg(feature = "native")]
+            TtsHook::Http(url) => {
+                let audio = post_text(url, text)?;
+                if audio.is_empty() {
+                    return Err(LangwitchError::Audio(format!("{} sent back no audio", url)));
+                }
+                fs::write(path, audio)?;
+                Ok(())
+            }
+            #[cfg(not(feature = "native"))]
+            TtsHook::Http(url) => Err(LangwitchError::Audio(format!("can't reach {} without the native feature", url))),
+        }
+    }
+}
+
+impl GemCollection {
+    /// The audio for gem `gem_id`: its own recording (see [`GemCollection::audio_path`]) if it has one, otherwise speech synthesized from its text side with `options.tts`. None if the gem has neither a recording nor any text, or no hook is set up.
+    pub fn audio_or_speech(&self, gem_id: GemId, options: &AudioOptions) -> Result<Option<PathBuf>> {
+        if let Some(path) = self.audio_path(gem_id, options) {
+            return Ok(Some(path));
+        }
+        let hook = match &options.tts {
+            Some(hook) => hook,
+            None => return Ok(None),
+        };
+        let text = match self.text(gem_id).map(strip_ruby) {
+            Some(text) if !text.trim().is_empty() => text,
+            _ => return Ok(None),
+        };
+        //Named after a hash of the text (the same one gem keys use), so gems with the same sentence share a file and an edited sentence gets synthesized again.
+        let hash = GemKey::from_sides(&HashMap::from([(0, text.clone())]));
+        let path = options.resolve(&format!("tts-{}.{}", hash, options.tts_extension));
+        if path.is_file() {
+            return Ok(Some(path));
+        }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        //Written under another name first, so a synthesis that fails halfway doesn't leave a broken file in the cache.
+        let partial = path.with_file_name(format!("tts-{}.partial.{}", hash, options.tts_extension));
+        let synthesized = hook.synthesize(&text, &partial);
+        if synthesized.is_err() {
+            let _ = fs::remove_file(&partial);
+        }
+        synthesized?;
+        fs::rename(&partial, &path)?;
+        Ok(Some(path))
+    }
+}
@@ -0,0 +1,378 @@
+//The full-screen front end, built on cursive: a deck browser, the review screen with a toggle per facet, and a stats pane. b, r and s switch between them and q saves and quits.
+//Everything lives in one App as cursive's user data, and every screen is rebuilt from it when it's shown, so no screen ever holds state of its own that could go stale.
+//cursive can't draw pictures, so showing a card's picture steps out of the UI for a moment: the event loop stops, the image viewer draws it on the bare terminal, and the loop starts again on Enter.
+
+use std::{collections::HashMap, io::{self, BufRead}, path::PathBuf, time::SystemTime};
+
+use cursive::{
+    traits::{Nameable, Resizable, Scrollable},
+    theme::Effect,
+    utils::markup::{markdown, StyledString},
+    views::{Button, Checkbox, Dialog, EditView, LinearLayout, SelectView, TextView},
+    Cursive,
+};
+
+use langwitch::{flag::GemFlag, markdown::is_markdown, review::Card, ruby::ruby_to_plain, storage::Storage, GemCollection, GemId};
+
+use crate::{flag_card, set_card_aside, ReviewSetup, KNOWLEDGE_PATH};
+
+struct App {
+    setup: ReviewSetup,
+    //The card on the review screen, kept until it's graded so leaving the screen and coming back doesn't skip it.
+    card: Option<Card>,
+    reviewed: usize,
+    //Set when the event loop is stopped to show a picture rather than to quit.
+    picture: Option<PathBuf>,
+}
+
+const HELP: &str = "b: browse  r: review  s: stats  q: save and quit";
+
+/// Runs the TUI until the user quits, then saves progress and shared knowledge.
+pub fn run(setup: ReviewSetup) -> langwitch::Result<()> {
+    let mut siv = cursive::default();
+    siv.set_user_data(App { setup, card: None, reviewed: 0, picture: None });
+    siv.add_global_callback('b', show_browser);
+    siv.add_global_callback('r', show_review);
+    siv.add_global_callback('s', show_stats);
+    siv.add_global_callback('q', |s| s.quit());
+    show_review(&mut siv);
+    siv.run();
+    while let Some(Some(picture)) = siv.with_user_data(|app: &mut App| app.picture.take()) {
+        let shown = siv.with_user_data(|app: &mut App| app.setup.images.show(&picture));
+        if let Some(Err(e)) = shown {
+            eprintln!("{}", e);
+        }
+        println!("Press Enter to go back");
+        io::stdin().lock().read_line(&mut String::new())?;
+        siv.run();
+    }
+    let app: App = match siv.take_user_data() {
+        Some(app) => app,
+        None => return Ok(()),
+    };
+    let App { setup: ReviewSetup { mut storage, gem_collection, knowledge, mut player, .. }, .. } = app;
+    player.stop();
+    gem_collection.save_to(&mut storage)?;
+    storage.flush()?;
+    storage.inner_mut().save_knowledge(KNOWLEDGE_PATH, &knowledge.snapshot())?;
+    Ok(())
+}
+
+//Replaces whatever screen is showing, along with any dialog open on top of it.
+fn show(s: &mut Cursive, title: &str, view: LinearLayout) {
+    while s.pop_layer().is_some() {}
+    s.add_layer(Dialog::around(view.child(TextView::new(HELP))).title(title).full_screen());
+}
+
+fn show_error(s: &mut Cursive, e: langwitch::LangwitchError) {
+    s.add_layer(Dialog::info(e.to_string()));
+}
+
+fn text_side(gem_collection: &GemCollection, card: &Card) -> String {
+    ruby_to_plain(gem_collection.text(card.gem).unwrap_or_default())
+}
+
+//One side labelled with its role, with Markdown sides styled.
+fn styled_side(gem_collection: &GemCollection, side_number: usize, side: &str) -> StyledString {
+    let role = gem_collection.side_roles.role(side_number);
+    let mut styled = StyledString::styled(format!("{}: ", role.label()), Effect::Bold);
+    if is_markdown(role) {
+        styled.append(markdown::parse(side));
+    } else {
+        styled.append_plain(ruby_to_plain(side));
+    }
+    styled.append_plain("\n");
+    styled
+}
+
+//Every side of a gem but the ones in `skip`, in side order.
+fn styled_sides(gem_collection: &GemCollection, gem_id: GemId, skip: Option<usize>) -> StyledString {
+    let mut styled = StyledString::new();
+    let gem = match gem_collection.get(gem_id) {
+        Some(gem) => gem,
+        None => return styled,
+    };
+    let mut side_numbers: Vec<&usize> = gem.sides.keys().filter(|side_number| Some(**side_number) != skip).collect();
+    side_numbers.sort_unstable();
+    for side_number in side_numbers {
+        styled.append(styled_side(gem_collection, *side_number, &gem.sides[side_number]));
+    }
+    styled
+}
+
+//What the review screen shows for a card.
+struct CardView {
+    card: Card,
+    front: String,
+    back: StyledString,
+    has_audio: bool,
+    picture: Option<PathBuf>,
+}
+
+fn show_review(s: &mut Cursive) {
+    let mut playback = Ok(());
+    let next = s.with_user_data(|app: &mut App| -> langwitch::Result<Option<CardView>> {
+        let fresh = app.card.is_none();
+        if fresh {
+            app.card = app.setup.session.next_card(&mut app.setup.gem_collection, SystemTime::now())?;
+        }
+        let card = match app.card.clone() {
+            Some(card) => card,
+            None => return Ok(None),
+        };
+        let audio = match app.setup.player.audio(&app.setup.gem_collection, card.gem) {
+            Ok(audio) => audio,
+            Err(e) => {
+                playback = Err(e);
+                None
+            }
+        };
+        let gem_collection = &app.setup.gem_collection;
+        //With a card template, the template says what each face shows and whether the front plays the audio.
+        let (front, back, autoplay) = match &app.setup.template {
+            Some(template) => {
+                let front = gem_collection.render_face(&template.front, card.gem, &card.facets, &app.setup.cloze)?;
+                let back = gem_collection.render_face(&template.back, card.gem, &card.facets, &app.setup.cloze)?;
+                let front_text = match front.text.as_str() {
+                    "" if front.audio && audio.is_some() => "(listen)".to_string(),
+                    "" => text_side(gem_collection, &card),
+                    text => text.to_string(),
+                };
+                (front_text, StyledString::plain(back.text), front.audio)
+            }
+            None => (
+                text_side(gem_collection, &card),
+                styled_sides(gem_collection, card.gem, Some(gem_collection.side_roles.text_side())),
+                app.setup.player.options.autoplay,
+            ),
+        };
+        //Only autoplayed the first time the card comes up, not whenever the screen is switched back to.
+        if let Some(audio) = audio.as_deref().filter(|_| fresh && autoplay) {
+            playback = app.setup.player.play(audio);
+        }
+        Ok(Some(CardView {
+            front,
+            back,
+            has_audio: audio.is_some(),
+            picture: app.setup.gem_collection.image_path(card.gem, app.setup.player.options.media_dir.as_deref()),
+            card,
+        }))
+    });
+    let CardView { card, front, back, has_audio, picture } = match next {
+        Some(Ok(Some(next))) => next,
+        Some(Ok(None)) => {
+            let (reviews_limited, new_facets_limited) = s
+                .with_user_data(|app: &mut App| (app.setup.session.reached_review_limit(), app.setup.session.reached_new_facet_limit()))
+                .unwrap_or_default();
+            let message = if reviews_limited {
+                "That's today's reviews done; the rest wait for tomorrow."
+            } else if new_facets_limited {
+                "Nothing is due, and today's new facets are used up."
+            } else {
+                "Nothing is due and every gem has been unlocked."
+            };
+            show(s, "Review", LinearLayout::vertical().child(TextView::new(message)));
+            return;
+        }
+        Some(Err(e)) => return show_error(s, e),
+        None => return,
+    };
+    let mut facets = LinearLayout::vertical();
+    for (number, facet) in card.facets.iter().enumerate() {
+        facets.add_child(
+            LinearLayout::horizontal()
+                .child(Checkbox::new().with_name(format!("facet-{}", number)))
+                .child(TextView::new(format!(" {}", facet))),
+        );
+    }
+    let back = if back.is_empty() { StyledString::plain("(no other sides)") } else { back };
+    let mut buttons = LinearLayout::horizontal()
+        .child(Button::new("Reveal", move |s| {
+            s.call_on_name("back", |view: &mut TextView| view.set_content(back.clone()));
+        }))
+        .child(Button::new("Submit ticked", |s| submit(s, None)))
+        .child(Button::new("All right", |s| submit(s, Some(1.0))))
+        .child(Button::new("All wrong", |s| submit(s, Some(0.0))))
+        .child(Button::new("Suspend", |s| set_aside(s, false)))
+        .child(Button::new("Bury", |s| set_aside(s, true)))
+        .child(Button::new("Flag", show_flag));
+    if has_audio {
+        buttons.add_child(Button::new("Play", play_audio));
+    }
+    if let Some(picture) = picture {
+        buttons.add_child(Button::new("Picture", move |s| {
+            s.with_user_data(|app: &mut App| app.picture = Some(picture.clone()));
+            s.quit();
+        }));
+    }
+    let title = match (card.is_new, card.ahead) {
+        (true, _) => "Review: new gem",
+        (false, true) => "Review: ahead",
+        (false, false) => "Review: due",
+    };
+    show(
+        s,
+        title,
+        LinearLayout::vertical()
+            .child(TextView::new(front))
+            .child(TextView::new("").with_name("back"))
+            .child(TextView::new("Tick the facets you got right:"))
+            .child(facets.scrollable())
+            .child(buttons),
+    );
+    if let Err(e) = playback {
+        show_error(s, e);
+    }
+}
+
+fn play_audio(s: &mut Cursive) {
+    let played = s.with_user_data(|app: &mut App| {
+        let card = app.card.as_ref()?;
+        match app.setup.player.audio(&app.setup.gem_collection, card.gem) {
+            Ok(audio) => Some(app.setup.player.play(&audio?)),
+            Err(e) => Some(Err(e)),
+        }
+    });
+    if let Some(Some(Err(e))) = played {
+        show_error(s, e);
+    }
+}
+
+//Grades the card on screen, either from the ticked boxes or with one grade for every facet, then moves on to the next card.
+fn submit(s: &mut Cursive, everything: Option<f64>) {
+    let card = match s.with_user_data(|app: &mut App| app.card.clone()).flatten() {
+        Some(card) => card,
+        None => return,
+    };
+    let mut grades = HashMap::new();
+    for (number, facet) in card.facets.iter().enumerate() {
+        let grade = match everything {
+            Some(grade) => grade,
+            None => {
+                let ticked = s.call_on_name(&format!("facet-{}", number), |checkbox: &mut Checkbox| checkbox.is_checked()).unwrap_or(false);
+                if ticked { 1.0 } else { 0.0 }
+            }
+        };
+        grades.insert(facet.clone(), grade);
+    }
+    let graded = s.with_user_data(|app: &mut App| -> langwitch::Result<()> {
+        let result = app.setup.session.grade(&mut app.setup.gem_collection, &card, grades)?;
+        app.setup.storage.append_review(&result.event)?;
+        app.setup.gem_collection.save_to(&mut app.setup.storage)?;
+        app.card = None;
+        app.reviewed += 1;
+        Ok(())
+    });
+    match graded {
+        Some(Err(e)) => show_error(s, e),
+        _ => show_review(s),
+    }
+}
+
+//Suspends the card on screen's gem, or buries it until tomorrow, and moves on without grading it.
+fn set_aside(s: &mut Cursive, bury: bool) {
+    let set_aside = s.with_user_data(|app: &mut App| -> langwitch::Result<()> {
+        let card = match app.card.take() {
+            Some(card) => card,
+            None => return Ok(()),
+        };
+        let setup = &mut app.setup;
+        set_card_aside(&mut setup.gem_collection, &mut setup.set_aside, &setup.daily_limits, card.gem, bury)
+    });
+    match set_aside {
+        Some(Err(e)) => show_error(s, e),
+        _ => show_review(s),
+    }
+}
+
+//Asks what's wrong with the card on screen and flags its gem, leaving the card where it is.
+fn show_flag(s: &mut Cursive) {
+    let form = LinearLayout::vertical()
+        .child(TextView::new("What's wrong with it (typo, bad translation, too long...)?"))
+        .child(EditView::new().with_name("flag-reason"))
+        .child(TextView::new("Note:"))
+        .child(EditView::new().with_name("flag-note"));
+    s.add_layer(
+        Dialog::around(form)
+            .title("Flag")
+            .button("Flag", |s| {
+                let read = |s: &mut Cursive, name: &str| s.call_on_name(name, |view: &mut EditView| view.get_content().to_string()).unwrap_or_default();
+                let (reason, note) = (read(s, "flag-reason"), read(s, "flag-note"));
+                if reason.trim().is_empty() {
+                    return;
+                }
+                let flagged = s.with_user_data(|app: &mut App| match &app.card {
+                    Some(card) => flag_card(&mut app.setup.gem_collection, card.gem, GemFlag::new(&reason, Some(&note))),
+                    None => Ok(()),
+                });
+                s.pop_layer();
+                if let Some(Err(e)) = flagged {
+                    show_error(s, e);
+                }
+            })
+            .dismiss_button("Cancel"),
+    );
+}
+
+fn show_browser(s: &mut Cursive) {
+    let mut select: SelectView<usize> = SelectView::new();
+    let rows = s.with_user_data(|app: &mut App| {
+        let gem_collection = &app.setup.gem_collection;
+        gem_collection
+            .gem_ids()
+            .filter_map(|gem_id| {
+                let gem = gem_collection.get(gem_id)?;
+                let status = match gem.unknown_facets.len() {
+                    0 => "known".to_string(),
+                    unknown => format!("{} new", unknown),
+                };
+                let side: String = ruby_to_plain(gem_collection.text(gem_id).unwrap_or_default()).chars().take(100).collect();
+                Some((format!("{:>7}  {}", status, side), gem_id.0))
+            })
+            .collect::<Vec<(String, usize)>>()
+    });
+    select.add_all(rows.unwrap_or_default());
+    select.set_on_submit(show_gem);
+    show(s, "Deck", LinearLayout::vertical().child(select.scrollable().full_height()));
+}
+
+//A gem's sides and facets, each facet marked with whether it's known yet.
+fn show_gem(s: &mut Cursive, number: &usize) {
+    let details = s.with_user_data(|app: &mut App| {
+        let gem_collection = &app.setup.gem_collection;
+        let gem_id = GemId(*number);
+        gem_collection.get(gem_id)?;
+        let mut details = styled_sides(gem_collection, gem_id, None);
+        let known = gem_collection.known_facet_names();
+        for facet in app.setup.session.gem_facets(gem_collection, gem_id) {
+            let mark = if known.contains(&facet) { "known" } else { "new" };
+            details.append_plain(format!("\n{:>6}  {}", mark, facet));
+        }
+        for flag in gem_collection.get(gem_id)?.flags.iter() {
+            details.append_plain(format!("\n  flagged {}{}", flag.reason, flag.note.as_deref().map(|note| format!(": {}", note)).unwrap_or_default()));
+        }
+        Some(details)
+    });
+    if let Some(Some(details)) = details {
+        s.add_layer(Dialog::around(TextView::new(details).scrollable()).title("Gem").dismiss_button("Back"));
+    }
+}
+
+fn show_stats(s: &mut Cursive) {
+    let stats = s.with_user_data(|app: &mut App| {
+        let gem_collection = &app.setup.gem_collection;
+        let now = SystemTime::now();
+        let unlocked = gem_collection.gems.iter().filter(|gem| gem.unknown_facets.is_empty()).count();
+        let due = gem_collection.facet_states.values().filter(|state| state.review_date.is_some_and(|review_date| review_date <= now)).count();
+        format!(
+            "Gems: {} ({} with nothing new left)\nKnown facets: {}\nFacets with a schedule: {}\nDue now: {}\nReviewed this session: {}",
+            gem_collection.gems.len(),
+            unlocked,
+            gem_collection.known_facets.len(),
+            gem_collection.facet_states.len(),
+            due,
+            app.reviewed
+        )
+    });
+    show(s, "Stats", LinearLayout::vertical().child(TextView::new(stats.unwrap_or_default())));
+}
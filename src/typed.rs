@@ -0,0 +1,85 @@
+//Typed-answer grading: the learner types the answer out, and how close it is to the expected side becomes a fuzzy grade between 0.0 and 1.0, so a typo earns partial credit instead of a flat fail.
+//Closeness is Damerau-Levenshtein distance in its usual restricted form (insertions, deletions, substitutions and swaps of adjacent characters each cost one, and a swapped pair isn't edited again) over characters, after both strings have been put through the same normalization.
+
+use serde::{Serialize, Deserialize};
+
+use crate::{
+    normalize::{TextNormalization, UnicodeForm},
+    ruby::strip_ruby,
+};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct TypedAnswerOptions {
+    /// The side shown as the prompt.
+    pub prompt_side: usize,
+    /// The side the typed answer is checked against.
+    pub answer_side: usize,
+    /// How many edits are tolerated, as a share of the expected answer's length. An answer that needs more edits than that scores 0.0; 0.0 only accepts exact answers.
+    pub tolerance: f64,
+    pub ignore_case: bool,
+    /// Treats "cafe" as a perfect answer for "café".
+    pub ignore_diacritics: bool,
+}
+
+impl Default for TypedAnswerOptions {
+    fn default() -> Self {
+        TypedAnswerOptions {
+            prompt_side: 1,
+            answer_side: 0,
+            tolerance: 0.25,
+            ignore_case: true,
+            ignore_diacritics: false,
+        }
+    }
+}
+
+/// Damerau-Levenshtein distance between `a` and `b`, counted in characters.
+pub fn damerau_levenshtein(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    //Three rows are enough: a swap only ever looks two rows back.
+    let mut before_previous: Vec<usize> = vec![0; b.len() + 1];
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current: Vec<usize> = vec![0; b.len() + 1];
+    for i in 1..=a.len() {
+        current[0] = i;
+        for j in 1..=b.len() {
+            let substitution = if a[i - 1] == b[j - 1] { 0 } else { 1 };
+            current[j] = (previous[j] + 1).min(current[j - 1] + 1).min(previous[j - 1] + substitution);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                current[j] = current[j].min(before_previous[j - 2] + 1);
+            }
+        }
+        std::mem::swap(&mut before_previous, &mut previous);
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[b.len()]
+}
+
+impl TypedAnswerOptions {
+    //Both sides of the comparison go through this. Runs of whitespace count as one space, so a double space isn't an error.
+    fn prepare(&self, text: &str) -> String {
+        let normalization = TextNormalization {
+            lowercase: self.ignore_case,
+            unicode_form: UnicodeForm::Nfc,
+            strip_diacritics: self.ignore_diacritics,
+        };
+        normalization.apply(text).split_whitespace().collect::<Vec<&str>>().join(" ")
+    }
+
+    /// Grades a typed answer against the expected one: 1.0 for a match, falling off linearly with every edit, down to 0.0 once there are more edits than the tolerance allows. Furigana in the expected answer doesn't have to be typed.
+    pub fn grade(&self, expected: &str, typed: &str) -> f64 {
+        let expected = self.prepare(&strip_ruby(expected));
+        let typed = self.prepare(typed);
+        let distance = damerau_levenshtein(&expected, &typed);
+        if distance == 0 {
+            return 1.0;
+        }
+        let allowed = self.tolerance.max(0.0) * expected.chars().count() as f64;
+        if distance as f64 > allowed {
+            return 0.0;
+        }
+        1.0 - distance as f64 / (allowed + 1.0)
+    }
+}
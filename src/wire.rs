@@ -0,0 +1,106 @@
+//The JSON a card and its grade travel as between the engine and a front end outside it: `langwitch serve`, `--stdio` and gRPC in the binary, and wasm/ and ffi/ through [`crate::embed`]. They all use these, so a card looks the same whichever way it's asked for.
+
+use std::collections::{BTreeMap, HashMap};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    collection::GemCollection,
+    error::{LangwitchError, Result},
+    gem::GemId,
+    journal::ReviewResult,
+    review::Card,
+    side::SideRole,
+};
+
+#[derive(Serialize)]
+pub struct SideJson {
+    pub role: SideRole,
+    pub text: String,
+}
+
+#[derive(Serialize)]
+pub struct CardJson {
+    pub gem: usize,
+    pub key: String,
+    pub facets: Vec<String>,
+    pub is_new: bool,
+    pub sides: BTreeMap<usize, SideJson>,
+    //The card template's faces, when the front end renders one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub front: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub back: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct GradeRequest {
+    pub gem: usize,
+    #[serde(default)]
+    pub grades: HashMap<String, f64>,
+    //Given to every facet of the card that `grades` leaves out.
+    pub grade: Option<f64>,
+    #[serde(default)]
+    pub hints: HashMap<String, u8>,
+}
+
+#[derive(Serialize)]
+pub struct GradeResponse {
+    pub passed: Vec<String>,
+    pub failed: Vec<String>,
+    pub newly_known: Vec<String>,
+    pub unlocked: Vec<usize>,
+}
+
+impl CardJson {
+    /// `card`, with every side of its gem and the role it plays. `front` and `back` are left for the caller to render.
+    pub fn new(gem_collection: &GemCollection, card: &Card) -> Result<CardJson> {
+        let gem = gem_collection.gem(card.gem).ok_or(LangwitchError::MissingGem(card.gem.0))?;
+        let sides = gem
+            .sides
+            .into_iter()
+            .map(|(side_number, text)| (side_number, SideJson { role: gem_collection.side_roles.role(side_number), text }))
+            .collect();
+        Ok(CardJson {
+            gem: card.gem.0,
+            key: gem_collection.key(card.gem).map(|key| key.0.clone()).unwrap_or_default(),
+            facets: card.facets.clone(),
+            is_new: card.is_new,
+            sides,
+            front: None,
+            back: None,
+        })
+    }
+}
+
+impl GradeRequest {
+    /// The card this grades: `current`, unless the request names some other gem (or nothing is being studied).
+    pub fn card(&self, current: Option<&Card>) -> Result<Card> {
+        match current {
+            Some(card) if card.gem == GemId(self.gem) => Ok(card.clone()),
+            _ => Err(LangwitchError::NotCurrentCard(self.gem)),
+        }
+    }
+
+    /// The grade for each of `card`'s facets, with `grade` filling in the ones `grades` leaves out, and the hints each needed.
+    pub fn into_grades(self, card: &Card) -> (HashMap<String, f64>, HashMap<String, u8>) {
+        let mut grades = self.grades;
+        if let Some(grade) = self.grade {
+            for facet in card.facets.iter() {
+                grades.entry(facet.clone()).or_insert(grade);
+            }
+        }
+        (grades, self.hints)
+    }
+}
+
+impl From<ReviewResult> for GradeResponse {
+    fn from(result: ReviewResult) -> Self {
+        GradeResponse {
+            passed: result.passed,
+            failed: result.failed,
+            newly_known: result.newly_known,
+            unlocked: result.unlocked.into_iter().map(|gem_id| gem_id.0).collect(),
+        }
+    }
+}
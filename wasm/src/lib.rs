@@ -0,0 +1,49 @@
+//The review engine compiled to wasm32 for a browser, with no server behind it: the page hands over the deck (and any saved progress) as bytes, then asks for cards and grades them, saving progressBytes() wherever it likes (IndexedDB, localStorage, a download). See langwitch::embed for the JSON that goes back and forth.
+//A browser has no clock that std can read, so every call that needs the time takes it from JavaScript as milliseconds since the epoch (Date.now()).
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use wasm_bindgen::prelude::*;
+
+use langwitch::{embed::EmbeddedDeck, LangwitchError};
+
+fn js_error(e: LangwitchError) -> JsError {
+    JsError::new(&e.to_string())
+}
+
+fn time_at(now_ms: f64) -> SystemTime {
+    UNIX_EPOCH + Duration::from_millis(now_ms.max(0.0) as u64)
+}
+
+/// A deck being studied in the browser.
+#[wasm_bindgen]
+pub struct Deck {
+    deck: EmbeddedDeck,
+}
+
+#[wasm_bindgen]
+impl Deck {
+    /// Loads a deck from the bytes of a gems.json file, with the bytes of a progress.json and a config.json if there are any. Normalizers that need files or programs aren't available in a browser.
+    #[wasm_bindgen(js_name = loadFromBytes)]
+    pub fn load_from_bytes(gems: &[u8], progress: Option<Vec<u8>>, config: Option<Vec<u8>>) -> Result<Deck, JsError> {
+        let deck = EmbeddedDeck::from_json(gems, progress.as_deref(), config.as_deref()).map_err(js_error)?;
+        Ok(Deck { deck })
+    }
+
+    /// The card to study as of `now_ms`, as JSON, or "null" once nothing is left.
+    #[wasm_bindgen(js_name = nextGem)]
+    pub fn next_gem(&mut self, now_ms: f64) -> Result<String, JsError> {
+        self.deck.next_gem_json(time_at(now_ms)).map_err(js_error)
+    }
+
+    /// Grades the card being studied, e.g. {"gem": 3, "grades": {"cat": 1.0, "sat": 0.0}}, with "grade" for every facet it doesn't list and "hints" for how many hints each facet needed. Answers with what the grading did, as JSON.
+    pub fn grade(&mut self, request: &str, now_ms: f64) -> Result<String, JsError> {
+        self.deck.grade_json(request, time_at(now_ms)).map_err(js_error)
+    }
+
+    /// The progress so far, as the bytes of a progress.json that loadFromBytes takes back.
+    #[wasm_bindgen(js_name = progressBytes)]
+    pub fn progress_bytes(&self) -> Result<Vec<u8>, JsError> {
+        self.deck.progress_json().map_err(js_error)
+    }
+}